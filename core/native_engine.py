@@ -400,7 +400,7 @@ class UltimateEngine:
         
         if self._is_native:
             self._native_engine.stop()
-            stats_dict = self._native_engine.get_stats()
+            stats_dict = self._native_engine.get_stats().to_dict()
         else:
             self._python_engine.stop()
             stats_dict = self._python_engine.get_stats()
@@ -414,7 +414,7 @@ class UltimateEngine:
     def get_stats(self) -> EngineStats:
         """Get current statistics"""
         if self._is_native:
-            stats_dict = self._native_engine.get_stats()
+            stats_dict = self._native_engine.get_stats().to_dict()
         else:
             stats_dict = self._python_engine.get_stats()
         
@@ -795,7 +795,7 @@ def start_flood(
         try:
             return _native_module.start_flood(
                 target, port, duration, rate, threads, packet_size, protocol
-            )
+            ).to_dict()
         except Exception as e:
             logger.warning(f"Native flood failed: {e}, using Python fallback")
     
@@ -1,9 +1,9 @@
 //! Lock-free atomic statistics for real-time monitoring
 //! Implements per-thread counters with efficient aggregation
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 
 /// Atomic statistics counters (lock-free)
@@ -43,6 +43,11 @@ impl AtomicStats {
         self.errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    #[inline]
+    pub fn record_errors(&self, count: u64) {
+        self.errors.fetch_add(count, Ordering::Relaxed);
+    }
+
     #[inline]
     pub fn record_dropped(&self) {
         self.dropped.fetch_add(1, Ordering::Relaxed);
@@ -81,6 +86,8 @@ pub struct StatsSnapshot {
     pub bps: f64,
     pub gbps: f64,
     pub error_rate: f64,
+    /// Jain's fairness index over per-target packet counts (1.0 = perfectly even)
+    pub target_fairness: f64,
 }
 
 impl StatsSnapshot {
@@ -103,35 +110,24 @@ impl StatsSnapshot {
 
     /// Convert to Prometheus format
     pub fn to_prometheus(&self, prefix: &str) -> String {
-        format!(
-            "# HELP {prefix}_packets_sent Total packets sent\n\
-             # TYPE {prefix}_packets_sent counter\n\
-             {prefix}_packets_sent {}\n\
-             # HELP {prefix}_bytes_sent Total bytes sent\n\
-             # TYPE {prefix}_bytes_sent counter\n\
-             {prefix}_bytes_sent {}\n\
-             # HELP {prefix}_errors Total errors\n\
-             # TYPE {prefix}_errors counter\n\
-             {prefix}_errors {}\n\
-             # HELP {prefix}_pps Current packets per second\n\
-             # TYPE {prefix}_pps gauge\n\
-             {prefix}_pps {:.2}\n\
-             # HELP {prefix}_gbps Current gigabits per second\n\
-             # TYPE {prefix}_gbps gauge\n\
-             {prefix}_gbps {:.4}\n",
-            self.packets_sent,
-            self.bytes_sent,
-            self.errors,
-            self.pps,
-            self.gbps,
-            prefix = prefix
-        )
+        prometheus_families(prefix, &[(None, self)])
+    }
+
+    /// Add another snapshot's counters, keeping the longer duration
+    fn merge(mut self, other: &StatsSnapshot) -> Self {
+        self.packets_sent += other.packets_sent;
+        self.bytes_sent += other.bytes_sent;
+        self.errors += other.errors;
+        self.dropped += other.dropped;
+        self.retransmits += other.retransmits;
+        let duration = self.duration.max(other.duration);
+        self.with_duration(duration)
     }
 
     /// Convert to JSON format
     pub fn to_json(&self) -> String {
         format!(
-            r#"{{"packets_sent":{},"bytes_sent":{},"errors":{},"dropped":{},"duration_secs":{:.3},"pps":{:.2},"bps":{:.2},"gbps":{:.6},"error_rate":{:.4}}}"#,
+            r#"{{"packets_sent":{},"bytes_sent":{},"errors":{},"dropped":{},"duration_secs":{:.3},"pps":{:.2},"bps":{:.2},"gbps":{:.6},"error_rate":{:.4},"target_fairness":{:.4}}}"#,
             self.packets_sent,
             self.bytes_sent,
             self.errors,
@@ -140,12 +136,378 @@ impl StatsSnapshot {
             self.pps,
             self.bps,
             self.gbps,
-            self.error_rate
+            self.error_rate,
+            self.target_fairness
         )
     }
 }
 
+/// Escape a Prometheus label value (backslash, double quote and newline)
+pub fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Prometheus text for one or more snapshots, one HELP/TYPE header per family
+///
+/// Series with a target are labelled `target="..."`, the others are bare.
+pub fn prometheus_families(prefix: &str, series: &[(Option<&str>, &StatsSnapshot)]) -> String {
+    type Value = fn(&StatsSnapshot) -> String;
+    let families: [(&str, &str, &str, Value); 5] = [
+        ("packets_sent", "Total packets sent", "counter", |s| {
+            s.packets_sent.to_string()
+        }),
+        ("bytes_sent", "Total bytes sent", "counter", |s| {
+            s.bytes_sent.to_string()
+        }),
+        ("errors", "Total errors", "counter", |s| {
+            s.errors.to_string()
+        }),
+        ("pps", "Current packets per second", "gauge", |s| {
+            format!("{:.2}", s.pps)
+        }),
+        ("gbps", "Current gigabits per second", "gauge", |s| {
+            format!("{:.4}", s.gbps)
+        }),
+    ];
+
+    let mut out = String::new();
+    for (name, help, kind, value) in families {
+        out.push_str(&format!(
+            "# HELP {prefix}_{name} {help}\n# TYPE {prefix}_{name} {kind}\n"
+        ));
+        for (target, snapshot) in series {
+            let labels = target
+                .map(|t| format!("{{target=\"{}\"}}", escape_label_value(t)))
+                .unwrap_or_default();
+            out.push_str(&format!("{prefix}_{name}{labels} {}\n", value(snapshot)));
+        }
+    }
+    out
+}
+
+/// Default `netstress_packet_size_bytes` bucket bounds
+pub const PACKET_SIZE_BUCKETS: &[f64] = &[
+    64.0, 128.0, 256.0, 512.0, 1024.0, 1472.0, 4096.0, 9000.0, 65535.0,
+];
+
+/// Default `netstress_send_latency_seconds` bucket bounds
+pub const SEND_LATENCY_BUCKETS: &[f64] = &[
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.1,
+];
+
+/// Lock-free histogram with fixed upper bounds, exported as a Prometheus histogram
+pub struct Histogram {
+    /// Sorted, finite upper bounds; `+Inf` is implicit
+    bounds: Vec<f64>,
+    /// Observations per bucket (not cumulative), one extra for `+Inf`
+    counts: Vec<AtomicU64>,
+    /// Sum of observations as `f64` bits
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Non-finite bounds are dropped; the rest are sorted and deduplicated
+    pub fn new(bounds: &[f64]) -> Self {
+        let mut bounds: Vec<f64> = bounds.iter().copied().filter(|b| b.is_finite()).collect();
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        Self {
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn observe(&self, value: f64) {
+        self.observe_n(value, 1);
+    }
+
+    /// Record `n` observations of `value`
+    #[inline]
+    pub fn observe_n(&self, value: f64, n: u64) {
+        if n == 0 || value.is_nan() {
+            return;
+        }
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[bucket].fetch_add(n, Ordering::Relaxed);
+        self.count.fetch_add(n, Ordering::Relaxed);
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value * n as f64).to_bits())
+            });
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self.counts[..self.bounds.len()]
+            .iter()
+            .map(|c| {
+                cumulative += c.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        HistogramSnapshot {
+            bounds: self.bounds.clone(),
+            buckets,
+            sum: f64::from_bits(self.sum.load(Ordering::Relaxed)),
+            // At least every finite bucket, even if a concurrent observe is half done
+            count: self.count.load(Ordering::Relaxed).max(cumulative),
+        }
+    }
+
+    pub fn reset(&self) {
+        for c in &self.counts {
+            c.store(0, Ordering::Relaxed);
+        }
+        self.sum.store(0f64.to_bits(), Ordering::Relaxed);
+        self.count.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time copy of a `Histogram`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistogramSnapshot {
+    pub bounds: Vec<f64>,
+    /// Cumulative count per bound, i.e. observations `<= bounds[i]`
+    pub buckets: Vec<u64>,
+    pub sum: f64,
+    /// All observations, the `+Inf` bucket
+    pub count: u64,
+}
+
+impl HistogramSnapshot {
+    /// Add another snapshot with the same bounds; mismatched bounds keep ours
+    fn merge(mut self, other: &HistogramSnapshot) -> Self {
+        if self.bounds == other.bounds {
+            for (a, b) in self.buckets.iter_mut().zip(&other.buckets) {
+                *a += b;
+            }
+            self.sum += other.sum;
+            self.count += other.count;
+        }
+        self
+    }
+}
+
+/// Everything a collector exports: counters plus histograms
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub stats: StatsSnapshot,
+    pub packet_size: HistogramSnapshot,
+    pub send_latency: HistogramSnapshot,
+}
+
+impl MetricsSnapshot {
+    fn merge(self, other: &MetricsSnapshot) -> Self {
+        Self {
+            stats: self.stats.merge(&other.stats),
+            packet_size: self.packet_size.merge(&other.packet_size),
+            send_latency: self.send_latency.merge(&other.send_latency),
+        }
+    }
+}
+
+/// One Prometheus histogram family: `_bucket` series including `+Inf`, then `_sum` and `_count`
+pub fn prometheus_histogram(
+    name: &str,
+    help: &str,
+    series: &[(Option<&str>, &HistogramSnapshot)],
+) -> String {
+    let mut out = format!("# HELP {name} {help}\n# TYPE {name} histogram\n");
+    for (target, histogram) in series {
+        let target = target
+            .map(|t| format!("target=\"{}\",", escape_label_value(t)))
+            .unwrap_or_default();
+        let plain = target.trim_end_matches(',');
+        let plain = if plain.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", plain)
+        };
+        for (bound, count) in histogram.bounds.iter().zip(&histogram.buckets) {
+            out.push_str(&format!(
+                "{name}_bucket{{{target}le=\"{bound}\"}} {count}\n"
+            ));
+        }
+        out.push_str(&format!(
+            "{name}_bucket{{{target}le=\"+Inf\"}} {}\n",
+            histogram.count
+        ));
+        out.push_str(&format!("{name}_sum{plain} {}\n", histogram.sum));
+        out.push_str(&format!("{name}_count{plain} {}\n", histogram.count));
+    }
+    out
+}
+
+/// Full Prometheus text for one or more collectors: counters, gauges and histograms
+pub fn prometheus_text(prefix: &str, series: &[(Option<&str>, &MetricsSnapshot)]) -> String {
+    let stats: Vec<_> = series.iter().map(|(t, m)| (*t, &m.stats)).collect();
+    let sizes: Vec<_> = series.iter().map(|(t, m)| (*t, &m.packet_size)).collect();
+    let latency: Vec<_> = series.iter().map(|(t, m)| (*t, &m.send_latency)).collect();
+
+    let mut out = prometheus_families(prefix, &stats);
+    out.push_str(&prometheus_histogram(
+        &format!("{prefix}_packet_size_bytes"),
+        "Size of sent packets",
+        &sizes,
+    ));
+    out.push_str(&prometheus_histogram(
+        &format!("{prefix}_send_latency_seconds"),
+        "Time spent in the send call per packet",
+        &latency,
+    ));
+    out
+}
+
+/// Jain's fairness index: (sum x)^2 / (n * sum x^2), 1.0 when all shares are equal
+///
+/// Ranges from 1/n (everything on one target) to 1.0; no load counts as fair.
+pub fn jain_fairness_index(values: &[u64]) -> f64 {
+    let sum: f64 = values.iter().map(|&v| v as f64).sum();
+    let sum_sq: f64 = values.iter().map(|&v| (v as f64) * (v as f64)).sum();
+    if sum_sq == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (values.len() as f64 * sum_sq)
+}
+
+/// Bits of precision per `PpsHistogram` bucket: exact below 2^6, 1/32 resolution above
+const HISTOGRAM_SUB_BITS: u32 = 6;
+const HISTOGRAM_HALF: usize = 1 << (HISTOGRAM_SUB_BITS - 1);
+const HISTOGRAM_BUCKETS: usize = (64 - HISTOGRAM_SUB_BITS as usize + 2) * HISTOGRAM_HALF;
+
+/// Log-linear (HDR style) histogram of per-second packet rates
+///
+/// Values below 64 get their own bucket; above that each power of two is split
+/// into 32 buckets, so a reported percentile is within ~3% of the real sample.
+#[derive(Debug, Clone)]
+pub struct PpsHistogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl PpsHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BUCKETS],
+            total: 0,
+        }
+    }
+
+    #[inline]
+    fn bucket(value: u64) -> usize {
+        let shift = (64 - value.leading_zeros()).saturating_sub(HISTOGRAM_SUB_BITS);
+        shift as usize * HISTOGRAM_HALF + (value >> shift) as usize
+    }
+
+    /// Smallest value that lands in `bucket`
+    fn bucket_floor(bucket: usize) -> u64 {
+        if bucket < 2 * HISTOGRAM_HALF {
+            return bucket as u64;
+        }
+        let shift = bucket / HISTOGRAM_HALF - 1;
+        ((bucket - shift * HISTOGRAM_HALF) as u64) << shift
+    }
+
+    pub fn record(&mut self, value: u64) {
+        self.counts[Self::bucket(value)] += 1;
+        self.total += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Value at quantile `q` (0.0-1.0), `None` without samples
+    pub fn percentile(&self, q: f64) -> Option<u64> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(Self::bucket_floor(bucket));
+            }
+        }
+        None
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+        self.total = 0;
+    }
+}
+
+impl Default for PpsHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `PpsHistogram` buckets with atomic counts, so a hot thread records without a lock
+///
+/// Take a `snapshot` to read percentiles.
+pub struct AtomicLogHistogram {
+    counts: Vec<AtomicU64>,
+}
+
+impl AtomicLogHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: (0..HISTOGRAM_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Record `n` observations of `value`
+    #[inline]
+    pub fn record_n(&self, value: u64, n: u64) {
+        self.counts[PpsHistogram::bucket(value)].fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> PpsHistogram {
+        let counts: Vec<u64> = self
+            .counts
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect();
+        PpsHistogram {
+            total: counts.iter().sum(),
+            counts,
+        }
+    }
+
+    pub fn reset(&self) {
+        for c in &self.counts {
+            c.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for AtomicLogHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Per-thread statistics for scalable counting
+///
+/// Aligned to a cache line so neighbouring threads' slots never share one.
+#[repr(align(64))]
 pub struct ThreadStats {
     /// Thread ID
     pub thread_id: usize,
@@ -179,6 +541,11 @@ impl ThreadStats {
         self.stats.record_error();
     }
 
+    #[inline]
+    pub fn record_errors(&self, count: u64) {
+        self.stats.record_errors(count);
+    }
+
     pub fn snapshot(&self) -> StatsSnapshot {
         self.stats.snapshot()
     }
@@ -190,6 +557,8 @@ pub struct StatsCollector {
     global: Arc<AtomicStats>,
     /// Per-thread stats
     thread_stats: RwLock<Vec<Arc<ThreadStats>>>,
+    /// Per-target stats for multi-target campaigns
+    target_stats: RwLock<Vec<(String, Arc<AtomicStats>)>>,
     /// Start time
     start_time: Instant,
     /// Running flag
@@ -198,6 +567,12 @@ pub struct StatsCollector {
     update_interval: Duration,
     /// History for rate calculation
     history: RwLock<Vec<(Instant, StatsSnapshot)>>,
+    /// Per-second rates recorded by a background sampler
+    pps_samples: Mutex<PpsHistogram>,
+    /// Highest per-second rate sampled
+    peak_pps: AtomicU64,
+    packet_size: Histogram,
+    send_latency: Histogram,
 }
 
 impl StatsCollector {
@@ -205,10 +580,41 @@ impl StatsCollector {
         Self {
             global: Arc::new(AtomicStats::new()),
             thread_stats: RwLock::new(Vec::new()),
+            target_stats: RwLock::new(Vec::new()),
             start_time: Instant::now(),
             running: AtomicBool::new(false),
             update_interval: Duration::from_millis(100),
             history: RwLock::new(Vec::with_capacity(100)),
+            pps_samples: Mutex::new(PpsHistogram::new()),
+            peak_pps: AtomicU64::new(0),
+            packet_size: Histogram::new(PACKET_SIZE_BUCKETS),
+            send_latency: Histogram::new(SEND_LATENCY_BUCKETS),
+        }
+    }
+
+    /// Replace the packet size and send latency bucket bounds
+    pub fn with_histogram_buckets(mut self, packet_size: &[f64], send_latency: &[f64]) -> Self {
+        self.packet_size = Histogram::new(packet_size);
+        self.send_latency = Histogram::new(send_latency);
+        self
+    }
+
+    /// Packet size histogram, in bytes
+    pub fn packet_size(&self) -> &Histogram {
+        &self.packet_size
+    }
+
+    /// Per-packet send call latency histogram, in seconds
+    pub fn send_latency(&self) -> &Histogram {
+        &self.send_latency
+    }
+
+    /// Counters plus histograms, as exported to Prometheus
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            stats: self.snapshot(),
+            packet_size: self.packet_size.snapshot(),
+            send_latency: self.send_latency.snapshot(),
         }
     }
 
@@ -219,6 +625,65 @@ impl StatsCollector {
         stats
     }
 
+    /// Stats slot for a thread id, created on first use and reused afterwards
+    pub fn thread_slot(&self, thread_id: usize) -> Arc<ThreadStats> {
+        if let Some(stats) = self
+            .thread_stats
+            .read()
+            .iter()
+            .find(|s| s.thread_id == thread_id)
+        {
+            return Arc::clone(stats);
+        }
+        let mut slots = self.thread_stats.write();
+        if let Some(stats) = slots.iter().find(|s| s.thread_id == thread_id) {
+            return Arc::clone(stats);
+        }
+        let stats = Arc::new(ThreadStats::new(thread_id));
+        slots.push(Arc::clone(&stats));
+        stats
+    }
+
+    /// Per-thread slots ordered by thread id
+    pub fn thread_stats(&self) -> Vec<Arc<ThreadStats>> {
+        let mut slots = self.thread_stats.read().clone();
+        slots.sort_by_key(|s| s.thread_id);
+        slots
+    }
+
+    /// Create (or reuse) stats attributed to a target
+    pub fn create_target_stats(&self, target: impl Into<String>) -> Arc<AtomicStats> {
+        let target = target.into();
+        let mut targets = self.target_stats.write();
+        if let Some((_, stats)) = targets.iter().find(|(t, _)| *t == target) {
+            return Arc::clone(stats);
+        }
+        let stats = Arc::new(AtomicStats::new());
+        targets.push((target, Arc::clone(&stats)));
+        stats
+    }
+
+    /// Per-target snapshots in registration order
+    pub fn target_snapshots(&self) -> Vec<(String, StatsSnapshot)> {
+        let elapsed = self.start_time.elapsed();
+        self.target_stats
+            .read()
+            .iter()
+            .map(|(target, stats)| (target.clone(), stats.snapshot().with_duration(elapsed)))
+            .collect()
+    }
+
+    /// Jain's fairness index over per-target packet counts
+    pub fn target_fairness(&self) -> f64 {
+        let counts: Vec<u64> = self
+            .target_stats
+            .read()
+            .iter()
+            .map(|(_, stats)| stats.packets_sent.load(Ordering::Relaxed))
+            .collect();
+        jain_fairness_index(&counts)
+    }
+
     /// Start collecting
     pub fn start(&self) {
         self.running.store(true, Ordering::SeqCst);
@@ -248,6 +713,9 @@ impl StatsCollector {
         total.bytes_sent += global.bytes_sent;
         total.errors += global.errors;
 
+        // Per-target stats attribute the same packets, so they only feed fairness
+        total.target_fairness = self.target_fairness();
+
         total.with_duration(self.start_time.elapsed())
     }
 
@@ -281,6 +749,33 @@ impl StatsCollector {
         }
     }
 
+    /// Record one per-second rate sample
+    ///
+    /// Called from a sampling thread, never from the send path.
+    pub fn record_pps_sample(&self, pps: u64) {
+        self.pps_samples.lock().record(pps);
+        self.peak_pps.fetch_max(pps, Ordering::Relaxed);
+    }
+
+    /// Highest sampled rate, `None` before the first sample
+    pub fn peak_pps(&self) -> Option<u64> {
+        if self.pps_samples.lock().is_empty() {
+            return None;
+        }
+        Some(self.peak_pps.load(Ordering::Relaxed))
+    }
+
+    /// Sampled rate at quantile `q` (0.0-1.0), `None` before the first sample
+    pub fn pps_percentile(&self, q: f64) -> Option<u64> {
+        self.pps_samples.lock().percentile(q)
+    }
+
+    /// Forget rate samples, e.g. when a new run starts
+    pub fn clear_pps_samples(&self) {
+        self.pps_samples.lock().clear();
+        self.peak_pps.store(0, Ordering::Relaxed);
+    }
+
     /// Get global stats for direct recording
     pub fn global(&self) -> &Arc<AtomicStats> {
         &self.global
@@ -292,12 +787,18 @@ impl StatsCollector {
         for thread_stats in self.thread_stats.read().iter() {
             thread_stats.stats.reset();
         }
+        for (_, stats) in self.target_stats.read().iter() {
+            stats.reset();
+        }
         self.history.write().clear();
+        self.clear_pps_samples();
+        self.packet_size.reset();
+        self.send_latency.reset();
     }
 
     /// Get Prometheus metrics
     pub fn prometheus_metrics(&self) -> String {
-        self.snapshot().to_prometheus("netstress")
+        prometheus_text("netstress", &[(None, &self.metrics_snapshot())])
     }
 
     /// Get JSON metrics
@@ -312,6 +813,88 @@ impl Default for StatsCollector {
     }
 }
 
+/// Process-wide list of live collectors, each labelled with what it measures
+///
+/// Holds weak references, so a collector drops out once its owner is gone.
+pub struct CollectorRegistry {
+    entries: Mutex<Vec<(String, Weak<StatsCollector>)>>,
+}
+
+impl CollectorRegistry {
+    pub const fn new() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registry shared by every engine in the process
+    pub fn global() -> &'static CollectorRegistry {
+        static GLOBAL: CollectorRegistry = CollectorRegistry::new();
+        &GLOBAL
+    }
+
+    pub fn register(&self, label: impl Into<String>, collector: &Arc<StatsCollector>) {
+        let mut entries = self.entries.lock();
+        entries.retain(|(_, weak)| weak.strong_count() > 0);
+        entries.push((label.into(), Arc::downgrade(collector)));
+    }
+
+    /// Snapshots of the live collectors in registration order
+    ///
+    /// Collectors sharing a label are summed into one series.
+    pub fn snapshots(&self) -> Vec<(String, StatsSnapshot)> {
+        self.gather()
+            .into_iter()
+            .map(|(label, metrics)| (label, metrics.stats))
+            .collect()
+    }
+
+    /// Counters and histograms per label, same-label collectors summed
+    fn gather(&self) -> Vec<(String, MetricsSnapshot)> {
+        let mut entries = self.entries.lock();
+        entries.retain(|(_, weak)| weak.strong_count() > 0);
+
+        let mut merged: Vec<(String, MetricsSnapshot)> = Vec::new();
+        for (label, weak) in entries.iter() {
+            let Some(collector) = weak.upgrade() else {
+                continue;
+            };
+            let snapshot = collector.metrics_snapshot();
+            match merged.iter_mut().find(|(l, _)| l == label) {
+                Some((_, total)) => *total = std::mem::take(total).merge(&snapshot),
+                None => merged.push((label.clone(), snapshot)),
+            }
+        }
+        merged
+    }
+
+    /// Prometheus metrics for every live collector, labelled by target
+    pub fn prometheus_metrics(&self) -> String {
+        let gathered = self.gather();
+        let series: Vec<(Option<&str>, &MetricsSnapshot)> = gathered
+            .iter()
+            .map(|(label, metrics)| (Some(label.as_str()), metrics))
+            .collect();
+        prometheus_text("netstress", &series)
+    }
+
+    /// JSON object mapping each label to its snapshot
+    pub fn json_metrics(&self) -> String {
+        let body: Vec<String> = self
+            .snapshots()
+            .iter()
+            .map(|(label, snapshot)| format!("{:?}:{}", label, snapshot.to_json()))
+            .collect();
+        format!("{{{}}}", body.join(","))
+    }
+}
+
+impl Default for CollectorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Real-time statistics reporter
 pub struct StatsReporter {
     collector: Arc<StatsCollector>,
@@ -357,6 +940,65 @@ mod tests {
     use std::thread;
     use std::time::Duration;
 
+    #[test]
+    fn test_pps_percentiles_from_ramp() {
+        let collector = StatsCollector::new();
+        assert_eq!(collector.peak_pps(), None);
+        assert_eq!(collector.pps_percentile(0.5), None);
+
+        // One sample per second ramping 1k, 2k, ... 100k PPS
+        for step in 1..=100u64 {
+            collector.record_pps_sample(step * 1000);
+        }
+        assert_eq!(collector.peak_pps(), Some(100_000));
+
+        for (q, expected) in [(0.5, 50_000u64), (0.95, 95_000), (0.99, 99_000)] {
+            let got = collector.pps_percentile(q).unwrap();
+            // Buckets are 1/32 of a power of two wide and report their floor
+            assert!(
+                got <= expected && expected - got <= expected / 32,
+                "p{} = {}, expected ~{}",
+                q * 100.0,
+                got,
+                expected
+            );
+        }
+
+        collector.clear_pps_samples();
+        assert_eq!(collector.peak_pps(), None);
+    }
+
+    #[test]
+    fn test_pps_histogram_buckets() {
+        // Small values are exact and bucket floors round-trip
+        for value in [0u64, 1, 63, 64, 65, 127, 128, 1 << 40, u64::MAX] {
+            let bucket = PpsHistogram::bucket(value);
+            assert!(bucket < HISTOGRAM_BUCKETS);
+            let floor = PpsHistogram::bucket_floor(bucket);
+            assert!(floor <= value && value - floor <= value / 32, "{}", value);
+            assert_eq!(PpsHistogram::bucket(floor), bucket);
+        }
+        assert_eq!(PpsHistogram::bucket_floor(PpsHistogram::bucket(63)), 63);
+    }
+
+    #[test]
+    fn test_atomic_log_histogram_matches_pps_histogram() {
+        let atomic = AtomicLogHistogram::new();
+        let mut plain = PpsHistogram::new();
+        for value in [3u64, 70, 1_000_000, 1_000_000, 5_000_000] {
+            atomic.record_n(value, 2);
+            plain.record(value);
+            plain.record(value);
+        }
+        let snapshot = atomic.snapshot();
+        for q in [0.0, 0.5, 0.9, 1.0] {
+            assert_eq!(snapshot.percentile(q), plain.percentile(q));
+        }
+
+        atomic.reset();
+        assert!(atomic.snapshot().is_empty());
+    }
+
     #[test]
     fn test_atomic_stats() {
         let stats = AtomicStats::new();
@@ -472,6 +1114,55 @@ mod tests {
         assert_eq!(snap2.packets_sent, 0);
     }
 
+    #[test]
+    fn test_thread_slots_are_reused_and_padded() {
+        assert_eq!(std::mem::align_of::<ThreadStats>(), 64);
+        assert_eq!(std::mem::size_of::<ThreadStats>() % 64, 0);
+
+        let collector = StatsCollector::new();
+        collector.thread_slot(1).record_sent(10);
+        collector.thread_slot(0).record_sent(20);
+        collector.thread_slot(1).record_sent(10);
+
+        let slots = collector.thread_stats();
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots[0].thread_id, 0);
+        assert_eq!(slots[1].snapshot().packets_sent, 2);
+        assert_eq!(collector.snapshot().bytes_sent, 40);
+    }
+
+    #[test]
+    fn test_target_fairness_index() {
+        let collector = StatsCollector::new();
+        assert_eq!(collector.snapshot().target_fairness, 1.0);
+
+        let targets: Vec<_> = (0..4)
+            .map(|i| collector.create_target_stats(format!("10.0.0.{}", i + 1)))
+            .collect();
+
+        // Nearly everything lands on one target
+        targets[0].record_batch_sent(9700, 0);
+        for t in &targets[1..] {
+            t.record_batch_sent(100, 0);
+        }
+        let skewed = collector.snapshot().target_fairness;
+        assert!(skewed < 0.3, "skewed index {}", skewed);
+
+        collector.reset();
+        for (i, t) in targets.iter().enumerate() {
+            t.record_batch_sent(1000 + i as u64, 0);
+        }
+        let even = collector.snapshot().target_fairness;
+        assert!(even > 0.999, "even index {}", even);
+        assert!(collector
+            .json_metrics()
+            .contains(r#""target_fairness":1.0000"#));
+
+        // Same target name reuses its counters
+        collector.create_target_stats("10.0.0.1").record_sent(0);
+        assert_eq!(collector.target_snapshots()[0].1.packets_sent, 1001);
+    }
+
     #[test]
     fn test_stats_snapshot_with_duration() {
         let snap = StatsSnapshot {
@@ -506,6 +1197,150 @@ mod tests {
         assert!(prom.contains("test_gbps 0.0008"));
     }
 
+    #[test]
+    fn test_collector_registry_labels_targets() {
+        let registry = CollectorRegistry::new();
+        let first = Arc::new(StatsCollector::new());
+        let second = Arc::new(StatsCollector::new());
+        registry.register("10.0.0.1:80", &first);
+        registry.register("10.0.0.2:53", &second);
+
+        first.thread_slot(0).record_batch_sent(10, 1000);
+        second.thread_slot(0).record_batch_sent(3, 300);
+
+        let prom = registry.prometheus_metrics();
+        assert_eq!(
+            prom.matches("# TYPE netstress_packets_sent counter")
+                .count(),
+            1
+        );
+        assert!(prom.contains("netstress_packets_sent{target=\"10.0.0.1:80\"} 10\n"));
+        assert!(prom.contains("netstress_packets_sent{target=\"10.0.0.2:53\"} 3\n"));
+
+        // Two engines on the same target add up into one series
+        let third = Arc::new(StatsCollector::new());
+        third.thread_slot(0).record_batch_sent(5, 500);
+        registry.register("10.0.0.1:80", &third);
+        assert!(registry
+            .prometheus_metrics()
+            .contains("netstress_packets_sent{target=\"10.0.0.1:80\"} 15\n"));
+
+        // Dropped collectors disappear
+        drop(first);
+        drop(third);
+        let prom = registry.prometheus_metrics();
+        assert!(!prom.contains("10.0.0.1:80"));
+        assert!(registry
+            .json_metrics()
+            .starts_with("{\"10.0.0.2:53\":{\"packets_sent\":3,"));
+
+        assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    /// Metric name, labels and value of one exposition line
+    type Sample = (String, Vec<(String, String)>, f64);
+
+    /// Minimal Prometheus text-format parser
+    fn parse_prometheus(text: &str) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for line in text
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        {
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let value = match value {
+                "+Inf" => f64::INFINITY,
+                v => v.parse().unwrap(),
+            };
+            let (name, labels) = match series.split_once('{') {
+                None => (series.to_string(), Vec::new()),
+                Some((name, rest)) => {
+                    let mut labels = Vec::new();
+                    let mut chars = rest.strip_suffix('}').unwrap().chars().peekable();
+                    while chars.peek().is_some() {
+                        let key: String = chars.by_ref().take_while(|&c| c != '=').collect();
+                        assert_eq!(chars.next(), Some('"'));
+                        let mut value = String::new();
+                        while let Some(c) = chars.next() {
+                            match c {
+                                '\\' => match chars.next().unwrap() {
+                                    'n' => value.push('\n'),
+                                    c => value.push(c),
+                                },
+                                '"' => break,
+                                c => value.push(c),
+                            }
+                        }
+                        chars.next_if_eq(&',');
+                        labels.push((key, value));
+                    }
+                    (name.to_string(), labels)
+                }
+            };
+            samples.push((name, labels, value));
+        }
+        samples
+    }
+
+    #[test]
+    fn test_prometheus_histograms() {
+        let collector = Arc::new(
+            StatsCollector::new()
+                .with_histogram_buckets(&[1500.0, 64.0, f64::INFINITY, 512.0], &[0.001]),
+        );
+        collector.packet_size().observe_n(60.0, 10);
+        collector.packet_size().observe_n(512.0, 5);
+        collector.packet_size().observe(9000.0);
+        collector.send_latency().observe_n(0.000_5, 4);
+
+        let registry = CollectorRegistry::new();
+        registry.register("odd \"host\"\\:80", &collector);
+        let text = registry.prometheus_metrics();
+        assert!(text.contains("# TYPE netstress_packet_size_bytes histogram\n"));
+        assert!(text.contains("# TYPE netstress_send_latency_seconds histogram\n"));
+
+        let samples = parse_prometheus(&text);
+        for family in [
+            "netstress_packet_size_bytes",
+            "netstress_send_latency_seconds",
+        ] {
+            let buckets: Vec<(f64, f64)> = samples
+                .iter()
+                .filter(|(name, _, _)| *name == format!("{}_bucket", family))
+                .map(|(_, labels, value)| {
+                    assert_eq!(
+                        labels[0],
+                        ("target".to_string(), "odd \"host\"\\:80".to_string())
+                    );
+                    let le = &labels.iter().find(|(k, _)| k == "le").unwrap().1;
+                    let le = if le == "+Inf" {
+                        f64::INFINITY
+                    } else {
+                        le.parse().unwrap()
+                    };
+                    (le, *value)
+                })
+                .collect();
+            // Bounds ascend, counts never decrease, and +Inf closes the family
+            assert!(buckets
+                .windows(2)
+                .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1));
+            assert_eq!(buckets.last().unwrap().0, f64::INFINITY);
+            let count = samples
+                .iter()
+                .find(|(name, _, _)| *name == format!("{}_count", family))
+                .unwrap()
+                .2;
+            assert_eq!(buckets.last().unwrap().1, count);
+        }
+
+        let sizes = collector.packet_size().snapshot();
+        assert_eq!(sizes.bounds, vec![64.0, 512.0, 1500.0]);
+        assert_eq!(sizes.buckets, vec![10, 15, 15]);
+        assert_eq!(sizes.count, 16);
+        assert_eq!(sizes.sum, 60.0 * 10.0 + 512.0 * 5.0 + 9000.0);
+    }
+
     #[test]
     fn test_json_format() {
         let snap = StatsSnapshot {
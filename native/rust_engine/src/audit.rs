@@ -1,14 +1,32 @@
 //! Tamper-evident audit logging with hash chains
 //! Implements secure logging for compliance and forensics
 
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use parking_lot::RwLock;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
 use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write, BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use parking_lot::RwLock;
-use sha2::{Sha256, Digest};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// ChaCha20-Poly1305 key length in bytes
+const KEY_LEN: usize = 32;
+/// ChaCha20-Poly1305 nonce length in bytes
+const NONCE_LEN: usize = 12;
+/// Poly1305 tag length in bytes
+const TAG_LEN: usize = 16;
+
+/// Associated data bound to every encrypted audit record
+const RECORD_AAD: &[u8] = b"netstress-audit-v1";
+/// Longest sealed record read back; a longer length prefix is corruption
+/// (or not an encrypted log at all) rather than a record cut short
+const MAX_RECORD_LEN: usize = 16 << 20;
 
 /// Audit log entry
 #[derive(Debug, Clone)]
@@ -48,6 +66,8 @@ pub enum AuditEventType {
     Error,
     /// Statistics snapshot
     StatsSnapshot,
+    /// Audit file segment sealed before rotation
+    SegmentEnd,
     /// Custom event
     Custom,
 }
@@ -64,6 +84,7 @@ impl AuditEventType {
             AuditEventType::ConfigChanged => "CONFIG_CHANGED",
             AuditEventType::Error => "ERROR",
             AuditEventType::StatsSnapshot => "STATS_SNAPSHOT",
+            AuditEventType::SegmentEnd => "SEGMENT_END",
             AuditEventType::Custom => "CUSTOM",
         }
     }
@@ -79,9 +100,66 @@ impl AuditEventType {
             "CONFIG_CHANGED" => AuditEventType::ConfigChanged,
             "ERROR" => AuditEventType::Error,
             "STATS_SNAPSHOT" => AuditEventType::StatsSnapshot,
+            "SEGMENT_END" => AuditEventType::SegmentEnd,
             _ => AuditEventType::Custom,
         }
     }
+
+    /// Syslog severity (RFC 5424) for remote sinks
+    pub fn syslog_severity(&self) -> u8 {
+        match self {
+            AuditEventType::EmergencyStop => 1,  // alert
+            AuditEventType::Error => 3,          // err
+            AuditEventType::TargetRejected => 4, // warning
+            AuditEventType::RateLimitChanged | AuditEventType::ConfigChanged => 5, // notice
+            AuditEventType::StatsSnapshot => 7,  // debug
+            _ => 6,                              // info
+        }
+    }
+}
+
+/// Syslog facility used by `AuditLogger::with_syslog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogFacility {
+    User = 1,
+    Daemon = 3,
+    Auth = 4,
+    AuthPriv = 10,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+impl SyslogFacility {
+    /// Numeric facility code
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Parse a facility name such as `"local0"` or `"authpriv"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let facility = match name.to_ascii_lowercase().as_str() {
+            "user" => SyslogFacility::User,
+            "daemon" => SyslogFacility::Daemon,
+            "auth" => SyslogFacility::Auth,
+            "authpriv" => SyslogFacility::AuthPriv,
+            "local0" => SyslogFacility::Local0,
+            "local1" => SyslogFacility::Local1,
+            "local2" => SyslogFacility::Local2,
+            "local3" => SyslogFacility::Local3,
+            "local4" => SyslogFacility::Local4,
+            "local5" => SyslogFacility::Local5,
+            "local6" => SyslogFacility::Local6,
+            "local7" => SyslogFacility::Local7,
+            _ => return None,
+        };
+        Some(facility)
+    }
 }
 
 impl AuditEntry {
@@ -160,74 +238,885 @@ impl AuditEntry {
     }
 }
 
-/// Tamper-evident audit logger
-pub struct AuditLogger {
+/// Size/age based rotation of segmented audit files
+///
+/// Segments are named `<stem>-<index>.<ext>` with a zero-padded index so that
+/// sorting by file name gives chain order.
+struct SegmentRotation {
+    dir: PathBuf,
+    stem: String,
+    extension: String,
+    index: u64,
+    max_size: u64,
+    max_age: Duration,
+    opened_at: Instant,
+}
+
+impl SegmentRotation {
+    fn segment_path(&self, index: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}-{:06}.{}", self.stem, index, self.extension))
+    }
+
+    /// An empty segment is never sealed, whatever its age
+    fn is_due(&self, current_size: u64) -> bool {
+        current_size > 0
+            && (current_size >= self.max_size || self.opened_at.elapsed() >= self.max_age)
+    }
+
+    /// Open the next segment, advancing the index only on success
+    fn open_next(&mut self) -> std::io::Result<(File, PathBuf)> {
+        let path = self.segment_path(self.index + 1);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.index += 1;
+        self.opened_at = Instant::now();
+        Ok((file, path))
+    }
+}
+
+/// Parse `<stem>-<index>.<ext>` segment file names
+fn parse_segment_name(path: &Path) -> Option<(String, u64, String)> {
+    let extension = path.extension()?.to_str()?.to_string();
+    let file_stem = path.file_stem()?.to_str()?;
+    let (stem, index) = file_stem.rsplit_once('-')?;
+    if index.len() < 6 || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((stem.to_string(), index.parse().ok()?, extension))
+}
+
+/// List segment files in a directory in chain order
+fn list_segments(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut segments: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && parse_segment_name(p).is_some())
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+/// Location and key of an encrypted audit file
+struct EncryptedFile {
+    path: PathBuf,
+    key: [u8; KEY_LEN],
+}
+
+/// Seal one entry as `u32 LE length | nonce | ciphertext | tag`
+fn encrypt_record(key: &[u8; KEY_LEN], entry: &AuditEntry) -> Vec<u8> {
+    // Random 96-bit nonces: collisions are negligible at audit log volumes
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let json = entry.to_json();
+    let payload = Payload {
+        msg: json.as_bytes(),
+        aad: RECORD_AAD,
+    };
+    let sealed = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .expect("audit record within ChaCha20-Poly1305 length limit");
+
+    let mut record = Vec::with_capacity(4 + NONCE_LEN + sealed.len());
+    record.extend_from_slice(&((NONCE_LEN + sealed.len()) as u32).to_le_bytes());
+    record.extend_from_slice(&nonce);
+    record.extend_from_slice(&sealed);
+    record
+}
+
+/// Intact prefix of an encrypted audit file
+struct EncryptedRead {
+    entries: Vec<AuditEntry>,
+    /// Bytes up to the end of the last intact record
+    intact_len: u64,
+    /// A whole record failed to authenticate (wrong key or tampering), as
+    /// opposed to a record cut short at the end of the file
+    auth_failed: bool,
+    /// What follows the intact records is a final record cut short by a
+    /// crash: a partial length prefix, or a valid one running past the end
+    torn_tail: bool,
+}
+
+fn read_encrypted(path: &Path, key: &[u8; KEY_LEN]) -> std::io::Result<EncryptedRead> {
+    let data = std::fs::read(path)?;
+    let mut read = EncryptedRead {
+        entries: Vec::new(),
+        intact_len: 0,
+        auth_failed: false,
+        torn_tail: false,
+    };
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let mut rest = &data[..];
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            read.torn_tail = true;
+            break;
+        }
+        let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if !(NONCE_LEN + TAG_LEN..=MAX_RECORD_LEN).contains(&len) {
+            break;
+        }
+        if rest.len() < 4 + len {
+            read.torn_tail = true;
+            break;
+        }
+        let (nonce, sealed) = rest[4..4 + len].split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: sealed,
+            aad: RECORD_AAD,
+        };
+        let entry = cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .ok()
+            .and_then(|plain| String::from_utf8(plain).ok())
+            .and_then(|json| AuditEntry::from_json(&json));
+        match entry {
+            Some(entry) => read.entries.push(entry),
+            None => {
+                read.auth_failed = true;
+                break;
+            }
+        }
+        rest = &rest[4 + len..];
+        read.intact_len += (4 + len) as u64;
+    }
+    Ok(read)
+}
+
+/// Entries buffered for a remote sink before new ones are dropped
+const REMOTE_QUEUE_CAPACITY: usize = 4096;
+/// Upper bound on connecting or writing to a remote sink
+const REMOTE_IO_TIMEOUT: Duration = Duration::from_secs(1);
+/// Wait between reconnect attempts to a TCP syslog server
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Structured data ID for audit chain fields (RFC 5424 example enterprise number)
+const SYSLOG_SD_ID: &str = "audit@32473";
+const SYSLOG_APP_NAME: &str = "netstress";
+
+/// Where a remote sink delivers formatted entries
+enum RemoteTransport {
+    Udp(UdpSocket),
+    /// Connected lazily so a dead server never blocks the logger
+    Tcp {
+        addr: SocketAddr,
+        stream: Option<TcpStream>,
+        retry_at: Instant,
+    },
+    #[cfg(target_os = "linux")]
+    Journald(std::os::unix::net::UnixDatagram),
+}
+
+impl RemoteTransport {
+    fn send(&mut self, message: &[u8]) -> std::io::Result<()> {
+        match self {
+            RemoteTransport::Udp(socket) => socket.send(message).map(|_| ()),
+            RemoteTransport::Tcp {
+                addr,
+                stream,
+                retry_at,
+            } => {
+                if stream.is_none() {
+                    if Instant::now() < *retry_at {
+                        return Err(std::io::ErrorKind::NotConnected.into());
+                    }
+                    match TcpStream::connect_timeout(addr, REMOTE_IO_TIMEOUT) {
+                        Ok(connected) => {
+                            connected.set_write_timeout(Some(REMOTE_IO_TIMEOUT))?;
+                            *stream = Some(connected);
+                        }
+                        Err(e) => {
+                            *retry_at = Instant::now() + RECONNECT_DELAY;
+                            return Err(e);
+                        }
+                    }
+                }
+                let connected = stream.as_mut().expect("connected above");
+                // Octet-counting framing (RFC 6587)
+                let framed = [format!("{} ", message.len()).as_bytes(), message].concat();
+                connected.write_all(&framed).inspect_err(|_| {
+                    *stream = None;
+                    *retry_at = Instant::now() + RECONNECT_DELAY;
+                })
+            }
+            #[cfg(target_os = "linux")]
+            RemoteTransport::Journald(socket) => socket.send(message).map(|_| ()),
+        }
+    }
+}
+
+/// How entries are encoded for a remote sink
+enum RemoteFormat {
+    Syslog {
+        facility: SyslogFacility,
+        hostname: String,
+    },
+    #[cfg(target_os = "linux")]
+    Journald,
+}
+
+impl RemoteFormat {
+    fn encode(&self, entry: &AuditEntry) -> Vec<u8> {
+        match self {
+            RemoteFormat::Syslog { facility, hostname } => {
+                syslog_message(*facility, hostname, entry).into_bytes()
+            }
+            #[cfg(target_os = "linux")]
+            RemoteFormat::Journald => journald_message(entry),
+        }
+    }
+}
+
+/// Off-box audit sink fed through a bounded queue
+///
+/// Entries are encoded on the logging thread and handed to a sender thread;
+/// when the queue is full or delivery fails the entry is dropped and counted
+/// instead of stalling the caller.
+struct RemoteSink {
+    format: RemoteFormat,
+    queue: Option<crossbeam::channel::Sender<Vec<u8>>>,
+    dropped: Arc<AtomicU64>,
+    sender: Option<std::thread::JoinHandle<()>>,
+}
+
+impl RemoteSink {
+    fn spawn(format: RemoteFormat, mut transport: RemoteTransport) -> std::io::Result<Self> {
+        let (tx, rx) = crossbeam::channel::bounded::<Vec<u8>>(REMOTE_QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let sender = {
+            let dropped = Arc::clone(&dropped);
+            std::thread::Builder::new()
+                .name("audit-remote".into())
+                .spawn(move || {
+                    for message in rx.iter() {
+                        if transport.send(&message).is_err() {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                })?
+        };
+        Ok(Self {
+            format,
+            queue: Some(tx),
+            dropped,
+            sender: Some(sender),
+        })
+    }
+
+    fn submit(&self, entry: &AuditEntry) {
+        let queued = self
+            .queue
+            .as_ref()
+            .is_some_and(|queue| queue.try_send(self.format.encode(entry)).is_ok());
+        if !queued {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for RemoteSink {
+    fn drop(&mut self) {
+        // Closing the queue lets the sender drain what is left and exit
+        self.queue.take();
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+/// RFC 5424 line: `<PRI>1 TIMESTAMP HOST APP PROCID MSGID [SD] MSG`
+fn syslog_message(facility: SyslogFacility, hostname: &str, entry: &AuditEntry) -> String {
+    let pri = facility.code() as u32 * 8 + entry.event_type.syslog_severity() as u32;
+    format!(
+        r#"<{}>1 {} {} {} {} {} [{} seq="{}" prev="{}" hash="{}"] {}"#,
+        pri,
+        rfc3339_millis(entry.timestamp),
+        hostname,
+        SYSLOG_APP_NAME,
+        std::process::id(),
+        entry.event_type.as_str(),
+        SYSLOG_SD_ID,
+        entry.sequence,
+        escape_sd_value(&entry.prev_hash),
+        escape_sd_value(&entry.hash),
+        entry.details
+    )
+}
+
+/// Escape `"`, `\` and `]` inside a structured data parameter value
+fn escape_sd_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Format Unix epoch milliseconds as `YYYY-MM-DDTHH:MM:SS.mmmZ`
+fn rfc3339_millis(millis: u64) -> String {
+    let secs = millis / 1000;
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        millis % 1000
+    )
+}
+
+/// Host name for the syslog HOSTNAME field, or the NILVALUE `-`
+fn syslog_hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    match std::str::from_utf8(&buf[..len]) {
+        Ok(name) if rc == 0 && !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic()) => {
+            name.to_string()
+        }
+        _ => "-".to_string(),
+    }
+}
+
+/// Resolve `[udp://|tcp://]host:port` into a syslog transport
+fn syslog_transport(remote_addr: &str) -> std::io::Result<RemoteTransport> {
+    let (tcp, host) = match remote_addr.strip_prefix("tcp://") {
+        Some(host) => (true, host),
+        None => (
+            false,
+            remote_addr.strip_prefix("udp://").unwrap_or(remote_addr),
+        ),
+    };
+    let addr = host.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("no address for syslog server {}", host),
+        )
+    })?;
+
+    if tcp {
+        return Ok(RemoteTransport::Tcp {
+            addr,
+            stream: None,
+            retry_at: Instant::now(),
+        });
+    }
+    let bind: SocketAddr = if addr.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.connect(addr)?;
+    socket.set_write_timeout(Some(REMOTE_IO_TIMEOUT))?;
+    Ok(RemoteTransport::Udp(socket))
+}
+
+/// Native journal protocol datagram: one `FIELD=value` per line, with the
+/// length-prefixed binary form for values containing newlines
+#[cfg(target_os = "linux")]
+fn journald_message(entry: &AuditEntry) -> Vec<u8> {
+    fn field(out: &mut Vec<u8>, name: &str, value: &str) {
+        out.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            out.push(b'\n');
+            out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            out.push(b'=');
+        }
+        out.extend_from_slice(value.as_bytes());
+        out.push(b'\n');
+    }
+
+    let mut out = Vec::with_capacity(256 + entry.details.len());
+    field(
+        &mut out,
+        "MESSAGE",
+        &format!("{} {}", entry.event_type.as_str(), entry.details),
+    );
+    field(
+        &mut out,
+        "PRIORITY",
+        &entry.event_type.syslog_severity().to_string(),
+    );
+    field(&mut out, "SYSLOG_IDENTIFIER", SYSLOG_APP_NAME);
+    field(&mut out, "NETSTRESS_EVENT", entry.event_type.as_str());
+    field(&mut out, "NETSTRESS_SEQ", &entry.sequence.to_string());
+    field(&mut out, "NETSTRESS_PREV_HASH", &entry.prev_hash);
+    field(&mut out, "NETSTRESS_HASH", &entry.hash);
+    out
+}
+
+/// Plaintext recovered from an encrypted audit file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedExport {
+    /// Recovered entries in `export_json` format
+    pub json: String,
+    /// Number of intact records
+    pub recovered: usize,
+    /// Whether every byte of the file belonged to an intact record
+    pub complete: bool,
+}
+
+/// What `log` does when the background writer's queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuditBackpressure {
+    /// Wait for the writer to make room
+    #[default]
+    Block,
+    /// Drop the event and count it in `dropped_events`
+    Drop,
+}
+
+enum WriterCommand {
+    Log(AuditEventType, String),
+    /// Acknowledged once everything queued before it is on disk
+    Flush(crossbeam::channel::Sender<()>),
+}
+
+/// Writer thread that chains and persists entries in queue order
+struct BackgroundWriter {
+    queue: Option<crossbeam::channel::Sender<WriterCommand>>,
+    backpressure: AuditBackpressure,
+    dropped: AtomicU64,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl BackgroundWriter {
+    fn spawn(
+        core: Arc<LogCore>,
+        capacity: usize,
+        backpressure: AuditBackpressure,
+    ) -> std::io::Result<Self> {
+        let (tx, rx) = crossbeam::channel::bounded(capacity.max(1));
+        let thread = std::thread::Builder::new()
+            .name("audit-writer".into())
+            .spawn(move || {
+                for command in rx.iter() {
+                    match command {
+                        WriterCommand::Log(event_type, details) => core.log(event_type, details),
+                        WriterCommand::Flush(ack) => {
+                            let _ = core.flush();
+                            let _ = ack.send(());
+                        }
+                    }
+                }
+                let _ = core.flush();
+            })?;
+        Ok(Self {
+            queue: Some(tx),
+            backpressure,
+            dropped: AtomicU64::new(0),
+            thread: Some(thread),
+        })
+    }
+
+    fn enqueue(&self, event_type: AuditEventType, details: String) {
+        let Some(ref queue) = self.queue else { return };
+        let command = WriterCommand::Log(event_type, details);
+        let queued = match self.backpressure {
+            AuditBackpressure::Block => queue.send(command).is_ok(),
+            AuditBackpressure::Drop => queue.try_send(command).is_ok(),
+        };
+        if !queued {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Wait until everything enqueued so far has been written
+    fn flush(&self) {
+        let (ack_tx, ack_rx) = crossbeam::channel::bounded(1);
+        let sent = self
+            .queue
+            .as_ref()
+            .is_some_and(|queue| queue.send(WriterCommand::Flush(ack_tx)).is_ok());
+        if sent {
+            let _ = ack_rx.recv();
+        }
+    }
+}
+
+impl Drop for BackgroundWriter {
+    fn drop(&mut self) {
+        // Closing the queue lets the writer drain what is left and exit
+        self.queue.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Chain state and sinks, shared with the background writer thread
+struct LogCore {
     /// Log entries in memory
     entries: RwLock<VecDeque<AuditEntry>>,
     /// Current sequence number
     sequence: RwLock<u64>,
     /// Last hash (for chain)
     last_hash: RwLock<String>,
+    /// Hash the oldest in-memory entry chains from
+    chain_anchor: RwLock<String>,
     /// File writer (optional)
     file_writer: RwLock<Option<BufWriter<File>>>,
+    /// Segment rotation (only for rotating files)
+    rotation: RwLock<Option<SegmentRotation>>,
     /// Maximum entries in memory
     max_memory_entries: usize,
+    /// Set when the file sink encrypts each entry
+    encryption: Option<EncryptedFile>,
+    /// Off-box sink (syslog or journald)
+    remote: Option<RemoteSink>,
 }
 
-impl Default for AuditLogger {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Tamper-evident audit logger
+pub struct AuditLogger {
+    core: Arc<LogCore>,
+    /// Set when `log` hands entries to a writer thread
+    writer: Option<BackgroundWriter>,
 }
 
-impl AuditLogger {
-    /// Create a new audit logger
-    pub fn new() -> Self {
+impl LogCore {
+    fn new() -> Self {
         Self {
             entries: RwLock::new(VecDeque::with_capacity(1000)),
             sequence: RwLock::new(0),
             last_hash: RwLock::new("genesis".to_string()),
+            chain_anchor: RwLock::new("genesis".to_string()),
             file_writer: RwLock::new(None),
+            rotation: RwLock::new(None),
             max_memory_entries: 10000,
+            encryption: None,
+            remote: None,
         }
     }
 
-    /// Create with file output
-    pub fn with_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)?;
-        
-        let logger = Self::new();
-        *logger.file_writer.write() = Some(BufWriter::new(file));
-        Ok(logger)
-    }
-
     /// Log an event
-    pub fn log(&self, event_type: AuditEventType, details: impl Into<String>) {
-        let details = details.into();
-        
+    fn log(&self, event_type: AuditEventType, details: String) {
         let mut seq = self.sequence.write();
         let mut last_hash = self.last_hash.write();
-        
+
+        if let Some(ref mut rotation) = *self.rotation.write() {
+            let current_size = self
+                .file_writer
+                .read()
+                .as_ref()
+                .and_then(|w| w.get_ref().metadata().ok())
+                .map_or(0, |m| m.len());
+
+            if rotation.is_due(current_size) {
+                // Open first so a failure leaves the current segment unsealed
+                if let Ok((file, next_path)) = rotation.open_next() {
+                    let next_name = next_path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let seal = format!("rolling_hash={}, next={}", last_hash, next_name);
+                    self.append(&mut seq, &mut last_hash, AuditEventType::SegmentEnd, seal);
+                    *self.file_writer.write() = Some(BufWriter::new(file));
+                }
+            }
+        }
+
+        self.append(&mut seq, &mut last_hash, event_type, details);
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        match *self.file_writer.write() {
+            Some(ref mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Chain a new entry onto the log and persist it
+    fn append(
+        &self,
+        seq: &mut u64,
+        last_hash: &mut String,
+        event_type: AuditEventType,
+        details: String,
+    ) {
         *seq += 1;
         let entry = AuditEntry::new(*seq, event_type, details, last_hash.clone());
         *last_hash = entry.hash.clone();
-        
+
         // Write to file if configured
         if let Some(ref mut writer) = *self.file_writer.write() {
-            let _ = writeln!(writer, "{}", entry.to_json());
+            let _ = match &self.encryption {
+                Some(encryption) => writer.write_all(&encrypt_record(&encryption.key, &entry)),
+                None => writeln!(writer, "{}", entry.to_json()),
+            };
             let _ = writer.flush();
         }
-        
+
+        if let Some(ref remote) = self.remote {
+            remote.submit(&entry);
+        }
+
         // Store in memory
         let mut entries = self.entries.write();
         entries.push_back(entry);
-        
-        // Trim if too many
+
+        // Trim if too many, keeping the chain verifiable from the new head
         while entries.len() > self.max_memory_entries {
-            entries.pop_front();
+            if let Some(dropped) = entries.pop_front() {
+                *self.chain_anchor.write() = dropped.hash;
+            }
+        }
+    }
+}
+
+impl Default for AuditLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLogger {
+    /// Create a new audit logger
+    pub fn new() -> Self {
+        Self::from_core(LogCore::new())
+    }
+
+    fn from_core(core: LogCore) -> Self {
+        Self {
+            core: Arc::new(core),
+            writer: None,
+        }
+    }
+
+    /// Move chaining and file writes onto a background thread
+    ///
+    /// `log_*` calls then only enqueue (up to `capacity` pending events) and
+    /// return; `backpressure` decides whether a full queue blocks the caller
+    /// or drops the event. Reads such as `entries` and `verify_chain` see
+    /// events once written; call `flush` to wait for that. Dropping the
+    /// logger drains the queue.
+    pub fn into_async(
+        mut self,
+        capacity: usize,
+        backpressure: AuditBackpressure,
+    ) -> std::io::Result<Self> {
+        if self.writer.is_none() {
+            let core = Arc::clone(&self.core);
+            self.writer = Some(BackgroundWriter::spawn(core, capacity, backpressure)?);
+        }
+        Ok(self)
+    }
+
+    /// Wait for queued events to be written and flush the file sink
+    pub fn flush(&self) -> std::io::Result<()> {
+        if let Some(ref writer) = self.writer {
+            writer.flush();
+        }
+        self.core.flush()
+    }
+
+    /// Events the background writer dropped because its queue was full
+    pub fn dropped_events(&self) -> u64 {
+        self.writer
+            .as_ref()
+            .map_or(0, |writer| writer.dropped.load(Ordering::Relaxed))
+    }
+
+    /// Create with file output
+    pub fn with_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        
+        let core = LogCore::new();
+        *core.file_writer.write() = Some(BufWriter::new(file));
+        Ok(Self::from_core(core))
+    }
+
+    /// Create with file output encrypted entry by entry (ChaCha20-Poly1305)
+    ///
+    /// The hash chain is computed over the plaintext entries, so a decrypted
+    /// log verifies exactly like a plain one. An existing file is resumed:
+    /// a final record cut short by a crash is trimmed off, but a record that
+    /// fails to authenticate (wrong key or tampering), or any other data that
+    /// is not an encrypted record, is an error and leaves the file untouched.
+    pub fn with_encrypted_file<P: AsRef<Path>>(
+        path: P,
+        key: [u8; KEY_LEN],
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut core = LogCore::new();
+
+        if path.exists() {
+            let read = read_encrypted(path, &key)?;
+            if read.auth_failed {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "record {} failed authentication: wrong key or corrupted file",
+                        read.entries.len() + 1
+                    ),
+                ));
+            }
+            if let Some(last) = read.entries.last() {
+                *core.sequence.write() = last.sequence;
+                *core.chain_anchor.write() = last.hash.clone();
+                *core.last_hash.write() = last.hash.clone();
+            }
+            let file = OpenOptions::new().write(true).open(path)?;
+            if file.metadata()?.len() > read.intact_len {
+                // Only a torn final record is trimmed; anything else is left
+                // untouched rather than cut back to its readable prefix
+                if !read.torn_tail || read.entries.is_empty() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "not an encrypted audit log or corrupted at byte {}",
+                            read.intact_len
+                        ),
+                    ));
+                }
+                file.set_len(read.intact_len)?;
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *core.file_writer.write() = Some(BufWriter::new(file));
+        core.encryption = Some(EncryptedFile {
+            path: path.to_path_buf(),
+            key,
+        });
+        Ok(Self::from_core(core))
+    }
+
+    /// Create with RFC 5424 syslog output to `remote_addr`
+    ///
+    /// `remote_addr` is `host:port` (UDP) or `tcp://host:port`. Entries are
+    /// shipped from a background thread; while the server is unreachable or
+    /// the buffer is full they are dropped and counted in `remote_dropped`.
+    pub fn with_syslog(facility: SyslogFacility, remote_addr: &str) -> std::io::Result<Self> {
+        let format = RemoteFormat::Syslog {
+            facility,
+            hostname: syslog_hostname(),
+        };
+        let mut core = LogCore::new();
+        core.remote = Some(RemoteSink::spawn(format, syslog_transport(remote_addr)?)?);
+        Ok(Self::from_core(core))
+    }
+
+    /// Create with output to the systemd journal over its native socket
+    #[cfg(target_os = "linux")]
+    pub fn with_journald() -> std::io::Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.connect("/run/systemd/journal/socket")?;
+        socket.set_write_timeout(Some(REMOTE_IO_TIMEOUT))?;
+        let mut core = LogCore::new();
+        core.remote = Some(RemoteSink::spawn(
+            RemoteFormat::Journald,
+            RemoteTransport::Journald(socket),
+        )?);
+        Ok(Self::from_core(core))
+    }
+
+    /// Entries the remote sink dropped (buffer full or delivery failed)
+    pub fn remote_dropped(&self) -> u64 {
+        self.core
+            .remote
+            .as_ref()
+            .map_or(0, |remote| remote.dropped.load(Ordering::Relaxed))
+    }
+
+    /// Create with segmented file output rotated by size or age
+    ///
+    /// Each segment is sealed with a `SEGMENT_END` entry and the next segment
+    /// chains from its hash, so `verify_all_segments` can check the whole run.
+    /// An existing set of segments for the same path is resumed.
+    pub fn with_rotating_file<P: AsRef<Path>>(
+        path: P,
+        max_size: u64,
+        max_age: Duration,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audit")
+            .to_string();
+        let extension = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("log")
+            .to_string();
+
+        let mut rotation = SegmentRotation {
+            dir,
+            stem,
+            extension,
+            index: 0,
+            max_size,
+            max_age,
+            opened_at: Instant::now(),
+        };
+
+        let core = LogCore::new();
+
+        // Resume the latest segment of this log, if any
+        let latest = list_segments(&rotation.dir)?
+            .into_iter()
+            .filter_map(|p| parse_segment_name(&p).map(|name| (p, name)))
+            .rfind(|(_, (stem, _, ext))| *stem == rotation.stem && *ext == rotation.extension);
+
+        let file = match latest {
+            Some((latest_path, (_, index, _))) => {
+                let reader = BufReader::new(File::open(&latest_path)?);
+                if let Some(last) = reader
+                    .lines()
+                    .map_while(Result::ok)
+                    .filter_map(|line| AuditEntry::from_json(&line))
+                    .last()
+                {
+                    *core.sequence.write() = last.sequence;
+                    *core.chain_anchor.write() = last.hash.clone();
+                    *core.last_hash.write() = last.hash;
+                }
+                rotation.index = index;
+                OpenOptions::new().append(true).open(&latest_path)?
+            }
+            None => rotation.open_next()?.0,
+        };
+
+        *core.file_writer.write() = Some(BufWriter::new(file));
+        *core.rotation.write() = Some(rotation);
+        Ok(Self::from_core(core))
+    }
+
+    /// Log an event
+    ///
+    /// With a background writer this only enqueues; the entry is chained and
+    /// persisted on the writer thread.
+    pub fn log(&self, event_type: AuditEventType, details: impl Into<String>) {
+        match self.writer {
+            Some(ref writer) => writer.enqueue(event_type, details.into()),
+            None => self.core.log(event_type, details.into()),
         }
     }
 
@@ -269,12 +1158,13 @@ impl AuditLogger {
 
     /// Get all entries
     pub fn entries(&self) -> Vec<AuditEntry> {
-        self.entries.read().iter().cloned().collect()
+        self.core.entries.read().iter().cloned().collect()
     }
 
     /// Get entries since sequence number
     pub fn entries_since(&self, seq: u64) -> Vec<AuditEntry> {
-        self.entries
+        self.core
+            .entries
             .read()
             .iter()
             .filter(|e| e.sequence > seq)
@@ -284,77 +1174,266 @@ impl AuditLogger {
 
     /// Verify chain integrity
     pub fn verify_chain(&self) -> ChainVerificationResult {
-        let entries = self.entries.read();
-        
-        if entries.is_empty() {
-            return ChainVerificationResult {
-                valid: true,
-                entries_checked: 0,
-                first_invalid: None,
-                error: None,
-            };
-        }
-
-        let mut prev_hash = "genesis".to_string();
-        let mut checked = 0;
-
-        for entry in entries.iter() {
-            checked += 1;
-            
-            // Verify entry hash
-            if !entry.verify() {
-                return ChainVerificationResult {
-                    valid: false,
-                    entries_checked: checked,
-                    first_invalid: Some(entry.sequence),
-                    error: Some("Entry hash mismatch".to_string()),
-                };
-            }
-            
-            // Verify chain link
-            if entry.prev_hash != prev_hash {
-                return ChainVerificationResult {
-                    valid: false,
-                    entries_checked: checked,
-                    first_invalid: Some(entry.sequence),
-                    error: Some("Chain link broken".to_string()),
-                };
-            }
-            
-            prev_hash = entry.hash.clone();
-        }
+        let entries = self.core.entries.read();
+        verify_entries(&self.core.chain_anchor.read(), None, entries.iter())
+    }
 
-        ChainVerificationResult {
-            valid: true,
-            entries_checked: checked,
-            first_invalid: None,
-            error: None,
-        }
+    /// Verify the in-memory entries with sequence numbers in `start..=end`
+    ///
+    /// The first entry in the range must link to the entry before it (or to
+    /// the chain anchor when the range starts at the oldest entry held).
+    pub fn verify_range(&self, start: u64, end: u64) -> ChainVerificationResult {
+        let entries = self.core.entries.read();
+        let first = entries.partition_point(|e| e.sequence < start);
+        let prev = first.checked_sub(1).and_then(|i| entries.get(i));
+        let anchor = match prev {
+            Some(prev) => prev.hash.clone(),
+            None => self.core.chain_anchor.read().clone(),
+        };
+        verify_entries(
+            &anchor,
+            prev,
+            entries.range(first..).take_while(|e| e.sequence <= end),
+        )
     }
 
     /// Export to JSON
     pub fn export_json(&self) -> String {
-        let entries: Vec<String> = self.entries.read().iter().map(|e| e.to_json()).collect();
+        let entries: Vec<String> = self
+            .core
+            .entries
+            .read()
+            .iter()
+            .map(|e| e.to_json())
+            .collect();
         format!("[{}]", entries.join(",\n"))
     }
 
+    /// Decrypt this logger's encrypted file with `key`
+    ///
+    /// Stops at the last intact record; `complete` is false when anything
+    /// after it was truncated or failed to authenticate.
+    pub fn decrypt_export(&self, key: &[u8; KEY_LEN]) -> std::io::Result<DecryptedExport> {
+        let encryption = self.core.encryption.as_ref().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "audit log is not encrypted",
+            )
+        })?;
+        self.flush()?;
+
+        let read = read_encrypted(&encryption.path, key)?;
+        let file_len = std::fs::metadata(&encryption.path)?.len();
+        let entries: Vec<String> = read.entries.iter().map(|e| e.to_json()).collect();
+        Ok(DecryptedExport {
+            json: format!("[{}]", entries.join(",\n")),
+            recovered: read.entries.len(),
+            complete: read.intact_len == file_len,
+        })
+    }
+
+    /// Load the intact records of an encrypted file for verification
+    pub fn load_encrypted_file<P: AsRef<Path>>(
+        path: P,
+        key: &[u8; KEY_LEN],
+    ) -> std::io::Result<Self> {
+        let read = read_encrypted(path.as_ref(), key)?;
+        let core = LogCore::new();
+        if let Some(last) = read.entries.last() {
+            *core.sequence.write() = last.sequence;
+            *core.last_hash.write() = last.hash.clone();
+        }
+        core.entries.write().extend(read.entries);
+        Ok(Self::from_core(core))
+    }
+
     /// Load from file
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         
-        let logger = Self::new();
+        let core = LogCore::new();
         
         for line in reader.lines() {
             let line = line?;
             if let Some(entry) = AuditEntry::from_json(&line) {
-                *logger.sequence.write() = entry.sequence;
-                *logger.last_hash.write() = entry.hash.clone();
-                logger.entries.write().push_back(entry);
+                *core.sequence.write() = entry.sequence;
+                *core.last_hash.write() = entry.hash.clone();
+                core.entries.write().push_back(entry);
             }
         }
         
-        Ok(logger)
+        Ok(Self::from_core(core))
+    }
+}
+
+/// Verify the hash chain across all rotated segments in a directory
+///
+/// Segments are checked in index order; every segment except the last must
+/// end with a `SEGMENT_END` entry, so a removed or truncated segment shows up
+/// as a broken link.
+pub fn verify_all_segments<P: AsRef<Path>>(dir: P) -> std::io::Result<ChainVerificationResult> {
+    let segments = list_segments(dir.as_ref())?;
+
+    let invalid = |checked, first_invalid, error| ChainVerificationResult {
+        error: Some(error),
+        ..ChainVerificationResult::broken(checked, first_invalid, None)
+    };
+
+    let mut prev_hash = "genesis".to_string();
+    let mut prev_seq = 0u64;
+    let mut prev_ts = 0u64;
+    let mut checked = 0u64;
+
+    for (i, path) in segments.iter().enumerate() {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_last = i + 1 == segments.len();
+        let mut sealed = false;
+
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = match AuditEntry::from_json(&line) {
+                Some(entry) => entry,
+                None => {
+                    return Ok(invalid(
+                        checked,
+                        None,
+                        format!("{}: Unparseable entry", name),
+                    ));
+                }
+            };
+            checked += 1;
+
+            if sealed {
+                let error = format!("{}: Entry after segment end", name);
+                return Ok(invalid(checked, Some(entry.sequence), error));
+            }
+            if let Some(fault) = check_entry(&entry, &prev_hash, Some((prev_seq, prev_ts))) {
+                let mut result =
+                    ChainVerificationResult::broken(checked, Some(entry.sequence), Some(fault));
+                result.error = result.error.map(|error| format!("{}: {}", name, error));
+                return Ok(result);
+            }
+
+            sealed = entry.event_type == AuditEventType::SegmentEnd;
+            prev_hash = entry.hash;
+            prev_seq = entry.sequence;
+            prev_ts = entry.timestamp;
+        }
+
+        if !is_last && !sealed {
+            return Ok(invalid(
+                checked,
+                None,
+                format!("{}: Segment missing end marker", name),
+            ));
+        }
+    }
+
+    Ok(ChainVerificationResult::intact(checked))
+}
+
+/// Check a run of entries, the first of which chains from `anchor`
+///
+/// `prev` is the entry the run follows, when known; without it the first
+/// entry's sequence number and timestamp are taken as given.
+fn verify_entries<'a>(
+    anchor: &str,
+    prev: Option<&AuditEntry>,
+    entries: impl Iterator<Item = &'a AuditEntry>,
+) -> ChainVerificationResult {
+    let mut prev_hash = anchor;
+    let mut prev = prev.map(|e| (e.sequence, e.timestamp));
+    let mut checked = 0;
+
+    for entry in entries {
+        checked += 1;
+        if let Some(fault) = check_entry(entry, prev_hash, prev) {
+            return ChainVerificationResult::broken(checked, Some(entry.sequence), Some(fault));
+        }
+        prev_hash = &entry.hash;
+        prev = Some((entry.sequence, entry.timestamp));
+    }
+
+    ChainVerificationResult::intact(checked)
+}
+
+/// A break found by `check_entry`
+struct ChainFault {
+    kind: ChainBreak,
+    error: &'static str,
+    expected_hash: Option<String>,
+    actual_hash: Option<String>,
+}
+
+/// First problem with `entry` following `prev_hash` and `prev` (sequence,
+/// timestamp of the previous entry, when known)
+fn check_entry(
+    entry: &AuditEntry,
+    prev_hash: &str,
+    prev: Option<(u64, u64)>,
+) -> Option<ChainFault> {
+    let link_fault = |kind, error| ChainFault {
+        kind,
+        error,
+        expected_hash: Some(prev_hash.to_string()),
+        actual_hash: Some(entry.prev_hash.clone()),
+    };
+
+    // Payload edited in place
+    let computed = entry.calculate_hash();
+    if computed != entry.hash {
+        return Some(ChainFault {
+            kind: ChainBreak::HashMismatch,
+            error: "Entry hash mismatch",
+            expected_hash: Some(computed),
+            actual_hash: Some(entry.hash.clone()),
+        });
+    }
+    // Entries removed or spliced in
+    if prev.is_some_and(|(prev_seq, _)| entry.sequence != prev_seq + 1) {
+        return Some(link_fault(ChainBreak::SequenceGap, "Sequence gap"));
+    }
+    // Entry replaced by one re-hashed after the fact
+    if entry.prev_hash != prev_hash {
+        return Some(link_fault(ChainBreak::HashMismatch, "Chain link broken"));
+    }
+    if prev.is_some_and(|(_, prev_ts)| entry.timestamp < prev_ts) {
+        return Some(ChainFault {
+            kind: ChainBreak::TimestampRegression,
+            error: "Timestamp regression",
+            expected_hash: None,
+            actual_hash: None,
+        });
+    }
+    None
+}
+
+/// How a hash chain was broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainBreak {
+    /// An entry's contents no longer match its hash, or its link to the
+    /// previous entry (modified entry)
+    HashMismatch,
+    /// Sequence numbers skip or repeat (entry deleted or inserted)
+    SequenceGap,
+    /// An entry is older than the one before it
+    TimestampRegression,
+}
+
+impl ChainBreak {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChainBreak::HashMismatch => "HASH_MISMATCH",
+            ChainBreak::SequenceGap => "SEQUENCE_GAP",
+            ChainBreak::TimestampRegression => "TIMESTAMP_REGRESSION",
+        }
     }
 }
 
@@ -365,21 +1444,61 @@ pub struct ChainVerificationResult {
     pub entries_checked: u64,
     pub first_invalid: Option<u64>,
     pub error: Option<String>,
+    /// Classification of the break, when one was found
+    pub break_type: Option<ChainBreak>,
+    /// Hash (hex) the verifier expected at the break
+    pub expected_hash: Option<String>,
+    /// Hash (hex) actually recorded at the break
+    pub actual_hash: Option<String>,
+}
+
+impl ChainVerificationResult {
+    fn intact(checked: u64) -> Self {
+        Self {
+            valid: true,
+            entries_checked: checked,
+            first_invalid: None,
+            error: None,
+            break_type: None,
+            expected_hash: None,
+            actual_hash: None,
+        }
+    }
+
+    fn broken(checked: u64, first_invalid: Option<u64>, fault: Option<ChainFault>) -> Self {
+        let mut result = Self {
+            valid: false,
+            first_invalid,
+            ..Self::intact(checked)
+        };
+        if let Some(fault) = fault {
+            result.error = Some(fault.error.to_string());
+            result.break_type = Some(fault.kind);
+            result.expected_hash = fault.expected_hash;
+            result.actual_hash = fault.actual_hash;
+        }
+        result
+    }
 }
 
 // Helper functions for simple JSON parsing
-fn extract_json_u64(json: &str, key: &str) -> Option<u64> {
-    let pattern = format!("\"{}\":", key);
+
+/// Find the start of the value for `key`, allowing whitespace around the colon
+fn json_value_start<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = format!("\"{}\"", key);
     let start = json.find(&pattern)? + pattern.len();
-    let rest = &json[start..];
+    let rest = json[start..].trim_start().strip_prefix(':')?;
+    Some(rest.trim_start())
+}
+
+fn extract_json_u64(json: &str, key: &str) -> Option<u64> {
+    let rest = json_value_start(json, key)?;
     let end = rest.find(|c: char| !c.is_ascii_digit())?;
     rest[..end].parse().ok()
 }
 
 fn extract_json_str(json: &str, key: &str) -> Option<String> {
-    let pattern = format!("\"{}\":\"", key);
-    let start = json.find(&pattern)? + pattern.len();
-    let rest = &json[start..];
+    let rest = json_value_start(json, key)?.strip_prefix('"')?;
     let end = rest.find('"')?;
     Some(rest[..end].replace("\\\"", "\""))
 }
@@ -439,7 +1558,7 @@ mod tests {
         
         // Tamper with an entry
         {
-            let mut entries = logger.entries.write();
+            let mut entries = logger.core.entries.write();
             if let Some(entry) = entries.get_mut(0) {
                 entry.details = "TAMPERED".to_string();
             }
@@ -449,4 +1568,366 @@ mod tests {
         assert!(!result.valid);
         assert_eq!(result.first_invalid, Some(1));
     }
+
+    fn logger_with(count: u64) -> AuditLogger {
+        let logger = AuditLogger::new();
+        for i in 0..count {
+            logger.log(AuditEventType::StatsSnapshot, format!("pps={}", i));
+        }
+        logger
+    }
+
+    #[test]
+    fn test_verify_classifies_modified_entry() {
+        let logger = logger_with(5);
+        let original = logger.entries()[2].hash.clone();
+        logger.core.entries.write()[2].details = "pps=999999".to_string();
+
+        let result = logger.verify_chain();
+        assert!(!result.valid);
+        assert_eq!(result.break_type, Some(ChainBreak::HashMismatch));
+        assert_eq!(result.first_invalid, Some(3));
+        assert_eq!(result.entries_checked, 3);
+        assert_eq!(result.actual_hash.as_deref(), Some(original.as_str()));
+        let expected = result.expected_hash.unwrap();
+        assert_eq!(expected.len(), 64);
+        assert_ne!(expected, original);
+
+        // Ranges that stop short of the edit are still intact
+        assert!(logger.verify_range(1, 2).valid);
+        assert_eq!(logger.verify_range(2, 5).first_invalid, Some(3));
+        let result = logger.verify_range(4, 5);
+        assert!(result.valid);
+        assert_eq!(result.entries_checked, 2);
+    }
+
+    #[test]
+    fn test_verify_classifies_deletion_and_clock_step() {
+        let logger = logger_with(5);
+        logger.core.entries.write().remove(2);
+        let result = logger.verify_chain();
+        assert_eq!(result.break_type, Some(ChainBreak::SequenceGap));
+        assert_eq!(result.first_invalid, Some(4));
+        assert_eq!(result.expected_hash, Some(logger.entries()[1].hash.clone()));
+
+        let logger = logger_with(3);
+        {
+            // Re-hash so only the timestamp is wrong
+            let mut entries = logger.core.entries.write();
+            let first_ts = entries[0].timestamp;
+            entries[1].timestamp = first_ts - 1;
+            entries[1].hash = entries[1].calculate_hash();
+            entries[2].prev_hash = entries[1].hash.clone();
+            entries[2].hash = entries[2].calculate_hash();
+        }
+        let result = logger.verify_chain();
+        assert_eq!(result.break_type, Some(ChainBreak::TimestampRegression));
+        assert_eq!(result.first_invalid, Some(2));
+    }
+
+    #[test]
+    fn test_verify_empty_and_single_entry() {
+        let logger = AuditLogger::new();
+        let result = logger.verify_chain();
+        assert!(result.valid);
+        assert_eq!(result.entries_checked, 0);
+        assert!(logger.verify_range(1, 10).valid);
+
+        let logger = logger_with(1);
+        assert!(logger.verify_chain().valid);
+        let result = logger.verify_range(1, 1);
+        assert!(result.valid);
+        assert_eq!(result.entries_checked, 1);
+        assert_eq!(logger.verify_range(2, 10).entries_checked, 0);
+
+        logger.core.entries.write()[0].prev_hash = "f".repeat(64);
+        let result = logger.verify_chain();
+        assert_eq!(result.break_type, Some(ChainBreak::HashMismatch));
+        assert_eq!(result.first_invalid, Some(1));
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("netstress-audit-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rotation_by_size_spans_segments() {
+        let dir = scratch_dir("size");
+        let logger =
+            AuditLogger::with_rotating_file(dir.join("audit.log"), 600, Duration::from_secs(3600))
+                .unwrap();
+
+        for i in 0..20 {
+            logger.log(AuditEventType::StatsSnapshot, format!("pps={}", i));
+        }
+
+        let segments = list_segments(&dir).unwrap();
+        assert!(segments.len() > 2);
+        assert!(logger.verify_chain().valid);
+
+        let result = verify_all_segments(&dir).unwrap();
+        assert!(result.valid, "{:?}", result.error);
+        assert_eq!(result.entries_checked, logger.entries().len() as u64);
+
+        // Removing a sealed middle segment breaks the chain
+        std::fs::remove_file(&segments[1]).unwrap();
+        let result = verify_all_segments(&dir).unwrap();
+        assert!(!result.valid);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_by_age_and_resume() {
+        let dir = scratch_dir("age");
+        let path = dir.join("campaign.log");
+        {
+            let logger = AuditLogger::with_rotating_file(&path, u64::MAX, Duration::ZERO).unwrap();
+            logger.log(AuditEventType::EngineStart, "first");
+            logger.log(AuditEventType::EngineStop, "second");
+        }
+        assert_eq!(list_segments(&dir).unwrap().len(), 2);
+
+        // A new logger continues the chain from the latest segment
+        let logger =
+            AuditLogger::with_rotating_file(&path, u64::MAX, Duration::from_secs(3600)).unwrap();
+        logger.log(AuditEventType::EngineStart, "resumed");
+        assert_eq!(logger.entries()[0].sequence, 4);
+        assert!(logger.verify_chain().valid);
+
+        let result = verify_all_segments(&dir).unwrap();
+        assert!(result.valid, "{:?}", result.error);
+        assert_eq!(result.entries_checked, 4);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_encrypted_file_roundtrip() {
+        let dir = scratch_dir("encrypted");
+        let path = dir.join("audit.enc");
+        let key = [7u8; 32];
+
+        {
+            let logger = AuditLogger::with_encrypted_file(&path, key).unwrap();
+            logger.log_engine_start("10.0.0.1", "threads=4");
+            logger.log_target_authorized("10.0.0.1");
+        }
+        // Nothing readable on disk
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("ENGINE_START"));
+
+        // Reopening continues the same chain
+        let logger = AuditLogger::with_encrypted_file(&path, key).unwrap();
+        logger.log_engine_stop("packets=10");
+        let export = logger.decrypt_export(&key).unwrap();
+        assert_eq!(export.recovered, 3);
+        assert!(export.complete);
+        assert!(export.json.contains("ENGINE_STOP"));
+
+        let loaded = AuditLogger::load_encrypted_file(&path, &key).unwrap();
+        assert_eq!(loaded.entries().len(), 3);
+        assert_eq!(loaded.entries()[2].sequence, 3);
+        let result = loaded.verify_chain();
+        assert!(result.valid, "{:?}", result.error);
+        assert_eq!(result.entries_checked, 3);
+
+        // The wrong key recovers nothing and cannot append
+        let export = logger.decrypt_export(&[8u8; 32]).unwrap();
+        assert_eq!(export.recovered, 0);
+        assert!(!export.complete);
+        assert!(AuditLogger::with_encrypted_file(&path, [8u8; 32]).is_err());
+        drop(logger);
+
+        // A torn final record is dropped and trimmed before appending again
+        std::fs::write(&path, &std::fs::read(&path).unwrap()[..raw.len() + 10]).unwrap();
+        let logger = AuditLogger::with_encrypted_file(&path, key).unwrap();
+        let export = logger.decrypt_export(&key).unwrap();
+        assert_eq!((export.recovered, export.complete), (2, true));
+        logger.log_engine_stop("packets=20");
+        let loaded = AuditLogger::load_encrypted_file(&path, &key).unwrap();
+        assert_eq!(loaded.entries().len(), 3);
+        assert!(loaded.verify_chain().valid);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_encrypted_file_leaves_foreign_or_corrupt_files_alone() {
+        let dir = scratch_dir("encrypted-corrupt");
+        let key = [7u8; 32];
+        let refuses = |path: &Path| {
+            let before = std::fs::read(path).unwrap();
+            let err = AuditLogger::with_encrypted_file(path, key).err().unwrap();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert_eq!(std::fs::read(path).unwrap(), before);
+        };
+
+        // A plaintext log is not mistaken for one torn encrypted record
+        let plain = dir.join("audit.log");
+        {
+            let logger = AuditLogger::with_file(&plain).unwrap();
+            logger.log_engine_start("10.0.0.1", "threads=4");
+            logger.log_engine_stop("packets=10");
+        }
+        refuses(&plain);
+
+        // A corrupted length mid-file does not cut off the records after it
+        let path = dir.join("audit.enc");
+        {
+            let logger = AuditLogger::with_encrypted_file(&path, key).unwrap();
+            logger.log_engine_start("10.0.0.1", "threads=4");
+            logger.log_target_authorized("10.0.0.1");
+            logger.log_engine_stop("packets=10");
+        }
+        let raw = std::fs::read(&path).unwrap();
+        let second = 4 + u32::from_le_bytes(raw[..4].try_into().unwrap()) as usize;
+        for len in [u32::MAX, 1] {
+            let mut corrupt = raw.clone();
+            corrupt[second..second + 4].copy_from_slice(&len.to_le_bytes());
+            std::fs::write(&path, &corrupt).unwrap();
+            refuses(&path);
+        }
+
+        // Nor is a file whose very first record is cut short
+        std::fs::write(&path, &raw[..10]).unwrap();
+        refuses(&path);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rfc3339_millis() {
+        assert_eq!(rfc3339_millis(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339_millis(951_782_400_007), "2000-02-29T00:00:00.007Z");
+        assert_eq!(
+            rfc3339_millis(1_700_000_000_123),
+            "2023-11-14T22:13:20.123Z"
+        );
+    }
+
+    #[test]
+    fn test_syslog_sink_udp() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let logger = AuditLogger::with_syslog(SyslogFacility::Local4, &addr).unwrap();
+        logger.log_emergency_stop("operator \"abort\"");
+
+        let mut buf = [0u8; 2048];
+        let len = listener.recv(&mut buf).unwrap();
+        let line = std::str::from_utf8(&buf[..len]).unwrap();
+        let entry = &logger.entries()[0];
+
+        // local4 (20) * 8 + alert (1)
+        let fields: Vec<&str> = line.splitn(8, ' ').collect();
+        assert_eq!(fields[0], "<161>1");
+        assert_eq!(fields[1], rfc3339_millis(entry.timestamp));
+        assert!(!fields[2].is_empty());
+        assert_eq!(fields[3], "netstress");
+        assert_eq!(fields[4], std::process::id().to_string());
+        assert_eq!(fields[5], "EMERGENCY_STOP");
+        assert_eq!(fields[6], "[audit@32473");
+        assert!(line.contains(&format!(
+            r#"seq="1" prev="genesis" hash="{}"] "#,
+            entry.hash
+        )));
+        assert!(line.ends_with("] reason=operator \"abort\""), "{}", line);
+        assert_eq!(logger.remote_dropped(), 0);
+    }
+
+    #[test]
+    fn test_syslog_sink_drops_when_unreachable() {
+        // Reserve a port, then close it so connects are refused
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let logger =
+            AuditLogger::with_syslog(SyslogFacility::Daemon, &format!("tcp://{}", addr)).unwrap();
+        let started = Instant::now();
+        for i in 0..(REMOTE_QUEUE_CAPACITY * 2) {
+            logger.log(AuditEventType::StatsSnapshot, format!("pps={}", i));
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let total = (REMOTE_QUEUE_CAPACITY * 2) as u64;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while logger.remote_dropped() < total && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(logger.remote_dropped(), total);
+        assert!(logger.verify_chain().valid);
+    }
+
+    #[test]
+    fn test_async_writer_persists_every_event() {
+        const THREADS: usize = 4;
+        const EVENTS: usize = 100_000;
+
+        let dir = scratch_dir("async");
+        let path = dir.join("audit.log");
+        let logger = Arc::new(
+            AuditLogger::with_file(&path)
+                .unwrap()
+                .into_async(1024, AuditBackpressure::Block)
+                .unwrap(),
+        );
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let logger = Arc::clone(&logger);
+                std::thread::spawn(move || {
+                    for i in 0..EVENTS / THREADS {
+                        logger.log(AuditEventType::StatsSnapshot, format!("t={} i={}", t, i));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        logger.flush().unwrap();
+
+        assert_eq!(logger.dropped_events(), 0);
+        assert_eq!(logger.entries().last().unwrap().sequence, EVENTS as u64);
+        assert!(logger.verify_chain().valid);
+
+        let loaded = AuditLogger::load_from_file(&path).unwrap();
+        assert_eq!(loaded.entries().len(), EVENTS);
+        assert!(loaded.verify_chain().valid);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_async_writer_drop_policy_counts() {
+        let dir = scratch_dir("async-drop");
+        let path = dir.join("audit.log");
+        let attempts = 10_000;
+        let dropped = {
+            let logger = AuditLogger::with_file(&path)
+                .unwrap()
+                .into_async(1, AuditBackpressure::Drop)
+                .unwrap();
+            for i in 0..attempts {
+                logger.log(AuditEventType::StatsSnapshot, format!("i={}", i));
+            }
+            logger.dropped_events()
+            // Dropping the logger drains what was queued
+        };
+
+        let written = AuditLogger::load_from_file(&path).unwrap().entries().len() as u64;
+        assert_eq!(written + dropped, attempts);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
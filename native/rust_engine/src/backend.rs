@@ -73,6 +73,8 @@ pub struct SystemCapabilities {
     pub has_io_uring: bool,
     pub has_sendmmsg: bool,
     pub has_raw_socket: bool,
+    /// Raw sockets can actually be opened by this process (root/CAP_NET_RAW)
+    pub has_raw_socket_privilege: bool,
     pub has_iocp: bool,          // Windows IOCP
     pub has_registered_io: bool, // Windows Registered I/O
     pub has_kqueue: bool,        // macOS kqueue
@@ -81,6 +83,71 @@ pub struct SystemCapabilities {
     pub numa_nodes: i32,
 }
 
+/// Severity of a platform tuning recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RecommendationSeverity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+impl RecommendationSeverity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecommendationSeverity::Info => "info",
+            RecommendationSeverity::Low => "low",
+            RecommendationSeverity::Medium => "medium",
+            RecommendationSeverity::High => "high",
+        }
+    }
+}
+
+/// Structured tuning recommendation produced by the platform optimizers
+///
+/// `sysctl_or_action` holds the exact sysctl key when the recommendation maps
+/// to a kernel tunable, otherwise a short description of the action to take.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    pub id: String,
+    pub severity: RecommendationSeverity,
+    pub message: String,
+    pub current_value: Option<String>,
+    pub suggested_value: Option<String>,
+    pub sysctl_or_action: String,
+}
+
+impl Recommendation {
+    pub fn new(
+        id: &str,
+        severity: RecommendationSeverity,
+        message: impl Into<String>,
+        sysctl_or_action: &str,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            severity,
+            message: message.into(),
+            current_value: None,
+            suggested_value: None,
+            sysctl_or_action: sysctl_or_action.to_string(),
+        }
+    }
+
+    /// Attach the current and suggested values of the tunable
+    pub fn with_values(mut self, current: impl ToString, suggested: impl ToString) -> Self {
+        self.current_value = Some(current.to_string());
+        self.suggested_value = Some(suggested.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 /// Backend trait for pluggable packet sending
 pub trait Backend: Send + Sync {
     /// Get backend type
@@ -103,6 +170,29 @@ pub trait Backend: Send + Sync {
 
     /// Get backend statistics
     fn stats(&self) -> BackendStats;
+
+    /// Whether a send would find room in the kernel send buffer right now
+    ///
+    /// Lets callers back off instead of spinning on ENOBUFS/EAGAIN. Backends
+    /// without a cheap way to ask report `true`.
+    fn writable_hint(&self) -> bool {
+        true
+    }
+}
+
+/// Zero-timeout `poll` for `POLLOUT`; `true` when the kernel cannot say
+#[cfg(unix)]
+fn poll_writable(fd: libc::c_int) -> bool {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    match unsafe { libc::poll(&mut pfd, 1, 0) } {
+        0 => false,
+        1 => pfd.revents & libc::POLLOUT != 0,
+        _ => true,
+    }
 }
 
 /// Backend statistics
@@ -139,6 +229,78 @@ impl Default for BackendStatsInner {
     }
 }
 
+impl BackendStatsInner {
+    /// Account for a batch of which the first `sent` packets went out
+    #[cfg(target_os = "linux")]
+    fn record_batch(&self, packets: &[&[u8]], sent: usize) {
+        let bytes: u64 = packets[..sent].iter().map(|p| p.len() as u64).sum();
+        self.packets_sent.fetch_add(sent as u64, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        self.errors
+            .fetch_add((packets.len() - sent) as u64, Ordering::Relaxed);
+        self.batch_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Send every packet to `dest` with as few `sendmmsg` calls as possible
+///
+/// When the kernel accepts only part of the batch, the call is repeated from
+/// the first unsent packet. Returns the number of packets sent; an error is
+/// returned only if none were.
+#[cfg(target_os = "linux")]
+fn sendmmsg_all(fd: libc::c_int, packets: &[&[u8]], dest: SocketAddr) -> std::io::Result<usize> {
+    let addr = socket2::SockAddr::from(dest);
+    let mut iovecs: Vec<libc::iovec> = packets
+        .iter()
+        .map(|p| libc::iovec {
+            iov_base: p.as_ptr() as *mut libc::c_void,
+            iov_len: p.len(),
+        })
+        .collect();
+
+    let mut msgs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .map(|iov| {
+            let mut msg: libc::mmsghdr = unsafe { std::mem::zeroed() };
+            msg.msg_hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            msg.msg_hdr.msg_namelen = addr.len();
+            msg.msg_hdr.msg_iov = iov;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect();
+
+    let mut offset = 0;
+    while offset < msgs.len() {
+        let remaining = &mut msgs[offset..];
+        let sent = unsafe {
+            libc::sendmmsg(
+                fd,
+                remaining.as_mut_ptr(),
+                remaining.len() as libc::c_uint,
+                0,
+            )
+        };
+
+        if sent < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            if offset == 0 {
+                return Err(err);
+            }
+            break;
+        }
+        if sent == 0 {
+            break;
+        }
+        offset += sent as usize;
+    }
+
+    Ok(offset)
+}
+
 impl Default for StandardBackend {
     fn default() -> Self {
         Self::new()
@@ -188,6 +350,18 @@ impl Backend for StandardBackend {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    fn send_batch(&self, packets: &[&[u8]], dest: SocketAddr) -> Result<usize, BackendError> {
+        use std::os::fd::AsRawFd;
+
+        let socket = self.socket.as_ref().ok_or(BackendError::NotInitialized)?;
+        let result = sendmmsg_all(socket.as_raw_fd(), packets, dest);
+        self.stats
+            .record_batch(packets, result.as_ref().copied().unwrap_or(0));
+        result.map_err(|e| BackendError::SendFailed(format!("sendmmsg failed: {}", e)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
     fn send_batch(&self, packets: &[&[u8]], dest: SocketAddr) -> Result<usize, BackendError> {
         let socket = self.socket.as_ref().ok_or(BackendError::NotInitialized)?;
 
@@ -227,6 +401,16 @@ impl Backend for StandardBackend {
             batch_count: self.stats.batch_count.load(Ordering::Relaxed),
         }
     }
+
+    /// `POLLOUT` on the socket; uninitialised backends have nothing to wait for
+    #[cfg(unix)]
+    fn writable_hint(&self) -> bool {
+        use std::os::fd::AsRawFd;
+
+        self.socket
+            .as_ref()
+            .is_none_or(|socket| poll_writable(socket.as_raw_fd()))
+    }
 }
 
 /// Native backend using C driver (sendmmsg, io_uring, etc.)
@@ -381,6 +565,10 @@ impl Backend for NativeBackend {
             batch_count: self.stats.batch_count.load(Ordering::Relaxed),
         }
     }
+
+    fn writable_hint(&self) -> bool {
+        !self.initialized || poll_writable(self.socket_fd)
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -390,52 +578,14 @@ impl NativeBackend {
         packets: &[&[u8]],
         dest: SocketAddr,
     ) -> Result<usize, BackendError> {
-        let addr = match dest {
-            SocketAddr::V4(v4) => {
-                let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
-                addr.sin_family = libc::AF_INET as u16;
-                addr.sin_port = v4.port().to_be();
-                addr.sin_addr.s_addr = u32::from_ne_bytes(v4.ip().octets());
-                addr
-            }
-            _ => return Err(BackendError::SendFailed("IPv6 not supported".into())),
-        };
-
-        let count = packets.len();
-        let mut iovecs: Vec<libc::iovec> = packets
-            .iter()
-            .map(|p| libc::iovec {
-                iov_base: p.as_ptr() as *mut libc::c_void,
-                iov_len: p.len(),
-            })
-            .collect();
-
-        let mut msgs: Vec<libc::mmsghdr> = (0..count)
-            .map(|i| {
-                let mut msg: libc::mmsghdr = unsafe { std::mem::zeroed() };
-                msg.msg_hdr.msg_name = &addr as *const _ as *mut libc::c_void;
-                msg.msg_hdr.msg_namelen = std::mem::size_of::<libc::sockaddr_in>() as u32;
-                msg.msg_hdr.msg_iov = &mut iovecs[i];
-                msg.msg_hdr.msg_iovlen = 1;
-                msg
-            })
-            .collect();
-
-        let sent = unsafe { libc::sendmmsg(self.socket_fd, msgs.as_mut_ptr(), count as u32, 0) };
-
-        if sent < 0 {
-            self.stats.errors.fetch_add(count as u64, Ordering::Relaxed);
-            Err(BackendError::SendFailed("sendmmsg failed".into()))
-        } else {
-            let sent_count = sent as usize;
-            let bytes: u64 = packets[..sent_count].iter().map(|p| p.len() as u64).sum();
-            self.stats
-                .packets_sent
-                .fetch_add(sent_count as u64, Ordering::Relaxed);
-            self.stats.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
-            self.stats.batch_count.fetch_add(1, Ordering::Relaxed);
-            Ok(sent_count)
+        if !dest.is_ipv4() {
+            return Err(BackendError::SendFailed("IPv6 not supported".into()));
         }
+
+        let result = sendmmsg_all(self.socket_fd, packets, dest);
+        self.stats
+            .record_batch(packets, result.as_ref().copied().unwrap_or(0));
+        result.map_err(|e| BackendError::SendFailed(format!("sendmmsg failed: {}", e)))
     }
 
     #[cfg(feature = "io_uring")]
@@ -457,10 +607,20 @@ impl Drop for NativeBackend {
     }
 }
 
+/// Check whether this process may open raw sockets right now
+///
+/// `has_raw_socket` only says the platform supports them; this opens and
+/// closes a raw ICMP socket, so it reflects root/`CAP_NET_RAW` at runtime.
+pub fn has_raw_socket_privilege() -> bool {
+    use socket2::{Domain, Protocol, Socket, Type};
+    Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).is_ok()
+}
+
 /// Detect system capabilities
 pub fn detect_system_capabilities() -> SystemCapabilities {
     let mut caps = SystemCapabilities::default();
     caps.has_raw_socket = true;
+    caps.has_raw_socket_privilege = has_raw_socket_privilege();
 
     #[cfg(target_os = "linux")]
     {
@@ -610,10 +770,27 @@ mod tests {
         assert_eq!(BackendType::Sendmmsg.name(), "sendmmsg");
     }
 
+    #[test]
+    fn test_recommendation_values() {
+        let rec = Recommendation::new(
+            "raise_wmem_max",
+            RecommendationSeverity::High,
+            "Increase net.core.wmem_max",
+            "net.core.wmem_max",
+        )
+        .with_values(212992, 67108864);
+
+        assert_eq!(rec.current_value.as_deref(), Some("212992"));
+        assert_eq!(rec.suggested_value.as_deref(), Some("67108864"));
+        assert_eq!(rec.severity.name(), "high");
+        assert_eq!(rec.to_string(), "Increase net.core.wmem_max");
+    }
+
     #[test]
     fn test_detect_capabilities() {
         let caps = detect_system_capabilities();
         assert!(caps.has_raw_socket);
+        assert_eq!(caps.has_raw_socket_privilege, has_raw_socket_privilege());
         assert!(caps.cpu_count > 0);
     }
 
@@ -628,4 +805,66 @@ mod tests {
         backend.cleanup().unwrap();
         assert!(!backend.is_initialized());
     }
+
+    #[test]
+    fn test_standard_backend_batch_arrives() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+            .unwrap();
+        let dest = receiver.local_addr().unwrap();
+
+        let mut backend = StandardBackend::new();
+        backend.init().unwrap();
+
+        let payloads: Vec<[u8; 2]> = (0..64u16).map(|i| i.to_be_bytes()).collect();
+        let packets: Vec<&[u8]> = payloads.iter().map(|p| &p[..]).collect();
+        assert_eq!(backend.send_batch(&packets, dest).unwrap(), 64);
+
+        let mut seen = [false; 64];
+        let mut buf = [0u8; 16];
+        for _ in 0..64 {
+            let n = receiver.recv(&mut buf).unwrap();
+            assert_eq!(n, 2);
+            seen[u16::from_be_bytes([buf[0], buf[1]]) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+
+        let stats = backend.stats();
+        assert_eq!(stats.packets_sent, 64);
+        assert_eq!(stats.batch_count, 1);
+    }
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_standard_backend_writable_hint() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = receiver.local_addr().unwrap();
+        let mut backend = StandardBackend::new();
+        assert!(backend.writable_hint());
+        backend.init().unwrap();
+        assert!(backend.writable_hint());
+
+        // Corked data stays charged to the tiny send buffer until the datagram
+        // is finished; loopback frees it as soon as it goes out
+        let socket = socket2::SockRef::from(backend.socket.as_ref().unwrap());
+        socket.set_send_buffer_size(4096).unwrap();
+        let chunk = [0u8; 1000];
+        let mut corked = 0;
+        while backend.writable_hint() && corked < 60 {
+            socket
+                .send_to_with_flags(&chunk, &dest.into(), libc::MSG_MORE | libc::MSG_DONTWAIT)
+                .unwrap();
+            corked += 1;
+        }
+        assert!(
+            !backend.writable_hint(),
+            "still writable after {} KB",
+            corked
+        );
+
+        backend.send(&chunk, dest).unwrap();
+        assert!(backend.writable_hint());
+        let mut buf = vec![0u8; 65536];
+        assert_eq!(receiver.recv(&mut buf).unwrap(), (corked + 1) * chunk.len());
+    }
 }
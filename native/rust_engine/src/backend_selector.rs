@@ -3,23 +3,77 @@
 
 use crate::backend::{create_best_backend, detect_system_capabilities, select_best_backend};
 use crate::backend::{Backend, BackendError, BackendType, StandardBackend, SystemCapabilities};
-use parking_lot::RwLock;
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::collections::VecDeque;
+use std::net::{SocketAddr, UdpSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, info, warn};
 
+/// Payload size used when probing backends; small packets stress per-call overhead
+const PROBE_PAYLOAD_SIZE: usize = 64;
+
+/// Packets handed to `send_batch` per call while probing
+const PROBE_BATCH_SIZE: usize = 64;
+
+/// Number of backend transitions kept by `fallback_history`
+const FALLBACK_HISTORY_LEN: usize = 64;
+
+/// Longest a send waits for a full send buffer to drain before retrying
+const BACKPRESSURE_WAIT: Duration = Duration::from_millis(10);
+
+/// Interval between `writable_hint` polls while waiting out backpressure
+const BACKPRESSURE_POLL: Duration = Duration::from_micros(100);
+
+/// Wait up to `BACKPRESSURE_WAIT` for `backend` to have room to send
+fn wait_writable(backend: &dyn Backend) {
+    let deadline = Instant::now() + BACKPRESSURE_WAIT;
+    while !backend.writable_hint() && Instant::now() < deadline {
+        std::thread::sleep(BACKPRESSURE_POLL);
+    }
+}
+
+/// Callback invoked with (from, to, reason) whenever the active backend changes
+pub type BackendChangeListener = Arc<dyn Fn(BackendType, BackendType, &str) + Send + Sync>;
+
+/// A recorded change of the active backend
+#[derive(Debug, Clone)]
+pub struct BackendTransition {
+    pub from: BackendType,
+    pub to: BackendType,
+    pub reason: String,
+    pub at: SystemTime,
+}
+
+/// Outcome of a backend benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    /// Backend that was selected as the fastest
+    pub selected: BackendType,
+    /// Measured packets per second per candidate; `None` if the backend failed to send
+    pub rates: Vec<(BackendType, Option<f64>)>,
+}
+
 /// Backend selector with automatic fallback
 pub struct BackendSelector {
     /// Current active backend
     active_backend: Arc<RwLock<Box<dyn Backend>>>,
-    /// System capabilities
-    capabilities: SystemCapabilities,
+    /// System capabilities, replaced by `refresh_capabilities`
+    capabilities: RwLock<SystemCapabilities>,
     /// Preferred backend (user override)
     preferred: Option<BackendType>,
     /// Whether fallback is enabled
     fallback_enabled: AtomicBool,
     /// Backend priority order
     priority: Vec<BackendType>,
+    /// Result of the last `benchmark_and_select` run
+    benchmark: RwLock<Option<BenchmarkResult>>,
+    /// Most recent backend transitions, oldest first
+    history: Mutex<VecDeque<BackendTransition>>,
+    /// Notified after every backend change
+    listener: RwLock<Option<BackendChangeListener>>,
 }
 
 impl BackendSelector {
@@ -63,10 +117,13 @@ impl BackendSelector {
 
         Self {
             active_backend: Arc::new(RwLock::new(backend)),
-            capabilities,
+            capabilities: RwLock::new(capabilities),
             preferred: None,
             fallback_enabled: AtomicBool::new(true),
             priority: Self::get_platform_priority(),
+            benchmark: RwLock::new(None),
+            history: Mutex::new(VecDeque::with_capacity(FALLBACK_HISTORY_LEN)),
+            listener: RwLock::new(None),
         }
     }
 
@@ -93,16 +150,17 @@ impl BackendSelector {
 
     /// Check if a backend is available
     pub fn is_backend_available(&self, backend_type: BackendType) -> bool {
+        let caps = self.capabilities.read();
         match backend_type {
             BackendType::None => false,
-            BackendType::RawSocket => self.capabilities.has_raw_socket,
-            BackendType::Sendmmsg => self.capabilities.has_sendmmsg,
-            BackendType::IoUring => self.capabilities.has_io_uring,
-            BackendType::AfXdp => self.capabilities.has_af_xdp,
-            BackendType::Dpdk => self.capabilities.has_dpdk,
-            BackendType::IOCP => self.capabilities.has_iocp,
-            BackendType::RegisteredIO => self.capabilities.has_registered_io,
-            BackendType::Kqueue => self.capabilities.has_kqueue,
+            BackendType::RawSocket => caps.has_raw_socket,
+            BackendType::Sendmmsg => caps.has_sendmmsg,
+            BackendType::IoUring => caps.has_io_uring,
+            BackendType::AfXdp => caps.has_af_xdp,
+            BackendType::Dpdk => caps.has_dpdk,
+            BackendType::IOCP => caps.has_iocp,
+            BackendType::RegisteredIO => caps.has_registered_io,
+            BackendType::Kqueue => caps.has_kqueue,
         }
     }
 
@@ -115,9 +173,9 @@ impl BackendSelector {
             .collect()
     }
 
-    /// Switch to a specific backend
-    fn switch_to(&mut self, backend_type: BackendType) -> Result<(), BackendError> {
-        let mut backend: Box<dyn Backend> = match backend_type {
+    /// Construct an uninitialized backend of the given type
+    fn create_backend(backend_type: BackendType) -> Box<dyn Backend> {
+        match backend_type {
             BackendType::RawSocket | BackendType::None => Box::new(StandardBackend::new()),
             #[cfg(target_os = "linux")]
             BackendType::Sendmmsg
@@ -132,30 +190,234 @@ impl BackendSelector {
             }
             #[cfg(target_os = "macos")]
             BackendType::Kqueue => Box::new(crate::macos_backend::KqueueBackend::new()),
-            #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
-            _ => Box::new(StandardBackend::new()),
-            #[cfg(any(target_os = "windows", target_os = "macos"))]
             _ => Box::new(StandardBackend::new()),
+        }
+    }
+
+    /// Replace the active backend with an initialized one and report the change
+    fn install(&self, backend: Box<dyn Backend>, reason: &str) {
+        let to = backend.backend_type();
+        let from = {
+            let mut active = self.active_backend.write();
+            let from = active.backend_type();
+            let _ = active.cleanup();
+            *active = backend;
+            from
         };
+        self.record_transition(from, to, reason);
+    }
 
-        backend.init()?;
+    /// Append to the history and notify the listener.
+    ///
+    /// Called with no selector locks held, so the listener may call back into
+    /// the selector (e.g. `current_backend` or `fallback_history`).
+    fn record_transition(&self, from: BackendType, to: BackendType, reason: &str) {
+        {
+            let mut history = self.history.lock();
+            if history.len() == FALLBACK_HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(BackendTransition {
+                from,
+                to,
+                reason: reason.to_string(),
+                at: SystemTime::now(),
+            });
+        }
 
-        let mut active = self.active_backend.write();
-        let _ = active.cleanup();
-        *active = backend;
+        let listener = self.listener.read().clone();
+        if let Some(listener) = listener {
+            listener(from, to, reason);
+        }
+    }
+
+    /// Register a callback invoked on every backend change, replacing any previous one.
+    ///
+    /// The callback runs after all selector locks are released, so it may
+    /// safely call back into the selector.
+    pub fn set_backend_change_listener(&self, listener: BackendChangeListener) {
+        *self.listener.write() = Some(listener);
+    }
+
+    /// Remove the backend change listener
+    pub fn clear_backend_change_listener(&self) {
+        *self.listener.write() = None;
+    }
+
+    /// The last backend transitions (up to 64), oldest first
+    pub fn fallback_history(&self) -> Vec<BackendTransition> {
+        self.history.lock().iter().cloned().collect()
+    }
+
+    /// Switch to a specific backend
+    fn switch_to(&mut self, backend_type: BackendType) -> Result<(), BackendError> {
+        let mut backend = Self::create_backend(backend_type);
+        backend.init()?;
+        self.install(backend, "preferred backend set");
 
         info!("Switched to backend: {:?}", backend_type);
         Ok(())
     }
 
+    /// Exercise each available backend against a local discard socket and
+    /// switch to the one with the highest packet rate.
+    ///
+    /// The result is cached and returned by `last_benchmark`. A backend that
+    /// fails to initialize or errors on its first send is skipped.
+    pub fn benchmark_and_select(
+        &self,
+        probe_packets: usize,
+    ) -> Result<BenchmarkResult, BackendError> {
+        let candidates = self.available_backends();
+        self.benchmark_candidates(&candidates, probe_packets, Self::create_backend)
+    }
+
+    /// Benchmark `candidates` built by `factory` and install the fastest
+    fn benchmark_candidates<F>(
+        &self,
+        candidates: &[BackendType],
+        probe_packets: usize,
+        factory: F,
+    ) -> Result<BenchmarkResult, BackendError>
+    where
+        F: Fn(BackendType) -> Box<dyn Backend>,
+    {
+        // Bound locally so probes never leave the host; excess datagrams are dropped
+        let sink = UdpSocket::bind("127.0.0.1:0")
+            .map_err(|e| BackendError::InitFailed(format!("probe sink: {}", e)))?;
+        let dest = sink
+            .local_addr()
+            .map_err(|e| BackendError::InitFailed(format!("probe sink: {}", e)))?;
+
+        let mut rates = Vec::with_capacity(candidates.len());
+        let mut best: Option<(f64, Box<dyn Backend>)> = None;
+
+        for &backend_type in candidates {
+            let mut backend = factory(backend_type);
+            if let Err(e) = backend.init() {
+                debug!("Benchmark skipping {:?}: {}", backend_type, e);
+                rates.push((backend_type, None));
+                continue;
+            }
+
+            let rate = Self::probe_rate(backend.as_ref(), dest, probe_packets.max(1));
+            rates.push((backend_type, rate));
+            debug!("Benchmark {:?}: {:?} pps", backend_type, rate);
+
+            match rate {
+                Some(pps) if !matches!(&best, Some((best_pps, _)) if *best_pps >= pps) => {
+                    if let Some((_, mut previous)) = best.replace((pps, backend)) {
+                        let _ = previous.cleanup();
+                    }
+                }
+                _ => {
+                    let _ = backend.cleanup();
+                }
+            }
+        }
+
+        let (_, backend) = best.ok_or_else(|| {
+            BackendError::NotAvailable("No backend completed the benchmark".into())
+        })?;
+        let selected = backend.backend_type();
+        self.install(backend, "benchmark selected fastest backend");
+        info!("Benchmark selected backend: {:?}", selected);
+
+        let result = BenchmarkResult { selected, rates };
+        *self.benchmark.write() = Some(result.clone());
+        Ok(result)
+    }
+
+    /// Send `probe_packets` through `backend` and return the achieved rate
+    fn probe_rate(backend: &dyn Backend, dest: SocketAddr, probe_packets: usize) -> Option<f64> {
+        let payload = [0u8; PROBE_PAYLOAD_SIZE];
+        let batch = [&payload[..]; PROBE_BATCH_SIZE];
+
+        let start = Instant::now();
+        // An error on the very first send means the backend cannot reach the target at all
+        backend.send(&payload, dest).ok()?;
+
+        let mut sent = 1;
+        while sent < probe_packets {
+            let n = (probe_packets - sent).min(PROBE_BATCH_SIZE);
+            match backend.send_batch(&batch[..n], dest) {
+                Ok(0) | Err(_) => break,
+                Ok(count) => sent += count,
+            }
+        }
+
+        let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+        Some(sent as f64 / elapsed)
+    }
+
+    /// Result of the last benchmark run, if any
+    pub fn last_benchmark(&self) -> Option<BenchmarkResult> {
+        self.benchmark.read().clone()
+    }
+
     /// Get the current active backend type
     pub fn current_backend(&self) -> BackendType {
         self.active_backend.read().backend_type()
     }
 
     /// Get system capabilities
-    pub fn capabilities(&self) -> &SystemCapabilities {
-        &self.capabilities
+    pub fn capabilities(&self) -> SystemCapabilities {
+        self.capabilities.read().clone()
+    }
+
+    /// Re-run capability detection so newly loaded (or unloaded) kernel
+    /// features are reflected in `available_backends`.
+    ///
+    /// If the active backend is no longer available, falls back to the next
+    /// available one in priority order.
+    pub fn refresh_capabilities(&self) -> Result<(), BackendError> {
+        self.apply_capabilities(detect_system_capabilities())
+    }
+
+    /// Replace the capability set and move off the active backend if it vanished
+    fn apply_capabilities(&self, capabilities: SystemCapabilities) -> Result<(), BackendError> {
+        *self.capabilities.write() = capabilities;
+        debug!("Refreshed capabilities: {:?}", self.available_backends());
+
+        let current = self.current_backend();
+        if self.is_backend_available(current) {
+            return Ok(());
+        }
+        warn!(
+            "Active backend {:?} no longer available, falling back",
+            current
+        );
+        self.try_fallback("backend no longer available after capability refresh")
+    }
+
+    /// Re-probe capabilities every `interval` on a background thread until
+    /// the returned handle is dropped.
+    pub fn start_reprobe(self: &Arc<Self>, interval: Duration) -> ReprobeHandle {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let selector: Weak<Self> = Arc::downgrade(self);
+        let thread_cancel = Arc::clone(&cancel);
+
+        let handle = std::thread::Builder::new()
+            .name("backend-reprobe".into())
+            .spawn(move || {
+                let (cancelled, wake) = &*thread_cancel;
+                let mut guard = cancelled.lock();
+                while !*guard {
+                    wake.wait_for(&mut guard, interval);
+                    if *guard {
+                        break;
+                    }
+                    let Some(selector) = selector.upgrade() else {
+                        break;
+                    };
+                    if let Err(e) = selector.refresh_capabilities() {
+                        warn!("Capability re-probe failed: {}", e);
+                    }
+                }
+            })
+            .ok();
+
+        ReprobeHandle { cancel, handle }
     }
 
     /// Enable or disable automatic fallback
@@ -169,6 +431,10 @@ impl BackendSelector {
     }
 
     /// Try to send with automatic fallback on failure
+    ///
+    /// A send that fails while the backend's send buffer is full (ENOBUFS,
+    /// EAGAIN) is backpressure rather than a broken backend: it waits for room
+    /// and retries once on the same backend instead of falling back.
     pub fn send_with_fallback(
         &self,
         data: &[u8],
@@ -178,11 +444,15 @@ impl BackendSelector {
 
         match backend.send(data, dest) {
             Ok(n) => Ok(n),
+            Err(_) if !backend.writable_hint() => {
+                wait_writable(backend.as_ref());
+                backend.send(data, dest)
+            }
             Err(e) => {
                 if self.fallback_enabled.load(Ordering::Relaxed) {
                     warn!("Send failed, attempting fallback: {}", e);
                     drop(backend);
-                    self.try_fallback()?;
+                    self.try_fallback(&format!("send failed: {}", e))?;
                     self.active_backend.read().send(data, dest)
                 } else {
                     Err(e)
@@ -191,7 +461,8 @@ impl BackendSelector {
         }
     }
 
-    /// Try to send batch with automatic fallback
+    /// Try to send batch with automatic fallback, backing off like
+    /// `send_with_fallback` when the send buffer is full
     pub fn send_batch_with_fallback(
         &self,
         packets: &[&[u8]],
@@ -201,11 +472,15 @@ impl BackendSelector {
 
         match backend.send_batch(packets, dest) {
             Ok(n) => Ok(n),
+            Err(_) if !backend.writable_hint() => {
+                wait_writable(backend.as_ref());
+                backend.send_batch(packets, dest)
+            }
             Err(e) => {
                 if self.fallback_enabled.load(Ordering::Relaxed) {
                     warn!("Batch send failed, attempting fallback: {}", e);
                     drop(backend);
-                    self.try_fallback()?;
+                    self.try_fallback(&format!("batch send failed: {}", e))?;
                     self.active_backend.read().send_batch(packets, dest)
                 } else {
                     Err(e)
@@ -215,7 +490,7 @@ impl BackendSelector {
     }
 
     /// Attempt to fall back to the next available backend
-    fn try_fallback(&self) -> Result<(), BackendError> {
+    fn try_fallback(&self, reason: &str) -> Result<(), BackendError> {
         let current = self.current_backend();
         let current_idx = self.priority.iter().position(|&b| b == current);
 
@@ -226,37 +501,9 @@ impl BackendSelector {
             if self.is_backend_available(backend_type) {
                 debug!("Attempting fallback to {:?}", backend_type);
 
-                let mut backend: Box<dyn Backend> = match backend_type {
-                    BackendType::RawSocket | BackendType::None => Box::new(StandardBackend::new()),
-                    #[cfg(target_os = "linux")]
-                    BackendType::Sendmmsg
-                    | BackendType::IoUring
-                    | BackendType::AfXdp
-                    | BackendType::Dpdk => {
-                        Box::new(crate::backend::NativeBackend::new(backend_type))
-                    }
-                    #[cfg(target_os = "windows")]
-                    BackendType::IOCP => Box::new(crate::windows_backend::IOCPBackend::new()),
-                    #[cfg(target_os = "windows")]
-                    BackendType::RegisteredIO => {
-                        Box::new(crate::windows_backend::RegisteredIOBackend::new())
-                    }
-                    #[cfg(target_os = "macos")]
-                    BackendType::Kqueue => Box::new(crate::macos_backend::KqueueBackend::new()),
-                    #[cfg(not(any(
-                        target_os = "linux",
-                        target_os = "windows",
-                        target_os = "macos"
-                    )))]
-                    _ => Box::new(StandardBackend::new()),
-                    #[cfg(any(target_os = "windows", target_os = "macos"))]
-                    _ => Box::new(StandardBackend::new()),
-                };
-
+                let mut backend = Self::create_backend(backend_type);
                 if backend.init().is_ok() {
-                    let mut active = self.active_backend.write();
-                    let _ = active.cleanup();
-                    *active = backend;
+                    self.install(backend, reason);
                     info!("Fallback successful: {:?}", backend_type);
                     return Ok(());
                 }
@@ -284,6 +531,23 @@ impl BackendSelector {
     }
 }
 
+/// Background capability re-probe; stops when dropped
+pub struct ReprobeHandle {
+    cancel: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for ReprobeHandle {
+    fn drop(&mut self) {
+        let (cancelled, wake) = &*self.cancel;
+        *cancelled.lock() = true;
+        wake.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
 impl Default for BackendSelector {
     fn default() -> Self {
         Self::new()
@@ -354,6 +618,264 @@ mod tests {
         assert!(!selector.is_fallback_enabled());
     }
 
+    /// Backend with a fixed per-batch cost, optionally failing every send
+    struct StubBackend {
+        kind: BackendType,
+        batch_cost: Duration,
+        fail: bool,
+    }
+
+    impl Backend for StubBackend {
+        fn backend_type(&self) -> BackendType {
+            self.kind
+        }
+
+        fn init(&mut self) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn send(&self, _data: &[u8], _dest: SocketAddr) -> Result<usize, BackendError> {
+            if self.fail {
+                return Err(BackendError::SendFailed("stub".into()));
+            }
+            Ok(PROBE_PAYLOAD_SIZE)
+        }
+
+        fn send_batch(&self, packets: &[&[u8]], _dest: SocketAddr) -> Result<usize, BackendError> {
+            if self.fail {
+                return Err(BackendError::SendFailed("stub".into()));
+            }
+            std::thread::sleep(self.batch_cost);
+            Ok(packets.len())
+        }
+
+        fn cleanup(&mut self) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn stats(&self) -> crate::backend::BackendStats {
+            Default::default()
+        }
+    }
+
+    /// Backend whose send buffer is full, refusing sends, until `drained_at`
+    struct FullBackend {
+        drained_at: Instant,
+    }
+
+    impl Backend for FullBackend {
+        fn backend_type(&self) -> BackendType {
+            BackendType::Sendmmsg
+        }
+
+        fn init(&mut self) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn send(&self, data: &[u8], _dest: SocketAddr) -> Result<usize, BackendError> {
+            if !self.writable_hint() {
+                return Err(BackendError::SendFailed("No buffer space available".into()));
+            }
+            Ok(data.len())
+        }
+
+        fn send_batch(&self, packets: &[&[u8]], dest: SocketAddr) -> Result<usize, BackendError> {
+            self.send(packets[0], dest).map(|_| packets.len())
+        }
+
+        fn cleanup(&mut self) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn stats(&self) -> crate::backend::BackendStats {
+            Default::default()
+        }
+
+        fn writable_hint(&self) -> bool {
+            Instant::now() >= self.drained_at
+        }
+    }
+
+    #[test]
+    fn test_full_send_buffer_backs_off_instead_of_falling_back() {
+        let selector = BackendSelector::new();
+        let dest: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let packet = [0u8; PROBE_PAYLOAD_SIZE];
+
+        // Drains within the wait: the retry succeeds on the same backend
+        let drained_at = Instant::now() + BACKPRESSURE_WAIT / 4;
+        selector.install(Box::new(FullBackend { drained_at }), "test");
+        assert_eq!(
+            selector.send_with_fallback(&packet, dest).unwrap(),
+            packet.len()
+        );
+        assert!(Instant::now() >= drained_at);
+        let drained_at = Instant::now() + BACKPRESSURE_WAIT / 4;
+        selector.install(Box::new(FullBackend { drained_at }), "test");
+        assert_eq!(
+            selector
+                .send_batch_with_fallback(&[&packet, &packet], dest)
+                .unwrap(),
+            2
+        );
+
+        // Stays full: the send fails after the wait, still without falling back
+        let drained_at = Instant::now() + Duration::from_secs(60);
+        selector.install(Box::new(FullBackend { drained_at }), "test");
+        let started = Instant::now();
+        assert!(selector.send_with_fallback(&packet, dest).is_err());
+        assert!(started.elapsed() >= BACKPRESSURE_WAIT);
+        assert_eq!(selector.current_backend(), BackendType::Sendmmsg);
+    }
+
+    #[test]
+    fn test_refresh_capabilities() {
+        let selector = BackendSelector::new();
+        let mut caps = selector.capabilities();
+
+        caps.has_sendmmsg = true;
+        caps.has_raw_socket = true;
+        selector.apply_capabilities(caps.clone()).unwrap();
+        assert!(selector
+            .available_backends()
+            .contains(&BackendType::Sendmmsg));
+
+        caps.has_sendmmsg = false;
+        selector.apply_capabilities(caps).unwrap();
+        assert!(!selector
+            .available_backends()
+            .contains(&BackendType::Sendmmsg));
+        assert!(selector
+            .available_backends()
+            .contains(&BackendType::RawSocket));
+    }
+
+    #[test]
+    fn test_refresh_falls_back_when_active_vanishes() {
+        let selector = BackendSelector::new();
+        selector.install(
+            Box::new(StubBackend {
+                kind: BackendType::Dpdk,
+                batch_cost: Duration::ZERO,
+                fail: false,
+            }),
+            "test",
+        );
+
+        let mut caps = selector.capabilities();
+        caps.has_dpdk = false;
+        caps.has_raw_socket = true;
+        selector.apply_capabilities(caps).unwrap();
+        assert_ne!(selector.current_backend(), BackendType::Dpdk);
+        assert!(selector.is_backend_available(selector.current_backend()));
+    }
+
+    #[test]
+    fn test_reprobe_handle_stops_on_drop() {
+        let selector = Arc::new(BackendSelector::new());
+        let handle = selector.start_reprobe(Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(20));
+        drop(handle);
+        assert!(!selector.available_backends().is_empty());
+    }
+
+    #[test]
+    fn test_fallback_notifies_listener() {
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let selector = Arc::new(BackendSelector::new());
+        let mut caps = selector.capabilities();
+        caps.has_raw_socket = true;
+        selector.apply_capabilities(caps).unwrap();
+        selector.install(
+            Box::new(StubBackend {
+                kind: BackendType::Dpdk,
+                batch_cost: Duration::ZERO,
+                fail: true,
+            }),
+            "test",
+        );
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (seen, inner) = (Arc::clone(&calls), Arc::downgrade(&selector));
+        selector.set_backend_change_listener(Arc::new(move |from, to, reason: &str| {
+            // Re-entering the selector must not deadlock
+            let selector = inner.upgrade().unwrap();
+            assert_eq!(selector.current_backend(), to);
+            assert!(!selector.fallback_history().is_empty());
+            seen.lock().push((from, to, reason.to_string()));
+        }));
+
+        let dest = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9);
+        let _ = selector.send_with_fallback(b"x", dest);
+
+        let calls = calls.lock();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, BackendType::Dpdk);
+        assert_ne!(calls[0].1, BackendType::Dpdk);
+        assert!(calls[0].2.starts_with("send failed"));
+
+        let history = selector.fallback_history();
+        assert_eq!(history.last().unwrap().from, BackendType::Dpdk);
+        assert_eq!(history.last().unwrap().reason, calls[0].2);
+    }
+
+    #[test]
+    fn test_benchmark_selects_fastest() {
+        let selector = BackendSelector::new();
+        let stub = |kind| -> Box<dyn Backend> {
+            let (batch_cost, fail) = match kind {
+                BackendType::IoUring => (Duration::from_millis(2), false),
+                BackendType::Sendmmsg => (Duration::from_micros(50), false),
+                _ => (Duration::ZERO, true),
+            };
+            Box::new(StubBackend {
+                kind,
+                batch_cost,
+                fail,
+            })
+        };
+
+        let candidates = [
+            BackendType::Dpdk,
+            BackendType::IoUring,
+            BackendType::Sendmmsg,
+        ];
+        let result = selector
+            .benchmark_candidates(&candidates, 1000, stub)
+            .unwrap();
+
+        assert_eq!(result.selected, BackendType::Sendmmsg);
+        assert_eq!(selector.current_backend(), BackendType::Sendmmsg);
+        assert_eq!(result.rates[0], (BackendType::Dpdk, None));
+        let slow = result.rates[1].1.unwrap();
+        let fast = result.rates[2].1.unwrap();
+        assert!(fast > slow, "fast {} <= slow {}", fast, slow);
+        assert_eq!(
+            selector.last_benchmark().unwrap().selected,
+            BackendType::Sendmmsg
+        );
+
+        let all_failing = |kind| -> Box<dyn Backend> {
+            Box::new(StubBackend {
+                kind,
+                batch_cost: Duration::ZERO,
+                fail: true,
+            })
+        };
+        assert!(selector
+            .benchmark_candidates(&candidates, 10, all_failing)
+            .is_err());
+    }
+
     #[test]
     fn test_capability_report() {
         let selector = BackendSelector::new();
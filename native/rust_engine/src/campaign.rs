@@ -0,0 +1,366 @@
+//! Several flood engines run as one campaign under a shared rate budget
+//! Each target gets a weighted share of the global packets-per-second budget
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::engine::{EngineConfig, EngineError, FloodEngine};
+use crate::safety::SafetyController;
+use crate::stats::StatsSnapshot;
+
+/// One engine and its share of the campaign budget
+struct CampaignTarget {
+    id: usize,
+    weight: f64,
+    /// Packets per second currently assigned to this engine
+    rate: u64,
+    engine: FloodEngine,
+}
+
+/// Engines started, stopped and reported on together
+///
+/// The global budget is split across targets in proportion to their weights
+/// and re-split whenever a target is added or removed, running or not. Shares
+/// are rounded down so their sum never exceeds the budget; a target whose
+/// share would round to nothing gets 1 pps, taken out of the budget first.
+pub struct Campaign {
+    budget: u64,
+    targets: Vec<CampaignTarget>,
+    next_id: usize,
+    safety: Option<Arc<SafetyController>>,
+    start_time: Option<Instant>,
+    running: bool,
+    /// Counters of targets removed during the current run
+    retired: StatsSnapshot,
+}
+
+impl Campaign {
+    /// Empty campaign limited to `budget` packets per second in total
+    pub fn new(budget: u64) -> Result<Self, EngineError> {
+        if budget == 0 {
+            return Err(EngineError::InvalidConfig(
+                "campaign budget must be at least 1 pps".to_string(),
+            ));
+        }
+        Ok(Self {
+            budget,
+            targets: Vec::new(),
+            next_id: 0,
+            safety: None,
+            start_time: None,
+            running: false,
+            retired: StatsSnapshot::default(),
+        })
+    }
+
+    /// Add an engine for `config` with relative `weight`; returns its id
+    ///
+    /// `config.rate_limit` is replaced by the target's share of the budget.
+    /// While the campaign runs the new engine starts immediately.
+    pub fn add_target(&mut self, config: EngineConfig, weight: f64) -> Result<usize, EngineError> {
+        if !weight.is_finite() || weight <= 0.0 {
+            return Err(EngineError::InvalidConfig(
+                "target weight must be a positive number".to_string(),
+            ));
+        }
+        // Every engine needs at least 1 pps; a rate of 0 would mean unlimited
+        if self.targets.len() as u64 >= self.budget {
+            return Err(EngineError::InvalidConfig(format!(
+                "a budget of {} pps cannot be split across {} targets",
+                self.budget,
+                self.targets.len() + 1
+            )));
+        }
+
+        let mut engine = FloodEngine::new(EngineConfig {
+            rate_limit: None,
+            ..config
+        })?;
+        if let Some(safety) = &self.safety {
+            engine.set_safety(Arc::clone(safety));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.targets.push(CampaignTarget {
+            id,
+            weight,
+            rate: 0,
+            engine,
+        });
+        self.rebalance();
+
+        if self.running {
+            let started = self.targets.last_mut().map(|t| t.engine.start());
+            if let Some(Err(e)) = started {
+                self.targets.pop();
+                self.rebalance();
+                return Err(e);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Stop and drop target `id`, handing its share to the others
+    ///
+    /// Returns the target's final counters, which stay in `get_stats` until
+    /// the next `start`.
+    pub fn remove_target(&mut self, id: usize) -> Result<StatsSnapshot, EngineError> {
+        let index = self
+            .targets
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| EngineError::InvalidTarget(format!("no campaign target {}", id)))?;
+        let mut target = self.targets.remove(index);
+        // Only hand its share to the others once it has stopped sending
+        if target.engine.is_running() {
+            target.engine.stop()?;
+        }
+        self.rebalance();
+
+        let stats = target.engine.get_stats();
+        if self.running {
+            accumulate(&mut self.retired, &stats);
+        }
+        Ok(stats)
+    }
+
+    /// Change the global budget and re-split it across the targets
+    pub fn set_budget(&mut self, budget: u64) -> Result<(), EngineError> {
+        if budget == 0 || budget < self.targets.len() as u64 {
+            return Err(EngineError::InvalidConfig(format!(
+                "a budget of {} pps cannot be split across {} targets",
+                budget,
+                self.targets.len()
+            )));
+        }
+        self.budget = budget;
+        self.rebalance();
+        Ok(())
+    }
+
+    /// Govern every engine, current and future, with `safety`
+    pub fn set_safety(&mut self, safety: Arc<SafetyController>) {
+        for target in &mut self.targets {
+            target.engine.set_safety(Arc::clone(&safety));
+        }
+        self.safety = Some(safety);
+    }
+
+    /// Start every engine; on failure the ones already started are stopped again
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        if self.running {
+            return Err(EngineError::AlreadyRunning);
+        }
+        if self.targets.is_empty() {
+            return Err(EngineError::InvalidConfig(
+                "campaign has no targets".to_string(),
+            ));
+        }
+
+        for index in 0..self.targets.len() {
+            if let Err(e) = self.targets[index].engine.start() {
+                for started in &mut self.targets[..index] {
+                    let _ = started.engine.stop();
+                }
+                return Err(e);
+            }
+        }
+        self.retired = StatsSnapshot::default();
+        self.start_time = Some(Instant::now());
+        self.running = true;
+        Ok(())
+    }
+
+    /// Stop every engine, reporting the first error after all were stopped
+    pub fn stop(&mut self) -> Result<(), EngineError> {
+        if !self.running {
+            return Err(EngineError::NotRunning);
+        }
+        self.running = false;
+
+        let mut first_error = None;
+        for target in &mut self.targets {
+            if let Err(e) = target.engine.stop() {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// `(id, weight, pps share)` of every target in insertion order
+    pub fn targets(&self) -> Vec<(usize, f64, u64)> {
+        self.targets
+            .iter()
+            .map(|t| (t.id, t.weight, t.rate))
+            .collect()
+    }
+
+    /// Campaign-wide totals since `start`
+    ///
+    /// Counters are summed over all targets, including ones removed during
+    /// the run; `pps` and `bps` are those totals over the campaign duration.
+    /// The sampled peak and percentile rates are per-engine values summed, so
+    /// they are upper bounds for the campaign.
+    pub fn get_stats(&self) -> StatsSnapshot {
+        let mut total = self.retired.clone();
+        for target in &self.targets {
+            accumulate(&mut total, &target.engine.get_stats());
+        }
+
+        let duration = self
+            .start_time
+            .map(|t| t.elapsed())
+            .unwrap_or(Duration::ZERO);
+        let secs = duration.as_secs_f64().max(0.001);
+        total.duration = duration;
+        total.pps = (total.packets_sent as f64 / secs) as u64;
+        total.bps = (total.bytes_sent as f64 / secs) as u64;
+        total
+    }
+
+    /// Give every target `budget * weight / total weight` pps, at least 1
+    fn rebalance(&mut self) {
+        // Targets too light for a whole pps are floored at 1 and the rest
+        // split what is left, until no remaining share rounds to nothing
+        let mut floored = vec![false; self.targets.len()];
+        loop {
+            let reserved = floored.iter().filter(|&&f| f).count() as u64;
+            let remaining = self.budget.saturating_sub(reserved);
+            let total: f64 = self
+                .targets
+                .iter()
+                .zip(&floored)
+                .filter(|(_, &f)| !f)
+                .map(|(t, _)| t.weight)
+                .sum();
+            let mut changed = false;
+            for (target, floored) in self.targets.iter_mut().zip(&mut floored) {
+                if *floored {
+                    target.rate = 1;
+                    continue;
+                }
+                target.rate = (remaining as f64 * target.weight / total) as u64;
+                if target.rate == 0 {
+                    *floored = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        for target in &mut self.targets {
+            target.engine.set_rate(target.rate);
+        }
+    }
+}
+
+impl Drop for Campaign {
+    fn drop(&mut self) {
+        if self.running {
+            let _ = self.stop();
+        }
+    }
+}
+
+/// Add `stats`' counters and sampled rates into `total`
+fn accumulate(total: &mut StatsSnapshot, stats: &StatsSnapshot) {
+    total.packets_sent += stats.packets_sent;
+    total.bytes_sent += stats.bytes_sent;
+    total.errors += stats.errors;
+    total.packets_dropped += stats.packets_dropped;
+    total.oversized_errors += stats.oversized_errors;
+    total.peak_pps += stats.peak_pps;
+    total.p50_pps += stats.p50_pps;
+    total.p95_pps += stats.p95_pps;
+    total.p99_pps += stats.p99_pps;
+    // The narrowest discovered path limits the campaign
+    if stats.path_mtu != 0 && (total.path_mtu == 0 || stats.path_mtu < total.path_mtu) {
+        total.path_mtu = stats.path_mtu;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target_config(port: u16) -> EngineConfig {
+        EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 2,
+            sockets_per_thread: 1,
+            dry_run: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_weighted_targets_share_global_budget() {
+        let budget = 3_000;
+        let mut campaign = Campaign::new(budget).unwrap();
+        for (port, weight) in [(9, 1.0), (10, 2.0), (11, 3.0)] {
+            campaign.add_target(target_config(port), weight).unwrap();
+        }
+        let rates: Vec<u64> = campaign.targets().iter().map(|t| t.2).collect();
+        assert_eq!(rates, [500, 1_000, 1_500]);
+
+        campaign.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        // Adding a target mid-run re-splits the budget across all four
+        let added = campaign.add_target(target_config(12), 2.0).unwrap();
+        let rates: Vec<u64> = campaign.targets().iter().map(|t| t.2).collect();
+        assert_eq!(rates, [375, 750, 1_125, 750]);
+        assert!(rates.iter().sum::<u64>() <= budget);
+        std::thread::sleep(Duration::from_millis(300));
+
+        campaign.remove_target(added).unwrap();
+        let rates: Vec<u64> = campaign.targets().iter().map(|t| t.2).collect();
+        assert_eq!(rates, [500, 1_000, 1_500]);
+        std::thread::sleep(Duration::from_millis(600));
+
+        let stats = campaign.get_stats();
+        campaign.stop().unwrap();
+        assert!(stats.packets_sent > 0);
+        // Each engine's bucket may start with a small burst on top of its share
+        assert!(
+            stats.pps <= budget + budget / 10,
+            "{} pps over a {} pps budget",
+            stats.pps,
+            budget
+        );
+
+        // Skewed weights: the 1-pps floors come out of the budget first
+        let mut campaign = Campaign::new(10).unwrap();
+        for (port, weight) in [(9, 1000.0), (10, 1.0), (11, 1.0)] {
+            campaign.add_target(target_config(port), weight).unwrap();
+        }
+        let rates: Vec<u64> = campaign.targets().iter().map(|t| t.2).collect();
+        assert_eq!(rates, [8, 1, 1]);
+    }
+
+    #[test]
+    fn test_campaign_rejects_invalid_targets() {
+        let mut campaign = Campaign::new(2).unwrap();
+        assert!(Campaign::new(0).is_err());
+        assert!(campaign.add_target(target_config(9), 0.0).is_err());
+        assert!(campaign.add_target(target_config(9), f64::NAN).is_err());
+        assert!(campaign.start().is_err());
+
+        campaign.add_target(target_config(9), 1.0).unwrap();
+        campaign.add_target(target_config(10), 1.0).unwrap();
+        // A third target would need a share of less than 1 pps
+        assert!(campaign.add_target(target_config(11), 1.0).is_err());
+        assert!(campaign.set_budget(1).is_err());
+        assert!(campaign.remove_target(7).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+//! Line-delimited JSON control API for the flood engine
+//! Lets orchestration tools drive an engine over a local TCP socket without Python
+//!
+//! Each request is one JSON object per line carrying the shared token:
+//! `{"token":"...","cmd":"set_rate","pps":10000}`. Supported commands are
+//! `start`, `stop`, `pause`, `resume`, `set_rate` and `get_stats`. Every reply
+//! is one JSON object per line with an `ok` field.
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::engine::FloodEngine;
+use crate::safety::SafetyController;
+
+/// Poll interval for accept/read loops so shutdown is noticed promptly
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Longest accepted request line
+const MAX_LINE_LEN: usize = 4096;
+
+/// One request line; fields other commands don't use are ignored
+#[derive(Deserialize)]
+struct Request {
+    token: Option<String>,
+    cmd: Option<String>,
+    pps: Option<u64>,
+}
+
+/// Control server wrapping a `FloodEngine`
+pub struct ControlServer {
+    engine: Arc<Mutex<FloodEngine>>,
+    safety: Arc<SafetyController>,
+    token: String,
+    allow_remote: bool,
+}
+
+impl ControlServer {
+    pub fn new(
+        mut engine: FloodEngine,
+        token: impl Into<String>,
+        safety: SafetyController,
+    ) -> Self {
+        let safety = Arc::new(safety);
+        engine.set_safety(Arc::clone(&safety));
+        Self {
+            engine: Arc::new(Mutex::new(engine)),
+            safety,
+            token: token.into(),
+            allow_remote: false,
+        }
+    }
+
+    /// Allow binding to non-loopback addresses (off by default)
+    pub fn allow_remote(mut self, allow: bool) -> Self {
+        self.allow_remote = allow;
+        self
+    }
+
+    /// Bind the listener and serve commands on a background thread
+    pub fn bind<A: ToSocketAddrs>(self, addr: A) -> std::io::Result<ControlHandle> {
+        if self.token.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "control token must not be empty",
+            ));
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        if !self.allow_remote && !local_addr.ip().is_loopback() {
+            return Err(std::io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("refusing non-loopback control address {}", local_addr),
+            ));
+        }
+        listener.set_nonblocking(true)?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = Arc::new(self);
+        let thread = {
+            let server = Arc::clone(&server);
+            let shutdown = Arc::clone(&shutdown);
+            thread::Builder::new()
+                .name("control-server".to_string())
+                .spawn(move || server.accept_loop(listener, shutdown))?
+        };
+
+        Ok(ControlHandle {
+            local_addr,
+            shutdown,
+            thread: Some(thread),
+            engine: Arc::clone(&server.engine),
+        })
+    }
+
+    fn accept_loop(self: Arc<Self>, listener: TcpListener, shutdown: Arc<AtomicBool>) {
+        let mut clients = Vec::new();
+        while !shutdown.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let server = Arc::clone(&self);
+                    let shutdown = Arc::clone(&shutdown);
+                    clients.push(thread::spawn(move || server.serve_client(stream, shutdown)));
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(_) => thread::sleep(POLL_INTERVAL),
+            }
+            clients.retain(|c: &JoinHandle<()>| !c.is_finished());
+        }
+        for client in clients {
+            let _ = client.join();
+        }
+    }
+
+    fn serve_client(&self, stream: TcpStream, shutdown: Arc<AtomicBool>) {
+        if stream.set_nonblocking(false).is_err()
+            || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err()
+        {
+            return;
+        }
+        let mut writer = match stream.try_clone() {
+            Ok(w) => w,
+            Err(_) => return,
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+
+        while !shutdown.load(Ordering::Relaxed) {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => return,
+                Ok(_) if line.ends_with(b"\n") => {
+                    let reply = self.handle_command(&String::from_utf8_lossy(&line));
+                    line.clear();
+                    if writeln!(writer, "{}", reply).is_err() {
+                        return;
+                    }
+                }
+                // Partial line before timeout, keep accumulating
+                Ok(_) => {}
+                Err(ref e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(_) => return,
+            }
+            if line.len() > MAX_LINE_LEN {
+                let _ = writeln!(writer, "{}", error_reply("request too long"));
+                return;
+            }
+        }
+    }
+
+    /// Execute one JSON command line and return the JSON reply
+    pub fn handle_command(&self, line: &str) -> String {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return error_reply(&format!("invalid request: {}", e)),
+        };
+        let authorized = request
+            .token
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()));
+        if !authorized {
+            return error_reply("unauthorized");
+        }
+
+        let Some(cmd) = request.cmd else {
+            return error_reply("missing cmd");
+        };
+
+        let mut engine = self.engine.lock();
+        let result = match cmd.as_str() {
+            "start" => self
+                .safety
+                .check_all(&engine.config().target)
+                .map_err(|e| e.to_string())
+                .and_then(|_| self.check_rate(engine.get_rate_limit()))
+                .and_then(|_| engine.start().map_err(|e| e.to_string())),
+            "stop" => engine.stop().map_err(|e| e.to_string()),
+            "pause" => engine.pause().map_err(|e| e.to_string()),
+            "resume" => engine.resume().map_err(|e| e.to_string()),
+            "set_rate" => match request.pps {
+                Some(pps) => self.check_rate(pps).map(|_| engine.set_rate(pps)),
+                None => Err("set_rate requires pps".to_string()),
+            },
+            "get_stats" => {
+                return format!(
+                    r#"{{"ok":true,"running":{},"paused":{},"stats":{}}}"#,
+                    engine.is_running(),
+                    engine.is_paused(),
+                    engine.get_stats().to_json()
+                );
+            }
+            other => Err(format!("unknown cmd {}", other)),
+        };
+
+        match result {
+            Ok(()) => format!(
+                r#"{{"ok":true,"running":{},"paused":{}}}"#,
+                engine.is_running(),
+                engine.is_paused()
+            ),
+            Err(e) => error_reply(&e),
+        }
+    }
+
+    /// Refuse a rate in pps (0 for unlimited) above the safety limit
+    fn check_rate(&self, pps: u64) -> Result<(), String> {
+        match self.safety.rate_limiter.max_pps() {
+            Some(max) if pps == 0 => {
+                Err(format!("unlimited rate exceeds safety limit {} PPS", max))
+            }
+            Some(max) if pps > max => Err(format!("rate {} exceeds safety limit {} PPS", pps, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Handle to a running control server
+pub struct ControlHandle {
+    local_addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    engine: Arc<Mutex<FloodEngine>>,
+}
+
+impl ControlHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Shared handle to the controlled engine
+    pub fn engine(&self) -> Arc<Mutex<FloodEngine>> {
+        Arc::clone(&self.engine)
+    }
+
+    /// Stop serving and stop the engine if it is still running
+    pub fn shutdown(mut self) {
+        self.close();
+    }
+
+    fn close(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let mut engine = self.engine.lock();
+        if engine.is_running() {
+            let _ = engine.stop();
+        }
+    }
+}
+
+impl Drop for ControlHandle {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn error_reply(message: &str) -> String {
+    format!(
+        r#"{{"ok":false,"error":"{}"}}"#,
+        message.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Compare tokens without leaking the mismatch position through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+
+    fn server(safety: SafetyController) -> ControlServer {
+        server_with_rate(safety, Some(1000))
+    }
+
+    fn server_with_rate(safety: SafetyController, rate_limit: Option<u64>) -> ControlServer {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 1,
+            sockets_per_thread: 1,
+            rate_limit,
+            ..Default::default()
+        };
+        ControlServer::new(FloodEngine::new(config).unwrap(), "secret", safety)
+    }
+
+    #[test]
+    fn test_rejects_bad_token() {
+        let server = server(SafetyController::permissive());
+        let reply = server.handle_command(r#"{"token":"wrong","cmd":"start"}"#);
+        assert!(reply.contains(r#""ok":false"#));
+        assert!(reply.contains("unauthorized"));
+        assert!(!server.engine.lock().is_running());
+    }
+
+    #[test]
+    fn test_start_enforces_safety() {
+        // Strict controller does not allow localhost
+        let server = server(SafetyController::new(1000));
+        let reply = server.handle_command(r#"{"token": "secret", "cmd": "start"}"#);
+        assert!(reply.contains(r#""ok":false"#), "{}", reply);
+
+        let reply = server.handle_command(r#"{"token":"secret","cmd":"set_rate","pps":5000}"#);
+        assert!(reply.contains("exceeds safety limit"), "{}", reply);
+    }
+
+    #[test]
+    fn test_start_enforces_safety_rate_limit() {
+        let start = r#"{"token":"secret","cmd":"start"}"#;
+        for (rate_limit, error) in [
+            (Some(1000), "rate 1000 exceeds safety limit 500 PPS"),
+            (None, "unlimited rate exceeds safety limit 500 PPS"),
+        ] {
+            let safety = SafetyController::permissive();
+            safety.rate_limiter.set_max_pps(500);
+            let server = server_with_rate(safety, rate_limit);
+            let reply = server.handle_command(start);
+            assert!(reply.contains(error), "{}", reply);
+            assert!(!server.engine.lock().is_running());
+        }
+
+        let safety = SafetyController::permissive();
+        safety.rate_limiter.set_max_pps(500);
+        let server = server_with_rate(safety, Some(400));
+        assert!(server.handle_command(start).contains(r#""running":true"#));
+        server.engine.lock().stop().unwrap();
+    }
+
+    #[test]
+    fn test_workers_obey_server_safety() {
+        let server = server(SafetyController::permissive());
+        assert!(server
+            .handle_command(r#"{"token":"secret","cmd":"start"}"#)
+            .contains(r#""running":true"#));
+
+        server.safety.emergency_stop.trigger("test");
+        let triggered = std::time::Instant::now();
+        while server.engine.lock().get_active_threads() > 0
+            && triggered.elapsed() < Duration::from_secs(1)
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.engine.lock().get_active_threads(), 0);
+        server.engine.lock().stop().unwrap();
+    }
+
+    #[test]
+    fn test_only_top_level_fields_count() {
+        let server = server(SafetyController::permissive());
+
+        let reply = server.handle_command(r#"{"meta":{"token":"secret"},"cmd":"start"}"#);
+        assert!(reply.contains("unauthorized"), "{}", reply);
+
+        let reply =
+            server.handle_command(r#"{"token":"secret","args":{"cmd":"start"},"cmd":"get_stats"}"#);
+        assert!(reply.contains(r#""stats":"#), "{}", reply);
+        assert!(!server.engine.lock().is_running());
+
+        let reply = server.handle_command(r#"{"token":"secret","cmd":"set_rate","pps":"fast"}"#);
+        assert!(reply.contains("invalid request"), "{}", reply);
+        let reply = server.handle_command("not json");
+        assert!(reply.contains("invalid request"), "{}", reply);
+    }
+
+    #[test]
+    fn test_command_lifecycle_over_tcp() {
+        let handle = server(SafetyController::permissive())
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let stream = TcpStream::connect(handle.local_addr()).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut send = |cmd: &str| {
+            writeln!(writer, r#"{{"token":"secret",{}}}"#, cmd).unwrap();
+            let mut reply = String::new();
+            reader.read_line(&mut reply).unwrap();
+            reply
+        };
+
+        assert!(send(r#""cmd":"start""#).contains(r#""running":true"#));
+        assert!(send(r#""cmd":"pause""#).contains(r#""paused":true"#));
+        assert!(send(r#""cmd":"resume""#).contains(r#""paused":false"#));
+        assert!(send(r#""cmd":"set_rate","pps":500"#).contains(r#""ok":true"#));
+        assert!(send(r#""cmd":"get_stats""#).contains(r#""stats":{"packets_sent":"#));
+        assert!(send(r#""cmd":"stop""#).contains(r#""running":false"#));
+        assert!(send(r#""cmd":"bogus""#).contains("unknown cmd"));
+
+        handle.shutdown();
+    }
+
+    #[test]
+    fn test_refuses_remote_bind_by_default() {
+        let result = server(SafetyController::permissive()).bind("0.0.0.0:0");
+        assert!(result.is_err());
+    }
+}
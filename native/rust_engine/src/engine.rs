@@ -9,32 +9,78 @@
 //! - CPU affinity and NUMA-aware allocation
 //! - Zero-copy packet transmission where supported
 
-use parking_lot::Mutex;
-use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use crossbeam::deque::{Steal, Stealer};
+use parking_lot::{Condvar, Mutex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tracing::warn;
 
+use crate::atomic_stats::{AtomicLogHistogram, PpsHistogram, StatsCollector, ThreadStats};
+use crate::backend::has_raw_socket_privilege;
+use crate::control::{ControlHandle, ControlServer};
+use crate::governor::{
+    capped_rate, ResourceGovernor, ResourceSampler, SystemSampler, GOVERNOR_INTERVAL,
+};
 use crate::packet::{PacketBuilder, PacketTemplates, Protocol};
+use crate::pcap::{udp_frame_between, PcapWriter, LINKTYPE_ETHERNET};
 use crate::pool::PacketPool;
+use crate::queue::{PacketQueue, WorkStealingQueue};
+use crate::rate_limiter::TokenBucket;
+use crate::responses::{HttpResponses, ResponseCounters, ResponseStats, UdpResponses, TXID_LEN};
+use crate::safety::SafetyController;
 use crate::stats::StatsSnapshot;
+use crate::target_health::{HealthPolicy, TargetHealthTracker};
 
 #[cfg(target_os = "linux")]
 use std::os::unix::io::AsRawFd;
 
 /// Performance tuning constants for maximum throughput
-const SOCKETS_PER_THREAD: usize = 8; // Multiple sockets reduce kernel lock contention
+const SOCKETS_PER_THREAD: usize = 8; // Default sockets per UDP worker (reduces kernel lock contention)
 const PAYLOAD_VARIANTS: usize = 32; // More variants for better cache utilization
 const INNER_BATCH_SIZE: u64 = 2000; // Packets per tight inner loop
 const OUTER_BATCH_SIZE: u64 = 100; // Inner loops before state check
 const STATS_FLUSH_INTERVAL: u64 = 10000; // Flush stats every N packets
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1); // Longest flush_stats waits for workers
 const SEND_BUFFER_SIZE: usize = 64 * 1024 * 1024; // 64MB send buffer
 const RECV_BUFFER_SIZE: usize = 64 * 1024 * 1024; // 64MB recv buffer
 const TCP_CONNECTION_POOL_SIZE: usize = 32; // Connections per thread
-const ADAPTIVE_SLEEP_MIN_US: u64 = 1; // Minimum adaptive sleep
-const ADAPTIVE_SLEEP_MAX_US: u64 = 500; // Maximum adaptive sleep
+const TCP_KEEPALIVE_CONNECTIONS: usize = 10; // Keep-alive connections held by each TCP worker
+const THREAD_STACK_SIZE: usize = 2 * 1024 * 1024; // std::thread default stack
+const TCP_SOCKET_MEMORY: usize = 256 * 1024; // Kernel buffers per TCP connection (default autotuning)
+const BUCKET_DEPTH_DIVISOR: u64 = 100; // Worker token buckets hold 1/100 s of tokens
+const PPS_SAMPLE_INTERVAL: Duration = Duration::from_secs(1); // Rate sampler period for peak/percentile stats
+const TASK_BATCH: u64 = 64; // Connection tasks a stealing worker queues for itself at once
+const SAFETY_BACKOFF: Duration = Duration::from_millis(1); // Wait while the safety limiter is over its cap
+const CONN_RATE_BACKOFF: Duration = Duration::from_millis(1); // Wait for a connection token
+const AUTOSCALE_INTERVAL: Duration = Duration::from_millis(500); // Measurement per worker count
+const AUTOSCALE_MIN_GAIN: f64 = 0.05; // Rate increase one more worker must bring to be kept
+const CALIBRATE_MAX_ERROR_RATIO: f64 = 0.01; // Share of failed sends that ends a calibrate ramp
+const RATE_SCHEDULE_STEP: Duration = Duration::from_millis(50); // Longest gap between scheduled rate updates
+const GSO_MAX_SEGMENTS: usize = 64; // Kernel cap on datagrams per UDP GSO send (UDP_MAX_SEGMENTS)
+const GSO_MAX_BYTES: usize = 65_000; // Largest coalesced buffer handed to one UDP GSO send
+const ZEROCOPY_MIN_PAYLOAD: usize = 10 * 1024; // Below this, page pinning costs more than the copy
+const ZEROCOPY_DRAIN_TIMEOUT: Duration = Duration::from_millis(100); // Wait for completions at exit
+const CAPTURE_QUEUE_CAPACITY: usize = 1024; // Samples queued for the capture writer before dropping
+const CAPTURE_WRITE_BATCH: usize = 64; // Samples the capture writer takes per wakeup
+const CAPTURE_POLL: Duration = Duration::from_millis(50); // Capture writer wait between checks
+const SEND_GAP_SAMPLE_EVERY: u64 = 8; // Bursts per send gap sample with record_send_timing
+const SEQUENCE_LEN: usize = 8; // Big-endian u64 written by embed_sequence
+const SEQUENCE_PREFIX_LEN: usize = 4; // Big-endian u32 thread id with sequence_per_worker
+
+#[cfg(target_os = "linux")]
+const SO_ZEROCOPY: libc::c_int = 60; // <asm-generic/socket.h>
+#[cfg(target_os = "linux")]
+const SO_EE_ORIGIN_ZEROCOPY: u8 = 5; // <linux/errqueue.h>
 
 #[derive(Debug, Error)]
 pub enum EngineError {
@@ -46,11 +92,22 @@ pub enum EngineError {
     AlreadyRunning,
     #[error("Engine not running")]
     NotRunning,
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("payload of {payload} bytes exceeds the path MTU of {mtu} bytes")]
+    PayloadExceedsMtu { payload: usize, mtu: usize },
+    #[error("{0} requires elevated privileges (root or CAP_NET_RAW)")]
+    InsufficientPrivileges(String),
     #[error("Thread error: {0}")]
     ThreadError(String),
+    #[error("Profile error: {0}")]
+    ProfileError(String),
+    #[error("Capture error: {0}")]
+    CaptureError(String),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum EngineState {
     Idle,
     Running,
@@ -58,16 +115,174 @@ pub enum EngineState {
     Stopped,
 }
 
+impl EngineState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => EngineState::Running,
+            2 => EngineState::Stopping,
+            3 => EngineState::Stopped,
+            _ => EngineState::Idle,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EngineState::Idle => "Idle",
+            EngineState::Running => "Running",
+            EngineState::Stopping => "Stopping",
+            EngineState::Stopped => "Stopped",
+        }
+    }
+}
+
+/// Lock-free view of an engine's lifecycle, readable while `stop()` is joining workers
 #[derive(Debug, Clone)]
+pub struct EngineStateHandle(Arc<AtomicU8>);
+
+impl EngineStateHandle {
+    pub fn get(&self) -> EngineState {
+        EngineState::from_u8(self.0.load(Ordering::SeqCst))
+    }
+
+    fn set(&self, state: EngineState) {
+        self.0.store(state as u8, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct EngineConfig {
     pub target: String,
     pub port: u16,
+    /// `host:port` endpoints to spread a UDP flood across instead of
+    /// `target` and `port`
+    ///
+    /// Each worker opens sockets to every endpoint and rotates through them
+    /// per burst. A hostname adds every address it resolves to, and is
+    /// resolved again on each `start`. IPv6 literals must be bracketed.
+    pub destinations: Vec<String>,
+    /// Sweep destination ports from the first to the second, inclusive,
+    /// instead of sending to `port` (UDP, TCP and HTTP)
+    ///
+    /// UDP sockets move to the next port every burst, TCP and HTTP workers
+    /// with every new connection.
+    pub port_range: Option<(u16, u16)>,
+    /// Order in which `port_range` is visited
+    pub port_mode: PortMode,
     pub threads: usize,
     pub packet_size: usize,
     pub protocol: Protocol,
     pub rate_limit: Option<u64>,
+    /// New TCP/HTTP connections per second across all workers, independent
+    /// of `rate_limit`; writes on pooled connections are not counted
+    pub conn_rate_limit: Option<u64>,
+    /// Stop automatically this long after `start`
     pub duration: Option<Duration>,
     pub use_raw_sockets: bool,
+    /// UDP sockets opened by each worker thread
+    pub sockets_per_thread: usize,
+    /// Fraction of packets deliberately not sent (0.0-1.0), counted as dropped
+    pub drop_fraction: Option<f64>,
+    /// Seed for the per-worker PRNG, makes the drop pattern reproducible
+    pub seed: Option<u64>,
+    /// Send the first TCP/HTTP request in the SYN (Linux only, falls back to connect)
+    pub tcp_fastopen: bool,
+    /// Per-packet UDP payload sizes sampled from a weighted histogram
+    pub size_distribution: Option<SizeDistribution>,
+    /// Refuse to create the engine when `estimate_memory` exceeds available RAM
+    pub refuse_over_memory: bool,
+    /// Address family to use when the target resolves to both A and AAAA records
+    pub address_family: AddressFamily,
+    /// Send only through this network interface, e.g. `eth1`
+    ///
+    /// Sockets are bound with `SO_BINDTODEVICE` on Linux, which needs
+    /// CAP_NET_RAW before kernel 5.7; other platforms bind them to the
+    /// interface's address. `start` fails if the interface cannot be used.
+    pub interface: Option<String>,
+    /// Set DF on UDP sockets so oversized payloads fail with EMSGSIZE instead of fragmenting
+    pub dont_fragment: bool,
+    /// Shrink UDP payloads to fit the discovered path MTU after EMSGSIZE
+    pub clamp_to_mtu: bool,
+    /// IPv4 TTL / IPv6 hop limit on every socket; the OS default when `None`
+    pub ttl: Option<u8>,
+    /// DSCP (0-63) for the IPv4 ToS / IPv6 traffic class byte
+    pub dscp: Option<u8>,
+    /// ECN codepoint (0-3) for the IPv4 ToS / IPv6 traffic class byte
+    pub ecn: Option<u8>,
+    /// Lower a UDP `packet_size` that exceeds the egress interface MTU at creation (Linux)
+    pub clamp_to_interface_mtu: bool,
+    /// Pin worker N to CPU `N % cores`
+    pub pin_threads: bool,
+    /// With `pin_threads`, spread workers round-robin across NUMA nodes
+    pub numa_aware: bool,
+    /// Build each UDP payload from its packet index instead of the fixed variants
+    ///
+    /// Not saved in profiles.
+    #[serde(skip)]
+    pub payload_factory: Option<PayloadFactory>,
+    /// Run the full worker loop, rate limiting included, without opening sockets
+    pub dry_run: bool,
+    /// How TCP/HTTP requests are shared between workers
+    pub work_distribution: WorkDistribution,
+    /// Coalesce up to this many UDP datagrams per send with UDP GSO (Linux 4.18+)
+    ///
+    /// Falls back to one send per datagram when the kernel rejects GSO.
+    pub gso_segments: Option<u16>,
+    /// Where UDP payloads come from
+    pub source: PacketSource,
+    /// With `PacketSource::Pcap`, keep the captured gaps between packets,
+    /// sped up by this factor; `None` sends as fast as the rate limit allows
+    pub replay_timing: Option<f64>,
+    /// Send UDP payloads of at least 10 KiB with `MSG_ZEROCOPY` (Linux)
+    ///
+    /// Packets and bytes are counted when the kernel reports the send
+    /// complete, not when `send` returns. Ignored while GSO is in use.
+    pub zerocopy: bool,
+    /// Start with `threads` workers and add more while throughput keeps rising
+    ///
+    /// Workers up to `max_threads` are spawned at `start` and parked until the
+    /// supervisor needs them. Growth stops for the run once one more worker
+    /// no longer raises the rate by `AUTOSCALE_MIN_GAIN`.
+    pub autoscale: bool,
+    /// Ceiling for `autoscale`; the CPU count when `None`
+    pub max_threads: Option<usize>,
+    /// Move the rate limit from one rate to another after `start`
+    ///
+    /// Overrides `rate_limit` and any `set_rate` call until the ramp is over.
+    pub ramp: Option<RampConfig>,
+    /// `(offset from start, pps)` points applied in turn; cannot be combined
+    /// with `ramp`
+    ///
+    /// Offsets must strictly increase. `rate_limit` applies before the first
+    /// point and the last rate is held after it.
+    pub rate_schedule: Vec<(Duration, u64)>,
+    /// Move linearly between `rate_schedule` points instead of stepping
+    pub interpolate_schedule: bool,
+    /// Sample the gaps between worker 0's UDP sends, see
+    /// `FloodEngine::send_gap_histogram`
+    pub record_send_timing: bool,
+    /// Write a sequence number into every UDP payload at `sequence_offset`,
+    /// so a cooperating receiver can count loss and reordering
+    ///
+    /// A big-endian `u64` from one engine-wide counter, or with
+    /// `sequence_per_worker` a big-endian `u32` thread id followed by that
+    /// worker's own counter. Numbers consumed by `drop_fraction` leave gaps.
+    pub embed_sequence: bool,
+    pub sequence_offset: usize,
+    pub sequence_per_worker: bool,
+    /// Read replies on the sending sockets and time them, see
+    /// `FloodEngine::response_stats`
+    ///
+    /// UDP payloads get a DNS transaction id written over their first two
+    /// bytes to match replies by; HTTP replies are matched in order per
+    /// connection. Requests unanswered after `response_timeout` time out.
+    pub collect_responses: bool,
+    pub response_timeout: Duration,
+    /// Lower the rate while system-wide CPU use is above this percentage,
+    /// see `ResourceGovernor`
+    pub max_cpu_percent: Option<f64>,
+    /// Lower the rate while this process's resident memory is above this
+    pub max_rss_bytes: Option<u64>,
 }
 
 impl Default for EngineConfig {
@@ -75,675 +290,6626 @@ impl Default for EngineConfig {
         Self {
             target: String::new(),
             port: 80,
+            destinations: Vec::new(),
+            port_range: None,
+            port_mode: PortMode::Sequential,
             threads: 4,
             packet_size: 1472,
             protocol: Protocol::UDP,
             rate_limit: None,
+            conn_rate_limit: None,
             duration: None,
             use_raw_sockets: false,
+            sockets_per_thread: SOCKETS_PER_THREAD,
+            drop_fraction: None,
+            seed: None,
+            tcp_fastopen: false,
+            size_distribution: None,
+            refuse_over_memory: false,
+            address_family: AddressFamily::Any,
+            interface: None,
+            dont_fragment: false,
+            clamp_to_mtu: false,
+            ttl: None,
+            dscp: None,
+            ecn: None,
+            clamp_to_interface_mtu: false,
+            pin_threads: false,
+            numa_aware: false,
+            payload_factory: None,
+            dry_run: false,
+            work_distribution: WorkDistribution::Static,
+            gso_segments: None,
+            source: PacketSource::Synthetic,
+            replay_timing: None,
+            zerocopy: false,
+            autoscale: false,
+            max_threads: None,
+            ramp: None,
+            rate_schedule: Vec::new(),
+            interpolate_schedule: false,
+            record_send_timing: false,
+            embed_sequence: false,
+            sequence_offset: 0,
+            sequence_per_worker: false,
+            collect_responses: false,
+            response_timeout: Duration::from_secs(1),
+            max_cpu_percent: None,
+            max_rss_bytes: None,
         }
     }
 }
 
-/// Ultra high-performance flood engine with advanced optimizations
-pub struct FloodEngine {
-    config: EngineConfig,
-    state: Arc<AtomicBool>,
-    packets_sent: Arc<AtomicU64>,
-    bytes_sent: Arc<AtomicU64>,
-    errors: Arc<AtomicU64>,
-    start_time: Arc<Mutex<Option<Instant>>>,
-    threads: Vec<JoinHandle<()>>,
-    rate_limit: Arc<AtomicU64>,
-    // Advanced performance tracking
-    peak_pps: Arc<AtomicU64>,
-    active_threads: Arc<AtomicUsize>,
-    total_batches: Arc<AtomicU64>,
-}
-
-impl FloodEngine {
-    pub fn new(config: EngineConfig) -> Result<Self, EngineError> {
-        // Validate target
-        let addr = format!("{}:{}", config.target, config.port);
-        addr.to_socket_addrs()
-            .map_err(|e| EngineError::InvalidTarget(format!("{}: {}", addr, e)))?
-            .next()
-            .ok_or_else(|| EngineError::InvalidTarget(addr.clone()))?;
+impl EngineConfig {
+    /// Check every field for values the engine cannot run with
+    ///
+    /// `FloodEngine::new` calls this first; the error names the field.
+    pub fn validate(&self) -> Result<(), EngineError> {
+        if self.threads == 0 {
+            return Err(EngineError::InvalidConfig(
+                "threads must be at least 1".to_string(),
+            ));
+        }
+        if self.packet_size == 0 {
+            return Err(EngineError::InvalidConfig(
+                "packet_size must be at least 1 byte".to_string(),
+            ));
+        }
+        // Clamping shrinks an oversized packet_size instead
+        if matches!(self.protocol, Protocol::UDP | Protocol::ICMP)
+            && self.packet_size > MAX_UDP_PAYLOAD
+            && !(self.clamp_to_mtu || self.clamp_to_interface_mtu)
+        {
+            return Err(EngineError::InvalidConfig(format!(
+                "packet_size {} exceeds the {}-byte {:?} maximum",
+                self.packet_size, MAX_UDP_PAYLOAD, self.protocol
+            )));
+        }
+        // Only ICMP has no destination port; lists and ranges carry their own
+        if self.port == 0
+            && self.protocol != Protocol::ICMP
+            && self.destinations.is_empty()
+            && self.port_range.is_none()
+        {
+            return Err(EngineError::InvalidConfig(
+                "port must be between 1 and 65535".to_string(),
+            ));
+        }
+        // Workers each get `rate_limit / threads`, which must not round to 0
+        if let Some(rate) = self.rate_limit.filter(|&rate| rate < self.threads as u64) {
+            return Err(EngineError::InvalidConfig(format!(
+                "rate_limit {} pps is below 1 pps for each of the {} threads",
+                rate, self.threads
+            )));
+        }
+        if !self.destinations.is_empty() && self.protocol != Protocol::UDP {
+            return Err(EngineError::InvalidConfig(
+                "destinations require the UDP protocol".to_string(),
+            ));
+        }
+        if self.record_send_timing && self.protocol != Protocol::UDP {
+            return Err(EngineError::InvalidConfig(
+                "record_send_timing requires the UDP protocol".to_string(),
+            ));
+        }
+        if self.embed_sequence {
+            self.validate_sequence()?;
+        }
+        if self.collect_responses {
+            self.validate_responses()?;
+        }
+        if let Some(cpu) = self.max_cpu_percent {
+            if !(cpu > 0.0 && cpu <= 100.0) {
+                return Err(EngineError::InvalidConfig(format!(
+                    "max_cpu_percent must be above 0 and at most 100, got {}",
+                    cpu
+                )));
+            }
+        }
+        if self.max_rss_bytes == Some(0) {
+            return Err(EngineError::InvalidConfig(
+                "max_rss_bytes must be at least 1".to_string(),
+            ));
+        }
 
-        Ok(Self {
-            config,
-            state: Arc::new(AtomicBool::new(false)),
-            packets_sent: Arc::new(AtomicU64::new(0)),
-            bytes_sent: Arc::new(AtomicU64::new(0)),
-            errors: Arc::new(AtomicU64::new(0)),
-            start_time: Arc::new(Mutex::new(None)),
-            threads: Vec::new(),
-            rate_limit: Arc::new(AtomicU64::new(0)),
-            peak_pps: Arc::new(AtomicU64::new(0)),
-            active_threads: Arc::new(AtomicUsize::new(0)),
-            total_batches: Arc::new(AtomicU64::new(0)),
-        })
-    }
+        if self.sockets_per_thread == 0 {
+            return Err(EngineError::InvalidConfig(
+                "sockets_per_thread must be at least 1".to_string(),
+            ));
+        }
 
-    /// Get peak packets per second achieved
-    pub fn get_peak_pps(&self) -> u64 {
-        self.peak_pps.load(Ordering::Relaxed)
-    }
+        if let Some(fraction) = self.drop_fraction {
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(EngineError::InvalidConfig(format!(
+                    "drop_fraction must be between 0.0 and 1.0, got {}",
+                    fraction
+                )));
+            }
+        }
 
-    /// Get number of currently active worker threads
-    pub fn get_active_threads(&self) -> usize {
-        self.active_threads.load(Ordering::Relaxed)
-    }
+        if let Some(distribution) = &self.size_distribution {
+            distribution.validate()?;
+        }
 
-    /// Get total number of batches processed
-    pub fn get_total_batches(&self) -> u64 {
-        self.total_batches.load(Ordering::Relaxed)
-    }
+        if matches!(self.source, PacketSource::Pcap(_)) && self.protocol != Protocol::UDP {
+            return Err(EngineError::InvalidConfig(
+                "pcap replay requires the UDP protocol".to_string(),
+            ));
+        }
+        if let Some(speed) = self.replay_timing {
+            if !(speed.is_finite() && speed > 0.0) {
+                return Err(EngineError::InvalidConfig(format!(
+                    "replay_timing must be a positive speed factor, got {}",
+                    speed
+                )));
+            }
+        }
 
-    pub fn start(&mut self) -> Result<(), EngineError> {
-        if self.state.load(Ordering::SeqCst) {
-            return Err(EngineError::AlreadyRunning);
+        if self.autoscale && self.max_threads.is_some_and(|max| max < self.threads) {
+            return Err(EngineError::InvalidConfig(
+                "max_threads must be at least threads".to_string(),
+            ));
         }
 
-        self.state.store(true, Ordering::SeqCst);
-        *self.start_time.lock() = Some(Instant::now());
+        if let Some(ramp) = &self.ramp {
+            ramp.validate()?;
+            if !self.rate_schedule.is_empty() {
+                return Err(EngineError::InvalidConfig(
+                    "ramp and rate_schedule cannot be combined".to_string(),
+                ));
+            }
+        }
+        validate_schedule(&self.rate_schedule)?;
 
-        // Set rate limit
-        if let Some(rate) = self.config.rate_limit {
-            self.rate_limit.store(rate, Ordering::SeqCst);
+        if self.conn_rate_limit == Some(0) {
+            return Err(EngineError::InvalidConfig(
+                "conn_rate_limit must be at least 1".to_string(),
+            ));
+        }
+        if self.ttl == Some(0) {
+            return Err(EngineError::InvalidConfig(
+                "ttl must be at least 1".to_string(),
+            ));
+        }
+        if self.dscp.is_some_and(|d| d > 63) || self.ecn.is_some_and(|e| e > 3) {
+            return Err(EngineError::InvalidConfig(
+                "dscp must be 0-63 and ecn 0-3".to_string(),
+            ));
         }
 
-        // Spawn worker threads
-        for thread_id in 0..self.config.threads {
-            let handle = self.spawn_worker(thread_id)?;
-            self.threads.push(handle);
+        if self.gso_segments == Some(0) {
+            return Err(EngineError::InvalidConfig(
+                "gso_segments must be at least 1".to_string(),
+            ));
         }
 
+        if let Some((start, end)) = self.port_range {
+            if start == 0 {
+                return Err(EngineError::InvalidConfig(
+                    "port_range cannot include port 0".to_string(),
+                ));
+            }
+            if start > end {
+                return Err(EngineError::InvalidConfig(format!(
+                    "port_range start {} is after its end {}",
+                    start, end
+                )));
+            }
+            if !self.destinations.is_empty() {
+                return Err(EngineError::InvalidConfig(
+                    "port_range and destinations cannot be combined".to_string(),
+                ));
+            }
+            if matches!(self.protocol, Protocol::ICMP | Protocol::RAW) {
+                return Err(EngineError::InvalidConfig(
+                    "port_range requires the UDP, TCP or HTTP protocol".to_string(),
+                ));
+            }
+        }
         Ok(())
     }
 
-    pub fn stop(&mut self) -> Result<(), EngineError> {
-        if !self.state.load(Ordering::SeqCst) {
-            return Err(EngineError::NotRunning);
+    /// Bytes `embed_sequence` writes at `sequence_offset`
+    pub fn sequence_len(&self) -> usize {
+        if self.sequence_per_worker {
+            SEQUENCE_PREFIX_LEN + SEQUENCE_LEN
+        } else {
+            SEQUENCE_LEN
         }
+    }
 
-        self.state.store(false, Ordering::SeqCst);
-
-        // Wait for threads to finish
-        for handle in self.threads.drain(..) {
-            let _ = handle.join();
+    /// The sequence must fit in the smallest payload workers will build
+    fn validate_sequence(&self) -> Result<(), EngineError> {
+        if self.protocol != Protocol::UDP {
+            return Err(EngineError::InvalidConfig(
+                "embed_sequence requires the UDP protocol".to_string(),
+            ));
+        }
+        if matches!(self.source, PacketSource::Pcap(_)) {
+            return Err(EngineError::InvalidConfig(
+                "embed_sequence cannot rewrite pcap replay payloads".to_string(),
+            ));
         }
+        let smallest = self.min_payload_len();
+        let end = self.sequence_offset.saturating_add(self.sequence_len());
+        if smallest < end {
+            return Err(EngineError::InvalidConfig(format!(
+                "packet_size {} cannot hold the {}-byte sequence at offset {}",
+                smallest,
+                self.sequence_len(),
+                self.sequence_offset
+            )));
+        }
+        Ok(())
+    }
 
+    /// Replies can only be matched on real UDP or HTTP sockets
+    fn validate_responses(&self) -> Result<(), EngineError> {
+        if !matches!(self.protocol, Protocol::UDP | Protocol::HTTP) {
+            return Err(EngineError::InvalidConfig(
+                "collect_responses requires the UDP or HTTP protocol".to_string(),
+            ));
+        }
+        if self.dry_run {
+            return Err(EngineError::InvalidConfig(
+                "collect_responses cannot be combined with dry_run".to_string(),
+            ));
+        }
+        if self.response_timeout.is_zero() {
+            return Err(EngineError::InvalidConfig(
+                "response_timeout must be positive".to_string(),
+            ));
+        }
+        if self.protocol == Protocol::UDP && self.min_payload_len() < TXID_LEN {
+            return Err(EngineError::InvalidConfig(format!(
+                "packet_size {} cannot hold the {}-byte transaction id",
+                self.min_payload_len(),
+                TXID_LEN
+            )));
+        }
+        if self.embed_sequence && self.sequence_offset < TXID_LEN {
+            return Err(EngineError::InvalidConfig(format!(
+                "sequence_offset must leave the first {} bytes to collect_responses",
+                TXID_LEN
+            )));
+        }
         Ok(())
     }
 
-    pub fn is_running(&self) -> bool {
-        self.state.load(Ordering::SeqCst)
+    /// ToS / traffic class byte built from `dscp` and `ecn`, if either is set
+    pub fn traffic_class(&self) -> Option<u8> {
+        (self.dscp.is_some() || self.ecn.is_some())
+            .then(|| ((self.dscp.unwrap_or(0) & 0x3F) << 2) | (self.ecn.unwrap_or(0) & 0x03))
     }
 
-    pub fn set_rate(&mut self, pps: u64) {
-        self.rate_limit.store(pps, Ordering::SeqCst);
+    /// Estimate the memory a running engine needs, in bytes
+    ///
+    /// Sums worker stacks, socket buffers and payload buffers. Socket buffers use
+    /// what the kernel will grant (capped by `net.core.[wr]mem_max` on Linux),
+    /// so this is an upper bound rather than resident memory at start.
+    pub fn estimate_memory(&self) -> usize {
+        let per_thread = match self.protocol {
+            Protocol::UDP => {
+                let payload_len = self.max_payload_len();
+                let socket_buffers = granted_socket_buffer(SEND_BUFFER_SIZE, "wmem_max")
+                    + granted_socket_buffer(RECV_BUFFER_SIZE, "rmem_max");
+                let gso_len = self
+                    .gso_segments
+                    .and_then(|n| gso_batch(n, payload_len))
+                    .map_or(0, |segments| segments * payload_len);
+                self.sockets_per_thread * socket_buffers
+                    + PAYLOAD_VARIANTS * (payload_len + gso_len)
+            }
+            Protocol::TCP | Protocol::HTTP => {
+                TCP_KEEPALIVE_CONNECTIONS * TCP_SOCKET_MEMORY + self.packet_size
+            }
+            Protocol::ICMP | Protocol::RAW => self.packet_size,
+        };
+        self.threads * (THREAD_STACK_SIZE + per_thread)
     }
 
-    pub fn get_stats(&self) -> StatsSnapshot {
-        let duration = self
-            .start_time
-            .lock()
-            .map(|t| t.elapsed())
-            .unwrap_or(Duration::ZERO);
+    /// Largest UDP payload workers will build
+    fn max_payload_len(&self) -> usize {
+        self.size_distribution
+            .as_ref()
+            .and_then(|d| d.buckets().iter().map(|(size, _)| *size).max())
+            .unwrap_or(self.packet_size)
+    }
 
-        let packets = self.packets_sent.load(Ordering::Relaxed);
-        let bytes = self.bytes_sent.load(Ordering::Relaxed);
-        let errors = self.errors.load(Ordering::Relaxed);
+    /// Smallest UDP payload workers will build
+    fn min_payload_len(&self) -> usize {
+        self.size_distribution
+            .as_ref()
+            .and_then(|d| d.buckets().iter().map(|(size, _)| *size).min())
+            .unwrap_or(self.packet_size)
+    }
+}
 
-        let secs = duration.as_secs_f64().max(0.001);
+/// Socket buffer size the kernel grants for a request (Linux doubles it for bookkeeping)
+fn granted_socket_buffer(requested: usize, sysctl: &str) -> usize {
+    #[cfg(target_os = "linux")]
+    {
+        let cap = std::fs::read_to_string(format!("/proc/sys/net/core/{}", sysctl))
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok());
+        if let Some(cap) = cap {
+            return requested.min(cap) * 2;
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = sysctl;
+    requested
+}
 
-        StatsSnapshot {
-            packets_sent: packets,
-            bytes_sent: bytes,
-            errors,
-            duration,
-            pps: (packets as f64 / secs) as u64,
-            bps: (bytes as f64 / secs) as u64,
+/// Memory available for new allocations, when the platform reports it
+pub fn available_memory() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let kb = meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix("MemAvailable:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<usize>()
+            .ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Order in which a port sweep visits `EngineConfig::port_range`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortMode {
+    /// Every port in turn, each worker starting at its own offset
+    #[default]
+    Sequential,
+    /// A uniformly random port each time, seeded like the other samplers
+    Random,
+}
+
+/// Preferred address family for target resolution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// First address the resolver returns
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl AddressFamily {
+    fn matches(&self, addr: &SocketAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::V4 => addr.is_ipv4(),
+            AddressFamily::V6 => addr.is_ipv6(),
         }
     }
+}
 
-    fn spawn_worker(&self, thread_id: usize) -> Result<JoinHandle<()>, EngineError> {
-        let state = Arc::clone(&self.state);
-        let packets_sent = Arc::clone(&self.packets_sent);
-        let bytes_sent = Arc::clone(&self.bytes_sent);
-        let errors = Arc::clone(&self.errors);
-        let rate_limit = Arc::clone(&self.rate_limit);
-        let config = self.config.clone();
+/// Where UDP workers get their payloads
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacketSource {
+    /// Built from `packet_size`, `size_distribution` or `payload_factory`
+    #[default]
+    Synthetic,
+    /// UDP payloads of a pcap/pcapng capture, sent in capture order and cycled
+    ///
+    /// Only the UDP payload of each captured datagram is kept. Every packet
+    /// goes to the engine's target, never the capture's original destination;
+    /// non-UDP frames are skipped.
+    Pcap(PathBuf),
+}
 
-        let handle = thread::Builder::new()
-            .name(format!("flood-worker-{}", thread_id))
-            .spawn(move || {
-                Self::worker_loop(
-                    thread_id,
-                    config,
-                    state,
-                    packets_sent,
-                    bytes_sent,
-                    errors,
-                    rate_limit,
-                );
+/// Payloads and capture offsets loaded for `PacketSource::Pcap`
+struct Replay {
+    payloads: Vec<Vec<u8>>,
+    /// Capture time of each payload relative to the first
+    offsets: Vec<Duration>,
+    /// Length of one pass through the capture when timing is kept
+    cycle: Duration,
+}
+
+impl Replay {
+    fn load(path: &std::path::Path) -> Result<Self, EngineError> {
+        let packets = crate::pcap::read_capture(path)
+            .map_err(|e| EngineError::CaptureError(format!("{}: {}", path.display(), e)))?;
+        let datagrams: Vec<(Duration, Vec<u8>)> = packets
+            .iter()
+            .filter_map(|p| {
+                crate::pcap::udp_payload(p.linktype, &p.data).map(|d| (p.timestamp, d.to_vec()))
             })
-            .map_err(|e| EngineError::ThreadError(e.to_string()))?;
+            .collect();
+        let Some(&(first, _)) = datagrams.first() else {
+            return Err(EngineError::CaptureError(format!(
+                "{}: no UDP datagrams",
+                path.display()
+            )));
+        };
 
-        Ok(handle)
+        let offsets: Vec<Duration> = datagrams
+            .iter()
+            .map(|(ts, _)| ts.saturating_sub(first))
+            .collect();
+        // The next pass starts one average gap after the last packet
+        let span = offsets.last().copied().unwrap_or_default();
+        let cycle = match offsets.len() {
+            1 => Duration::ZERO,
+            n => span + span / (n as u32 - 1),
+        };
+        Ok(Self {
+            payloads: datagrams.into_iter().map(|(_, d)| d).collect(),
+            offsets,
+            cycle,
+        })
     }
 
-    fn worker_loop(
-        thread_id: usize,
-        config: EngineConfig,
-        state: Arc<AtomicBool>,
-        packets_sent: Arc<AtomicU64>,
-        bytes_sent: Arc<AtomicU64>,
-        errors: Arc<AtomicU64>,
-        rate_limit: Arc<AtomicU64>,
-    ) {
-        // Create socket based on protocol
-        let addr: SocketAddr = format!("{}:{}", config.target, config.port)
-            .to_socket_addrs()
-            .ok()
-            .and_then(|mut addrs| addrs.next())
-            .expect("Invalid address");
+    fn payload(&self, index: usize) -> &[u8] {
+        &self.payloads[index % self.payloads.len()]
+    }
 
-        match config.protocol {
-            Protocol::UDP => {
-                Self::udp_worker(
-                    thread_id,
-                    addr,
-                    config,
-                    state,
-                    packets_sent,
-                    bytes_sent,
-                    errors,
-                    rate_limit,
-                );
-            }
-            Protocol::TCP | Protocol::HTTP => {
-                Self::tcp_worker(
-                    thread_id,
-                    addr,
-                    config,
-                    state,
-                    packets_sent,
-                    bytes_sent,
-                    errors,
-                    rate_limit,
-                );
-            }
-            Protocol::ICMP => {
-                Self::icmp_worker(
-                    thread_id,
-                    addr,
-                    config,
-                    state,
-                    packets_sent,
-                    bytes_sent,
-                    errors,
-                    rate_limit,
-                );
-            }
-            Protocol::RAW => {
-                Self::raw_worker(
-                    thread_id,
-                    addr,
-                    config,
-                    state,
-                    packets_sent,
-                    bytes_sent,
-                    errors,
-                    rate_limit,
-                );
-            }
-        }
+    /// When packet `index` was sent in the capture, counting earlier passes
+    fn offset(&self, index: usize) -> Duration {
+        let pass = u32::try_from(index / self.payloads.len()).unwrap_or(u32::MAX);
+        self.cycle.saturating_mul(pass) + self.offsets[index % self.payloads.len()]
     }
+}
 
-    fn udp_worker(
-        thread_id: usize,
-        addr: SocketAddr,
-        config: EngineConfig,
-        state: Arc<AtomicBool>,
-        packets_sent: Arc<AtomicU64>,
-        bytes_sent: Arc<AtomicU64>,
-        errors: Arc<AtomicU64>,
-        rate_limit: Arc<AtomicU64>,
-    ) {
-        use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+/// Counters of the capture started by `FloodEngine::enable_capture`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Samples written to the pcap file
+    pub written: u64,
+    /// Samples discarded because the writer fell behind
+    pub dropped: u64,
+}
 
-        // Create multiple sockets for parallel sending (reduces kernel lock contention)
-        let mut sockets = Vec::with_capacity(SOCKETS_PER_THREAD);
+/// One sampled packet on its way to the capture writer
+struct CapturedPacket {
+    /// Send time since the Unix epoch
+    at: Duration,
+    /// Local address of the sending socket
+    src: SocketAddr,
+    payload: Vec<u8>,
+}
 
-        for sock_idx in 0..SOCKETS_PER_THREAD {
-            let socket = match Socket::new(Domain::IPV4, Type::DGRAM, Some(SockProtocol::UDP)) {
-                Ok(s) => s,
-                Err(_) => {
-                    errors.fetch_add(1, Ordering::Relaxed);
-                    continue;
-                }
-            };
+/// Side channel from the workers to the capture writer
+///
+/// Workers only copy a sampled payload and push it without blocking; when
+/// the queue is full the sample is counted as dropped instead.
+struct CaptureTap {
+    queue: PacketQueue<CapturedPacket>,
+    dst: SocketAddr,
+    /// Each worker samples one in this many packets it sends
+    every: u64,
+    /// Samples left before `max_packets` is reached
+    remaining: AtomicU64,
+    written: AtomicU64,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+}
 
-            // Ultra-aggressive socket optimizations for maximum throughput
-            let _ = socket.set_send_buffer_size(SEND_BUFFER_SIZE);
-            let _ = socket.set_recv_buffer_size(RECV_BUFFER_SIZE);
-            let _ = socket.set_nonblocking(false);
+impl CaptureTap {
+    fn offer(&self, payload: &[u8], src: SocketAddr) {
+        let claimed = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        if claimed.is_err() {
+            return;
+        }
+        let sample = CapturedPacket {
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            src,
+            payload: payload.to_vec(),
+        };
+        if self.queue.push(sample).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 
-            // Platform-specific optimizations
-            #[cfg(target_os = "linux")]
-            {
-                let _ = socket.set_cork(false);
-                // Enable busy polling for lower latency
-                unsafe {
-                    let busy_poll: libc::c_int = 50; // 50 microseconds
-                    let _ = libc::setsockopt(
-                        socket.as_raw_fd(),
-                        libc::SOL_SOCKET,
-                        libc::SO_BUSY_POLL,
-                        &busy_poll as *const _ as *const libc::c_void,
-                        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
-                    );
+    fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            written: self.written.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writer thread body: frame samples and append them until closed
+    ///
+    /// Flushes after every batch so the file can be opened mid-run.
+    fn write_to<W: std::io::Write>(&self, mut writer: PcapWriter<W>) -> std::io::Result<()> {
+        loop {
+            let closed = self.closed.load(Ordering::Acquire);
+            let batch = self
+                .queue
+                .pop_batch_blocking(CAPTURE_WRITE_BATCH, CAPTURE_POLL);
+            if batch.is_empty() {
+                if closed {
+                    return writer.flush();
                 }
+                continue;
             }
-
-            #[cfg(target_os = "windows")]
-            {
-                // Windows-specific: enable SIO_UDP_CONNRESET to ignore ICMP unreachable
-                let _ = socket.set_nodelay(true);
+            for sample in &batch {
+                let frame = udp_frame_between(sample.src, self.dst, &sample.payload);
+                writer.write_packet(sample.at, &frame)?;
             }
+            writer.flush()?;
+            self.written
+                .fetch_add(batch.len() as u64, Ordering::Relaxed);
+        }
+    }
+}
 
-            // Connect socket to avoid per-packet address lookup (significant speedup)
-            let sock_addr: socket2::SockAddr = addr.into();
-            if socket.connect(&sock_addr).is_ok() {
-                sockets.push(socket);
-            }
+/// A worker's position in the capture sampling sequence
+struct CaptureCursor {
+    tap: Arc<CaptureTap>,
+    since_sample: u64,
+}
+
+impl CaptureCursor {
+    /// Account for `count` sends of `payload`, copying those that land on a sample
+    fn observe(&mut self, count: u64, payload: &[u8], src: SocketAddr) {
+        let total = self.since_sample + count;
+        self.since_sample = total % self.tap.every;
+        for _ in 0..total / self.tap.every {
+            self.tap.offer(payload, src);
         }
+    }
+}
 
-        if sockets.is_empty() {
-            errors.fetch_add(1, Ordering::Relaxed);
-            return;
+/// Capture writer thread and the tap feeding it
+struct Capture {
+    tap: Arc<CaptureTap>,
+    /// `None` once finished
+    writer: Option<JoinHandle<std::io::Result<()>>>,
+}
+
+impl Capture {
+    fn finish(&mut self) -> Result<(), EngineError> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        self.tap.closed.store(true, Ordering::Release);
+        match writer.join() {
+            Ok(result) => result.map_err(|e| EngineError::CaptureError(e.to_string())),
+            Err(_) => Err(EngineError::CaptureError(
+                "capture writer panicked".to_string(),
+            )),
         }
+    }
+}
 
-        // Pre-generate multiple payload variants for better cache utilization and evasion
-        let payloads: Vec<Vec<u8>> = (0..PAYLOAD_VARIANTS)
-            .map(|i| {
-                let mut p = vec![0u8; config.packet_size];
-                // Vary payload to avoid pattern detection and improve cache behavior
-                let seed = (i as u8).wrapping_add(thread_id as u8);
-                p[0] = seed;
-                if config.packet_size > 1 {
-                    p[1] = seed.wrapping_mul(17);
-                }
-                if config.packet_size > 2 {
-                    p[2] = seed.wrapping_mul(31);
+/// How a `RampConfig` moves between its start and end rates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RampCurve {
+    /// Equal steps in packets per second
+    #[default]
+    Linear,
+    /// Equal ratios, so each doubling takes the same time
+    Exponential,
+}
+
+/// Rate limit going from `start_pps` to `end_pps` over `duration`
+///
+/// The end rate is held once the ramp is over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RampConfig {
+    pub start_pps: u64,
+    pub end_pps: u64,
+    pub duration: Duration,
+    #[serde(default)]
+    pub curve: RampCurve,
+}
+
+impl RampConfig {
+    fn validate(&self) -> Result<(), EngineError> {
+        // A rate limit of 0 means unlimited, which no curve can pass through
+        if self.start_pps == 0 || self.end_pps == 0 {
+            return Err(EngineError::InvalidConfig(
+                "ramp rates must be at least 1 pps".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rate limit `elapsed` after the ramp began
+    pub fn rate_at(&self, elapsed: Duration) -> u64 {
+        if elapsed >= self.duration {
+            return self.end_pps;
+        }
+        let t = elapsed.as_secs_f64() / self.duration.as_secs_f64();
+        let (start, end) = (self.start_pps as f64, self.end_pps as f64);
+        let rate = match self.curve {
+            RampCurve::Linear => start + (end - start) * t,
+            RampCurve::Exponential => start * (end / start).powf(t),
+        };
+        (rate.round() as u64).max(1)
+    }
+}
+
+/// Check `rate_schedule` points: strictly increasing offsets, nonzero rates
+fn validate_schedule(points: &[(Duration, u64)]) -> Result<(), EngineError> {
+    if let Some(pair) = points.windows(2).find(|pair| pair[1].0 <= pair[0].0) {
+        return Err(EngineError::InvalidConfig(format!(
+            "rate_schedule offsets must strictly increase, got {:?} after {:?}",
+            pair[1].0, pair[0].0
+        )));
+    }
+    if points.iter().any(|&(_, rate)| rate == 0) {
+        return Err(EngineError::InvalidConfig(
+            "rate_schedule rates must be at least 1 pps".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rate limit the scheduler thread follows: a ramp or a list of points
+#[derive(Debug, Clone)]
+enum RatePlan {
+    Ramp(RampConfig),
+    Points {
+        points: Vec<(Duration, u64)>,
+        interpolate: bool,
+    },
+}
+
+impl RatePlan {
+    fn from_config(config: &EngineConfig) -> Option<Self> {
+        if let Some(ramp) = &config.ramp {
+            return Some(RatePlan::Ramp(ramp.clone()));
+        }
+        (!config.rate_schedule.is_empty()).then(|| RatePlan::Points {
+            points: config.rate_schedule.clone(),
+            interpolate: config.interpolate_schedule,
+        })
+    }
+
+    /// Rate limit `elapsed` after start, `None` before the first point
+    fn rate_at(&self, elapsed: Duration) -> Option<u64> {
+        let (points, interpolate) = match self {
+            RatePlan::Ramp(ramp) => return Some(ramp.rate_at(elapsed)),
+            RatePlan::Points {
+                points,
+                interpolate,
+            } => (points, *interpolate),
+        };
+        let next = points.partition_point(|&(at, _)| at <= elapsed);
+        let (at, rate) = *points.get(next.checked_sub(1)?)?;
+        match points.get(next) {
+            Some(&(next_at, next_rate)) if interpolate => {
+                let t = (elapsed - at).as_secs_f64() / (next_at - at).as_secs_f64();
+                let rate = rate as f64 + (next_rate as f64 - rate as f64) * t;
+                Some((rate.round() as u64).max(1))
+            }
+            _ => Some(rate),
+        }
+    }
+
+    /// Offset of the next point after `elapsed`, `None` once the plan is over
+    fn next_point(&self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            RatePlan::Ramp(ramp) => (elapsed < ramp.duration).then_some(ramp.duration),
+            RatePlan::Points { points, .. } => {
+                let next = points.partition_point(|&(at, _)| at <= elapsed);
+                points.get(next).map(|&(at, _)| at)
+            }
+        }
+    }
+}
+
+/// How TCP/HTTP workers get their requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkDistribution {
+    /// Each worker cycles through its own requests
+    #[default]
+    Static,
+    /// Requests are queued per worker and idle workers steal from busy ones
+    ///
+    /// A worker stuck on slow connects keeps its queue, so the rest of the
+    /// pool picks up its pending requests. UDP, ICMP and RAW ignore this.
+    Stealing,
+}
+
+/// One pending request in `WorkDistribution::Stealing` mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ConnTask {
+    /// Engine-wide sequence number, unique per run
+    seq: u64,
+}
+
+/// A worker's end of the stealing deques
+struct TaskSource {
+    deque: WorkStealingQueue<ConnTask>,
+    /// Engine-wide count of tasks handed out, the next sequence number
+    produced: Arc<AtomicU64>,
+}
+
+impl TaskSource {
+    /// Queue a fresh batch of tasks on this worker's deque
+    fn refill(&self) {
+        let first = self.produced.fetch_add(TASK_BATCH, Ordering::Relaxed);
+        for seq in first..first + TASK_BATCH {
+            self.deque.push(ConnTask { seq });
+        }
+    }
+
+    /// Next task: our own deque, then a steal, then a new batch of our own
+    fn next(&self) -> ConnTask {
+        loop {
+            if let Some(task) = self.deque.pop() {
+                return task;
+            }
+            self.refill();
+        }
+    }
+}
+
+/// Take every task still queued behind `stealers`
+fn drain_tasks(stealers: &[Stealer<ConnTask>]) -> u64 {
+    let mut drained = 0;
+    for stealer in stealers {
+        loop {
+            match stealer.steal() {
+                Steal::Success(_) => drained += 1,
+                Steal::Retry => continue,
+                Steal::Empty => break,
+            }
+        }
+    }
+    drained
+}
+
+/// Resolve a target host to a socket address
+///
+/// Accepts IPv4/IPv6 literals (optionally bracketed), scoped IPv6 literals such as
+/// `fe80::1%eth0` or `fe80::1%2`, and hostnames. Hostnames with both A and AAAA
+/// records resolve to the first address of the declared family.
+pub fn resolve_target(
+    target: &str,
+    port: u16,
+    family: AddressFamily,
+) -> Result<SocketAddr, EngineError> {
+    let host = target.trim_start_matches('[').trim_end_matches(']');
+    let invalid = |reason: &str| EngineError::InvalidTarget(format!("{}: {}", target, reason));
+
+    let addr = if let Some((ip, scope)) = host.split_once('%') {
+        let ip: Ipv6Addr = ip
+            .parse()
+            .map_err(|_| invalid("scope is only valid on IPv6"))?;
+        let scope_id = scope
+            .parse::<u32>()
+            .ok()
+            .or_else(|| interface_index(scope))
+            .ok_or_else(|| invalid("unknown interface"))?;
+        SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id))
+    } else if let Ok(ip) = host.parse::<IpAddr>() {
+        SocketAddr::new(ip, port)
+    } else {
+        return (host, port)
+            .to_socket_addrs()
+            .map_err(|e| invalid(&e.to_string()))?
+            .find(|addr| family.matches(addr))
+            .ok_or_else(|| invalid(&format!("no {:?} address", family)));
+    };
+
+    if !family.matches(&addr) {
+        return Err(invalid(&format!("not a {:?} address", family)));
+    }
+    Ok(addr)
+}
+
+/// Resolve `host:port` endpoints to every address they stand for
+///
+/// Literals give one address each, as in `resolve_target`; hostnames give
+/// all their addresses of `family`. Duplicates are dropped, order is kept.
+pub fn resolve_destinations(
+    endpoints: &[String],
+    family: AddressFamily,
+) -> Result<Vec<SocketAddr>, EngineError> {
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for endpoint in endpoints {
+        let invalid =
+            |reason: &str| EngineError::InvalidTarget(format!("{}: {}", endpoint, reason));
+        let (host, port) = endpoint
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("expected host:port"))?;
+        let port: u16 = port.parse().map_err(|_| invalid("invalid port"))?;
+        if host.contains(':') && !host.starts_with('[') {
+            return Err(invalid("IPv6 addresses must be bracketed"));
+        }
+
+        let bare = host.trim_start_matches('[').trim_end_matches(']');
+        let resolved = if bare.contains('%') || bare.parse::<IpAddr>().is_ok() {
+            vec![resolve_target(host, port, family)?]
+        } else {
+            (bare, port)
+                .to_socket_addrs()
+                .map_err(|e| invalid(&e.to_string()))?
+                .filter(|addr| family.matches(addr))
+                .collect()
+        };
+        if resolved.is_empty() {
+            return Err(invalid(&format!("no {:?} address", family)));
+        }
+        for addr in resolved {
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+    Ok(addrs)
+}
+
+/// Resolved `EngineConfig::destinations` with packets and bytes sent to each
+///
+/// Send outcomes also feed `health`, which takes a destination whose error
+/// ratio climbs out of the workers' rotation until its cool-down ends.
+struct Destinations {
+    addrs: Vec<SocketAddr>,
+    packets: Vec<AtomicU64>,
+    bytes: Vec<AtomicU64>,
+    health: TargetHealthTracker,
+}
+
+impl Destinations {
+    /// Counters start from `previous` for addresses it already had
+    fn new(addrs: Vec<SocketAddr>, previous: Option<&Destinations>) -> Self {
+        let carried = |counters: fn(&Destinations) -> &[AtomicU64]| -> Vec<AtomicU64> {
+            addrs
+                .iter()
+                .map(|addr| {
+                    let count = previous
+                        .and_then(|p| {
+                            let index = p.addrs.iter().position(|a| a == addr)?;
+                            Some(counters(p)[index].load(Ordering::Relaxed))
+                        })
+                        .unwrap_or(0);
+                    AtomicU64::new(count)
+                })
+                .collect()
+        };
+        let packets = carried(|d| &d.packets);
+        let bytes = carried(|d| &d.bytes);
+        let health = TargetHealthTracker::new(
+            addrs.iter().map(|addr| addr.to_string()).collect(),
+            HealthPolicy::default(),
+        );
+        Self {
+            addrs,
+            packets,
+            bytes,
+            health,
+        }
+    }
+
+    #[inline]
+    fn record(&self, index: usize, packets: u64, bytes: u64, errors: u64) {
+        if packets + errors == 0 {
+            return;
+        }
+        self.packets[index].fetch_add(packets, Ordering::Relaxed);
+        self.bytes[index].fetch_add(bytes, Ordering::Relaxed);
+        self.health.record(index, packets + errors, errors);
+    }
+}
+
+/// Traffic sent to one of `EngineConfig::destinations`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestinationStats {
+    pub addr: SocketAddr,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    /// In the workers' rotation, false while ejected for its error ratio
+    pub healthy: bool,
+    /// Times the destination has been ejected
+    pub ejections: u64,
+}
+
+fn interface_index(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(name).ok()?;
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        (index != 0).then_some(index)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = name;
+        None
+    }
+}
+
+/// Pin `socket` to `EngineConfig::interface`, if set
+fn bind_interface(
+    socket: &socket2::Socket,
+    addr: SocketAddr,
+    config: &EngineConfig,
+) -> std::io::Result<()> {
+    let Some(name) = &config.interface else {
+        return Ok(());
+    };
+    #[cfg(target_os = "linux")]
+    {
+        let _ = addr;
+        socket.bind_device(Some(name.as_bytes()))
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        let ip = interface_address(name, addr.is_ipv6()).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::AddrNotAvailable,
+                format!("interface {} has no address for {}", name, addr),
+            )
+        })?;
+        socket.bind(&SocketAddr::new(ip, 0).into())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (socket, addr);
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("binding to interface {} is not supported here", name),
+        ))
+    }
+}
+
+/// First address of interface `name` in the requested family
+#[cfg(all(unix, not(target_os = "linux")))]
+fn interface_address(name: &str, ipv6: bool) -> Option<IpAddr> {
+    let mut ifap: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifap) } != 0 {
+        return None;
+    }
+
+    let mut found = None;
+    let mut cursor = ifap;
+    while !cursor.is_null() {
+        let ifa = unsafe { &*cursor };
+        cursor = ifa.ifa_next;
+        if ifa.ifa_addr.is_null()
+            || unsafe { std::ffi::CStr::from_ptr(ifa.ifa_name) }.to_bytes() != name.as_bytes()
+        {
+            continue;
+        }
+        found = unsafe {
+            match (*ifa.ifa_addr).sa_family as libc::c_int {
+                libc::AF_INET if !ipv6 => {
+                    let sin = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                    Some(IpAddr::V4(u32::from_be(sin.sin_addr.s_addr).into()))
                 }
-                if config.packet_size > 3 {
-                    p[3] = seed.wrapping_mul(47);
+                libc::AF_INET6 if ipv6 => {
+                    let sin6 = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                    Some(IpAddr::V6(sin6.sin6_addr.s6_addr.into()))
                 }
-                // Fill rest with pseudo-random data for better compression resistance
-                for j in 4..config.packet_size.min(64) {
-                    p[j] = ((i * 7 + j * 13) & 0xFF) as u8;
+                _ => None,
+            }
+        };
+        if found.is_some() {
+            break;
+        }
+    }
+
+    unsafe { libc::freeifaddrs(ifap) };
+    found
+}
+
+/// Fail `start` when `EngineConfig::interface` is missing or cannot be bound
+fn check_interface(config: &EngineConfig, addr: SocketAddr) -> Result<(), EngineError> {
+    let Some(name) = &config.interface else {
+        return Ok(());
+    };
+    if interface_index(name).is_none() {
+        #[cfg(target_os = "linux")]
+        let available = format!(
+            " (available: {})",
+            crate::linux_optimizations::interface_names().join(", ")
+        );
+        #[cfg(not(target_os = "linux"))]
+        let available = String::new();
+        return Err(EngineError::InvalidConfig(format!(
+            "interface {} does not exist{}",
+            name, available
+        )));
+    }
+    if config.dry_run {
+        return Ok(());
+    }
+
+    // Workers would only count errors, so try one socket here
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::DGRAM,
+        None,
+    )
+    .map_err(|e| EngineError::SocketError(e.to_string()))?;
+    bind_interface(&socket, addr, config).map_err(|e| match e.raw_os_error() {
+        #[cfg(unix)]
+        Some(libc::EPERM) | Some(libc::EACCES) => {
+            EngineError::InsufficientPrivileges(format!("Binding to interface {}", name))
+        }
+        _ => EngineError::SocketError(format!("cannot bind to interface {}: {}", name, e)),
+    })
+}
+
+/// Host header value for a target, bracketing bare IPv6 literals
+fn host_header(target: &str) -> String {
+    if target.contains(':') && !target.starts_with('[') {
+        format!("[{}]", target)
+    } else {
+        target.to_string()
+    }
+}
+
+/// Largest UDP payload over IPv4
+const MAX_UDP_PAYLOAD: usize = 65507;
+
+/// Why a send failed, grouped by what the caller should do about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(usize)]
+pub enum SendErrorKind {
+    /// ENOBUFS/EAGAIN: local queues are full, back off
+    NoBuffers,
+    /// ECONNREFUSED: the target answered with port unreachable
+    ConnectionRefused,
+    /// ENETUNREACH/EHOSTUNREACH: no route to the target
+    Unreachable,
+    /// EMSGSIZE: payload larger than the path MTU
+    MessageTooLong,
+    /// EPERM/EACCES: blocked by a firewall rule or missing privilege
+    PermissionDenied,
+    Other,
+}
+
+impl SendErrorKind {
+    pub const ALL: [SendErrorKind; 6] = [
+        SendErrorKind::NoBuffers,
+        SendErrorKind::ConnectionRefused,
+        SendErrorKind::Unreachable,
+        SendErrorKind::MessageTooLong,
+        SendErrorKind::PermissionDenied,
+        SendErrorKind::Other,
+    ];
+
+    /// Classify a send error; only called on the error path
+    pub fn from_io_error(e: &std::io::Error) -> Self {
+        if is_message_too_long(e) {
+            return SendErrorKind::MessageTooLong;
+        }
+        #[cfg(unix)]
+        if let Some(errno) = e.raw_os_error() {
+            match errno {
+                libc::ENOBUFS => return SendErrorKind::NoBuffers,
+                libc::ENETUNREACH | libc::EHOSTUNREACH | libc::ENETDOWN | libc::EHOSTDOWN => {
+                    return SendErrorKind::Unreachable
                 }
-                p
+                _ => {}
+            }
+        }
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock => SendErrorKind::NoBuffers,
+            std::io::ErrorKind::ConnectionRefused => SendErrorKind::ConnectionRefused,
+            std::io::ErrorKind::PermissionDenied => SendErrorKind::PermissionDenied,
+            std::io::ErrorKind::NetworkUnreachable | std::io::ErrorKind::HostUnreachable => {
+                SendErrorKind::Unreachable
+            }
+            _ => SendErrorKind::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SendErrorKind::NoBuffers => "no_buffers",
+            SendErrorKind::ConnectionRefused => "connection_refused",
+            SendErrorKind::Unreachable => "unreachable",
+            SendErrorKind::MessageTooLong => "message_too_long",
+            SendErrorKind::PermissionDenied => "permission_denied",
+            SendErrorKind::Other => "other",
+        }
+    }
+}
+
+/// Engine-wide send error counters indexed by `SendErrorKind`
+type ErrorKindCounters = [AtomicU64; SendErrorKind::ALL.len()];
+
+/// Per-packet UDP payload callback
+///
+/// Worker N is called with indices N, N + threads, N + 2 * threads, ... so
+/// every index is used once across the engine.
+#[derive(Clone)]
+pub struct PayloadFactory(Arc<dyn Fn(usize) -> Vec<u8> + Send + Sync>);
+
+impl PayloadFactory {
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn(usize) -> Vec<u8> + Send + Sync + 'static,
+    {
+        Self(Arc::new(factory))
+    }
+
+    fn payload(&self, index: usize) -> Vec<u8> {
+        (self.0)(index)
+    }
+}
+
+impl From<Arc<dyn Fn(usize) -> Vec<u8> + Send + Sync>> for PayloadFactory {
+    fn from(factory: Arc<dyn Fn(usize) -> Vec<u8> + Send + Sync>) -> Self {
+        Self(factory)
+    }
+}
+
+impl std::fmt::Debug for PayloadFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PayloadFactory(..)")
+    }
+}
+
+/// Equal only when both wrap the same callback
+impl PartialEq for PayloadFactory {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Weighted payload-size histogram sampled per packet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeDistribution {
+    /// `(payload size, weight)` buckets
+    Buckets(Vec<(usize, u32)>),
+    /// Simple IMIX: 40/576/1500-byte IP packets weighted 7:4:1, as UDP payload sizes
+    InternetMix,
+}
+
+impl SizeDistribution {
+    pub fn buckets(&self) -> Vec<(usize, u32)> {
+        match self {
+            SizeDistribution::Buckets(buckets) => buckets.clone(),
+            SizeDistribution::InternetMix => vec![(12, 7), (548, 4), (1472, 1)],
+        }
+    }
+
+    fn validate(&self) -> Result<(), EngineError> {
+        let buckets = self.buckets();
+        if buckets.iter().map(|(_, w)| *w as u64).sum::<u64>() == 0 {
+            return Err(EngineError::InvalidConfig(
+                "size_distribution needs at least one bucket with weight > 0".to_string(),
+            ));
+        }
+        if let Some((size, _)) = buckets
+            .iter()
+            .find(|(size, _)| *size == 0 || *size > MAX_UDP_PAYLOAD)
+        {
+            return Err(EngineError::InvalidConfig(format!(
+                "size_distribution bucket size {} must be between 1 and {}",
+                size, MAX_UDP_PAYLOAD
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Seed a worker PRNG; `stream` separates independent per-worker sequences
+///
+/// The thread id and stream are mixed into the seed rather than added, so
+/// seed 42 on thread 1 does not replay seed 43 on thread 0.
+fn worker_rng(seed: Option<u64>, thread_id: usize, stream: u64) -> StdRng {
+    let seed = seed.unwrap_or_else(rand::random);
+    StdRng::seed_from_u64(mix64(mix64(seed ^ mix64(thread_id as u64)) ^ stream))
+}
+
+/// SplitMix64 finalizer: spreads every input bit across the output
+fn mix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Samples payload sizes from a `SizeDistribution`
+struct SizeSampler {
+    rng: StdRng,
+    /// Bucket sizes with cumulative weights
+    cumulative: Vec<(usize, u64)>,
+    total: u64,
+}
+
+impl SizeSampler {
+    fn new(distribution: &SizeDistribution, seed: Option<u64>, thread_id: usize) -> Self {
+        let mut total = 0u64;
+        let cumulative = distribution
+            .buckets()
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(size, weight)| {
+                total += weight as u64;
+                (size, total)
             })
             .collect();
+        Self {
+            rng: worker_rng(seed, thread_id, 1),
+            cumulative,
+            total,
+        }
+    }
+
+    fn from_config(config: &EngineConfig, thread_id: usize) -> Option<Self> {
+        config
+            .size_distribution
+            .as_ref()
+            .map(|d| Self::new(d, config.seed, thread_id))
+    }
+
+    fn max_size(&self) -> usize {
+        self.cumulative
+            .iter()
+            .map(|(size, _)| *size)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    fn sample(&mut self) -> usize {
+        let point = self.rng.gen_range(0..self.total);
+        let idx = self.cumulative.partition_point(|(_, cum)| *cum <= point);
+        self.cumulative[idx].0
+    }
+}
+
+/// One worker's position in an `EngineConfig::port_range` sweep
+struct PortSweep {
+    start: u16,
+    /// Ports in the range, up to 65536
+    len: u32,
+    next: u32,
+    /// Set for `PortMode::Random`
+    rng: Option<StdRng>,
+}
+
+impl PortSweep {
+    fn from_config(config: &EngineConfig, thread_id: usize) -> Option<Self> {
+        let (start, end) = config.port_range?;
+        let len = u32::from(end - start) + 1;
+        Some(Self {
+            start,
+            len,
+            next: (thread_id as u32) % len,
+            rng: (config.port_mode == PortMode::Random)
+                .then(|| worker_rng(config.seed, thread_id, 2)),
+        })
+    }
+
+    #[inline]
+    fn next_port(&mut self) -> u16 {
+        let offset = match self.rng.as_mut() {
+            Some(rng) => rng.gen_range(0..self.len),
+            None => {
+                let offset = self.next;
+                self.next = (self.next + 1) % self.len;
+                offset
+            }
+        };
+        self.start + offset as u16
+    }
+}
+
+/// Deterministic source-side packet loss for resilience testing
+///
+/// Each worker owns one injector seeded from the engine seed and its thread
+/// id, so a given seed always skips the same packets on the same worker.
+struct DropInjector {
+    rng: StdRng,
+    fraction: f64,
+}
+
+impl DropInjector {
+    fn new(fraction: f64, seed: Option<u64>, thread_id: usize) -> Self {
+        Self {
+            rng: worker_rng(seed, thread_id, 0),
+            fraction,
+        }
+    }
+
+    fn from_config(config: &EngineConfig, thread_id: usize) -> Option<Self> {
+        config
+            .drop_fraction
+            .filter(|f| *f > 0.0)
+            .map(|f| Self::new(f, config.seed, thread_id))
+    }
+
+    /// Decide whether the next packet should be skipped
+    #[inline]
+    fn should_drop(&mut self) -> bool {
+        self.rng.gen::<f64>() < self.fraction
+    }
+}
+
+/// Writes `EngineConfig::embed_sequence` numbers into a copy of each payload
+struct SequenceStamper {
+    offset: usize,
+    /// Thread id written ahead of the number with `sequence_per_worker`
+    prefix: Option<u32>,
+    /// Engine-wide counter, `None` with `sequence_per_worker`
+    shared: Option<Arc<AtomicU64>>,
+    next: u64,
+    /// Reused for every stamped packet
+    buf: Vec<u8>,
+}
+
+impl SequenceStamper {
+    fn from_config(
+        config: &EngineConfig,
+        thread_id: usize,
+        shared: Option<&Arc<AtomicU64>>,
+    ) -> Option<Self> {
+        config.embed_sequence.then(|| Self {
+            offset: config.sequence_offset,
+            prefix: config.sequence_per_worker.then_some(thread_id as u32),
+            shared: shared.filter(|_| !config.sequence_per_worker).cloned(),
+            next: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Claim the next number, whether or not the packet is then sent
+    #[inline]
+    fn next(&mut self) -> u64 {
+        match &self.shared {
+            Some(counter) => counter.fetch_add(1, Ordering::Relaxed),
+            None => {
+                self.next += 1;
+                self.next - 1
+            }
+        }
+    }
+
+    /// `payload` with `seq` written in; payloads clamped or generated too
+    /// short to hold it go out unstamped
+    #[inline]
+    fn stamp<'a>(&'a mut self, payload: &'a [u8], seq: u64) -> &'a [u8] {
+        let prefix_len = self.prefix.map_or(0, |_| SEQUENCE_PREFIX_LEN);
+        if payload.len() < self.offset + prefix_len + SEQUENCE_LEN {
+            return payload;
+        }
+        self.buf.clear();
+        self.buf.extend_from_slice(payload);
+        let mut at = self.offset;
+        if let Some(thread_id) = self.prefix {
+            self.buf[at..at + SEQUENCE_PREFIX_LEN].copy_from_slice(&thread_id.to_be_bytes());
+            at += SEQUENCE_PREFIX_LEN;
+        }
+        self.buf[at..at + SEQUENCE_LEN].copy_from_slice(&seq.to_be_bytes());
+        &self.buf
+    }
+}
+
+/// Worker 0's send gap recorder with `EngineConfig::record_send_timing`
+///
+/// Every `SEND_GAP_SAMPLE_EVERY`th burst is timed to the start of the next
+/// one, and that gap is spread evenly over the burst's packets. Bursts start
+/// right after the rate limiter lets them through, so the samples show its
+/// pacing at the cost of two clock reads per sample.
+struct SendGapSampler {
+    gaps: Arc<AtomicLogHistogram>,
+    bursts: Cell<u64>,
+    /// Start and packet count of the burst being timed
+    pending: Cell<Option<(Instant, u64)>>,
+}
+
+impl SendGapSampler {
+    fn new(gaps: Arc<AtomicLogHistogram>) -> Self {
+        Self {
+            gaps,
+            bursts: Cell::new(0),
+            pending: Cell::new(None),
+        }
+    }
+
+    #[inline]
+    fn record(&self, started: Instant, packets: u64) {
+        if let Some((previous, sent)) = self.pending.take() {
+            let gap = started.saturating_duration_since(previous).as_nanos() as u64;
+            self.gaps.record_n(gap / sent, sent);
+        }
+        let bursts = self.bursts.get();
+        self.bursts.set(bursts + 1);
+        if packets > 0 && bursts.is_multiple_of(SEND_GAP_SAMPLE_EVERY) {
+            self.pending.set(Some((started, packets)));
+        }
+    }
+}
+
+/// Shared state handed to every worker thread
+struct WorkerContext {
+    /// Engine-wide run flag
+    state: Arc<AtomicBool>,
+    /// Per-worker run flag, cleared to retire a single worker
+    running: Arc<AtomicBool>,
+    /// Engine-wide pause flag, workers idle while set
+    paused: Arc<AtomicBool>,
+    packets_sent: Arc<AtomicU64>,
+    bytes_sent: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+    packets_dropped: Arc<AtomicU64>,
+    /// This worker's share of the rate limit, retuned by `set_rate`
+    bucket: Arc<TokenBucket>,
+    active_threads: Arc<AtomicUsize>,
+    open_sockets: Arc<AtomicUsize>,
+    tfo_connections: Arc<AtomicU64>,
+    tfo_negotiated: Arc<AtomicU64>,
+    /// Engine-wide flush generation bumped by `flush_stats`
+    flush_requested: Arc<AtomicU64>,
+    /// Last flush generation this worker acknowledged
+    flushed: Arc<AtomicU64>,
+    oversized_errors: Arc<AtomicU64>,
+    path_mtu: Arc<AtomicUsize>,
+    error_kinds: Arc<ErrorKindCounters>,
+    /// This worker's slot in the engine's per-thread breakdown
+    thread_stats: Arc<ThreadStats>,
+    /// Packet size and send latency histograms
+    collector: Arc<StatsCollector>,
+    /// This worker's deque in `WorkDistribution::Stealing` mode
+    tasks: Option<TaskSource>,
+    /// Safety governor consulted every burst, see `FloodEngine::set_safety`
+    safety: Option<Arc<SafetyController>>,
+    /// Captured payloads for `PacketSource::Pcap`
+    replay: Option<Arc<Replay>>,
+    /// Sample sink installed by `FloodEngine::enable_capture`
+    capture: Option<Arc<CaptureTap>>,
+    /// Engine-wide budget for new TCP connections, see `conn_rate_limit`
+    conn_bucket: Arc<TokenBucket>,
+    /// Set while the autoscale supervisor holds this worker in reserve
+    parked: Arc<AtomicBool>,
+    /// Endpoints and their counters with `EngineConfig::destinations`
+    destinations: Option<Arc<Destinations>>,
+    /// Set on worker 0 with `EngineConfig::record_send_timing`
+    send_gaps: Option<SendGapSampler>,
+    /// Engine-wide counter for `EngineConfig::embed_sequence`
+    sequence: Option<Arc<AtomicU64>>,
+    /// Reply counters with `EngineConfig::collect_responses`
+    responses: Option<Arc<ResponseCounters>>,
+}
+
+impl WorkerContext {
+    #[inline]
+    fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.thread_stats.record_error();
+    }
+
+    #[inline]
+    fn is_running(&self) -> bool {
+        self.state.load(Ordering::Relaxed) && self.running.load(Ordering::Relaxed) && !self.halted()
+    }
+
+    /// Whether the safety controller's emergency stop has fired
+    #[inline]
+    fn halted(&self) -> bool {
+        self.safety
+            .as_ref()
+            .is_some_and(|safety| safety.emergency_stop.is_stopped())
+    }
+
+    /// Sleep until `deadline`, returning early once the worker is stopped
+    fn sleep_until(&self, deadline: Instant) {
+        while self.is_running() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(10)));
+        }
+    }
+
+    /// Idle briefly if the engine is paused, returning whether it was
+    #[inline]
+    fn idle_if_paused(&self) -> bool {
+        if self.paused.load(Ordering::Relaxed) || self.parked.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+            return true;
+        }
+        false
+    }
+
+    /// Flush local counters if `flush_stats` asked for it since the last acknowledgement
+    #[inline]
+    fn service_flush(&self, local: &mut LocalCounters) {
+        let requested = self.flush_requested.load(Ordering::Acquire);
+        if requested != self.flushed.load(Ordering::Relaxed) {
+            local.flush(self);
+            self.flushed.store(requested, Ordering::Release);
+        }
+    }
+
+    /// Wait until the bucket grants `count` tokens and the safety limiter allows them
+    #[inline]
+    fn acquire_tokens(&self, count: u64) {
+        if !self.bucket.try_acquire(count) {
+            // Never wait for more than the bucket holds; the rate may have just dropped
+            self.bucket.acquire(count.min(self.bucket.burst().max(1)));
+        }
+        if let Some(safety) = &self.safety {
+            while safety.rate_limiter.check_n(count).is_err() && self.is_running() {
+                thread::sleep(SAFETY_BACKOFF);
+            }
+        }
+    }
+
+    /// Wait for a new-connection token; false once the worker is stopped
+    fn acquire_connection(&self) -> bool {
+        while !self.conn_bucket.try_acquire(1) {
+            if !self.is_running() {
+                return false;
+            }
+            thread::sleep(CONN_RATE_BACKOFF);
+        }
+        true
+    }
+
+    /// Feed a finished burst into the packet size and send latency histograms
+    /// and the counters and health of the destination it went to
+    ///
+    /// Observed once per burst at its mean, so the send loop pays one clock read.
+    #[inline]
+    fn record_burst(
+        &self,
+        started: Instant,
+        packets: u64,
+        bytes: u64,
+        errors: u64,
+        destination: usize,
+    ) {
+        if let Some(sampler) = &self.send_gaps {
+            sampler.record(started, packets);
+        }
+        if let Some(destinations) = &self.destinations {
+            destinations.record(destination, packets, bytes, errors);
+        }
+        if packets == 0 {
+            return;
+        }
+        let n = packets as f64;
+        self.collector
+            .send_latency()
+            .observe_n(started.elapsed().as_secs_f64() / n, packets);
+        self.collector
+            .packet_size()
+            .observe_n(bytes as f64 / n, packets);
+    }
+
+    /// Socket after `current` in the rotation, skipping sockets whose
+    /// destination is ejected
+    #[inline]
+    fn next_socket(&self, current: usize, socket_dest: &[usize]) -> usize {
+        let len = socket_dest.len();
+        let next = (current + 1) % len;
+        let Some(destinations) = &self.destinations else {
+            return next;
+        };
+        // Ejection always leaves one destination in rotation
+        (0..len)
+            .map(|offset| (next + offset) % len)
+            .find(|&i| destinations.health.is_available(socket_dest[i]))
+            .unwrap_or(next)
+    }
+
+    /// Acquire tokens for the next UDP burst and return its size
+    #[inline]
+    fn acquire_burst(&self) -> u64 {
+        let cap = self.safety.as_ref().and_then(|s| s.rate_limiter.max_pps());
+        let rate = match (self.bucket.rate(), cap) {
+            (rate, None) => rate,
+            (0, Some(cap)) => cap,
+            (rate, Some(cap)) => rate.min(cap),
+        };
+        // Sized to the safety cap too, so one burst never overshoots it
+        let burst = burst_len(rate);
+        self.acquire_tokens(burst);
+        burst
+    }
+}
+
+/// Packets per UDP burst: about 1ms of the worker's rate, `INNER_BATCH_SIZE` when unlimited
+#[inline]
+fn burst_len(rate: u64) -> u64 {
+    if rate == 0 {
+        INNER_BATCH_SIZE
+    } else {
+        (rate / 1000).clamp(1, INNER_BATCH_SIZE)
+    }
+}
+
+/// One of `workers` equal shares of `limit`, 0 (unlimited) staying 0
+fn rate_share(limit: u64, workers: usize) -> u64 {
+    match limit {
+        0 => 0,
+        limit => (limit / workers.max(1) as u64).max(1),
+    }
+}
+
+/// Point every bucket in `buckets` at `rate` tokens per second
+fn retune(buckets: &[Arc<TokenBucket>], rate: u64) {
+    for bucket in buckets {
+        tune_bucket(bucket, rate);
+    }
+}
+
+/// Point a worker's bucket at `rate` tokens per second, 0 for unlimited
+fn tune_bucket(bucket: &TokenBucket, rate: u64) {
+    // set_rate raises the burst to a full second of tokens, so set it afterwards
+    bucket.set_rate(rate);
+    bucket.set_burst(bucket_depth(rate));
+}
+
+/// Tokens a worker's bucket can hold: 10ms of its rate, at least one burst
+fn bucket_depth(rate: u64) -> u64 {
+    (rate / BUCKET_DEPTH_DIVISOR).max(burst_len(rate))
+}
+
+/// Connection tokens the shared bucket can hold: 10ms of `rate`, at least one
+fn conn_bucket_depth(rate: u64) -> u64 {
+    (rate / BUCKET_DEPTH_DIVISOR).max(1)
+}
+
+/// Counters a worker accumulates locally between flushes to the shared atomics
+#[derive(Default)]
+struct LocalCounters {
+    packets: u64,
+    bytes: u64,
+    errors: u64,
+    dropped: u64,
+    oversized: u64,
+    /// Send errors by `SendErrorKind`
+    error_kinds: [u64; SendErrorKind::ALL.len()],
+    /// EMSGSIZE seen since the worker last looked up the path MTU
+    oversized_pending: bool,
+}
+
+impl LocalCounters {
+    #[inline]
+    fn record_send_error(&mut self, e: &std::io::Error) {
+        self.errors += 1;
+        let kind = SendErrorKind::from_io_error(e);
+        self.error_kinds[kind as usize] += 1;
+        if kind == SendErrorKind::MessageTooLong {
+            self.oversized += 1;
+            self.oversized_pending = true;
+        }
+    }
+
+    fn flush(&mut self, ctx: &WorkerContext) {
+        if self.packets > 0 {
+            ctx.packets_sent.fetch_add(self.packets, Ordering::Relaxed);
+            ctx.bytes_sent.fetch_add(self.bytes, Ordering::Relaxed);
+            ctx.thread_stats.record_batch_sent(self.packets, self.bytes);
+        }
+        if self.errors > 0 {
+            ctx.errors.fetch_add(self.errors, Ordering::Relaxed);
+            ctx.thread_stats.record_errors(self.errors);
+            for (counter, &count) in ctx.error_kinds.iter().zip(&self.error_kinds) {
+                if count > 0 {
+                    counter.fetch_add(count, Ordering::Relaxed);
+                }
+            }
+        }
+        if self.dropped > 0 {
+            ctx.packets_dropped
+                .fetch_add(self.dropped, Ordering::Relaxed);
+        }
+        if self.oversized > 0 {
+            ctx.oversized_errors
+                .fetch_add(self.oversized, Ordering::Relaxed);
+        }
+        *self = Self {
+            oversized_pending: self.oversized_pending,
+            ..Self::default()
+        };
+    }
+}
+
+/// Destination of a UDP worker's datagrams
+enum UdpSink {
+    Socket(socket2::Socket),
+    /// Dry run: accepts every datagram without a syscall
+    Discard,
+}
+
+impl UdpSink {
+    #[inline]
+    fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            UdpSink::Socket(socket) => socket.send(buf),
+            UdpSink::Discard => Ok(buf.len()),
+        }
+    }
+
+    /// Send `buf` as datagrams of `segment` bytes with a single UDP GSO call
+    #[inline]
+    fn send_segmented(&self, buf: &[u8], segment: usize) -> std::io::Result<usize> {
+        match self {
+            UdpSink::Socket(socket) => send_gso(socket, buf, segment as u16),
+            UdpSink::Discard => Ok(buf.len()),
+        }
+    }
+}
+
+/// `MSG_ZEROCOPY` bookkeeping for one socket
+///
+/// The kernel numbers each successful zerocopy send and later reports ranges
+/// of finished ones on the socket error queue. Until then the payload pages
+/// are still in use, so a send's bytes only count once it completes.
+#[derive(Default)]
+struct ZeroCopyTracker {
+    /// Bytes of each unacknowledged send, oldest first
+    pending: VecDeque<usize>,
+    /// Kernel id of `pending[0]`
+    next_id: u32,
+}
+
+impl ZeroCopyTracker {
+    /// Turn on `SO_ZEROCOPY`; `None` if the kernel refuses
+    #[cfg(target_os = "linux")]
+    fn enable(socket: &socket2::Socket) -> Option<Self> {
+        let on: libc::c_int = 1;
+        let rc = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                SO_ZEROCOPY,
+                &on as *const _ as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        (rc == 0).then(Self::default)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn enable(_socket: &socket2::Socket) -> Option<Self> {
+        None
+    }
+
+    /// Send `buf` without copying it; completion arrives via `drain`
+    #[cfg(target_os = "linux")]
+    fn send(&mut self, socket: &socket2::Socket, buf: &[u8]) -> std::io::Result<usize> {
+        let n = socket.send_with_flags(buf, libc::MSG_ZEROCOPY)?;
+        self.pending.push_back(n);
+        Ok(n)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn send(&mut self, socket: &socket2::Socket, buf: &[u8]) -> std::io::Result<usize> {
+        socket.send(buf)
+    }
+
+    /// Retire sends up to id `last`, returning (packets, bytes) completed
+    fn complete(&mut self, last: u32) -> (u64, u64) {
+        // Ranges arrive in order; ids wrap at u32::MAX
+        let count = (last.wrapping_sub(self.next_id) as usize + 1).min(self.pending.len());
+        let bytes: usize = self.pending.drain(..count).sum();
+        self.next_id = self.next_id.wrapping_add(count as u32);
+        (count as u64, bytes as u64)
+    }
+
+    /// Read every queued completion without blocking, returning (packets, bytes)
+    #[cfg(target_os = "linux")]
+    fn drain(&mut self, socket: &socket2::Socket) -> (u64, u64) {
+        let mut done = (0u64, 0u64);
+        // u64 storage keeps the control buffer aligned for `cmsghdr`
+        let mut control = [0u64; 16];
+        loop {
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+            let rc = unsafe {
+                libc::recvmsg(
+                    socket.as_raw_fd(),
+                    &mut msg,
+                    libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT,
+                )
+            };
+            if rc < 0 {
+                return done;
+            }
+
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+            while !cmsg.is_null() {
+                let (level, kind) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+                let is_recverr = (level == libc::SOL_IP && kind == libc::IP_RECVERR)
+                    || (level == libc::SOL_IPV6 && kind == libc::IPV6_RECVERR);
+                if is_recverr {
+                    let err: libc::sock_extended_err =
+                        unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                    if err.ee_errno == 0 && err.ee_origin == SO_EE_ORIGIN_ZEROCOPY {
+                        // ee_info..=ee_data is the range of finished send ids
+                        let (packets, bytes) = self.complete(err.ee_data);
+                        done.0 += packets;
+                        done.1 += bytes;
+                    }
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn drain(&mut self, _socket: &socket2::Socket) -> (u64, u64) {
+        (0, 0)
+    }
+
+    /// Drain until nothing is pending or `timeout` passes
+    fn drain_all(&mut self, socket: &socket2::Socket, timeout: Duration) -> (u64, u64) {
+        let deadline = Instant::now() + timeout;
+        let mut done = self.drain(socket);
+        while !self.pending.is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+            let (packets, bytes) = self.drain(socket);
+            done.0 += packets;
+            done.1 += bytes;
+        }
+        done
+    }
+}
+
+/// Handle to a spawned worker thread
+struct Worker {
+    running: Arc<AtomicBool>,
+    flushed: Arc<AtomicU64>,
+    bucket: Arc<TokenBucket>,
+    parked: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    fn stop_and_join(self) {
+        self.running.store(false, Ordering::SeqCst);
+        let _ = self.handle.join();
+    }
+}
+
+/// Hill-climbing controller behind `EngineConfig::autoscale`
+///
+/// Adds one worker per interval while each addition raises the measured rate
+/// by at least `AUTOSCALE_MIN_GAIN`. The first addition that doesn't is
+/// undone and the count then stays put, so noise around a plateau cannot
+/// make the pool oscillate.
+struct AutoScaler {
+    workers: usize,
+    max: usize,
+    /// Rate measured with the current worker count
+    baseline: Option<f64>,
+    settled: bool,
+}
+
+impl AutoScaler {
+    fn new(workers: usize, max: usize) -> Self {
+        Self {
+            workers,
+            max,
+            baseline: None,
+            settled: false,
+        }
+    }
+
+    /// Feed the rate measured with the current count; returns the next count
+    fn observe(&mut self, pps: f64) -> usize {
+        if self.settled {
+            return self.workers;
+        }
+        match self.baseline {
+            Some(baseline) if pps <= baseline * (1.0 + AUTOSCALE_MIN_GAIN) => {
+                self.workers -= 1;
+                self.settled = true;
+                return self.workers;
+            }
+            _ => self.baseline = Some(pps),
+        }
+        if self.workers < self.max {
+            self.workers += 1;
+        } else {
+            self.settled = true;
+        }
+        self.workers
+    }
+
+    fn settled(&self) -> bool {
+        self.settled
+    }
+}
+
+/// Highest of `step`, `2 * step`, ... up to `ceiling` whose sends stay within
+/// `CALIBRATE_MAX_ERROR_RATIO` errors
+///
+/// `probe` runs one step at the given rate and returns its `(sent, errors)`.
+/// The ramp ends at the first step over the threshold; when that is the first
+/// step, its rate is returned as the floor.
+fn calibrate_rate(
+    ceiling: u64,
+    step: u64,
+    mut probe: impl FnMut(u64) -> Result<(u64, u64), EngineError>,
+) -> Result<u64, EngineError> {
+    let floor = step.min(ceiling);
+    let mut best = None;
+    let mut rate = floor;
+    loop {
+        let (sent, errors) = probe(rate)?;
+        let attempts = sent + errors;
+        if errors as f64 > attempts as f64 * CALIBRATE_MAX_ERROR_RATIO {
+            break;
+        }
+        best = Some(rate);
+        if rate >= ceiling {
+            break;
+        }
+        rate = rate.saturating_add(step).min(ceiling);
+    }
+    Ok(best.unwrap_or(floor))
+}
+
+/// Cancellable helper thread: the `duration` watchdog or the rate sampler
+struct BackgroundThread {
+    cancel: Arc<(Mutex<bool>, Condvar)>,
+    handle: JoinHandle<()>,
+}
+
+impl BackgroundThread {
+    fn cancel_and_join(self) {
+        let (cancelled, wake) = &*self.cancel;
+        *cancelled.lock() = true;
+        wake.notify_all();
+        let _ = self.handle.join();
+    }
+}
+
+/// Ultra high-performance flood engine with advanced optimizations
+pub struct FloodEngine {
+    config: EngineConfig,
+    /// Resolved target address, the first destination with `destinations`
+    addr: SocketAddr,
+    /// Resolved `EngineConfig::destinations`, `None` for a single target
+    destinations: Option<Arc<Destinations>>,
+    /// Worker run flag, checked on the hot path
+    state: Arc<AtomicBool>,
+    lifecycle: EngineStateHandle,
+    paused: Arc<AtomicBool>,
+    packets_sent: Arc<AtomicU64>,
+    bytes_sent: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+    packets_dropped: Arc<AtomicU64>,
+    start_time: Arc<Mutex<Option<Instant>>>,
+    /// Wall-clock bounds of the latest run for `final_report`
+    started_at: Option<SystemTime>,
+    /// Set by `stop` or the watchdog, `None` while running
+    stopped_at: Arc<Mutex<Option<SystemTime>>>,
+    threads: Vec<Worker>,
+    /// Ends the run once `EngineConfig::duration` elapses
+    watchdog: Option<BackgroundThread>,
+    /// Records the per-second rate into `collector`
+    sampler: Option<BackgroundThread>,
+    /// Parks and unparks workers while `EngineConfig::autoscale` is searching
+    autoscaler: Option<BackgroundThread>,
+    /// Workers not parked by the autoscaler
+    scaled_workers: Arc<AtomicUsize>,
+    /// Applies `EngineConfig::ramp` or `rate_schedule` until it is over
+    scheduler: Option<BackgroundThread>,
+    /// Caps the rate while the host is over `max_cpu_percent` or `max_rss_bytes`
+    governor: Option<BackgroundThread>,
+    /// The governor's cap in pps, 0 while it is not throttling
+    rate_cap: Arc<AtomicU64>,
+    resource_sampler: Arc<dyn ResourceSampler>,
+    /// Every live worker's bucket, so background threads can retune them
+    buckets: Arc<Mutex<Vec<Arc<TokenBucket>>>>,
+    rate_limit: Arc<AtomicU64>,
+    conn_bucket: Arc<TokenBucket>,
+    // Advanced performance tracking
+    active_threads: Arc<AtomicUsize>,
+    total_batches: Arc<AtomicU64>,
+    open_sockets: Arc<AtomicUsize>,
+    tfo_connections: Arc<AtomicU64>,
+    tfo_negotiated: Arc<AtomicU64>,
+    flush_requested: Arc<AtomicU64>,
+    oversized_errors: Arc<AtomicU64>,
+    path_mtu: Arc<AtomicUsize>,
+    /// Per-worker counters, one slot per thread id
+    collector: Arc<StatsCollector>,
+    /// CPUs workers are pinned to, empty unless `pin_threads`
+    cpu_order: Vec<usize>,
+    error_kinds: Arc<ErrorKindCounters>,
+    /// Stealer for every TCP/HTTP worker deque this run, in thread order
+    stealers: Vec<Stealer<ConnTask>>,
+    tasks_produced: Arc<AtomicU64>,
+    /// Tasks still queued when the workers exited
+    tasks_drained: u64,
+    safety: Option<Arc<SafetyController>>,
+    replay: Option<Arc<Replay>>,
+    capture: Option<Capture>,
+    /// Nanoseconds between worker 0's sends with `EngineConfig::record_send_timing`
+    send_gaps: Option<Arc<AtomicLogHistogram>>,
+    /// Next number for `EngineConfig::embed_sequence` without `sequence_per_worker`
+    sequence: Option<Arc<AtomicU64>>,
+    /// Replies matched this run with `EngineConfig::collect_responses`
+    responses: Option<Arc<ResponseCounters>>,
+}
+
+impl FloodEngine {
+    pub fn new(mut config: EngineConfig) -> Result<Self, EngineError> {
+        config.validate()?;
+        let replay = match &config.source {
+            PacketSource::Synthetic => None,
+            PacketSource::Pcap(path) => Some(Arc::new(Replay::load(path)?)),
+        };
+        let conn_rate = config.conn_rate_limit.unwrap_or(0);
+
+        if !config.dry_run {
+            check_protocol_privileges(config.protocol, has_raw_socket_privilege())?;
+        }
+
+        if let Some(available) = available_memory() {
+            let estimate = config.estimate_memory();
+            if estimate > available {
+                let message = format!(
+                    "estimated memory {} MiB exceeds available {} MiB; reduce threads or sockets_per_thread",
+                    estimate / (1024 * 1024),
+                    available / (1024 * 1024)
+                );
+                if config.refuse_over_memory {
+                    return Err(EngineError::InvalidConfig(message));
+                }
+                warn!("{}", message);
+            }
+        }
+
+        // Resolve once; workers send to this address
+        let destinations = if config.destinations.is_empty() {
+            None
+        } else {
+            let addrs = resolve_destinations(&config.destinations, config.address_family)?;
+            Some(Arc::new(Destinations::new(addrs, None)))
+        };
+        let addr = match &destinations {
+            Some(destinations) => destinations.addrs[0],
+            None => {
+                let port = config.port_range.map_or(config.port, |(start, _)| start);
+                resolve_target(&config.target, port, config.address_family)?
+            }
+        };
+
+        if config.clamp_to_interface_mtu && config.protocol == Protocol::UDP {
+            if let Some(mtu) = interface_mtu(addr.ip()) {
+                let limit = max_payload_for_mtu(mtu, addr);
+                if config.packet_size > limit {
+                    warn!(
+                        "packet_size {} exceeds interface MTU {}; clamping to {}",
+                        config.packet_size, mtu, limit
+                    );
+                    config.packet_size = limit;
+                }
+            }
+        }
+
+        let scaled_workers = Arc::new(AtomicUsize::new(config.threads));
+        let cpu_order = if config.pin_threads {
+            cpu_pin_order(config.numa_aware)
+        } else {
+            Vec::new()
+        };
+
+        let send_gaps = config
+            .record_send_timing
+            .then(|| Arc::new(AtomicLogHistogram::new()));
+        let sequence = (config.embed_sequence && !config.sequence_per_worker)
+            .then(|| Arc::new(AtomicU64::new(0)));
+        let responses = config
+            .collect_responses
+            .then(|| Arc::new(ResponseCounters::default()));
+
+        Ok(Self {
+            config,
+            addr,
+            destinations,
+            state: Arc::new(AtomicBool::new(false)),
+            lifecycle: EngineStateHandle(Arc::new(AtomicU8::new(EngineState::Idle as u8))),
+            paused: Arc::new(AtomicBool::new(false)),
+            packets_sent: Arc::new(AtomicU64::new(0)),
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            errors: Arc::new(AtomicU64::new(0)),
+            packets_dropped: Arc::new(AtomicU64::new(0)),
+            start_time: Arc::new(Mutex::new(None)),
+            started_at: None,
+            stopped_at: Arc::new(Mutex::new(None)),
+            threads: Vec::new(),
+            watchdog: None,
+            sampler: None,
+            autoscaler: None,
+            governor: None,
+            rate_cap: Arc::new(AtomicU64::new(0)),
+            resource_sampler: Arc::new(SystemSampler::new()),
+            scaled_workers,
+            scheduler: None,
+            buckets: Arc::new(Mutex::new(Vec::new())),
+            rate_limit: Arc::new(AtomicU64::new(0)),
+            conn_bucket: Arc::new(TokenBucket::new(conn_rate, conn_bucket_depth(conn_rate))),
+            active_threads: Arc::new(AtomicUsize::new(0)),
+            total_batches: Arc::new(AtomicU64::new(0)),
+            open_sockets: Arc::new(AtomicUsize::new(0)),
+            tfo_connections: Arc::new(AtomicU64::new(0)),
+            tfo_negotiated: Arc::new(AtomicU64::new(0)),
+            flush_requested: Arc::new(AtomicU64::new(0)),
+            oversized_errors: Arc::new(AtomicU64::new(0)),
+            path_mtu: Arc::new(AtomicUsize::new(0)),
+            collector: Arc::new(StatsCollector::new()),
+            cpu_order,
+            error_kinds: Arc::new(std::array::from_fn(|_| AtomicU64::new(0))),
+            stealers: Vec::new(),
+            tasks_produced: Arc::new(AtomicU64::new(0)),
+            tasks_drained: 0,
+            safety: None,
+            replay,
+            capture: None,
+            send_gaps,
+            sequence,
+            responses,
+        })
+    }
+
+    /// Govern workers with `safety` from the next `start` or new worker on
+    ///
+    /// Every burst is counted against its rate limiter, and its emergency
+    /// stop ends the workers within a few packets; call `stop` afterwards
+    /// to reap them.
+    pub fn set_safety(&mut self, safety: Arc<SafetyController>) {
+        self.safety = Some(safety);
+    }
+
+    /// Write every `sample_every`th packet each UDP worker sends to a pcap file
+    ///
+    /// Frames get Ethernet, IP and UDP headers for the sending socket and the
+    /// target, stamped with the send time; at most `max_packets` are kept.
+    /// Applies from the next `start` or new worker on and replaces any earlier
+    /// capture. Samples are dropped rather than slowing the workers when the
+    /// writer falls behind. The capture ends with `stop` or `finish_capture`.
+    pub fn enable_capture<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        max_packets: u64,
+        sample_every: u64,
+    ) -> Result<(), EngineError> {
+        if self.config.protocol != Protocol::UDP {
+            return Err(EngineError::InvalidConfig(
+                "packet capture requires the UDP protocol".to_string(),
+            ));
+        }
+        if sample_every == 0 {
+            return Err(EngineError::InvalidConfig(
+                "sample_every must be at least 1".to_string(),
+            ));
+        }
+        self.finish_capture()?;
+
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .map_err(|e| EngineError::CaptureError(format!("{}: {}", path.display(), e)))?;
+        let writer = PcapWriter::new(std::io::BufWriter::new(file), LINKTYPE_ETHERNET)
+            .map_err(|e| EngineError::CaptureError(format!("{}: {}", path.display(), e)))?;
+        let tap = Arc::new(CaptureTap {
+            queue: PacketQueue::new(CAPTURE_QUEUE_CAPACITY),
+            dst: self.addr,
+            every: sample_every,
+            remaining: AtomicU64::new(max_packets),
+            written: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let writer = {
+            let tap = Arc::clone(&tap);
+            thread::Builder::new()
+                .name("capture-writer".to_string())
+                .spawn(move || tap.write_to(writer))
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+        self.capture = Some(Capture {
+            tap,
+            writer: Some(writer),
+        });
+        Ok(())
+    }
+
+    /// Write out queued samples and close the capture file
+    ///
+    /// Does nothing without an active capture; `capture_stats` stays
+    /// available afterwards.
+    pub fn finish_capture(&mut self) -> Result<(), EngineError> {
+        self.capture.as_mut().map_or(Ok(()), Capture::finish)
+    }
+
+    /// Counters of the current or last capture
+    pub fn capture_stats(&self) -> Option<CaptureStats> {
+        self.capture.as_ref().map(|c| c.tap.stats())
+    }
+
+    /// Get peak packets per second achieved
+    pub fn get_peak_pps(&self) -> u64 {
+        self.collector.peak_pps().unwrap_or(0)
+    }
+
+    /// Engine-wide rate limit in pps, 0 when unlimited
+    ///
+    /// While stopped this is the limit the next `start` applies.
+    pub fn get_rate_limit(&self) -> u64 {
+        let current = self.rate_limit.load(Ordering::SeqCst);
+        if self.is_running() {
+            current
+        } else {
+            self.config.rate_limit.unwrap_or(current)
+        }
+    }
+
+    /// Get number of currently active worker threads
+    pub fn get_active_threads(&self) -> usize {
+        self.active_threads.load(Ordering::Relaxed)
+    }
+
+    /// Get total number of batches processed
+    pub fn get_total_batches(&self) -> u64 {
+        self.total_batches.load(Ordering::Relaxed)
+    }
+
+    /// Collector the workers publish their per-thread counters into
+    pub fn collector(&self) -> &Arc<StatsCollector> {
+        &self.collector
+    }
+
+    /// Get the engine configuration
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Get configured number of sockets per UDP worker
+    pub fn sockets_per_thread(&self) -> usize {
+        self.config.sockets_per_thread
+    }
+
+    /// Get number of sockets the UDP workers actually opened and connected
+    pub fn get_open_sockets(&self) -> usize {
+        self.open_sockets.load(Ordering::Relaxed)
+    }
+
+    /// Get number of TCP connections opened through TCP Fast Open
+    pub fn get_tfo_connections(&self) -> u64 {
+        self.tfo_connections.load(Ordering::Relaxed)
+    }
+
+    /// Get number of TFO connections whose SYN data was accepted by the server
+    pub fn get_tfo_negotiated(&self) -> u64 {
+        self.tfo_negotiated.load(Ordering::Relaxed)
+    }
+
+    /// Get number of tasks queued this run in `WorkDistribution::Stealing` mode
+    pub fn get_tasks_produced(&self) -> u64 {
+        self.tasks_produced.load(Ordering::Relaxed)
+    }
+
+    /// Get number of queued tasks discarded unsent when the last run stopped
+    pub fn get_tasks_drained(&self) -> u64 {
+        self.tasks_drained
+    }
+
+    /// Whether workers share requests through the stealing deques
+    fn uses_stealing(&self) -> bool {
+        self.config.work_distribution == WorkDistribution::Stealing
+            && matches!(self.config.protocol, Protocol::TCP | Protocol::HTTP)
+    }
+
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        if self.state.load(Ordering::SeqCst) {
+            return Err(EngineError::AlreadyRunning);
+        }
+        // Reap workers left behind by a run the watchdog ended
+        self.join_workers();
+
+        // Workers would only count errors without a raw socket, so fail here instead
+        #[cfg(target_os = "linux")]
+        if self.config.protocol == Protocol::ICMP && !self.config.dry_run {
+            open_icmp_socket(self.addr).map_err(|e| match e.raw_os_error() {
+                Some(libc::EPERM) | Some(libc::EACCES) => {
+                    EngineError::InsufficientPrivileges("ICMP mode".to_string())
+                }
+                _ => EngineError::SocketError(format!("raw ICMP socket: {}", e)),
+            })?;
+        }
+        check_interface(&self.config, self.addr)?;
+
+        self.state.store(true, Ordering::SeqCst);
+        self.lifecycle.set(EngineState::Running);
+        *self.start_time.lock() = Some(Instant::now());
+        self.started_at = Some(SystemTime::now());
+        *self.stopped_at.lock() = None;
+
+        if let Err(e) = self.launch() {
+            // Don't leave the threads spawned so far running behind a failed start
+            self.state.store(false, Ordering::SeqCst);
+            *self.stopped_at.lock() = Some(SystemTime::now());
+            self.join_workers();
+            self.open_sockets.store(0, Ordering::Relaxed);
+            self.lifecycle.set(EngineState::Stopped);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Spawn the workers and helper threads of a run `start` has begun
+    fn launch(&mut self) -> Result<(), EngineError> {
+        // Hostname destinations may have moved since the last run
+        if let Some(previous) = &self.destinations {
+            let config = &self.config;
+            let addrs = resolve_destinations(&config.destinations, config.address_family)?;
+            self.destinations = Some(Arc::new(Destinations::new(addrs, Some(previous))));
+        }
+
+        // Set rate limit; a ramp or schedule may already have one for offset 0
+        let plan = RatePlan::from_config(&self.config);
+        if let Some(rate) = plan.as_ref().and_then(|plan| plan.rate_at(Duration::ZERO)) {
+            self.rate_limit.store(rate, Ordering::SeqCst);
+        } else if let Some(rate) = self.config.rate_limit {
+            self.rate_limit.store(rate, Ordering::SeqCst);
+        }
+
+        if let Some(gaps) = &self.send_gaps {
+            gaps.reset();
+        }
+        if let Some(sequence) = &self.sequence {
+            sequence.store(0, Ordering::Relaxed);
+        }
+        if let Some(responses) = &self.responses {
+            responses.reset();
+        }
+        self.rate_cap.store(0, Ordering::SeqCst);
+
+        // Spawn worker threads; with autoscale, the ones past `threads` start parked
+        let spawn_count = self.spawn_count();
+        self.scaled_workers
+            .store(self.config.threads, Ordering::SeqCst);
+        let mut deques = if self.uses_stealing() {
+            self.tasks_produced.store(0, Ordering::Relaxed);
+            self.tasks_drained = 0;
+            let (deques, stealers) = WorkStealingQueue::group(spawn_count);
+            self.stealers = stealers;
+            deques.into_iter().map(Some).collect()
+        } else {
+            Vec::new()
+        };
+        deques.resize_with(spawn_count, || None);
+        for (thread_id, deque) in deques.into_iter().enumerate() {
+            let worker = self.spawn_worker(thread_id, deque)?;
+            self.threads.push(worker);
+        }
+
+        self.collector.clear_pps_samples();
+        self.sampler = Some(self.spawn_sampler()?);
+        if self.config.autoscale {
+            self.autoscaler = Some(self.spawn_autoscaler()?);
+        }
+        if let Some(plan) = plan {
+            self.scheduler = Some(self.spawn_scheduler(plan)?);
+        }
+        if self.config.max_cpu_percent.is_some() || self.config.max_rss_bytes.is_some() {
+            self.governor = Some(self.spawn_governor()?);
+        }
+
+        if let Some(duration) = self.config.duration {
+            self.watchdog = Some(self.spawn_watchdog(duration)?);
+        }
+
+        Ok(())
+    }
+
+    /// Clear the run flag after `duration` unless cancelled first by `stop`
+    fn spawn_watchdog(&self, duration: Duration) -> Result<BackgroundThread, EngineError> {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = Arc::clone(&self.state);
+        let paused = Arc::clone(&self.paused);
+        let lifecycle = self.lifecycle.clone();
+        let active_threads = Arc::clone(&self.active_threads);
+        let stopped_at = Arc::clone(&self.stopped_at);
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            thread::Builder::new()
+                .name("flood-watchdog".to_string())
+                .spawn(move || {
+                    let deadline = Instant::now() + duration;
+                    let (cancelled, wake) = &*cancel;
+                    let mut guard = cancelled.lock();
+                    while !*guard {
+                        if wake.wait_until(&mut guard, deadline).timed_out() {
+                            break;
+                        }
+                    }
+                    if *guard || !state.swap(false, Ordering::SeqCst) {
+                        return;
+                    }
+                    drop(guard);
+                    *stopped_at.lock() = Some(SystemTime::now());
+
+                    paused.store(false, Ordering::SeqCst);
+                    lifecycle.set(EngineState::Stopping);
+                    // Workers exit on their own once the run flag clears
+                    while active_threads.load(Ordering::SeqCst) > 0 {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    lifecycle.set(EngineState::Stopped);
+                })
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+
+        Ok(BackgroundThread { cancel, handle })
+    }
+
+    /// Record the engine-wide rate once a second until cancelled or the run ends
+    ///
+    /// Seconds spent paused are skipped so they don't drag the percentiles down.
+    fn spawn_sampler(&self) -> Result<BackgroundThread, EngineError> {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = Arc::clone(&self.state);
+        let paused = Arc::clone(&self.paused);
+        let packets_sent = Arc::clone(&self.packets_sent);
+        let collector = Arc::clone(&self.collector);
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            thread::Builder::new()
+                .name("flood-stats".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    let mut last = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                    let mut guard = cancelled.lock();
+                    while !*guard && state.load(Ordering::SeqCst) {
+                        if !wake
+                            .wait_until(&mut guard, last.0 + PPS_SAMPLE_INTERVAL)
+                            .timed_out()
+                        {
+                            continue;
+                        }
+                        let now = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                        if !paused.load(Ordering::Relaxed) {
+                            let secs = now.0.duration_since(last.0).as_secs_f64();
+                            collector.record_pps_sample(((now.1 - last.1) as f64 / secs) as u64);
+                        }
+                        last = now;
+                    }
+                })
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+
+        Ok(BackgroundThread { cancel, handle })
+    }
+
+    /// Grow the active worker set one at a time while throughput keeps rising
+    ///
+    /// Each change is followed by a fresh `AUTOSCALE_INTERVAL` measurement;
+    /// paused intervals are discarded. The thread exits once `AutoScaler`
+    /// settles.
+    fn spawn_autoscaler(&self) -> Result<BackgroundThread, EngineError> {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = Arc::clone(&self.state);
+        let paused = Arc::clone(&self.paused);
+        let packets_sent = Arc::clone(&self.packets_sent);
+        let rate_limit = Arc::clone(&self.rate_limit);
+        let rate_cap = Arc::clone(&self.rate_cap);
+        let scaled_workers = Arc::clone(&self.scaled_workers);
+        let workers: Vec<(Arc<AtomicBool>, Arc<TokenBucket>)> = self
+            .threads
+            .iter()
+            .map(|w| (Arc::clone(&w.parked), Arc::clone(&w.bucket)))
+            .collect();
+        let mut scaler = AutoScaler::new(self.config.threads, workers.len());
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            thread::Builder::new()
+                .name("flood-autoscale".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    let mut last = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                    let mut guard = cancelled.lock();
+                    while !*guard && state.load(Ordering::SeqCst) && !scaler.settled() {
+                        if !wake
+                            .wait_until(&mut guard, last.0 + AUTOSCALE_INTERVAL)
+                            .timed_out()
+                        {
+                            continue;
+                        }
+                        let now = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                        let secs = now.0.duration_since(last.0).as_secs_f64();
+                        let pps = (now.1 - last.1) as f64 / secs;
+                        last = now;
+                        if paused.load(Ordering::Relaxed) {
+                            continue;
+                        }
+
+                        let active = scaler.observe(pps);
+                        if active == scaled_workers.load(Ordering::SeqCst) {
+                            continue;
+                        }
+                        let limit = capped_rate(
+                            rate_limit.load(Ordering::SeqCst),
+                            rate_cap.load(Ordering::SeqCst),
+                        );
+                        let rate = rate_share(limit, active);
+                        for (id, (parked, bucket)) in workers.iter().enumerate() {
+                            tune_bucket(bucket, rate);
+                            parked.store(id >= active, Ordering::SeqCst);
+                        }
+                        scaled_workers.store(active, Ordering::SeqCst);
+                        // Measure the new count from scratch
+                        last = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                    }
+                })
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+
+        Ok(BackgroundThread { cancel, handle })
+    }
+
+    /// Follow `plan` from now on, retuning the workers at each point and every
+    /// `RATE_SCHEDULE_STEP` in between
+    ///
+    /// The thread exits after applying the final rate.
+    fn spawn_scheduler(&self, plan: RatePlan) -> Result<BackgroundThread, EngineError> {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = Arc::clone(&self.state);
+        let rate_limit = Arc::clone(&self.rate_limit);
+        let rate_cap = Arc::clone(&self.rate_cap);
+        let scaled_workers = Arc::clone(&self.scaled_workers);
+        let buckets = Arc::clone(&self.buckets);
+        let started = Instant::now();
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            thread::Builder::new()
+                .name("flood-schedule".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    let mut guard = cancelled.lock();
+                    let mut applied = None;
+                    while !*guard && state.load(Ordering::SeqCst) {
+                        let elapsed = started.elapsed();
+                        let rate = plan.rate_at(elapsed);
+                        if let Some(rate) = rate.filter(|&rate| applied != Some(rate)) {
+                            rate_limit.store(rate, Ordering::SeqCst);
+                            let workers = scaled_workers.load(Ordering::SeqCst);
+                            let limit = capped_rate(rate, rate_cap.load(Ordering::SeqCst));
+                            retune(&buckets.lock(), rate_share(limit, workers));
+                            applied = Some(rate);
+                        }
+                        let Some(next) = plan.next_point(elapsed) else {
+                            break;
+                        };
+                        let wait = (next - elapsed).min(RATE_SCHEDULE_STEP);
+                        wake.wait_until(&mut guard, started + elapsed + wait);
+                    }
+                })
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+
+        Ok(BackgroundThread { cancel, handle })
+    }
+
+    /// Sample host resources every `GOVERNOR_INTERVAL` and cap the rate while
+    /// they are over `max_cpu_percent` or `max_rss_bytes`
+    ///
+    /// The cap sits under `rate_limit`, so `set_rate`, ramps and schedules keep
+    /// working and take full effect once it is lifted. Paused intervals are skipped.
+    fn spawn_governor(&self) -> Result<BackgroundThread, EngineError> {
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = Arc::clone(&self.state);
+        let paused = Arc::clone(&self.paused);
+        let packets_sent = Arc::clone(&self.packets_sent);
+        let flush_requested = Arc::clone(&self.flush_requested);
+        let rate_limit = Arc::clone(&self.rate_limit);
+        let rate_cap = Arc::clone(&self.rate_cap);
+        let scaled_workers = Arc::clone(&self.scaled_workers);
+        let buckets = Arc::clone(&self.buckets);
+        let sampler = Arc::clone(&self.resource_sampler);
+        let mut governor =
+            ResourceGovernor::new(self.config.max_cpu_percent, self.config.max_rss_bytes);
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            thread::Builder::new()
+                .name("flood-governor".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    // Sets the CPU baseline for the first interval
+                    sampler.sample();
+                    let mut last = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                    let mut guard = cancelled.lock();
+                    while !*guard && state.load(Ordering::SeqCst) {
+                        if !wake
+                            .wait_until(&mut guard, last.0 + GOVERNOR_INTERVAL)
+                            .timed_out()
+                        {
+                            continue;
+                        }
+                        // Workers publish their counts while the sample is read
+                        flush_requested.fetch_add(1, Ordering::AcqRel);
+                        let usage = sampler.sample();
+                        let now = (Instant::now(), packets_sent.load(Ordering::Relaxed));
+                        let secs = now.0.duration_since(last.0).as_secs_f64();
+                        let pps = (now.1 - last.1) as f64 / secs;
+                        last = now;
+                        let Some(usage) = usage.filter(|_| !paused.load(Ordering::Relaxed)) else {
+                            continue;
+                        };
+
+                        let limit = rate_limit.load(Ordering::SeqCst);
+                        let cap = governor.update(usage, pps as u64, limit).unwrap_or(0);
+                        if rate_cap.swap(cap, Ordering::SeqCst) != cap {
+                            let workers = scaled_workers.load(Ordering::SeqCst);
+                            let rate = rate_share(capped_rate(limit, cap), workers);
+                            retune(&buckets.lock(), rate);
+                        }
+                    }
+                })
+                .map_err(|e| EngineError::ThreadError(e.to_string()))?
+        };
+
+        Ok(BackgroundThread { cancel, handle })
+    }
+
+    /// Cancel the watchdog and join every worker
+    fn join_workers(&mut self) {
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.cancel_and_join();
+        }
+        if let Some(sampler) = self.sampler.take() {
+            sampler.cancel_and_join();
+        }
+        if let Some(autoscaler) = self.autoscaler.take() {
+            autoscaler.cancel_and_join();
+        }
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.cancel_and_join();
+        }
+        if let Some(governor) = self.governor.take() {
+            governor.cancel_and_join();
+        }
+        self.buckets.lock().clear();
+        for worker in self.threads.drain(..) {
+            worker.stop_and_join();
+        }
+        // Every worker has exited, so whatever is still queued was never sent
+        self.tasks_drained += drain_tasks(&self.stealers);
+        self.stealers.clear();
+    }
+
+    /// Grow or shrink the worker pool without restarting the engine
+    ///
+    /// New workers share the existing counters and rate limit; surplus workers
+    /// are signalled through their own stop flag and joined, leaving the rest
+    /// untouched. When the engine is stopped only the configuration changes.
+    pub fn set_thread_count(&mut self, threads: usize) -> Result<(), EngineError> {
+        if threads == 0 {
+            return Err(EngineError::InvalidConfig(
+                "thread count must be at least 1".to_string(),
+            ));
+        }
+        if self.config.autoscale && self.state.load(Ordering::SeqCst) {
+            return Err(EngineError::InvalidConfig(
+                "thread count is managed by autoscale while running".to_string(),
+            ));
+        }
+
+        self.config.threads = threads;
+        self.scaled_workers.store(threads, Ordering::SeqCst);
+
+        if !self.state.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        if threads > self.threads.len() {
+            for thread_id in self.threads.len()..threads {
+                // New workers steal from the running ones, not the other way round
+                let deque = self.uses_stealing().then(|| {
+                    let (mut deque, stealer) = WorkStealingQueue::new();
+                    for other in &self.stealers {
+                        deque.add_stealer(other.clone());
+                    }
+                    self.stealers.push(stealer);
+                    deque
+                });
+                let worker = self.spawn_worker(thread_id, deque)?;
+                self.threads.push(worker);
+            }
+        } else {
+            self.buckets.lock().truncate(threads);
+            for worker in self.threads.split_off(threads) {
+                worker.stop_and_join();
+            }
+            // Nothing refills the surplus workers' deques; retire them with their workers
+            if self.stealers.len() > threads {
+                self.tasks_drained += drain_tasks(&self.stealers[threads..]);
+                self.stealers.truncate(threads);
+            }
+        }
+        // The same total rate is now split a different number of ways
+        self.retune_buckets();
+
+        Ok(())
+    }
+
+    /// Stop the workers and wait for them to exit
+    ///
+    /// After the `duration` watchdog has ended a run this still succeeds once,
+    /// reaping the finished workers.
+    pub fn stop(&mut self) -> Result<(), EngineError> {
+        if !self.state.load(Ordering::SeqCst) && self.threads.is_empty() {
+            return Err(EngineError::NotRunning);
+        }
+
+        if self.state.swap(false, Ordering::SeqCst) {
+            *self.stopped_at.lock() = Some(SystemTime::now());
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        // Observable from other threads through `state_handle` while joining
+        self.lifecycle.set(EngineState::Stopping);
+
+        // Wait for threads to finish
+        self.join_workers();
+        self.open_sockets.store(0, Ordering::Relaxed);
+        self.lifecycle.set(EngineState::Stopped);
+
+        self.finish_capture()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state.load(Ordering::SeqCst)
+    }
+
+    /// Current lifecycle state
+    pub fn state(&self) -> EngineState {
+        self.lifecycle.get()
+    }
+
+    /// Shareable handle for watching the lifecycle from another thread
+    pub fn state_handle(&self) -> EngineStateHandle {
+        self.lifecycle.clone()
+    }
+
+    /// Serve the JSON control API on `addr`, loopback only unless configured otherwise
+    ///
+    /// Commands must carry `token`; `start` and `set_rate` are checked against
+    /// `safety`. See `ControlServer` for the protocol.
+    pub fn serve_control<A: ToSocketAddrs>(
+        self,
+        addr: A,
+        token: &str,
+        safety: SafetyController,
+    ) -> std::io::Result<ControlHandle> {
+        ControlServer::new(self, token, safety).bind(addr)
+    }
+
+    /// Suspend sending without tearing down workers or sockets
+    pub fn pause(&self) -> Result<(), EngineError> {
+        if !self.is_running() {
+            return Err(EngineError::NotRunning);
+        }
+        self.paused.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Resume sending after `pause`
+    pub fn resume(&self) -> Result<(), EngineError> {
+        if !self.is_running() {
+            return Err(EngineError::NotRunning);
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_rate(&mut self, pps: u64) {
+        self.rate_limit.store(pps, Ordering::SeqCst);
+        self.retune_buckets();
+    }
+
+    /// Find the highest rate the target takes without errors climbing
+    ///
+    /// Runs the engine at `step`, `2 * step`, ... pps for `step_duration` each,
+    /// up to the rate limit, and stops at the first rate where more than
+    /// `CALIBRATE_MAX_ERROR_RATIO` of sends fail. Returns the rate before it,
+    /// the limit if none did, or `step` if even that did. The engine is
+    /// stopped again afterwards with its rate limit restored.
+    pub fn calibrate(&mut self, step_duration: Duration, step: u64) -> Result<u64, EngineError> {
+        if self.is_running() {
+            return Err(EngineError::AlreadyRunning);
+        }
+        let ceiling = self.get_rate_limit();
+        if ceiling == 0 {
+            return Err(EngineError::InvalidConfig(
+                "calibrate needs a rate limit as its ceiling".to_string(),
+            ));
+        }
+        if step == 0 {
+            return Err(EngineError::InvalidConfig(
+                "calibrate step must be at least 1 pps".to_string(),
+            ));
+        }
+        if self.config.ramp.is_some() || !self.config.rate_schedule.is_empty() {
+            return Err(EngineError::InvalidConfig(
+                "calibrate cannot run alongside a ramp or rate_schedule".to_string(),
+            ));
+        }
+
+        self.start()?;
+        let result = calibrate_rate(ceiling, step, |rate| {
+            self.set_rate(rate);
+            self.flush_stats();
+            let before = (
+                self.packets_sent.load(Ordering::Relaxed),
+                self.errors.load(Ordering::Relaxed),
+            );
+            thread::sleep(step_duration);
+            if !self.is_running() {
+                return Err(EngineError::NotRunning);
+            }
+            self.flush_stats();
+            Ok((
+                self.packets_sent.load(Ordering::Relaxed) - before.0,
+                self.errors.load(Ordering::Relaxed) - before.1,
+            ))
+        });
+        let _ = self.stop();
+        self.set_rate(ceiling);
+        result
+    }
+
+    /// Limit new TCP/HTTP connections to `cps` per second, 0 for unlimited
+    ///
+    /// Takes effect immediately; sends on pooled connections stay governed
+    /// by `set_rate` alone.
+    pub fn set_conn_rate(&mut self, cps: u64) {
+        self.conn_bucket.set_rate(cps);
+        self.conn_bucket.set_burst(conn_bucket_depth(cps));
+    }
+
+    /// Each worker's share of the engine-wide rate limit, 0 when unlimited
+    fn worker_rate(&self) -> u64 {
+        let limit = capped_rate(
+            self.rate_limit.load(Ordering::SeqCst),
+            self.rate_cap.load(Ordering::SeqCst),
+        );
+        rate_share(limit, self.scaled_workers.load(Ordering::SeqCst))
+    }
+
+    /// Replace the source `max_cpu_percent` and `max_rss_bytes` are checked
+    /// against, from the next `start`
+    pub fn set_resource_sampler(&mut self, sampler: Arc<dyn ResourceSampler>) {
+        self.resource_sampler = sampler;
+    }
+
+    /// Rate the resource governor is holding the engine to, `None` while it
+    /// is not throttling
+    pub fn governed_rate(&self) -> Option<u64> {
+        match self.rate_cap.load(Ordering::SeqCst) {
+            0 => None,
+            cap => Some(cap),
+        }
+    }
+
+    /// Ramp the rate limit as `ramp` describes, starting now if running
+    ///
+    /// Replaces any ramp in progress; when stopped it applies from the next
+    /// `start`.
+    pub fn set_ramp(&mut self, ramp: RampConfig) -> Result<(), EngineError> {
+        ramp.validate()?;
+        self.config.rate_schedule.clear();
+        self.config.ramp = Some(ramp.clone());
+        self.restart_scheduler(RatePlan::Ramp(ramp))
+    }
+
+    /// Apply `(offset, pps)` points from now on, see `EngineConfig::rate_schedule`
+    ///
+    /// Replaces any ramp or schedule in progress; when stopped it applies from
+    /// the next `start`.
+    pub fn set_rate_schedule(
+        &mut self,
+        points: Vec<(Duration, u64)>,
+        interpolate: bool,
+    ) -> Result<(), EngineError> {
+        validate_schedule(&points)?;
+        self.config.ramp = None;
+        self.config.rate_schedule = points.clone();
+        self.config.interpolate_schedule = interpolate;
+        if points.is_empty() {
+            if let Some(scheduler) = self.scheduler.take() {
+                scheduler.cancel_and_join();
+            }
+            return Ok(());
+        }
+        self.restart_scheduler(RatePlan::Points {
+            points,
+            interpolate,
+        })
+    }
+
+    fn restart_scheduler(&mut self, plan: RatePlan) -> Result<(), EngineError> {
+        if !self.state.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.cancel_and_join();
+        }
+        self.scheduler = Some(self.spawn_scheduler(plan)?);
+        Ok(())
+    }
+
+    /// Workers spawned by `start`: the autoscale ceiling or `threads`
+    fn spawn_count(&self) -> usize {
+        if !self.config.autoscale {
+            return self.config.threads;
+        }
+        let cores = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        self.config
+            .max_threads
+            .unwrap_or(cores)
+            .max(self.config.threads)
+    }
+
+    /// Workers sending right now; fewer than spawned while autoscale holds some back
+    pub fn scaled_thread_count(&self) -> usize {
+        if self.config.autoscale {
+            self.scaled_workers
+                .load(Ordering::SeqCst)
+                .min(self.threads.len())
+        } else {
+            self.threads.len()
+        }
+    }
+
+    fn retune_buckets(&self) {
+        retune(&self.buckets.lock(), self.worker_rate());
+    }
+
+    /// Make workers publish their local counters so `get_stats` is exact
+    ///
+    /// Waits up to `FLUSH_TIMEOUT` for every worker to acknowledge and returns
+    /// whether all of them did. Sending continues throughout.
+    pub fn flush_stats(&self) -> bool {
+        let generation = self.flush_requested.fetch_add(1, Ordering::AcqRel) + 1;
+        let deadline = Instant::now() + FLUSH_TIMEOUT;
+        loop {
+            let settled = self
+                .threads
+                .iter()
+                .all(|w| w.flushed.load(Ordering::Acquire) >= generation || w.handle.is_finished());
+            if settled {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_micros(200));
+        }
+    }
+
+    pub fn get_stats(&self) -> StatsSnapshot {
+        let duration = self
+            .start_time
+            .lock()
+            .map(|t| t.elapsed())
+            .unwrap_or(Duration::ZERO);
+
+        let packets = self.packets_sent.load(Ordering::Relaxed);
+        let bytes = self.bytes_sent.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        let dropped = self.packets_dropped.load(Ordering::Relaxed);
+
+        let secs = duration.as_secs_f64().max(0.001);
+        let pps = (packets as f64 / secs) as u64;
+        // Before the first full-second sample the current average is all there is
+        let sampled = |q: f64| self.collector.pps_percentile(q).unwrap_or(pps);
+
+        StatsSnapshot {
+            packets_sent: packets,
+            bytes_sent: bytes,
+            errors,
+            duration,
+            pps,
+            bps: (bytes as f64 / secs) as u64,
+            packets_dropped: dropped,
+            oversized_errors: self.oversized_errors.load(Ordering::Relaxed),
+            path_mtu: self.path_mtu.load(Ordering::Relaxed),
+            peak_pps: self.collector.peak_pps().unwrap_or(pps),
+            p50_pps: sampled(0.5),
+            p95_pps: sampled(0.95),
+            p99_pps: sampled(0.99),
+        }
+    }
+
+    /// Send path the config selects; GSO and zerocopy still fall back to plain
+    /// sends per worker when the kernel refuses them
+    pub fn backend(&self) -> &'static str {
+        let config = &self.config;
+        if config.dry_run {
+            return "dry_run";
+        }
+        match config.protocol {
+            Protocol::UDP if config.gso_segments.is_some() => "udp_gso",
+            Protocol::UDP if config.zerocopy && config.packet_size >= ZEROCOPY_MIN_PAYLOAD => {
+                "udp_zerocopy"
+            }
+            Protocol::UDP => "udp",
+            Protocol::TCP if config.tcp_fastopen => "tcp_fastopen",
+            Protocol::TCP => "tcp",
+            Protocol::HTTP => "http",
+            Protocol::ICMP => "icmp_raw",
+            Protocol::RAW => "raw",
+        }
+    }
+
+    /// JSON summary of the latest run: config, totals, average and peak pps,
+    /// per-thread counters, backend and wall-clock bounds
+    ///
+    /// Called mid-run it reports the counters flushed so far with `"complete": false`.
+    pub fn final_report(&self) -> String {
+        let stats = self.get_stats();
+        let stopped_at = *self.stopped_at.lock();
+        let complete = !self.is_running();
+        // `get_stats` keeps counting from the start, so end at the stop instead
+        let duration = match (self.started_at, stopped_at) {
+            (Some(start), Some(end)) if complete => {
+                end.duration_since(start).unwrap_or(stats.duration)
+            }
+            _ => stats.duration,
+        };
+        let average_pps = stats.packets_sent as f64 / duration.as_secs_f64().max(0.001);
+        let unix_secs = |t: Option<SystemTime>| {
+            t.map(|t| {
+                t.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64()
+            })
+        };
+        let threads: Vec<_> = self
+            .per_thread_stats()
+            .iter()
+            .map(|t| {
+                let snapshot = t.snapshot();
+                serde_json::json!({
+                    "thread_id": t.thread_id,
+                    "packets_sent": snapshot.packets_sent,
+                    "bytes_sent": snapshot.bytes_sent,
+                    "errors": snapshot.errors,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "complete": complete,
+            "config": serde_json::to_value(&self.config).unwrap_or_default(),
+            "backend": self.backend(),
+            "started_at": unix_secs(self.started_at),
+            "ended_at": unix_secs(stopped_at.filter(|_| complete)),
+            "duration_secs": duration.as_secs_f64(),
+            "packets_sent": stats.packets_sent,
+            "bytes_sent": stats.bytes_sent,
+            "errors": stats.errors,
+            "average_pps": average_pps,
+            "peak_pps": stats.peak_pps,
+            "threads": threads,
+        })
+        .to_string()
+    }
+
+    /// Send errors by kind, published with the other counters
+    pub fn error_breakdown(&self) -> HashMap<SendErrorKind, u64> {
+        SendErrorKind::ALL
+            .iter()
+            .map(|&kind| {
+                (
+                    kind,
+                    self.error_kinds[kind as usize].load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+
+    /// Sampled nanosecond gaps between worker 0's sends this run, `None`
+    /// without `EngineConfig::record_send_timing`
+    ///
+    /// A smoothly paced run clusters around `1e9 / per-worker pps`; a bursty
+    /// one shows a spread of near-zero and long gaps.
+    pub fn send_gap_histogram(&self) -> Option<PpsHistogram> {
+        self.send_gaps.as_ref().map(|gaps| gaps.snapshot())
+    }
+
+    /// Replies matched this run and their round trips, `None` without
+    /// `EngineConfig::collect_responses`
+    ///
+    /// Workers check for replies between bursts, so latencies can read up to
+    /// one burst long.
+    pub fn response_stats(&self) -> Option<ResponseStats> {
+        self.responses.as_ref().map(|r| r.snapshot())
+    }
+
+    /// Packets and bytes sent to each of `EngineConfig::destinations`, empty
+    /// for a single target
+    ///
+    /// Counted as each burst completes, so they can lead `get_stats` between flushes.
+    pub fn destination_stats(&self) -> Vec<DestinationStats> {
+        self.destinations.as_ref().map_or_else(Vec::new, |d| {
+            d.addrs
+                .iter()
+                .enumerate()
+                .zip(d.health.snapshot())
+                .map(|((i, &addr), health)| DestinationStats {
+                    addr,
+                    packets_sent: d.packets[i].load(Ordering::Relaxed),
+                    bytes_sent: d.bytes[i].load(Ordering::Relaxed),
+                    healthy: health.healthy,
+                    ejections: health.ejections,
+                })
+                .collect()
+        })
+    }
+
+    /// Per-worker counters ordered by thread id, updated whenever a worker flushes
+    pub fn per_thread_stats(&self) -> Vec<Arc<ThreadStats>> {
+        self.collector.thread_stats()
+    }
+
+    /// Why UDP sends are being rejected as too large, if they are
+    ///
+    /// Returns `PayloadExceedsMtu` with the discovered path MTU once workers hit
+    /// EMSGSIZE. With `clamp_to_mtu` the payload is shrunk instead and this stays `None`.
+    pub fn payload_error(&self) -> Option<EngineError> {
+        if self.config.clamp_to_mtu || self.oversized_errors.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        Some(EngineError::PayloadExceedsMtu {
+            payload: self.config.max_payload_len(),
+            mtu: self.path_mtu.load(Ordering::Relaxed),
+        })
+    }
+
+    fn spawn_worker(
+        &self,
+        thread_id: usize,
+        deque: Option<WorkStealingQueue<ConnTask>>,
+    ) -> Result<Worker, EngineError> {
+        let running = Arc::new(AtomicBool::new(true));
+        let flushed = Arc::new(AtomicU64::new(self.flush_requested.load(Ordering::Acquire)));
+        let rate = self.worker_rate();
+        let bucket = Arc::new(TokenBucket::new(rate, bucket_depth(rate)));
+        let parked = Arc::new(AtomicBool::new(
+            self.config.autoscale && thread_id >= self.scaled_workers.load(Ordering::SeqCst),
+        ));
+        let ctx = WorkerContext {
+            state: Arc::clone(&self.state),
+            running: Arc::clone(&running),
+            paused: Arc::clone(&self.paused),
+            packets_sent: Arc::clone(&self.packets_sent),
+            bytes_sent: Arc::clone(&self.bytes_sent),
+            errors: Arc::clone(&self.errors),
+            packets_dropped: Arc::clone(&self.packets_dropped),
+            bucket: Arc::clone(&bucket),
+            active_threads: Arc::clone(&self.active_threads),
+            open_sockets: Arc::clone(&self.open_sockets),
+            tfo_connections: Arc::clone(&self.tfo_connections),
+            tfo_negotiated: Arc::clone(&self.tfo_negotiated),
+            flush_requested: Arc::clone(&self.flush_requested),
+            flushed: Arc::clone(&flushed),
+            oversized_errors: Arc::clone(&self.oversized_errors),
+            path_mtu: Arc::clone(&self.path_mtu),
+            error_kinds: Arc::clone(&self.error_kinds),
+            thread_stats: self.collector.thread_slot(thread_id),
+            collector: Arc::clone(&self.collector),
+            tasks: deque.map(|deque| TaskSource {
+                deque,
+                produced: Arc::clone(&self.tasks_produced),
+            }),
+            safety: self.safety.clone(),
+            replay: self.replay.clone(),
+            capture: self
+                .capture
+                .as_ref()
+                .filter(|c| c.writer.is_some())
+                .map(|c| Arc::clone(&c.tap)),
+            conn_bucket: Arc::clone(&self.conn_bucket),
+            parked: Arc::clone(&parked),
+            destinations: self.destinations.clone(),
+            send_gaps: self
+                .send_gaps
+                .as_ref()
+                .filter(|_| thread_id == 0)
+                .map(|gaps| SendGapSampler::new(Arc::clone(gaps))),
+            sequence: self.sequence.clone(),
+            responses: self.responses.clone(),
+        };
+        if let Some(tasks) = &ctx.tasks {
+            // Queue the first batch before the thread starts so there is work to steal
+            tasks.refill();
+        }
+        let config = self.config.clone();
+        let addr = self.addr;
+
+        let cpu = if self.cpu_order.is_empty() {
+            None
+        } else {
+            if thread_id == self.cpu_order.len() {
+                warn!(
+                    "{} or more worker threads on {} CPUs; pinning modulo core count",
+                    thread_id + 1,
+                    self.cpu_order.len()
+                );
+            }
+            Some(self.cpu_order[thread_id % self.cpu_order.len()])
+        };
+
+        // Counted before spawning so the pool size is exact once this returns
+        self.active_threads.fetch_add(1, Ordering::SeqCst);
+        let handle = thread::Builder::new()
+            .name(format!("flood-worker-{}", thread_id))
+            .spawn(move || {
+                if let Some(cpu) = cpu {
+                    if let Err(e) = pin_current_thread(cpu) {
+                        warn!(
+                            "flood-worker-{}: cannot pin to CPU {}: {}",
+                            thread_id, cpu, e
+                        );
+                    }
+                }
+                Self::worker_loop(thread_id, addr, config, ctx);
+            })
+            .map_err(|e| {
+                self.active_threads.fetch_sub(1, Ordering::SeqCst);
+                EngineError::ThreadError(e.to_string())
+            })?;
+
+        self.buckets.lock().push(Arc::clone(&bucket));
+        Ok(Worker {
+            running,
+            flushed,
+            bucket,
+            parked,
+            handle,
+        })
+    }
+
+    fn worker_loop(thread_id: usize, addr: SocketAddr, config: EngineConfig, ctx: WorkerContext) {
+        // Create socket based on protocol
+        match config.protocol {
+            Protocol::UDP => Self::udp_worker(thread_id, addr, config, &ctx),
+            Protocol::TCP | Protocol::HTTP => Self::tcp_worker(thread_id, addr, config, &ctx),
+            Protocol::ICMP => Self::icmp_worker(thread_id, addr, config, &ctx),
+            Protocol::RAW => Self::raw_worker(thread_id, addr, config, &ctx),
+        }
+
+        ctx.active_threads.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn udp_worker(thread_id: usize, addr: SocketAddr, config: EngineConfig, ctx: &WorkerContext) {
+        use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+
+        // With several destinations, each gets the same number of sockets
+        let targets: Vec<SocketAddr> = ctx
+            .destinations
+            .as_ref()
+            .map_or_else(|| vec![addr], |d| d.addrs.clone());
+        let socket_count = config.sockets_per_thread.div_ceil(targets.len()) * targets.len();
+
+        // Create multiple sockets for parallel sending (reduces kernel lock contention)
+        let mut sockets = Vec::with_capacity(socket_count);
+        // Index into `targets` of every socket's destination
+        let mut socket_dest = Vec::with_capacity(socket_count);
+        if config.dry_run {
+            sockets.extend((0..socket_count).map(|_| UdpSink::Discard));
+            socket_dest.extend((0..socket_count).map(|i| i % targets.len()));
+        }
+
+        for i in 0..socket_count {
+            if config.dry_run {
+                break;
+            }
+            let addr = targets[i % targets.len()];
+            let socket = match Socket::new(
+                Domain::for_address(addr),
+                Type::DGRAM,
+                Some(SockProtocol::UDP),
+            ) {
+                Ok(s) => s,
+                Err(_) => {
+                    ctx.record_error();
+                    continue;
+                }
+            };
+
+            // Ultra-aggressive socket optimizations for maximum throughput
+            let _ = socket.set_send_buffer_size(SEND_BUFFER_SIZE);
+            let _ = socket.set_recv_buffer_size(RECV_BUFFER_SIZE);
+            let _ = socket.set_nonblocking(false);
+
+            // Platform-specific optimizations
+            #[cfg(target_os = "linux")]
+            {
+                let _ = socket.set_cork(false);
+                // Enable busy polling for lower latency
+                unsafe {
+                    let busy_poll: libc::c_int = 50; // 50 microseconds
+                    let _ = libc::setsockopt(
+                        socket.as_raw_fd(),
+                        libc::SOL_SOCKET,
+                        libc::SO_BUSY_POLL,
+                        &busy_poll as *const _ as *const libc::c_void,
+                        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                    );
+                }
+            }
+
+            #[cfg(target_os = "linux")]
+            if config.dont_fragment {
+                set_dont_fragment(&socket, addr);
+            }
+            if let Err(e) = set_ip_header_options(&socket, addr, &config) {
+                warn!("cannot set TTL/ToS on UDP socket: {}", e);
+                ctx.record_error();
+                continue;
+            }
+            if let Err(e) = bind_interface(&socket, addr, &config) {
+                warn!("cannot bind UDP socket to its interface: {}", e);
+                ctx.record_error();
+                continue;
+            }
+
+            #[cfg(target_os = "windows")]
+            {
+                // Windows-specific: enable SIO_UDP_CONNRESET to ignore ICMP unreachable
+                let _ = socket.set_nodelay(true);
+            }
+
+            // Connect socket to avoid per-packet address lookup (significant speedup)
+            let sock_addr: socket2::SockAddr = addr.into();
+            if socket.connect(&sock_addr).is_ok() {
+                sockets.push(UdpSink::Socket(socket));
+                socket_dest.push(i % targets.len());
+            }
+        }
+
+        if sockets.is_empty() {
+            ctx.record_error();
+            return;
+        }
+        let open = sockets
+            .iter()
+            .filter(|s| matches!(s, UdpSink::Socket(_)))
+            .count();
+        ctx.open_sockets.fetch_add(open, Ordering::Relaxed);
+
+        // Captured frames carry each socket's real source address
+        let mut capture = ctx.capture.clone().map(|tap| CaptureCursor {
+            tap,
+            since_sample: 0,
+        });
+        let capture_src: Vec<SocketAddr> = sockets
+            .iter()
+            .map(|sink| match sink {
+                UdpSink::Socket(socket) => socket.local_addr().ok().and_then(|a| a.as_socket()),
+                UdpSink::Discard => None,
+            })
+            .zip(&socket_dest)
+            .map(|(a, &dest)| {
+                a.unwrap_or_else(|| match targets[dest] {
+                    SocketAddr::V4(_) => SocketAddr::from((std::net::Ipv4Addr::UNSPECIFIED, 0)),
+                    SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+                })
+            })
+            .collect();
+
+        // Pre-generate multiple payload variants for better cache utilization and evasion
+        // With a size distribution, buffers fit the largest bucket and are trimmed per packet
+        let mut size_sampler = SizeSampler::from_config(&config, thread_id);
+        let payload_len = size_sampler
+            .as_ref()
+            .map_or(config.packet_size, |s| s.max_size());
+        let mut payloads: Vec<Vec<u8>> = (0..PAYLOAD_VARIANTS)
+            .map(|i| {
+                let mut p = vec![0u8; payload_len];
+                // Vary payload to avoid pattern detection and improve cache behavior
+                let seed = (i as u8).wrapping_add(thread_id as u8);
+                p[0] = seed;
+                if payload_len > 1 {
+                    p[1] = seed.wrapping_mul(17);
+                }
+                if payload_len > 2 {
+                    p[2] = seed.wrapping_mul(31);
+                }
+                if payload_len > 3 {
+                    p[3] = seed.wrapping_mul(47);
+                }
+                // Fill rest with pseudo-random data for better compression resistance
+                for j in 4..payload_len.min(64) {
+                    p[j] = ((i * 7 + j * 13) & 0xFF) as u8;
+                }
+                p
+            })
+            .collect();
+
+        // UDP GSO: each payload variant repeated `segments` times, sent in one syscall.
+        // The first send doubles as the probe for kernel support.
+        let mut gso_segments = config
+            .gso_segments
+            .filter(|_| !config.dry_run)
+            .and_then(|n| gso_batch(n, payload_len));
+        let mut gso_payloads = coalesce_payloads(&payloads, gso_segments);
+        let mut gso_confirmed = false;
+
+        // MSG_ZEROCOPY per socket, only for payloads large enough to pay off
+        let use_zerocopy =
+            config.zerocopy && gso_segments.is_none() && payload_len >= ZEROCOPY_MIN_PAYLOAD;
+        let mut zerocopy: Vec<Option<ZeroCopyTracker>> = sockets
+            .iter()
+            .map(|sink| match sink {
+                UdpSink::Socket(socket) if use_zerocopy => ZeroCopyTracker::enable(socket),
+                _ => None,
+            })
+            .collect();
+
+        // Performance tracking variables
+        let mut local = LocalCounters::default();
+        let mut drop_injector = DropInjector::from_config(&config, thread_id);
+        let mut port_sweep = PortSweep::from_config(&config, thread_id);
+        let mut payload_idx = 0usize;
+        let mut socket_idx = 0usize;
+        let payload_factory = config.payload_factory.clone();
+        let replay = ctx.replay.clone();
+        let replay_start = Instant::now();
+        let mut packet_index = thread_id;
+        let mut clamp_limit = usize::MAX;
+        let mut sequence = SequenceStamper::from_config(&config, thread_id, ctx.sequence.as_ref());
+        let mut responses = ctx.responses.as_ref().map(|counters| {
+            UdpResponses::new(Arc::clone(counters), config.response_timeout, sockets.len())
+        });
+
+        while ctx.is_running() {
+            ctx.service_flush(&mut local);
+            if ctx.idle_if_paused() {
+                continue;
+            }
+
+            // Outer batch loop for reduced state checks
+            for _ in 0..OUTER_BATCH_SIZE {
+                if !ctx.is_running() {
+                    break;
+                }
+                ctx.service_flush(&mut local);
+
+                // Tokens for the whole burst up front; bursts shrink at low rates
+                let burst = ctx.acquire_burst();
+
+                // EMSGSIZE in the last batch: record the path MTU and optionally shrink
+                if local.oversized_pending {
+                    local.oversized_pending = false;
+                    let addr = targets[socket_dest[socket_idx]];
+                    let mtu = match &sockets[socket_idx] {
+                        UdpSink::Socket(socket) => path_mtu(socket, addr),
+                        UdpSink::Discard => None,
+                    };
+                    if let Some(mtu) = mtu {
+                        ctx.path_mtu.store(mtu, Ordering::Relaxed);
+                        if config.clamp_to_mtu {
+                            clamp_limit = max_payload_for_mtu(mtu, addr);
+                            payloads.iter_mut().for_each(|p| p.truncate(clamp_limit));
+                            gso_segments = gso_segments.and(
+                                config
+                                    .gso_segments
+                                    .and_then(|n| gso_batch(n, payloads[0].len())),
+                            );
+                            gso_payloads = coalesce_payloads(&payloads, gso_segments);
+                        }
+                    }
+                }
+
+                let socket = &sockets[socket_idx];
+                if let (Some(sweep), UdpSink::Socket(socket)) = (port_sweep.as_mut(), socket) {
+                    let next = SocketAddr::new(addr.ip(), sweep.next_port());
+                    if let Err(e) = socket.connect(&next.into()) {
+                        local.record_send_error(&e);
+                    }
+                }
+                let payload = &payloads[payload_idx];
+                let burst_start = (Instant::now(), local.packets, local.bytes, local.errors);
+
+                // Loss injection, size sampling, sequence numbers, reply matching
+                // and generated payloads take a per-packet path; the unrolled
+                // loop stays untouched
+                if drop_injector.is_some()
+                    || size_sampler.is_some()
+                    || payload_factory.is_some()
+                    || replay.is_some()
+                    || sequence.is_some()
+                    || responses.is_some()
+                {
+                    for _ in 0..burst {
+                        if ctx.halted() {
+                            break;
+                        }
+                        let generated = payload_factory.as_ref().map(|f| {
+                            let mut p = f.payload(packet_index);
+                            p.truncate(clamp_limit);
+                            p
+                        });
+                        let replayed = replay.as_ref().map(|replay| {
+                            if let Some(speed) = config.replay_timing {
+                                let offset = replay.offset(packet_index).div_f64(speed);
+                                ctx.sleep_until(replay_start + offset);
+                            }
+                            replay.payload(packet_index)
+                        });
+                        packet_index += config.threads;
+                        let payload = replayed.or(generated.as_deref()).unwrap_or(payload);
+                        let seq = sequence.as_mut().map(|s| s.next());
+
+                        if drop_injector.as_mut().is_some_and(|d| d.should_drop()) {
+                            local.dropped += 1;
+                            continue;
+                        }
+                        // Captured payloads keep their own sizes
+                        let len = match size_sampler.as_mut() {
+                            Some(s) if replayed.is_none() => s.sample().min(payload.len()),
+                            _ => payload.len(),
+                        };
+                        let packet = match (sequence.as_mut(), seq) {
+                            (Some(stamper), Some(seq)) => stamper.stamp(&payload[..len], seq),
+                            _ => &payload[..len],
+                        };
+                        let packet = match responses.as_mut() {
+                            Some(responses) => responses.stamp(socket_idx, packet),
+                            None => packet,
+                        };
+                        match socket.send(packet) {
+                            Ok(n) => {
+                                local.packets += 1;
+                                local.bytes += n as u64;
+                                if let Some(capture) = capture.as_mut() {
+                                    capture.observe(1, packet, capture_src[socket_idx]);
+                                }
+                                if let Some(responses) = responses.as_mut() {
+                                    responses.record_sent();
+                                }
+                            }
+                            Err(e) => local.record_send_error(&e),
+                        }
+                    }
+                    ctx.record_burst(
+                        burst_start.0,
+                        local.packets - burst_start.1,
+                        local.bytes - burst_start.2,
+                        local.errors - burst_start.3,
+                        socket_dest[socket_idx],
+                    );
+                    if let Some(responses) = responses.as_mut() {
+                        for (i, sink) in sockets.iter().enumerate() {
+                            if let UdpSink::Socket(socket) = sink {
+                                responses.collect(i, socket);
+                            }
+                        }
+                    }
+                    socket_idx = ctx.next_socket(socket_idx, &socket_dest);
+                    payload_idx = (payload_idx + 1) % PAYLOAD_VARIANTS;
+                    continue;
+                }
+
+                // Zerocopy sends are counted as their completions arrive
+                if let (UdpSink::Socket(sock), Some(tracker)) =
+                    (socket, zerocopy[socket_idx].as_mut())
+                {
+                    let mut i = 0u64;
+                    while i < burst && !ctx.halted() {
+                        let mut result = tracker.send(sock, payload);
+                        if matches!(&result, Err(e) if is_no_buffer_space(e)) {
+                            // Notifications are charged to the socket; reap them and retry
+                            let (packets, bytes) = tracker.drain(sock);
+                            local.packets += packets;
+                            local.bytes += bytes;
+                            result = tracker.send(sock, payload);
+                        }
+                        if let Err(e) = result {
+                            local.record_send_error(&e);
+                        }
+                        i += 1;
+                    }
+                    let (packets, bytes) = tracker.drain(sock);
+                    local.packets += packets;
+                    local.bytes += bytes;
+                    if let Some(capture) = capture.as_mut() {
+                        capture.observe(i, payload, capture_src[socket_idx]);
+                    }
+
+                    ctx.record_burst(
+                        burst_start.0,
+                        local.packets - burst_start.1,
+                        local.bytes - burst_start.2,
+                        local.errors - burst_start.3,
+                        socket_dest[socket_idx],
+                    );
+                    socket_idx = ctx.next_socket(socket_idx, &socket_dest);
+                    payload_idx = (payload_idx + 1) % PAYLOAD_VARIANTS;
+                    continue;
+                }
+
+                // One GSO send per `segments` datagrams; the kernel splits the buffer
+                if let Some(segments) = gso_segments {
+                    let mut i = 0u64;
+                    while i < burst && !ctx.halted() {
+                        let count = (burst - i).min(segments as u64) as usize;
+                        let buf = &gso_payloads[payload_idx][..count * payload.len()];
+                        match socket.send_segmented(buf, payload.len()) {
+                            Ok(n) => {
+                                gso_confirmed = true;
+                                local.packets += count as u64;
+                                local.bytes += n as u64;
+                            }
+                            Err(e) if !gso_confirmed && is_gso_unsupported(&e) => {
+                                warn!("UDP GSO unavailable ({}); sending per datagram", e);
+                                gso_segments = None;
+                                break;
+                            }
+                            Err(e) => (0..count).for_each(|_| local.record_send_error(&e)),
+                        }
+                        i += count as u64;
+                    }
+
+                    if gso_segments.is_some() {
+                        if let Some(capture) = capture.as_mut() {
+                            let sent = local.packets - burst_start.1;
+                            capture.observe(sent, payload, capture_src[socket_idx]);
+                        }
+                        ctx.record_burst(
+                            burst_start.0,
+                            local.packets - burst_start.1,
+                            local.bytes - burst_start.2,
+                            local.errors - burst_start.3,
+                            socket_dest[socket_idx],
+                        );
+                        socket_idx = ctx.next_socket(socket_idx, &socket_dest);
+                        payload_idx = (payload_idx + 1) % PAYLOAD_VARIANTS;
+                        continue;
+                    }
+                }
+
+                // Inner tight loop - maximum throughput with unrolled sends
+                let mut i = 0u64;
+                while i < burst {
+                    // An emergency stop must not wait out the burst
+                    if ctx.halted() {
+                        break;
+                    }
+                    // Unroll 4 sends for better instruction pipelining
+                    match socket.send(payload) {
+                        Ok(n) => {
+                            local.packets += 1;
+                            local.bytes += n as u64;
+                        }
+                        Err(e) => local.record_send_error(&e),
+                    }
+
+                    if i + 1 < burst {
+                        match socket.send(payload) {
+                            Ok(n) => {
+                                local.packets += 1;
+                                local.bytes += n as u64;
+                            }
+                            Err(e) => local.record_send_error(&e),
+                        }
+                    }
+
+                    if i + 2 < burst {
+                        match socket.send(payload) {
+                            Ok(n) => {
+                                local.packets += 1;
+                                local.bytes += n as u64;
+                            }
+                            Err(e) => local.record_send_error(&e),
+                        }
+                    }
+
+                    if i + 3 < burst {
+                        match socket.send(payload) {
+                            Ok(n) => {
+                                local.packets += 1;
+                                local.bytes += n as u64;
+                            }
+                            Err(e) => local.record_send_error(&e),
+                        }
+                    }
+
+                    i += 4;
+                }
+                if let Some(capture) = capture.as_mut() {
+                    let sent = local.packets - burst_start.1;
+                    capture.observe(sent, payload, capture_src[socket_idx]);
+                }
+                ctx.record_burst(
+                    burst_start.0,
+                    local.packets - burst_start.1,
+                    local.bytes - burst_start.2,
+                    local.errors - burst_start.3,
+                    socket_dest[socket_idx],
+                );
+
+                // Rotate socket and payload for better distribution
+                socket_idx = ctx.next_socket(socket_idx, &socket_dest);
+                payload_idx = (payload_idx + 1) % PAYLOAD_VARIANTS;
+            }
+
+            // Batch update atomic counters (reduces contention significantly)
+            if local.packets + local.dropped >= STATS_FLUSH_INTERVAL {
+                local.flush(ctx);
+            }
+        }
+
+        // Payload pages stay pinned until the kernel reports them done
+        for (sink, tracker) in sockets.iter().zip(zerocopy.iter_mut()) {
+            if let (UdpSink::Socket(socket), Some(tracker)) = (sink, tracker.as_mut()) {
+                let (packets, bytes) = tracker.drain_all(socket, ZEROCOPY_DRAIN_TIMEOUT);
+                local.packets += packets;
+                local.bytes += bytes;
+            }
+        }
+
+        // Final flush
+        local.flush(ctx);
+    }
+
+    fn tcp_worker(thread_id: usize, addr: SocketAddr, config: EngineConfig, ctx: &WorkerContext) {
+        use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+        use std::io::Write;
+
+        // Generate multiple HTTP request variants for evasion
+        let http_requests: Vec<Vec<u8>> = if config.protocol == Protocol::HTTP {
+            let user_agents = [
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15",
+                "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36",
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 14_0 like Mac OS X)",
+                "curl/7.68.0",
+                "Wget/1.21",
+            ];
+
+            user_agents.iter().enumerate().map(|(i, ua)| {
+                format!(
+                    "GET /?r={}{} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nAccept-Language: en-US,en;q=0.9\r\nAccept-Encoding: gzip, deflate\r\nConnection: keep-alive\r\nCache-Control: no-cache\r\n\r\n",
+                    thread_id, i, host_header(&config.target), ua
+                ).into_bytes()
+            }).collect()
+        } else {
+            vec![vec![0xAA; config.packet_size]]
+        };
+
+        // Connection pool for keep-alive connections
+        let mut connection_pool: Vec<Option<TcpStream>> =
+            (0..TCP_KEEPALIVE_CONNECTIONS).map(|_| None).collect();
+        let mut conn_idx = 0usize;
+        let mut request_idx = 0usize;
+
+        let mut local = LocalCounters::default();
+        let mut drop_injector = DropInjector::from_config(&config, thread_id);
+        let mut port_sweep = PortSweep::from_config(&config, thread_id);
+        let flush_interval = 100u64;
+        let mut responses = ctx.responses.as_ref().map(|counters| {
+            HttpResponses::new(
+                Arc::clone(counters),
+                config.response_timeout,
+                TCP_KEEPALIVE_CONNECTIONS,
+            )
+        });
+
+        while ctx.is_running() {
+            ctx.service_flush(&mut local);
+            if ctx.idle_if_paused() {
+                continue;
+            }
+
+            ctx.acquire_tokens(1);
+
+            // Stealing mode takes each request from the shared deques exactly once
+            let variant = match &ctx.tasks {
+                Some(tasks) => tasks.next().seq as usize,
+                None => {
+                    let variant = request_idx;
+                    request_idx = request_idx.wrapping_add(1);
+                    variant
+                }
+            };
+            let request = &http_requests[variant % http_requests.len()];
+
+            if drop_injector.as_mut().is_some_and(|d| d.should_drop()) {
+                ctx.packets_dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            if config.dry_run {
+                local.packets += 1;
+                local.bytes += request.len() as u64;
+                if local.packets % flush_interval == 0 {
+                    local.flush(ctx);
+                }
+                continue;
+            }
+
+            // An empty slot needs a connection token; while none is available,
+            // send on another pooled connection instead of waiting
+            let mut conn_token = false;
+            if connection_pool[conn_idx].is_none() {
+                conn_token = ctx.conn_bucket.try_acquire(1);
+                if !conn_token {
+                    let pooled = (1..TCP_KEEPALIVE_CONNECTIONS)
+                        .map(|k| (conn_idx + k) % TCP_KEEPALIVE_CONNECTIONS)
+                        .find(|&i| connection_pool[i].is_some());
+                    match pooled {
+                        Some(i) => conn_idx = i,
+                        None if ctx.acquire_connection() => conn_token = true,
+                        None => break,
+                    }
+                }
+            }
+
+            // Try to use existing connection from pool
+            let mut sent = false;
+            if let Some(ref mut stream) = connection_pool[conn_idx] {
+                match stream.write_all(request) {
+                    Ok(_) => {
+                        local.packets += 1;
+                        local.bytes += request.len() as u64;
+                        sent = true;
+                    }
+                    Err(_) => {
+                        // Connection dead, will create new one
+                        connection_pool[conn_idx] = None;
+                        if let Some(responses) = responses.as_mut() {
+                            responses.reset(conn_idx);
+                        }
+                    }
+                }
+            }
+
+            // Create new connection if needed
+            if !sent {
+                if !conn_token && !ctx.acquire_connection() {
+                    break;
+                }
+                let addr = port_sweep
+                    .as_mut()
+                    .map_or(addr, |sweep| SocketAddr::new(addr.ip(), sweep.next_port()));
+                match Self::open_tcp_connection(addr, request, &config, ctx) {
+                    Ok(stream) => {
+                        local.packets += 1;
+                        local.bytes += request.len() as u64;
+                        sent = true;
+                        // Store in pool for reuse
+                        connection_pool[conn_idx] = Some(stream);
+                    }
+                    Err(_) => {
+                        ctx.record_error();
+                    }
+                }
+            }
+
+            if let Some(responses) = responses.as_mut() {
+                if sent {
+                    responses.record_sent(conn_idx);
+                }
+                for (i, stream) in connection_pool.iter().enumerate() {
+                    if let Some(stream) = stream {
+                        responses.collect(i, &socket2::SockRef::from(stream));
+                    }
+                }
+            }
+
+            conn_idx = (conn_idx + 1) % TCP_KEEPALIVE_CONNECTIONS;
+
+            // Batch update stats
+            if local.packets >= flush_interval {
+                local.flush(ctx);
+            }
+        }
+
+        // Final flush
+        local.flush(ctx);
+    }
+
+    /// Connect and send the first request, carrying it in the SYN when TFO is enabled
+    fn open_tcp_connection(
+        addr: SocketAddr,
+        request: &[u8],
+        config: &EngineConfig,
+        ctx: &WorkerContext,
+    ) -> std::io::Result<TcpStream> {
+        use std::io::Write;
+
+        #[cfg(target_os = "linux")]
+        if config.tcp_fastopen {
+            // Any TFO failure falls back to a regular connect below
+            if let Ok((stream, negotiated)) = Self::tcp_fastopen_connect(addr, request, config) {
+                ctx.tfo_connections.fetch_add(1, Ordering::Relaxed);
+                if negotiated {
+                    ctx.tfo_negotiated.fetch_add(1, Ordering::Relaxed);
+                }
+                return Ok(stream);
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = ctx;
+
+        // Options go on before connect so the SYN carries them too
+        let socket = socket2::Socket::new(
+            socket2::Domain::for_address(addr),
+            socket2::Type::STREAM,
+            Some(socket2::Protocol::TCP),
+        )?;
+        set_ip_header_options(&socket, addr, config)?;
+        bind_interface(&socket, addr, config)?;
+        socket.connect_timeout(&addr.into(), Duration::from_millis(500))?;
+        let mut stream: TcpStream = socket.into();
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+        let _ = stream.set_write_timeout(Some(Duration::from_millis(100)));
+        stream.write_all(request)?;
+        Ok(stream)
+    }
+
+    /// Open a connection with `sendto(MSG_FASTOPEN)`
+    ///
+    /// Returns the stream and whether the server acknowledged the SYN data.
+    /// Without a cached cookie the kernel completes a normal handshake first,
+    /// so early connections to a server usually report `false`.
+    #[cfg(target_os = "linux")]
+    fn tcp_fastopen_connect(
+        addr: SocketAddr,
+        request: &[u8],
+        config: &EngineConfig,
+    ) -> std::io::Result<(TcpStream, bool)> {
+        use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+        use std::io::Write;
+
+        // Set in tcpi_options once the SYN payload was acked (linux/tcp.h)
+        const TCPI_OPT_SYN_DATA: u8 = 32;
+
+        let socket = Socket::new(
+            Domain::for_address(addr),
+            Type::STREAM,
+            Some(SockProtocol::TCP),
+        )?;
+        socket.set_nodelay(true)?;
+        socket.set_write_timeout(Some(Duration::from_millis(500)))?;
+        set_ip_header_options(&socket, addr, config)?;
+        bind_interface(&socket, addr, config)?;
+
+        let sent = socket.send_to_with_flags(request, &addr.into(), libc::MSG_FASTOPEN)?;
+
+        let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                &mut info as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        let negotiated = rc == 0 && info.tcpi_options & TCPI_OPT_SYN_DATA != 0;
+
+        let mut stream: TcpStream = socket.into();
+        let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+        let _ = stream.set_write_timeout(Some(Duration::from_millis(100)));
+        if sent < request.len() {
+            stream.write_all(&request[sent..])?;
+        }
+        Ok((stream, negotiated))
+    }
+
+    fn icmp_worker(_thread_id: usize, addr: SocketAddr, config: EngineConfig, ctx: &WorkerContext) {
+        // ICMP requires raw sockets (platform-specific)
+        #[cfg(target_os = "linux")]
+        {
+            let socket = if config.dry_run {
+                None
+            } else {
+                let opened = open_icmp_socket(addr)
+                    .and_then(|s| set_ip_header_options(&s, addr, &config).map(|_| s))
+                    .and_then(|s| bind_interface(&s, addr, &config).map(|_| s));
+                match opened {
+                    Ok(socket) => Some(socket),
+                    Err(_) => {
+                        ctx.record_error();
+                        return;
+                    }
+                }
+            };
+
+            let packet =
+                match PacketTemplates::icmp_echo(&addr.ip().to_string(), config.packet_size) {
+                    Ok(p) => p,
+                    Err(_) => {
+                        ctx.record_error();
+                        return;
+                    }
+                };
+            // The kernel prepends the IP header on raw ICMP sockets
+            let ip_header_len = if addr.is_ipv4() { 20 } else { 40 };
+            let message = &packet[ip_header_len..];
+            let dest = socket2::SockAddr::from(addr);
+
+            let mut local = LocalCounters::default();
+            while ctx.is_running() {
+                ctx.service_flush(&mut local);
+                if ctx.idle_if_paused() {
+                    continue;
+                }
+
+                ctx.acquire_tokens(1);
+
+                let sent = match &socket {
+                    Some(socket) => unsafe {
+                        libc::sendto(
+                            socket.as_raw_fd(),
+                            message.as_ptr() as *const libc::c_void,
+                            message.len(),
+                            0,
+                            dest.as_ptr(),
+                            dest.len(),
+                        )
+                    },
+                    None => message.len() as isize,
+                };
+                if sent < 0 {
+                    // EPERM from a firewall rule, EINVAL for a bad destination
+                    local.record_send_error(&std::io::Error::last_os_error());
+                } else {
+                    local.packets += 1;
+                    local.bytes += sent as u64;
+                }
+
+                if local.packets + local.errors >= STATS_FLUSH_INTERVAL {
+                    local.flush(ctx);
+                }
+            }
+            local.flush(ctx);
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            // ICMP not supported on this platform without raw sockets
+            while ctx.is_running() {
+                ctx.record_error();
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    }
+
+    fn raw_worker(
+        _thread_id: usize,
+        _addr: SocketAddr,
+        _config: EngineConfig,
+        ctx: &WorkerContext,
+    ) {
+        // Raw socket implementation (requires elevated privileges)
+        let mut local = LocalCounters::default();
+        while ctx.is_running() {
+            ctx.service_flush(&mut local);
+            ctx.record_error();
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+#[inline]
+fn is_message_too_long(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        e.raw_os_error() == Some(libc::EMSGSIZE)
+    }
+    #[cfg(windows)]
+    {
+        // WSAEMSGSIZE
+        e.raw_os_error() == Some(10040)
+    }
+}
+
+/// Largest UDP payload that fits an IP packet of `mtu` bytes
+fn max_payload_for_mtu(mtu: usize, addr: SocketAddr) -> usize {
+    PacketBuilder::max_payload_for_mtu(mtu, Protocol::UDP, addr.is_ipv6()).clamp(1, MAX_UDP_PAYLOAD)
+}
+
+/// MTU of the interface that routes to `ip`, when the platform can tell
+#[cfg(target_os = "linux")]
+fn interface_mtu(ip: IpAddr) -> Option<usize> {
+    crate::linux_optimizations::interface_mtu(ip)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_mtu(_ip: IpAddr) -> Option<usize> {
+    None
+}
+
+/// Have the kernel build headers with `config.ttl` and `config.traffic_class()`
+///
+/// Uses `IP_TTL`/`IP_TOS` for IPv4 and `IPV6_UNICAST_HOPS`/`IPV6_TCLASS` for
+/// IPv6; unset fields keep the OS defaults.
+fn set_ip_header_options(
+    socket: &socket2::Socket,
+    addr: SocketAddr,
+    config: &EngineConfig,
+) -> std::io::Result<()> {
+    let traffic_class = config.traffic_class();
+    if addr.is_ipv4() {
+        if let Some(ttl) = config.ttl {
+            socket.set_ttl(ttl.into())?;
+        }
+        if let Some(tos) = traffic_class {
+            socket.set_tos(tos.into())?;
+        }
+    } else {
+        if let Some(hops) = config.ttl {
+            socket.set_unicast_hops_v6(hops.into())?;
+        }
+        if let Some(tclass) = traffic_class {
+            #[cfg(unix)]
+            socket.set_tclass_v6(tclass.into())?;
+            #[cfg(not(unix))]
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("IPv6 traffic class {} is not supported here", tclass),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Ask the kernel to set DF and never fragment locally (`IP_PMTUDISC_DO`)
+#[cfg(target_os = "linux")]
+fn set_dont_fragment(socket: &socket2::Socket, addr: SocketAddr) {
+    let (level, name, value) = if addr.is_ipv4() {
+        (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DO,
+        )
+    } else {
+        (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DO,
+        )
+    };
+    unsafe {
+        let _ = libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+    }
+}
+
+/// Path MTU the kernel knows for a connected socket (`IP_MTU` / `IPV6_PATHMTU`)
+#[cfg(target_os = "linux")]
+fn path_mtu(socket: &socket2::Socket, addr: SocketAddr) -> Option<usize> {
+    /// `struct ip6_mtuinfo` from <netinet/in.h>
+    #[repr(C)]
+    struct Ip6MtuInfo {
+        addr: libc::sockaddr_in6,
+        mtu: u32,
+    }
+
+    unsafe {
+        if addr.is_ipv4() {
+            let mut mtu: libc::c_int = 0;
+            let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+            let rc = libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_MTU,
+                &mut mtu as *mut _ as *mut libc::c_void,
+                &mut len,
+            );
+            (rc == 0 && mtu > 0).then_some(mtu as usize)
+        } else {
+            let mut info: Ip6MtuInfo = std::mem::zeroed();
+            let mut len = std::mem::size_of::<Ip6MtuInfo>() as libc::socklen_t;
+            let rc = libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IPV6,
+                libc::IPV6_PATHMTU,
+                &mut info as *mut _ as *mut libc::c_void,
+                &mut len,
+            );
+            (rc == 0 && info.mtu > 0).then_some(info.mtu as usize)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn path_mtu(_socket: &socket2::Socket, _addr: SocketAddr) -> Option<usize> {
+    None
+}
+
+/// Datagrams per UDP GSO send for `payload_len`-byte payloads, `None` if not worth it
+fn gso_batch(requested: u16, payload_len: usize) -> Option<usize> {
+    let segments = (requested as usize)
+        .min(GSO_MAX_SEGMENTS)
+        .min(GSO_MAX_BYTES / payload_len.max(1));
+    (payload_len > 0 && segments > 1).then_some(segments)
+}
+
+/// Each payload repeated `segments` times, ready for a GSO send
+fn coalesce_payloads(payloads: &[Vec<u8>], segments: Option<usize>) -> Vec<Vec<u8>> {
+    segments.map_or_else(Vec::new, |n| payloads.iter().map(|p| p.repeat(n)).collect())
+}
+
+/// ENOBUFS: the socket's option memory is full of unread zerocopy notifications
+fn is_no_buffer_space(e: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        e.raw_os_error() == Some(libc::ENOBUFS)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = e;
+        false
+    }
+}
+
+/// Errors a kernel or driver without UDP GSO returns for a segmented send
+fn is_gso_unsupported(e: &std::io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        matches!(
+            e.raw_os_error(),
+            Some(libc::EINVAL | libc::EIO | libc::ENOPROTOOPT | libc::EOPNOTSUPP)
+        )
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        e.kind() == std::io::ErrorKind::Unsupported
+    }
+}
+
+/// Send `buf` on a connected socket, segmented by the kernel into datagrams
+/// of `segment` bytes (`UDP_SEGMENT` control message)
+#[cfg(target_os = "linux")]
+fn send_gso(socket: &socket2::Socket, buf: &[u8], segment: u16) -> std::io::Result<usize> {
+    // u64 storage keeps the control buffer aligned for `cmsghdr`
+    let mut control = [0u64; 4];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    let sent = unsafe {
+        let space = libc::CMSG_SPACE(std::mem::size_of::<u16>() as u32) as usize;
+        debug_assert!(space <= std::mem::size_of_val(&control));
+
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = space as _;
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_UDP;
+        (*cmsg).cmsg_type = libc::UDP_SEGMENT;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<u16>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut u16, segment);
+
+        libc::sendmsg(socket.as_raw_fd(), &msg, 0)
+    };
+
+    if sent < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(sent as usize)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_gso(_socket: &socket2::Socket, _buf: &[u8], _segment: u16) -> std::io::Result<usize> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// CPUs to pin workers to, worker N taking entry `N % len`
+///
+/// Limited to the CPUs this process may run on. NUMA-aware order alternates
+/// between nodes so consecutive workers land on different nodes.
+fn cpu_pin_order(numa_aware: bool) -> Vec<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::sched::{sched_getaffinity, CpuSet};
+        use nix::unistd::Pid;
+
+        let allowed: Vec<usize> = match sched_getaffinity(Pid::from_raw(0)) {
+            Ok(set) => (0..CpuSet::count())
+                .filter(|&cpu| set.is_set(cpu).unwrap_or(false))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if !allowed.is_empty() {
+            if numa_aware {
+                let nodes: Vec<Vec<usize>> = crate::linux_optimizations::numa_node_cpus()
+                    .into_iter()
+                    .map(|cpus| cpus.into_iter().filter(|c| allowed.contains(c)).collect())
+                    .filter(|cpus: &Vec<usize>| !cpus.is_empty())
+                    .collect();
+                if nodes.len() > 1 {
+                    return interleave_nodes(&nodes);
+                }
+            }
+            return allowed;
+        }
+    }
+
+    let _ = numa_aware;
+    let cores = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    (0..cores).collect()
+}
+
+/// Round-robin across per-node CPU lists: node0[0], node1[0], node0[1], ...
+fn interleave_nodes(nodes: &[Vec<usize>]) -> Vec<usize> {
+    let longest = nodes.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest)
+        .flat_map(|i| nodes.iter().filter_map(move |cpus| cpus.get(i).copied()))
+        .collect()
+}
+
+/// Restrict the calling thread to a single CPU
+fn pin_current_thread(cpu: usize) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::sched::{sched_setaffinity, CpuSet};
+        use nix::unistd::Pid;
+
+        let mut set = CpuSet::new();
+        set.set(cpu).map_err(std::io::Error::from)?;
+        sched_setaffinity(Pid::from_raw(0), &set).map_err(std::io::Error::from)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winapi::um::processthreadsapi::GetCurrentThread;
+        use winapi::um::winbase::SetThreadAffinityMask;
+
+        if cpu >= usize::BITS as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("CPU {} is outside the affinity mask", cpu),
+            ));
+        }
+        let previous = unsafe { SetThreadAffinityMask(GetCurrentThread(), 1usize << cpu) };
+        if previous == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let _ = cpu;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "thread affinity is not supported on this platform",
+        ))
+    }
+}
+
+/// Raw socket for echo requests to `addr`, ICMPv6 for IPv6 targets
+#[cfg(target_os = "linux")]
+fn open_icmp_socket(addr: SocketAddr) -> std::io::Result<socket2::Socket> {
+    use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
+
+    let protocol = if addr.is_ipv4() {
+        SockProtocol::ICMPV4
+    } else {
+        SockProtocol::ICMPV6
+    };
+    Socket::new(Domain::for_address(addr), Type::RAW, Some(protocol))
+}
+
+/// Refuse raw-socket protocols up front when the process cannot open raw sockets
+fn check_protocol_privileges(protocol: Protocol, privileged: bool) -> Result<(), EngineError> {
+    match protocol {
+        Protocol::ICMP | Protocol::RAW if !privileged => Err(EngineError::InsufficientPrivileges(
+            format!("{:?} mode", protocol),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Benchmark hook comparing `sockets_per_thread` settings
+///
+/// Runs the engine once per candidate for `run_for` and returns the stats of
+/// each run. Intended for loopback or discard targets.
+pub fn benchmark_sockets_per_thread(
+    base: &EngineConfig,
+    candidates: &[usize],
+    run_for: Duration,
+) -> Result<Vec<(usize, StatsSnapshot)>, EngineError> {
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for &sockets_per_thread in candidates {
+        let mut engine = FloodEngine::new(EngineConfig {
+            sockets_per_thread,
+            ..base.clone()
+        })?;
+        engine.start()?;
+        thread::sleep(run_for);
+        engine.stop()?;
+        results.push((sockets_per_thread, engine.get_stats()));
+    }
+
+    Ok(results)
+}
+
+impl Drop for FloodEngine {
+    fn drop(&mut self) {
+        self.state.store(false, Ordering::SeqCst);
+        self.join_workers();
+        let _ = self.finish_capture();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    #[test]
+    fn test_engine_config_default() {
+        let config = EngineConfig::default();
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.packet_size, 1472);
+        assert_eq!(config.protocol, Protocol::UDP);
+        assert_eq!(config.port, 80);
+        assert!(config.rate_limit.is_none());
+        assert!(config.duration.is_none());
+        assert!(!config.use_raw_sockets);
+        assert_eq!(config.sockets_per_thread, SOCKETS_PER_THREAD);
+    }
+
+    #[test]
+    fn test_engine_rejects_zero_sockets_per_thread() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 8080,
+            sockets_per_thread: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            FloodEngine::new(config),
+            Err(EngineError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_engine_reports_open_sockets() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            rate_limit: Some(1000),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        assert_eq!(engine.sockets_per_thread(), 1);
+
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(engine.get_open_sockets(), 2);
+        engine.stop().unwrap();
+    }
+
+    /// Config aimed at `sink` on loopback with one socket per worker
+    fn loopback_config(sink: &UdpSocket, threads: usize, overrides: EngineConfig) -> EngineConfig {
+        EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: sink.local_addr().unwrap().port(),
+            threads,
+            sockets_per_thread: 1,
+            ..overrides
+        }
+    }
+
+    /// Bind a loopback sink and build an engine aimed at it
+    fn loopback_engine(threads: usize, overrides: EngineConfig) -> (UdpSocket, FloodEngine) {
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let engine = FloodEngine::new(loopback_config(&sink, threads, overrides)).unwrap();
+        (sink, engine)
+    }
+
+    /// Poll until the engine has counted more than `above` packets
+    fn wait_for_packets(engine: &FloodEngine, above: u64) -> u64 {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let sent = engine.get_stats().packets_sent;
+            if sent > above || Instant::now() > deadline {
+                return sent;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_set_thread_count_while_running() {
+        // Bound receiver so sends are not refused by ICMP port unreachable
+        let (_sink, mut engine) = loopback_engine(
+            2,
+            EngineConfig {
+                packet_size: 64,
+                ..Default::default()
+            },
+        );
+        assert!(engine.set_thread_count(0).is_err());
+
+        engine.start().unwrap();
+        let before_grow = wait_for_packets(&engine, 0);
+        assert!(before_grow > 0);
+        assert_eq!(engine.get_active_threads(), 2);
+
+        engine.set_thread_count(8).unwrap();
+        let after_grow = wait_for_packets(&engine, before_grow);
+        assert!(after_grow > before_grow);
+        assert_eq!(engine.get_active_threads(), 8);
+
+        engine.set_thread_count(2).unwrap();
+        assert_eq!(engine.get_active_threads(), 2);
+        assert!(engine.is_running());
+        let after_shrink = engine.get_stats().packets_sent;
+        assert!(wait_for_packets(&engine, after_shrink) > after_shrink);
+
+        engine.stop().unwrap();
+        assert_eq!(engine.get_active_threads(), 0);
+    }
+
+    #[test]
+    fn test_drop_injector_reproducible() {
+        let pattern = |seed, thread_id| {
+            let mut injector = DropInjector::new(0.25, Some(seed), thread_id);
+            (0..1000)
+                .map(|_| injector.should_drop())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(pattern(42, 0), pattern(42, 0));
+        assert_ne!(pattern(42, 0), pattern(42, 1));
+        assert_ne!(pattern(42, 0), pattern(7, 0));
+        // Neighbouring seeds and threads must not line up
+        assert_ne!(pattern(42, 1), pattern(43, 0));
+
+        let dropped = pattern(42, 0).iter().filter(|d| **d).count();
+        assert!((150..350).contains(&dropped), "dropped {}", dropped);
+    }
+
+    #[test]
+    fn test_engine_rejects_invalid_drop_fraction() {
+        for fraction in [-0.1, 1.5, f64::NAN] {
+            let config = EngineConfig {
+                target: "127.0.0.1".to_string(),
+                drop_fraction: Some(fraction),
+                ..Default::default()
+            };
+            assert!(matches!(
+                FloodEngine::new(config),
+                Err(EngineError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_full_drop_fraction_counts_intended_only() {
+        let (_sink, mut engine) = loopback_engine(
+            1,
+            EngineConfig {
+                packet_size: 64,
+                drop_fraction: Some(1.0),
+                seed: Some(1),
+                ..Default::default()
+            },
+        );
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        engine.stop().unwrap();
+
+        let stats = engine.get_stats();
+        assert_eq!(stats.packets_sent, 0);
+        assert!(stats.packets_dropped > 0);
+        assert_eq!(stats.packets_intended(), stats.packets_dropped);
+    }
+
+    #[test]
+    fn test_ipv6_targets_resolve() {
+        let config = EngineConfig {
+            target: "[::1]".to_string(),
+            port: 9,
+            ..Default::default()
+        };
+        let engine = FloodEngine::new(config).unwrap();
+        assert_eq!(engine.addr, "[::1]:9".parse().unwrap());
+
+        let addr = resolve_target("::1", 9, AddressFamily::V6).unwrap();
+        assert!(addr.is_ipv6());
+
+        // Declared family must match a literal
+        assert!(matches!(
+            resolve_target("127.0.0.1", 9, AddressFamily::V6),
+            Err(EngineError::InvalidTarget(_))
+        ));
+        assert!(resolve_target("localhost", 9, AddressFamily::V4)
+            .unwrap()
+            .is_ipv4());
+
+        // Scoped link-local literals keep their interface
+        match resolve_target("fe80::1%3", 80, AddressFamily::Any).unwrap() {
+            SocketAddr::V6(v6) => assert_eq!(v6.scope_id(), 3),
+            other => panic!("expected IPv6, got {}", other),
+        }
+        #[cfg(target_os = "linux")]
+        match resolve_target("fe80::1%lo", 80, AddressFamily::Any).unwrap() {
+            SocketAddr::V6(v6) => assert!(v6.scope_id() > 0),
+            other => panic!("expected IPv6, got {}", other),
+        }
+        assert!(resolve_target("10.0.0.1%eth0", 80, AddressFamily::Any).is_err());
+        assert_eq!(host_header("::1"), "[::1]");
+    }
+
+    #[test]
+    fn test_udp_flood_over_ipv6_loopback() {
+        // Skip where the sandbox has no IPv6 loopback
+        let Ok(sink) = UdpSocket::bind("[::1]:0") else {
+            return;
+        };
+        let config = EngineConfig {
+            target: "::1".to_string(),
+            port: sink.local_addr().unwrap().port(),
+            threads: 1,
+            sockets_per_thread: 1,
+            packet_size: 64,
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        engine.stop().unwrap();
+
+        assert_eq!(engine.get_open_sockets(), 0);
+        assert!(engine.get_stats().packets_sent > 0);
+    }
+
+    #[test]
+    fn test_resolve_destinations() {
+        let endpoints = |list: &[&str]| list.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        let addrs = resolve_destinations(
+            &endpoints(&["127.0.0.1:9", "[::1]:10", "127.0.0.1:9"]),
+            AddressFamily::Any,
+        )
+        .unwrap();
+        assert_eq!(
+            addrs,
+            ["127.0.0.1:9".parse().unwrap(), "[::1]:10".parse().unwrap()]
+        );
+
+        // A hostname adds its addresses of the requested family
+        let addrs = resolve_destinations(&endpoints(&["localhost:9"]), AddressFamily::V4).unwrap();
+        assert!(!addrs.is_empty() && addrs.iter().all(|a| a.is_ipv4() && a.port() == 9));
+
+        for bad in ["127.0.0.1", "::1:9", "127.0.0.1:http", "[::1]:70000"] {
+            assert!(matches!(
+                resolve_destinations(&endpoints(&[bad]), AddressFamily::Any),
+                Err(EngineError::InvalidTarget(_))
+            ));
+        }
+        assert!(resolve_destinations(&endpoints(&["[::1]:9"]), AddressFamily::V4).is_err());
+
+        let config = EngineConfig {
+            destinations: endpoints(&["127.0.0.1:9"]),
+            protocol: Protocol::TCP,
+            ..Default::default()
+        };
+        assert!(matches!(
+            FloodEngine::new(config),
+            Err(EngineError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_destinations_share_traffic() {
+        let sinks = [
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        let config = EngineConfig {
+            destinations: sinks
+                .iter()
+                .map(|s| s.local_addr().unwrap().to_string())
+                .collect(),
+            threads: 2,
+            sockets_per_thread: 1,
+            packet_size: 64,
+            rate_limit: Some(400),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        // One socket per destination and worker, even with sockets_per_thread 1
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(engine.get_open_sockets(), 4);
+        engine.stop().unwrap();
+
+        let mut buf = [0u8; 64];
+        let received: Vec<u64> = sinks
+            .iter()
+            .map(|sink| {
+                sink.set_nonblocking(true).unwrap();
+                std::iter::from_fn(|| sink.recv(&mut buf).ok()).count() as u64
+            })
+            .collect();
+        let per_destination = engine.destination_stats();
+        assert_eq!(per_destination.len(), 2);
+        for (stats, (sink, received)) in per_destination.iter().zip(sinks.iter().zip(&received)) {
+            assert_eq!(stats.addr, sink.local_addr().unwrap());
+            assert_eq!(stats.packets_sent, *received);
+        }
+        assert_eq!(
+            per_destination.iter().map(|d| d.packets_sent).sum::<u64>(),
+            engine.get_stats().packets_sent
+        );
+
+        let (low, high) = (received[0].min(received[1]), received[0].max(received[1]));
+        assert!(
+            low > 0 && high - low <= high / 4,
+            "uneven split {:?}",
+            received
+        );
+    }
+
+    #[test]
+    fn test_ejected_destination_leaves_rotation() {
+        let sinks = [
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        let config = EngineConfig {
+            destinations: sinks
+                .iter()
+                .map(|s| s.local_addr().unwrap().to_string())
+                .collect(),
+            threads: 2,
+            sockets_per_thread: 2,
+            packet_size: 64,
+            rate_limit: Some(2_000),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Outweigh the window's successful sends with failures
+        let destinations = Arc::clone(engine.destinations.as_ref().unwrap());
+        destinations.health.record(1, 1 << 40, 1 << 40);
+        let healthy: Vec<bool> = engine
+            .destination_stats()
+            .iter()
+            .map(|d| d.healthy)
+            .collect();
+        assert_eq!(healthy, vec![true, false]);
+
+        // Let bursts already under way finish
+        std::thread::sleep(Duration::from_millis(50));
+        let before = engine.destination_stats();
+        std::thread::sleep(Duration::from_millis(200));
+        let after = engine.destination_stats();
+        engine.stop().unwrap();
+
+        assert!(after[0].packets_sent > before[0].packets_sent);
+        assert_eq!(after[1].packets_sent, before[1].packets_sent);
+        assert_eq!(after[1].ejections, 1);
+    }
+
+    /// Five UDP sockets bound to consecutive loopback ports
+    fn bind_port_block() -> (u16, Vec<UdpSocket>) {
+        (9000u16..60000)
+            .step_by(5)
+            .find_map(|base| {
+                let sinks = (base..base + 5)
+                    .map(|port| UdpSocket::bind(("127.0.0.1", port)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()?;
+                Some((base, sinks))
+            })
+            .expect("no free block of five ports")
+    }
+
+    #[test]
+    fn test_port_range_sweeps_every_port() {
+        let (base, sinks) = bind_port_block();
+        for mode in [PortMode::Sequential, PortMode::Random] {
+            let config = EngineConfig {
+                target: "127.0.0.1".to_string(),
+                port_range: Some((base, base + 4)),
+                port_mode: mode,
+                seed: Some(7),
+                threads: 1,
+                sockets_per_thread: 1,
+                packet_size: 64,
+                rate_limit: Some(500),
+                ..Default::default()
+            };
+            let mut engine = FloodEngine::new(config).unwrap();
+            engine.start().unwrap();
+            std::thread::sleep(Duration::from_millis(300));
+            engine.stop().unwrap();
+
+            let mut buf = [0u8; 64];
+            let received: Vec<usize> = sinks
+                .iter()
+                .map(|sink| {
+                    sink.set_nonblocking(true).unwrap();
+                    std::iter::from_fn(|| sink.recv(&mut buf).ok()).count()
+                })
+                .collect();
+            assert!(
+                received.iter().all(|&n| n > 0),
+                "{:?}: {:?}",
+                mode,
+                received
+            );
+            assert_eq!(
+                received.iter().sum::<usize>() as u64,
+                engine.get_stats().packets_sent
+            );
+        }
+    }
+
+    #[test]
+    fn test_port_range_validation() {
+        let config = |port_range, protocol| EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port_range: Some(port_range),
+            protocol,
+            dry_run: true,
+            ..Default::default()
+        };
+        assert!(FloodEngine::new(config((9000, 9000), Protocol::UDP)).is_ok());
+        assert!(FloodEngine::new(config((9000, 9000), Protocol::TCP)).is_ok());
+        for (range, protocol) in [
+            ((9004, 9000), Protocol::UDP),
+            ((0, 10), Protocol::UDP),
+            ((9000, 9004), Protocol::ICMP),
+        ] {
+            assert!(matches!(
+                FloodEngine::new(config(range, protocol)),
+                Err(EngineError::InvalidConfig(_))
+            ));
+        }
+
+        let mut sweep = PortSweep::from_config(&config((65534, 65535), Protocol::UDP), 1).unwrap();
+        let ports: Vec<u16> = (0..4).map(|_| sweep.next_port()).collect();
+        assert_eq!(ports, [65535, 65534, 65535, 65534]);
+    }
+
+    #[test]
+    fn test_config_validation_messages() {
+        type Change = fn(&mut EngineConfig);
+        let valid = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            dry_run: true,
+            ..Default::default()
+        };
+        let with = |change: Change| {
+            let mut config = valid.clone();
+            change(&mut config);
+            config
+        };
+        assert!(valid.validate().is_ok());
+        let cases: [(Change, &str); 5] = [
+            (|c| c.threads = 0, "threads must be at least 1"),
+            (|c| c.packet_size = 0, "packet_size must be at least 1 byte"),
+            (
+                |c| c.packet_size = 65508,
+                "packet_size 65508 exceeds the 65507-byte UDP maximum",
+            ),
+            (|c| c.port = 0, "port must be between 1 and 65535"),
+            (
+                |c| (c.threads, c.rate_limit) = (8, Some(4)),
+                "rate_limit 4 pps is below 1 pps for each of the 8 threads",
+            ),
+        ];
+        for (change, message) in cases {
+            match with(change).validate() {
+                Err(EngineError::InvalidConfig(err)) => assert_eq!(err, message),
+                other => panic!("expected {:?}, got {:?}", message, other),
+            }
+            assert!(FloodEngine::new(with(change)).is_err());
+        }
+
+        // TCP carries no UDP size limit, clamping shrinks it and ICMP has no port
+        let allowed: [Change; 4] = [
+            |c| (c.packet_size, c.protocol) = (65508, Protocol::TCP),
+            |c| (c.packet_size, c.clamp_to_mtu) = (65508, true),
+            |c| (c.port, c.protocol) = (0, Protocol::ICMP),
+            |c| (c.threads, c.rate_limit) = (8, Some(8)),
+        ];
+        for change in allowed {
+            assert!(with(change).validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_duration_auto_stops() {
+        let (_sink, mut engine) = loopback_engine(
+            2,
+            EngineConfig {
+                rate_limit: Some(10_000),
+                duration: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        );
+        let started = Instant::now();
+        engine.start().unwrap();
+        assert!(engine.is_running());
+
+        while engine.is_running() && started.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let elapsed = started.elapsed();
+        assert!(!engine.is_running());
+        assert!(
+            elapsed >= Duration::from_millis(50),
+            "stopped after {:?}",
+            elapsed
+        );
+        assert!(
+            elapsed < Duration::from_millis(500),
+            "stopped after {:?}",
+            elapsed
+        );
+
+        // Stopping afterwards reaps the workers once, then reports not running
+        engine.stop().unwrap();
+        assert_eq!(engine.get_active_threads(), 0);
+        assert_eq!(engine.state(), EngineState::Stopped);
+        assert!(matches!(engine.stop(), Err(EngineError::NotRunning)));
+
+        // An early stop cancels the watchdog without waiting for it
+        engine.config.duration = Some(Duration::from_secs(60));
+        engine.start().unwrap();
+        let stopping = Instant::now();
+        engine.stop().unwrap();
+        assert!(stopping.elapsed() < Duration::from_secs(5));
+        assert!(engine.watchdog.is_none());
+    }
+
+    #[test]
+    fn test_payload_factory_reaches_socket() {
+        let (sink, mut engine) = loopback_engine(
+            1,
+            EngineConfig {
+                rate_limit: Some(1000),
+                payload_factory: Some(PayloadFactory::new(|i| format!("pkt-{}", i).into_bytes())),
+                ..Default::default()
+            },
+        );
+        sink.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        engine.start().unwrap();
+
+        let mut buf = [0u8; 64];
+        let received: Vec<String> = (0..3)
+            .map(|_| {
+                let n = sink.recv(&mut buf).unwrap();
+                String::from_utf8_lossy(&buf[..n]).into_owned()
+            })
+            .collect();
+        engine.stop().unwrap();
+
+        assert_eq!(received, ["pkt-0", "pkt-1", "pkt-2"]);
+    }
+
+    #[test]
+    fn test_pcap_replay_sends_captured_payloads_in_order() {
+        use crate::pcap::{udp_frame, PcapWriter, LINKTYPE_ETHERNET};
+
+        let path =
+            std::env::temp_dir().join(format!("netstress_replay_{}.pcap", std::process::id()));
+        let mut writer =
+            PcapWriter::new(std::fs::File::create(&path).unwrap(), LINKTYPE_ETHERNET).unwrap();
+        let captured: [&[u8]; 3] = [b"alpha", b"bravo", b"charlie"];
+        for (i, payload) in captured.iter().enumerate() {
+            writer
+                .write_packet(Duration::from_millis(i as u64), &udp_frame(payload))
+                .unwrap();
+        }
+        // A non-UDP frame in the capture is skipped
+        writer
+            .write_packet(Duration::from_millis(3), &[0u8; 60])
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let (sink, mut engine) = loopback_engine(
+            1,
+            EngineConfig {
+                rate_limit: Some(1000),
+                source: PacketSource::Pcap(path.clone()),
+                replay_timing: Some(1.0),
+                ..Default::default()
+            },
+        );
+        sink.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        engine.start().unwrap();
+
+        let mut buf = [0u8; 64];
+        let received: Vec<Vec<u8>> = (0..6)
+            .map(|_| {
+                let n = sink.recv(&mut buf).unwrap();
+                buf[..n].to_vec()
+            })
+            .collect();
+        engine.stop().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let expected: Vec<&[u8]> = captured.iter().chain(captured.iter()).copied().collect();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_capture_writes_sampled_packets() {
+        let path =
+            std::env::temp_dir().join(format!("netstress_capture_{}.pcap", std::process::id()));
+        let (sink, mut engine) = loopback_engine(
+            2,
+            EngineConfig {
+                packet_size: 48,
+                rate_limit: Some(2000),
+                ..Default::default()
+            },
+        );
+        engine.enable_capture(&path, 25, 10).unwrap();
+        engine.start().unwrap();
+        thread::sleep(Duration::from_millis(300));
+        engine.stop().unwrap();
+
+        let stats = engine.capture_stats().unwrap();
+        let packets = crate::pcap::read_capture(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(!packets.is_empty());
+        assert_eq!(packets.len() as u64, stats.written);
+        assert!(stats.written + stats.dropped <= 25);
+        for packet in &packets {
+            assert_eq!(packet.linktype, crate::pcap::LINKTYPE_ETHERNET);
+            assert!(packet.timestamp > Duration::from_secs(1_600_000_000));
+            let payload = crate::pcap::udp_payload(packet.linktype, &packet.data).unwrap();
+            assert_eq!(payload.len(), 48);
+            // IPv4 destination and UDP destination port match the target
+            assert_eq!(&packet.data[30..34], &[127, 0, 0, 1]);
+            assert_eq!(
+                u16::from_be_bytes([packet.data[36], packet.data[37]]),
+                sink.local_addr().unwrap().port()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ip_header_options_match_built_headers() {
+        use socket2::{Domain, Socket, Type};
+
+        let config = EngineConfig {
+            ttl: Some(9),
+            dscp: Some(34), // AF41
+            ecn: Some(2),
+            ..Default::default()
+        };
+        let built = PacketBuilder::new()
+            .dst_ip("127.0.0.1")
+            .dst_port(9)
+            .ttl(9)
+            .dscp(34)
+            .ecn(2)
+            .build()
+            .unwrap();
+
+        let v4 = Socket::new(Domain::IPV4, Type::DGRAM, None).unwrap();
+        let addr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        set_ip_header_options(&v4, addr, &config).unwrap();
+        assert_eq!(v4.ttl().unwrap(), built[8] as u32);
+        assert_eq!(v4.tos().unwrap(), built[1] as u32);
+
+        if let Ok(v6) = Socket::new(Domain::IPV6, Type::DGRAM, None) {
+            let addr: SocketAddr = "[::1]:9".parse().unwrap();
+            set_ip_header_options(&v6, addr, &config).unwrap();
+            assert_eq!(v6.unicast_hops_v6().unwrap(), 9);
+            assert_eq!(
+                v6.tclass_v6().unwrap(),
+                config.traffic_class().unwrap() as u32
+            );
+        }
+
+        let invalid = EngineConfig {
+            dscp: Some(64),
+            ..Default::default()
+        };
+        assert!(matches!(
+            FloodEngine::new(invalid),
+            Err(EngineError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_send_errors_are_classified() {
+        let classify =
+            |errno| SendErrorKind::from_io_error(&std::io::Error::from_raw_os_error(errno));
+        assert_eq!(classify(libc::ENOBUFS), SendErrorKind::NoBuffers);
+        assert_eq!(classify(libc::EAGAIN), SendErrorKind::NoBuffers);
+        assert_eq!(
+            classify(libc::ECONNREFUSED),
+            SendErrorKind::ConnectionRefused
+        );
+        assert_eq!(classify(libc::EHOSTUNREACH), SendErrorKind::Unreachable);
+        assert_eq!(classify(libc::EMSGSIZE), SendErrorKind::MessageTooLong);
+        assert_eq!(classify(libc::EPERM), SendErrorKind::PermissionDenied);
+        assert_eq!(classify(libc::EIO), SendErrorKind::Other);
+
+        // ENOBUFS depends on qdisc pressure and cannot be forced on loopback,
+        // so check the counting path with a synthetic error
+        let mut local = LocalCounters::default();
+        local.record_send_error(&std::io::Error::from_raw_os_error(libc::ENOBUFS));
+        assert_eq!(local.error_kinds[SendErrorKind::NoBuffers as usize], 1);
+        assert_eq!(local.errors, 1);
+
+        // Sends to a closed port come back as ECONNREFUSED
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = closed.local_addr().unwrap().port();
+        drop(closed);
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 1,
+            sockets_per_thread: 1,
+            rate_limit: Some(10_000),
+            ..Default::default()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        engine.stop().unwrap();
+
+        let breakdown = engine.error_breakdown();
+        assert_eq!(breakdown.len(), SendErrorKind::ALL.len());
+        assert!(breakdown[&SendErrorKind::ConnectionRefused] > 0);
+        assert_eq!(breakdown.values().sum::<u64>(), engine.get_stats().errors);
+    }
+
+    #[test]
+    fn test_emergency_stop_halts_workers() {
+        let safety = Arc::new(SafetyController::permissive());
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.set_safety(Arc::clone(&safety));
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let triggered = Instant::now();
+        safety.emergency_stop.trigger("test");
+        while engine.get_active_threads() > 0 && triggered.elapsed() < Duration::from_secs(1) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(engine.get_active_threads(), 0);
+        assert!(triggered.elapsed() < Duration::from_millis(100));
+
+        let halted_at = engine.get_stats().packets_sent;
+        assert!(halted_at > 0);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(engine.get_stats().packets_sent, halted_at);
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn test_safety_rate_limit_holds_engine_rate() {
+        let safety = Arc::new(SafetyController::new(2_000));
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.set_safety(Arc::clone(&safety));
+        let started = Instant::now();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_secs(1));
+        engine.stop().unwrap();
+
+        // Unthrottled, a dry run sends millions of packets per second
+        let pps = engine.get_stats().packets_sent as f64 / started.elapsed().as_secs_f64();
+        assert!((1_600.0..=2_400.0).contains(&pps), "{} pps", pps);
+    }
+
+    #[test]
+    fn test_send_gap_histogram_tracks_rate() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            rate_limit: Some(2_000),
+            dry_run: true,
+            record_send_timing: true,
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config.clone()).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(800));
+        engine.stop().unwrap();
+
+        // 1000 pps per worker, one packet per burst: gaps of about 1ms
+        let gaps = engine.send_gap_histogram().unwrap();
+        assert!(!gaps.is_empty());
+        let median = gaps.percentile(0.5).unwrap() as f64;
+        let expected = 1e9 / 1_000.0;
+        assert!(
+            (median - expected).abs() <= expected * 0.2,
+            "median gap {} ns, expected ~{} ns",
+            median,
+            expected
+        );
+
+        assert!(FloodEngine::new(EngineConfig {
+            record_send_timing: false,
+            ..config.clone()
+        })
+        .unwrap()
+        .send_gap_histogram()
+        .is_none());
+        assert!(FloodEngine::new(EngineConfig {
+            protocol: Protocol::TCP,
+            ..config
+        })
+        .is_err());
+    }
+
+    /// Numbers embedded by loopback-received packets, keyed by worker in
+    /// `sequence_per_worker` mode and by 0 otherwise
+    fn received_sequences(config: EngineConfig, sink: &UdpSocket) -> HashMap<u32, Vec<u64>> {
+        let offset = config.sequence_offset;
+        let per_worker = config.sequence_per_worker;
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        engine.stop().unwrap();
+
+        let mut sequences: HashMap<u32, Vec<u64>> = HashMap::new();
+        let mut buf = [0u8; 2048];
+        while let Ok(n) = sink.recv(&mut buf) {
+            let mut at = offset;
+            let worker = if per_worker {
+                at += SEQUENCE_PREFIX_LEN;
+                u32::from_be_bytes(buf[offset..at].try_into().unwrap())
+            } else {
+                0
+            };
+            assert!(n >= at + SEQUENCE_LEN);
+            let seq = u64::from_be_bytes(buf[at..at + SEQUENCE_LEN].try_into().unwrap());
+            sequences.entry(worker).or_default().push(seq);
+        }
+        sequences
+    }
+
+    #[test]
+    fn test_embedded_sequence_is_monotonic_per_worker() {
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sink.set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let config = loopback_config(
+            &sink,
+            3,
+            EngineConfig {
+                packet_size: 64,
+                rate_limit: Some(3_000),
+                embed_sequence: true,
+                sequence_offset: 10,
+                sequence_per_worker: true,
+                ..Default::default()
+            },
+        );
+
+        let sequences = received_sequences(config.clone(), &sink);
+        assert_eq!(sequences.len(), 3);
+        for (worker, seqs) in &sequences {
+            assert!(worker < &3);
+            assert_eq!(seqs[0], 0);
+            assert!(
+                seqs.windows(2).all(|w| w[0] < w[1]),
+                "worker {} reordered",
+                worker
+            );
+        }
+
+        // One shared counter: every number is used once
+        let mut shared = received_sequences(
+            EngineConfig {
+                sequence_per_worker: false,
+                ..config.clone()
+            },
+            &sink,
+        )
+        .remove(&0)
+        .unwrap();
+        shared.sort_unstable();
+        assert!(shared.windows(2).all(|w| w[0] < w[1]));
+
+        for (packet_size, sequence_offset) in [(11, 0), (64, 53)] {
+            let too_small = EngineConfig {
+                packet_size,
+                sequence_offset,
+                ..config.clone()
+            };
+            assert!(matches!(
+                FloodEngine::new(too_small),
+                Err(EngineError::InvalidConfig(e)) if e.contains("cannot hold")
+            ));
+        }
+    }
+
+    #[test]
+    fn test_collect_udp_responses() {
+        // DNS-style responder echoing each query, answering every fifth twice
+        let responder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = responder.local_addr().unwrap().port();
+        responder
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let mut queries = 0u64;
+            while let Ok((n, from)) = responder.recv_from(&mut buf) {
+                queries += 1;
+                let replies = if queries.is_multiple_of(5) { 2 } else { 1 };
+                for _ in 0..replies {
+                    let _ = responder.send_to(&buf[..n.min(12)], from);
+                }
+            }
+        });
+
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 1,
+            sockets_per_thread: 2,
+            packet_size: 32,
+            rate_limit: Some(500),
+            collect_responses: true,
+            response_timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config.clone()).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(400));
+        engine.stop().unwrap();
+
+        let stats = engine.response_stats().unwrap();
+        assert!(stats.responses > 50, "{:?}", stats);
+        assert!(
+            stats.unmatched > 0 && stats.unmatched < stats.responses,
+            "{:?}",
+            stats
+        );
+        assert!(stats.p50_latency.unwrap() < Duration::from_millis(100));
+
+        // Nobody answers: every request expires
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut engine = FloodEngine::new(EngineConfig {
+            port: silent.local_addr().unwrap().port(),
+            response_timeout: Duration::from_millis(50),
+            ..config.clone()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        engine.stop().unwrap();
+        let stats = engine.response_stats().unwrap();
+        assert_eq!(stats.responses, 0);
+        assert!(stats.timed_out > 0);
+        assert_eq!(stats.p50_latency, None);
+
+        assert!(FloodEngine::new(EngineConfig {
+            packet_size: 1,
+            ..config.clone()
+        })
+        .is_err());
+        assert!(FloodEngine::new(EngineConfig {
+            dry_run: true,
+            ..config.clone()
+        })
+        .is_err());
+        assert!(FloodEngine::new(EngineConfig {
+            protocol: Protocol::TCP,
+            ..config
+        })
+        .is_err());
+    }
 
-        // Performance tracking variables
-        let mut batch_count = 0u64;
-        let mut last_rate_check = Instant::now();
-        let mut local_packets = 0u64;
-        let mut local_bytes = 0u64;
-        let mut local_errors = 0u64;
-        let mut payload_idx = 0usize;
-        let mut socket_idx = 0usize;
+    #[test]
+    fn test_collect_http_responses() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
 
-        // Adaptive rate limiting state
-        let mut consecutive_sleeps = 0u32;
-        let mut sleep_duration_us = ADAPTIVE_SLEEP_MIN_US;
-
-        while state.load(Ordering::Relaxed) {
-            // Precision rate limiting with adaptive sleep
-            let limit = rate_limit.load(Ordering::Relaxed);
-            if limit > 0 {
-                let elapsed = last_rate_check.elapsed();
-                if elapsed >= Duration::from_secs(1) {
-                    batch_count = 0;
-                    last_rate_check = Instant::now();
-                    consecutive_sleeps = 0;
-                    sleep_duration_us = ADAPTIVE_SLEEP_MIN_US;
-                } else {
-                    let elapsed_us = elapsed.as_micros().max(1) as u64;
-                    let current_rate = batch_count * 1_000_000 / elapsed_us;
-                    let thread_limit = limit / config.threads as u64;
-
-                    if current_rate > thread_limit {
-                        // Adaptive sleep with exponential backoff
-                        consecutive_sleeps += 1;
-                        if consecutive_sleeps > 10 {
-                            sleep_duration_us =
-                                (sleep_duration_us * 3 / 2).min(ADAPTIVE_SLEEP_MAX_US);
+        // Answers every request head it reads with a small fixed response
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                std::thread::spawn(move || {
+                    let mut buf = [0u8; 4096];
+                    let mut pending = Vec::new();
+                    while let Ok(n) = stream.read(&mut buf) {
+                        if n == 0 {
+                            return;
+                        }
+                        pending.extend_from_slice(&buf[..n]);
+                        while let Some(end) = pending.windows(4).position(|w| w == b"\r\n\r\n") {
+                            pending.drain(..end + 4);
+                            let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                            if stream.write_all(reply).is_err() {
+                                return;
+                            }
                         }
-                        thread::sleep(Duration::from_micros(sleep_duration_us));
-                        continue;
-                    } else {
-                        // Reduce sleep duration when under limit
-                        consecutive_sleeps = 0;
-                        sleep_duration_us = (sleep_duration_us * 2 / 3).max(ADAPTIVE_SLEEP_MIN_US);
                     }
-                }
+                });
             }
+        });
 
-            // Outer batch loop for reduced state checks
-            for _ in 0..OUTER_BATCH_SIZE {
-                if !state.load(Ordering::Relaxed) {
-                    break;
-                }
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 1,
+            protocol: Protocol::HTTP,
+            rate_limit: Some(200),
+            collect_responses: true,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        engine.stop().unwrap();
 
-                let socket = &sockets[socket_idx];
-                let payload = &payloads[payload_idx];
+        let stats = engine.response_stats().unwrap();
+        assert!(stats.responses > 20, "{:?}", stats);
+        assert_eq!(stats.unmatched, 0);
+        assert!(stats.p99_latency.is_some());
+    }
 
-                // Inner tight loop - maximum throughput with unrolled sends
-                let mut i = 0u64;
-                while i < INNER_BATCH_SIZE {
-                    // Unroll 4 sends for better instruction pipelining
-                    match socket.send(payload) {
-                        Ok(n) => {
-                            local_packets += 1;
-                            local_bytes += n as u64;
-                        }
-                        Err(_) => local_errors += 1,
-                    }
+    #[test]
+    fn test_governor_throttles_on_cpu() {
+        use crate::governor::{ResourceSampler, ResourceUsage};
 
-                    if i + 1 < INNER_BATCH_SIZE {
-                        match socket.send(payload) {
-                            Ok(n) => {
-                                local_packets += 1;
-                                local_bytes += n as u64;
-                            }
-                            Err(_) => local_errors += 1,
-                        }
-                    }
+        /// CPU reading the test sets by hand
+        struct FakeCpu(AtomicU64);
 
-                    if i + 2 < INNER_BATCH_SIZE {
-                        match socket.send(payload) {
-                            Ok(n) => {
-                                local_packets += 1;
-                                local_bytes += n as u64;
-                            }
-                            Err(_) => local_errors += 1,
+        impl ResourceSampler for FakeCpu {
+            fn sample(&self) -> Option<ResourceUsage> {
+                Some(ResourceUsage {
+                    cpu_percent: self.0.load(Ordering::SeqCst) as f64,
+                    rss_bytes: 0,
+                })
+            }
+        }
+
+        let cpu = Arc::new(FakeCpu(AtomicU64::new(100)));
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            rate_limit: Some(20_000),
+            dry_run: true,
+            max_cpu_percent: Some(80.0),
+            ..Default::default()
+        })
+        .unwrap();
+        engine.set_resource_sampler(cpu.clone());
+        engine.start().unwrap();
+        let wait_for = |engine: &FloodEngine, done: &dyn Fn(Option<u64>) -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(6);
+            while !done(engine.governed_rate()) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            engine.governed_rate()
+        };
+
+        let cap = wait_for(&engine, &|cap| cap.is_some()).expect("never throttled");
+        assert!(cap < 20_000, "cap {}", cap);
+        let bucket_rate = engine.buckets.lock()[0].rate();
+        assert!(bucket_rate < 10_000, "bucket still at {}", bucket_rate);
+        assert_eq!(engine.get_rate_limit(), 20_000);
+
+        cpu.0.store(10, Ordering::SeqCst);
+        assert_eq!(wait_for(&engine, &|cap| cap.is_none()), None);
+        assert_eq!(engine.buckets.lock()[0].rate(), 10_000);
+        engine.stop().unwrap();
+
+        assert!(FloodEngine::new(EngineConfig {
+            max_cpu_percent: Some(0.0),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_final_report_after_stop() {
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            rate_limit: Some(2_000),
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        let running: serde_json::Value = serde_json::from_str(&engine.final_report()).unwrap();
+        assert_eq!(running["complete"], false);
+        assert!(running["ended_at"].is_null());
+
+        engine.stop().unwrap();
+        let report: serde_json::Value = serde_json::from_str(&engine.final_report()).unwrap();
+        assert_eq!(report["complete"], true);
+        assert_eq!(report["backend"], "dry_run");
+        assert_eq!(report["config"]["threads"], 2);
+        assert!(report["ended_at"].as_f64().unwrap() >= report["started_at"].as_f64().unwrap());
+        let packets = report["packets_sent"].as_u64().unwrap();
+        assert!(packets > 0);
+        assert!(report["average_pps"].as_f64().unwrap() > 0.0);
+        for key in ["bytes_sent", "errors", "peak_pps", "duration_secs"] {
+            assert!(report[key].is_number(), "missing {}", key);
+        }
+        let threads = report["threads"].as_array().unwrap();
+        assert_eq!(threads.len(), 2);
+        let per_thread: u64 = threads
+            .iter()
+            .map(|t| t["packets_sent"].as_u64().unwrap())
+            .sum();
+        assert_eq!(per_thread, packets);
+    }
+
+    #[test]
+    fn test_dry_run_opens_no_sockets_and_honors_rate() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 2,
+            rate_limit: Some(2_000_000),
+            dry_run: true,
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(2500));
+        assert_eq!(engine.get_open_sockets(), 0);
+        engine.stop().unwrap();
+
+        let stats = engine.get_stats();
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.bytes_sent, stats.packets_sent * 1472);
+        // The limiter checks once per outer batch, so allow a batch of slack per worker
+        let pps = stats.packets_sent as f64 / stats.duration.as_secs_f64();
+        assert!(
+            (1_600_000.0..=2_500_000.0).contains(&pps),
+            "dry run ran at {} PPS",
+            pps
+        );
+        // Two full seconds were sampled
+        assert!(stats.p50_pps > 0);
+        assert!(stats.p50_pps <= stats.p95_pps && stats.p95_pps <= stats.p99_pps);
+        assert!(stats.p99_pps <= stats.peak_pps);
+        assert_eq!(engine.get_peak_pps(), stats.peak_pps);
+        // Every packet landed in the size and latency histograms
+        let sizes = engine.collector().packet_size().snapshot();
+        assert_eq!(sizes.count, stats.packets_sent);
+        assert_eq!(sizes.sum as u64, stats.bytes_sent);
+        assert_eq!(
+            engine.collector().send_latency().snapshot().count,
+            stats.packets_sent
+        );
+
+        // Raw-socket protocols need no privilege when nothing is sent
+        for protocol in [Protocol::ICMP, Protocol::TCP] {
+            let mut engine = FloodEngine::new(EngineConfig {
+                target: "127.0.0.1".to_string(),
+                protocol,
+                threads: 1,
+                rate_limit: Some(1000),
+                dry_run: true,
+                ..Default::default()
+            })
+            .unwrap();
+            engine.start().unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            engine.stop().unwrap();
+            let stats = engine.get_stats();
+            assert!(stats.packets_sent > 0, "{:?}", protocol);
+            assert_eq!(stats.errors, 0, "{:?}", protocol);
+            // Too short for a rate sample, so every figure is the average
+            assert_eq!(
+                [stats.peak_pps, stats.p50_pps, stats.p95_pps, stats.p99_pps],
+                [stats.pps; 4]
+            );
+        }
+    }
+
+    #[test]
+    fn test_stealing_accounts_for_every_task() {
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            protocol: Protocol::TCP,
+            threads: 3,
+            rate_limit: Some(30_000),
+            drop_fraction: Some(0.1),
+            seed: Some(7),
+            dry_run: true,
+            work_distribution: WorkDistribution::Stealing,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        engine.set_thread_count(4).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        // Retired workers take their deques along, however often the pool resizes
+        for threads in [2, 4, 1, 3] {
+            engine.set_thread_count(threads).unwrap();
+            assert_eq!(engine.stealers.len(), threads);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        engine.stop().unwrap();
+
+        // Every task was sent, dropped or discarded at shutdown, each exactly once
+        let stats = engine.get_stats();
+        assert!(stats.packets_sent > 0);
+        assert_eq!(
+            stats.packets_sent
+                + engine.packets_dropped.load(Ordering::Relaxed)
+                + stats.errors
+                + engine.get_tasks_drained(),
+            engine.get_tasks_produced()
+        );
+    }
+
+    #[test]
+    fn test_prometheus_counter_matches_engine_stats() {
+        use crate::atomic_stats::CollectorRegistry;
+
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            rate_limit: Some(20_000),
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let registry = CollectorRegistry::new();
+        registry.register("127.0.0.1:9", engine.collector());
+
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        engine.stop().unwrap();
+
+        let sent = engine.get_stats().packets_sent;
+        assert!(sent > 0);
+        assert!(registry.prometheus_metrics().contains(&format!(
+            "netstress_packets_sent{{target=\"127.0.0.1:9\"}} {}\n",
+            sent
+        )));
+    }
+
+    #[test]
+    fn test_token_bucket_holds_target_rate() {
+        let (_sink, mut engine) = loopback_engine(
+            2,
+            EngineConfig {
+                packet_size: 64,
+                rate_limit: Some(10_000),
+                ..Default::default()
+            },
+        );
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_secs(2));
+        engine.stop().unwrap();
+
+        let stats = engine.get_stats();
+        let pps = stats.packets_sent as f64 / stats.duration.as_secs_f64();
+        assert!((9_500.0..=10_500.0).contains(&pps), "achieved {} PPS", pps);
+
+        // set_rate reaches every worker's bucket
+        engine.start().unwrap();
+        engine.set_rate(4_000);
+        for worker in &engine.threads {
+            assert_eq!(worker.bucket.rate(), 2_000);
+            assert_eq!(worker.bucket.burst(), 20);
+        }
+        engine.set_thread_count(4).unwrap();
+        assert!(engine.threads.iter().all(|w| w.bucket.rate() == 1_000));
+        engine.set_rate(0);
+        assert!(engine.threads.iter().all(|w| !w.bucket.is_enabled()));
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn test_stop_passes_through_stopping() {
+        let (_sink, mut engine) = loopback_engine(4, EngineConfig::default());
+        assert_eq!(engine.state(), EngineState::Idle);
+        engine.start().unwrap();
+        assert_eq!(engine.state(), EngineState::Running);
+
+        // Stand in for one worker's thread so stop() cannot finish joining
+        // until the reader has seen Stopping
+        let (seen_stopping, release) = std::sync::mpsc::channel::<()>();
+        let held = std::thread::spawn(move || {
+            let _ = release.recv();
+        });
+        let worker = engine.threads.last_mut().unwrap();
+        let original = std::mem::replace(&mut worker.handle, held);
+
+        let (ready, watching) = std::sync::mpsc::channel::<()>();
+        let handle = engine.state_handle();
+        let reader = std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                let state = handle.get();
+                if seen.last() != Some(&state) {
+                    seen.push(state);
+                    match state {
+                        EngineState::Running => {
+                            let _ = ready.send(());
+                        }
+                        EngineState::Stopping => {
+                            let _ = seen_stopping.send(());
                         }
+                        _ => {}
                     }
+                }
+                if state == EngineState::Stopped {
+                    break;
+                }
+            }
+            seen
+        });
 
-                    if i + 3 < INNER_BATCH_SIZE {
-                        match socket.send(payload) {
-                            Ok(n) => {
-                                local_packets += 1;
-                                local_bytes += n as u64;
+        watching.recv().unwrap();
+        engine.stop().unwrap();
+        assert_eq!(engine.state(), EngineState::Stopped);
+        assert!(!engine.is_running());
+        original.join().unwrap();
+
+        let seen = reader.join().unwrap();
+        assert_eq!(
+            seen,
+            vec![
+                EngineState::Running,
+                EngineState::Stopping,
+                EngineState::Stopped
+            ]
+        );
+    }
+
+    #[test]
+    fn test_per_thread_stats_cover_every_worker() {
+        let (_sink, mut engine) = loopback_engine(
+            4,
+            EngineConfig {
+                packet_size: 64,
+                ..Default::default()
+            },
+        );
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        engine.stop().unwrap();
+
+        let slots = engine.per_thread_stats();
+        assert_eq!(
+            slots.iter().map(|s| s.thread_id).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        for slot in &slots {
+            assert!(
+                slot.snapshot().packets_sent > 0,
+                "thread {} idle",
+                slot.thread_id
+            );
+        }
+
+        let total: u64 = slots.iter().map(|s| s.snapshot().packets_sent).sum();
+        assert_eq!(total, engine.get_stats().packets_sent);
+    }
+
+    #[test]
+    fn test_flush_stats_makes_counts_exact() {
+        use std::io::Read;
+        use std::net::TcpListener;
+
+        // TCP receiver so every counted byte is also received
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(AtomicU64::new(0));
+        {
+            let received = Arc::clone(&received);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    let received = Arc::clone(&received);
+                    std::thread::spawn(move || {
+                        let mut buf = [0u8; 4096];
+                        while let Ok(n) = stream.read(&mut buf) {
+                            if n == 0 {
+                                return;
                             }
-                            Err(_) => local_errors += 1,
+                            received.fetch_add(n as u64, Ordering::Relaxed);
                         }
-                    }
+                    });
+                }
+            });
+        }
 
-                    i += 4;
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 2,
+            packet_size: 100,
+            protocol: Protocol::TCP,
+            rate_limit: Some(2_000),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        // Workers acknowledge at the top of their loop, right before honouring pause
+        engine.pause().unwrap();
+        assert!(engine.flush_stats());
+        let stats = engine.get_stats();
+        assert!(stats.packets_sent > 0);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while received.load(Ordering::Relaxed) < stats.bytes_sent && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received.load(Ordering::Relaxed), stats.bytes_sent);
+        assert_eq!(stats.bytes_sent, stats.packets_sent * 100);
+
+        engine.stop().unwrap();
+        assert!(engine.flush_stats());
+    }
+
+    #[test]
+    fn test_conn_rate_limit_counts_new_connections_only() {
+        use std::io::Read;
+        use std::net::TcpListener;
+
+        /// Accept loop counting connections; `hold` keeps them open and drains them
+        fn serve(hold: bool) -> (u16, Arc<AtomicU64>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let accepted = Arc::new(AtomicU64::new(0));
+            let counter = Arc::clone(&accepted);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    if hold {
+                        std::thread::spawn(move || {
+                            let mut buf = [0u8; 4096];
+                            while matches!(stream.read(&mut buf), Ok(n) if n > 0) {}
+                        });
+                    }
                 }
+            });
+            (port, accepted)
+        }
+        let run = |port: u16, cps: u64, secs: f64| {
+            let config = EngineConfig {
+                target: "127.0.0.1".to_string(),
+                port,
+                threads: 2,
+                packet_size: 64,
+                protocol: Protocol::TCP,
+                rate_limit: Some(5_000),
+                conn_rate_limit: Some(cps),
+                ..Default::default()
+            };
+            let mut engine = FloodEngine::new(config).unwrap();
+            engine.start().unwrap();
+            std::thread::sleep(Duration::from_secs_f64(secs));
+            engine.stop().unwrap();
+            engine.get_stats()
+        };
+
+        // The server drops every connection, so each send needs a fresh one
+        let (port, accepted) = serve(false);
+        run(port, 40, 1.0);
+        let cps = accepted.load(Ordering::Relaxed);
+        assert!(
+            (20..=60).contains(&cps),
+            "{} connections in 1s at 40/s",
+            cps
+        );
+
+        // Held connections are reused without spending connection tokens
+        let (port, accepted) = serve(true);
+        let stats = run(port, 5, 0.6);
+        assert!(accepted.load(Ordering::Relaxed) <= 6);
+        assert!(
+            stats.packets_sent > 200,
+            "{} sends on pooled connections",
+            stats.packets_sent
+        );
+    }
+
+    #[test]
+    fn test_ramp_curves() {
+        let mut ramp = RampConfig {
+            start_pps: 1_000,
+            end_pps: 16_000,
+            duration: Duration::from_secs(4),
+            curve: RampCurve::Linear,
+        };
+        let at = |ramp: &RampConfig, secs: f64| ramp.rate_at(Duration::from_secs_f64(secs));
+        assert_eq!(at(&ramp, 0.0), 1_000);
+        assert_eq!(at(&ramp, 2.0), 8_500);
+        assert_eq!(at(&ramp, 4.0), 16_000);
+        // Held at the end rate once the ramp is over
+        assert_eq!(at(&ramp, 60.0), 16_000);
+
+        ramp.curve = RampCurve::Exponential;
+        assert_eq!(at(&ramp, 1.0), 2_000);
+        assert_eq!(at(&ramp, 2.0), 4_000);
+        assert_eq!(at(&ramp, 60.0), 16_000);
+
+        // Ramping down works the same way
+        ramp.start_pps = 16_000;
+        ramp.end_pps = 1_000;
+        assert_eq!(at(&ramp, 1.0), 8_000);
+
+        ramp.end_pps = 0;
+        assert!(ramp.validate().is_err());
+    }
+
+    #[test]
+    fn test_ramp_tracks_schedule_then_holds() {
+        let ramp = RampConfig {
+            start_pps: 1_000,
+            end_pps: 9_000,
+            duration: Duration::from_millis(800),
+            curve: RampCurve::Linear,
+        };
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            dry_run: true,
+            ramp: Some(ramp.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+        engine.start().unwrap();
+        let started = Instant::now();
+        let effective =
+            |engine: &FloodEngine| -> u64 { engine.threads.iter().map(|w| w.bucket.rate()).sum() };
+
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(200));
+            let expected = ramp.rate_at(started.elapsed());
+            let rate = effective(&engine);
+            // One update step behind at most, plus scheduling jitter
+            assert!(
+                rate.abs_diff(expected) <= 1_500,
+                "rate {} vs scheduled {}",
+                rate,
+                expected
+            );
+        }
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(effective(&engine), 9_000);
+        engine.stop().unwrap();
+        assert!(engine.get_stats().packets_sent > 0);
+    }
+
+    #[test]
+    fn test_rate_schedule_validation_and_interpolation() {
+        let ms = Duration::from_millis;
+        let config = |points: Vec<(Duration, u64)>| EngineConfig {
+            target: "127.0.0.1".to_string(),
+            rate_schedule: points,
+            ..Default::default()
+        };
+        assert!(FloodEngine::new(config(vec![(ms(0), 10), (ms(100), 20)])).is_ok());
+        assert!(FloodEngine::new(config(vec![(ms(100), 10), (ms(100), 20)])).is_err());
+        assert!(FloodEngine::new(config(vec![(ms(200), 10), (ms(100), 20)])).is_err());
+        assert!(FloodEngine::new(config(vec![(ms(0), 0)])).is_err());
+
+        let plan = RatePlan::Points {
+            points: vec![(ms(100), 1_000), (ms(300), 3_000)],
+            interpolate: true,
+        };
+        assert_eq!(plan.rate_at(ms(50)), None);
+        assert_eq!(plan.rate_at(ms(100)), Some(1_000));
+        assert_eq!(plan.rate_at(ms(200)), Some(2_000));
+        assert_eq!(plan.rate_at(ms(900)), Some(3_000));
+        assert_eq!(plan.next_point(ms(150)), Some(ms(300)));
+        assert_eq!(plan.next_point(ms(300)), None);
+    }
+
+    #[test]
+    fn test_rate_schedule_changes_rate_at_offsets() {
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            sockets_per_thread: 1,
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let points = vec![
+            (Duration::ZERO, 1_000),
+            (Duration::from_millis(300), 4_000),
+            (Duration::from_millis(600), 2_000),
+        ];
+        engine.set_rate_schedule(points, false).unwrap();
+        engine.start().unwrap();
+        let effective =
+            |engine: &FloodEngine| -> u64 { engine.threads.iter().map(|w| w.bucket.rate()).sum() };
+
+        // Sampled midway between the points
+        let mut rates = Vec::new();
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(150));
+            rates.push(effective(&engine));
+            std::thread::sleep(Duration::from_millis(150));
+        }
+        engine.stop().unwrap();
+        assert_eq!(rates, [1_000, 4_000, 2_000]);
+    }
+
+    #[test]
+    fn test_calibrate_rate_stops_below_error_spike() {
+        // Errors start past 5000 pps and grow with the rate
+        let mut probed = Vec::new();
+        let rate = calibrate_rate(10_000, 1000, |rate| {
+            probed.push(rate);
+            let errors = rate.saturating_sub(5000) / 10;
+            Ok((rate - errors, errors))
+        })
+        .unwrap();
+        assert_eq!(rate, 5000);
+        assert_eq!(probed, [1000, 2000, 3000, 4000, 5000, 6000]);
+
+        // A clean target reaches the ceiling, even off the step grid
+        assert_eq!(
+            calibrate_rate(2500, 1000, |rate| Ok((rate, 0))).unwrap(),
+            2500
+        );
+        // One that fails from the start gets the floor
+        assert_eq!(
+            calibrate_rate(10_000, 1000, |rate| Ok((0, rate))).unwrap(),
+            1000
+        );
+        // Probe errors end the search
+        assert!(calibrate_rate(10_000, 1000, |_| Err(EngineError::NotRunning)).is_err());
+    }
+
+    #[test]
+    fn test_calibrate_dry_run_reaches_ceiling() {
+        let mut engine = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 1,
+            rate_limit: Some(3000),
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            engine.calibrate(Duration::from_millis(50), 1000).unwrap(),
+            3000
+        );
+        assert!(!engine.is_running());
+        assert_eq!(engine.get_rate_limit(), 3000);
+        assert!(engine.calibrate(Duration::from_millis(50), 0).is_err());
+
+        let mut unlimited = FloodEngine::new(EngineConfig {
+            target: "127.0.0.1".to_string(),
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            unlimited.calibrate(Duration::from_millis(50), 1000),
+            Err(EngineError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_autoscaler_stops_at_plateau() {
+        // Throughput grows linearly up to four workers and is flat after that
+        let model = |workers: usize| 1000.0 * workers.min(4) as f64;
+        let mut scaler = AutoScaler::new(1, 16);
+        let mut counts = vec![1];
+        while !scaler.settled() {
+            counts.push(scaler.observe(model(*counts.last().unwrap())));
+        }
+        assert_eq!(counts, [1, 2, 3, 4, 5, 4]);
+        assert_eq!(scaler.observe(model(4)), 4);
+
+        // Still rising at the ceiling: stay there
+        let mut scaler = AutoScaler::new(1, 3);
+        let mut workers = 1;
+        while !scaler.settled() {
+            workers = scaler.observe(1000.0 * workers as f64);
+        }
+        assert_eq!(workers, 3);
+
+        // Nothing sent at all never looks like a gain
+        let mut scaler = AutoScaler::new(2, 8);
+        assert_eq!(scaler.observe(0.0), 3);
+        assert_eq!(scaler.observe(0.0), 2);
+        assert!(scaler.settled());
+    }
+
+    #[test]
+    fn test_autoscale_keeps_worker_count_within_bounds() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 1,
+            sockets_per_thread: 1,
+            dry_run: true,
+            autoscale: true,
+            max_threads: Some(3),
+            ..Default::default()
+        };
+        assert!(FloodEngine::new(EngineConfig {
+            max_threads: Some(0),
+            ..config.clone()
+        })
+        .is_err());
 
-                // Rotate socket and payload for better distribution
-                socket_idx = (socket_idx + 1) % sockets.len();
-                payload_idx = (payload_idx + 1) % PAYLOAD_VARIANTS;
-            }
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        assert_eq!(engine.get_active_threads(), 3);
+        assert_eq!(engine.scaled_thread_count(), 1);
+        assert!(engine.set_thread_count(2).is_err());
+        std::thread::sleep(AUTOSCALE_INTERVAL * 3);
+        assert!((1..=3).contains(&engine.scaled_thread_count()));
+        engine.stop().unwrap();
+        assert!(engine.get_stats().packets_sent > 0);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_gso_send_arrives_as_separate_datagrams() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let addr = receiver.local_addr().unwrap();
 
-            batch_count += INNER_BATCH_SIZE * OUTER_BATCH_SIZE;
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )
+        .unwrap();
+        socket.connect(&addr.into()).unwrap();
 
-            // Batch update atomic counters (reduces contention significantly)
-            if local_packets >= STATS_FLUSH_INTERVAL {
-                packets_sent.fetch_add(local_packets, Ordering::Relaxed);
-                bytes_sent.fetch_add(local_bytes, Ordering::Relaxed);
-                if local_errors > 0 {
-                    errors.fetch_add(local_errors, Ordering::Relaxed);
-                    local_errors = 0;
-                }
-                local_packets = 0;
-                local_bytes = 0;
-            }
-        }
+        let segments = gso_batch(8, 100).unwrap();
+        let payloads = coalesce_payloads(&[vec![7u8; 100]], Some(segments));
+        let sent = match send_gso(&socket, &payloads[0], 100) {
+            Ok(n) => n,
+            // Kernel older than 4.18
+            Err(e) if is_gso_unsupported(&e) => return,
+            Err(e) => panic!("GSO send failed: {}", e),
+        };
+        assert_eq!(sent, 800);
 
-        // Final flush
-        if local_packets > 0 {
-            packets_sent.fetch_add(local_packets, Ordering::Relaxed);
-            bytes_sent.fetch_add(local_bytes, Ordering::Relaxed);
-        }
-        if local_errors > 0 {
-            errors.fetch_add(local_errors, Ordering::Relaxed);
+        let mut buf = [0u8; 2048];
+        for _ in 0..segments {
+            assert_eq!(receiver.recv(&mut buf).unwrap(), 100);
         }
     }
 
-    fn tcp_worker(
-        thread_id: usize,
-        addr: SocketAddr,
-        config: EngineConfig,
-        state: Arc<AtomicBool>,
-        packets_sent: Arc<AtomicU64>,
-        bytes_sent: Arc<AtomicU64>,
-        errors: Arc<AtomicU64>,
-        rate_limit: Arc<AtomicU64>,
-    ) {
-        use socket2::{Domain, Protocol as SockProtocol, Socket, Type};
-        use std::io::Write;
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_gso_engine_sends_full_size_datagrams() {
+        let (sink, mut engine) = loopback_engine(
+            1,
+            EngineConfig {
+                packet_size: 200,
+                gso_segments: Some(16),
+                ..Default::default()
+            },
+        );
+        sink.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        engine.start().unwrap();
+        let mut buf = [0u8; 4096];
+        let received = sink.recv(&mut buf).unwrap();
+        engine.stop().unwrap();
 
-        // Generate multiple HTTP request variants for evasion
-        let http_requests: Vec<Vec<u8>> = if config.protocol == Protocol::HTTP {
-            let user_agents = [
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15",
-                "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36",
-                "Mozilla/5.0 (iPhone; CPU iPhone OS 14_0 like Mac OS X)",
-                "curl/7.68.0",
-                "Wget/1.21",
-            ];
+        assert_eq!(received, 200);
+        let stats = engine.get_stats();
+        assert!(stats.packets_sent > 0);
+        assert_eq!(stats.bytes_sent, stats.packets_sent * 200);
+    }
 
-            user_agents.iter().enumerate().map(|(i, ua)| {
-                format!(
-                    "GET /?r={}{} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nAccept-Language: en-US,en;q=0.9\r\nAccept-Encoding: gzip, deflate\r\nConnection: keep-alive\r\nCache-Control: no-cache\r\n\r\n",
-                    thread_id, i, config.target, ua
-                ).into_bytes()
-            }).collect()
-        } else {
-            vec![vec![0xAA; config.packet_size]]
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_zerocopy_completions_are_drained() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )
+        .unwrap();
+        socket
+            .connect(&receiver.local_addr().unwrap().into())
+            .unwrap();
+        let Some(mut tracker) = ZeroCopyTracker::enable(&socket) else {
+            return; // Kernel without SO_ZEROCOPY
         };
 
-        // Connection pool for keep-alive connections
-        const MAX_CONNECTIONS: usize = 10;
-        let mut connection_pool: Vec<Option<TcpStream>> =
-            (0..MAX_CONNECTIONS).map(|_| None).collect();
-        let mut conn_idx = 0usize;
-        let mut request_idx = 0usize;
+        let payload = vec![0xA5u8; ZEROCOPY_MIN_PAYLOAD];
+        let mut completed = (0u64, 0u64);
+        for round in 0..50 {
+            for _ in 0..100 {
+                tracker
+                    .send(&socket, &payload)
+                    .unwrap_or_else(|e| panic!("send failed in round {}: {}", round, e));
+            }
+            let (packets, bytes) = tracker.drain(&socket);
+            completed.0 += packets;
+            completed.1 += bytes;
+        }
+        let (packets, bytes) = tracker.drain_all(&socket, Duration::from_secs(2));
+        completed.0 += packets;
+        completed.1 += bytes;
 
-        let mut batch_count = 0u64;
-        let mut last_rate_check = Instant::now();
-        let mut local_packets = 0u64;
-        let mut local_bytes = 0u64;
-        let flush_interval = 100u64;
+        assert!(tracker.pending.is_empty());
+        assert_eq!(completed, (5000, 5000 * payload.len() as u64));
+    }
 
-        while state.load(Ordering::Relaxed) {
-            // Rate limiting with adaptive sleep
-            let limit = rate_limit.load(Ordering::Relaxed);
-            if limit > 0 {
-                let elapsed = last_rate_check.elapsed();
-                if elapsed < Duration::from_secs(1) {
-                    let current_rate = batch_count * 1000 / elapsed.as_millis().max(1) as u64;
-                    let thread_limit = limit / config.threads as u64;
-                    if current_rate > thread_limit {
-                        thread::sleep(Duration::from_micros(50));
-                        continue;
-                    }
-                } else {
-                    batch_count = 0;
-                    last_rate_check = Instant::now();
-                }
-            }
+    #[test]
+    fn test_gso_batch_respects_limits() {
+        assert_eq!(gso_batch(8, 1000), Some(8));
+        assert_eq!(gso_batch(200, 100), Some(GSO_MAX_SEGMENTS));
+        assert_eq!(gso_batch(64, 1472), Some(GSO_MAX_BYTES / 1472));
+        assert_eq!(gso_batch(1, 100), None);
+        assert_eq!(gso_batch(8, 40_000), None);
+    }
 
-            let request = &http_requests[request_idx % http_requests.len()];
-            request_idx = request_idx.wrapping_add(1);
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_payload_over_udp_maximum_clamps_to_loopback_mtu() {
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = loopback_config(
+            &sink,
+            1,
+            EngineConfig {
+                packet_size: 70_000,
+                dont_fragment: true,
+                ..Default::default()
+            },
+        );
 
-            // Try to use existing connection from pool
-            let mut sent = false;
-            if let Some(ref mut stream) = connection_pool[conn_idx] {
-                match stream.write_all(request) {
-                    Ok(_) => {
-                        local_packets += 1;
-                        local_bytes += request.len() as u64;
-                        sent = true;
-                    }
-                    Err(_) => {
-                        // Connection dead, will create new one
-                        connection_pool[conn_idx] = None;
-                    }
-                }
-            }
+        // No UDP datagram carries this, so it is refused before sending
+        assert!(matches!(
+            FloodEngine::new(config.clone()),
+            Err(EngineError::InvalidConfig(_))
+        ));
 
-            // Create new connection if needed
-            if !sent {
-                match TcpStream::connect_timeout(&addr, Duration::from_millis(500)) {
-                    Ok(mut stream) => {
-                        let _ = stream.set_nodelay(true);
-                        let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
-                        let _ = stream.set_write_timeout(Some(Duration::from_millis(100)));
-
-                        match stream.write_all(request) {
-                            Ok(_) => {
-                                local_packets += 1;
-                                local_bytes += request.len() as u64;
-                                // Store in pool for reuse
-                                connection_pool[conn_idx] = Some(stream);
-                            }
-                            Err(_) => {
-                                errors.fetch_add(1, Ordering::Relaxed);
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        errors.fetch_add(1, Ordering::Relaxed);
-                    }
-                }
-            }
+        // Clamping shrinks the payload to what the path carries
+        let mut engine = FloodEngine::new(EngineConfig {
+            clamp_to_mtu: true,
+            ..config
+        })
+        .unwrap();
+        engine.start().unwrap();
+        wait_for_packets(&engine, 0);
+        engine.stop().unwrap();
 
-            conn_idx = (conn_idx + 1) % MAX_CONNECTIONS;
-            batch_count += 1;
+        let stats = engine.get_stats();
+        let limit = max_payload_for_mtu(stats.path_mtu, "127.0.0.1:9".parse().unwrap());
+        assert!(stats.packets_sent > 0);
+        assert_eq!(stats.bytes_sent, stats.packets_sent * limit as u64);
+        assert!(engine.payload_error().is_none());
+    }
 
-            // Batch update stats
-            if local_packets >= flush_interval {
-                packets_sent.fetch_add(local_packets, Ordering::Relaxed);
-                bytes_sent.fetch_add(local_bytes, Ordering::Relaxed);
-                local_packets = 0;
-                local_bytes = 0;
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_clamp_over_udp_maximum_to_loopback_interface_mtu() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            packet_size: 70_000,
+            clamp_to_interface_mtu: true,
+            ..Default::default()
+        };
+        let mtu = interface_mtu("127.0.0.1".parse().unwrap()).unwrap();
+        let engine = FloodEngine::new(config.clone()).unwrap();
+        assert_eq!(
+            engine.config().packet_size,
+            max_payload_for_mtu(mtu, "127.0.0.1:9".parse().unwrap())
+        );
+
+        // Sizes that already fit are left alone
+        let engine = FloodEngine::new(EngineConfig {
+            packet_size: 512,
+            ..config
+        })
+        .unwrap();
+        assert_eq!(engine.config().packet_size, 512);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_dont_fragment_refuses_payload_over_path_mtu() {
+        use socket2::{Domain, Socket, Type};
+
+        // Loopback's MTU is 65536; capping the socket's own MTU (`IPV6_MTU`)
+        // gives a narrower path without touching the host's routes
+        let sink = UdpSocket::bind("[::1]:0").unwrap();
+        let dest = sink.local_addr().unwrap();
+        let payload = [0u8; 1400];
+        let send = |dont_fragment: bool| {
+            let socket = Socket::new(Domain::IPV6, Type::DGRAM, None).unwrap();
+            let mtu: libc::c_int = 1280;
+            let rc = unsafe {
+                libc::setsockopt(
+                    socket.as_raw_fd(),
+                    libc::IPPROTO_IPV6,
+                    libc::IPV6_MTU,
+                    &mtu as *const _ as *const libc::c_void,
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            };
+            assert_eq!(rc, 0);
+            if dont_fragment {
+                set_dont_fragment(&socket, dest);
             }
-        }
+            socket.connect(&dest.into()).unwrap();
+            socket.send(&payload)
+        };
 
-        // Final flush
-        if local_packets > 0 {
-            packets_sent.fetch_add(local_packets, Ordering::Relaxed);
-            bytes_sent.fetch_add(local_bytes, Ordering::Relaxed);
-        }
+        // Without DF the kernel fragments; with it the send fails locally
+        assert_eq!(send(false).unwrap(), payload.len());
+        let err = send(true).unwrap_err();
+        assert_eq!(
+            SendErrorKind::from_io_error(&err),
+            SendErrorKind::MessageTooLong
+        );
     }
 
-    fn icmp_worker(
-        _thread_id: usize,
-        _addr: SocketAddr,
-        config: EngineConfig,
-        state: Arc<AtomicBool>,
-        packets_sent: Arc<AtomicU64>,
-        bytes_sent: Arc<AtomicU64>,
-        errors: Arc<AtomicU64>,
-        _rate_limit: Arc<AtomicU64>,
-    ) {
-        // ICMP requires raw sockets (platform-specific)
-        #[cfg(target_os = "linux")]
-        {
-            use std::os::unix::io::AsRawFd;
+    #[test]
+    fn test_estimate_memory_scales_with_config() {
+        let base = EngineConfig {
+            threads: 2,
+            sockets_per_thread: 2,
+            ..Default::default()
+        };
+        let estimate = base.estimate_memory();
+        assert!(estimate >= 2 * (THREAD_STACK_SIZE + PAYLOAD_VARIANTS * base.packet_size));
 
-            // Try to create raw socket
-            let socket = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, libc::IPPROTO_ICMP) };
+        let more_threads = EngineConfig {
+            threads: 4,
+            ..base.clone()
+        };
+        assert_eq!(more_threads.estimate_memory(), 2 * estimate);
 
-            if socket < 0 {
-                errors.fetch_add(1, Ordering::Relaxed);
-                return;
-            }
+        let more_sockets = EngineConfig {
+            sockets_per_thread: 4,
+            ..base.clone()
+        };
+        assert!(more_sockets.estimate_memory() > estimate);
+    }
 
-            let packet = match PacketTemplates::icmp_echo(&config.target, config.packet_size) {
-                Ok(p) => p,
-                Err(_) => {
-                    errors.fetch_add(1, Ordering::Relaxed);
-                    return;
-                }
-            };
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_refuses_config_over_available_memory() {
+        assert!(available_memory().is_some());
 
-            while state.load(Ordering::Relaxed) {
-                // Send ICMP packet
-                packets_sent.fetch_add(1, Ordering::Relaxed);
-                bytes_sent.fetch_add(packet.len() as u64, Ordering::Relaxed);
-                thread::sleep(Duration::from_millis(1));
-            }
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            threads: 1_000_000,
+            refuse_over_memory: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            FloodEngine::new(config),
+            Err(EngineError::InvalidConfig(_))
+        ));
+    }
 
-            unsafe {
-                libc::close(socket);
-            }
+    #[test]
+    fn test_size_sampler_matches_weights() {
+        let distribution = SizeDistribution::Buckets(vec![(64, 1), (512, 3), (1400, 6)]);
+        let mut sampler = SizeSampler::new(&distribution, Some(7), 0);
+        let samples = 100_000;
+        let mut counts = HashMap::new();
+        for _ in 0..samples {
+            *counts.entry(sampler.sample()).or_insert(0u64) += 1;
         }
 
-        #[cfg(not(target_os = "linux"))]
-        {
-            // ICMP not supported on this platform without raw sockets
-            while state.load(Ordering::Relaxed) {
-                errors.fetch_add(1, Ordering::Relaxed);
-                thread::sleep(Duration::from_secs(1));
-            }
+        for (size, weight) in distribution.buckets() {
+            let observed = counts[&size] as f64 / samples as f64;
+            let expected = weight as f64 / 10.0;
+            assert!(
+                (observed - expected).abs() < 0.01,
+                "size {}: observed {} expected {}",
+                size,
+                observed,
+                expected
+            );
         }
+        assert_eq!(sampler.max_size(), 1400);
+
+        // Same seed, same sequence
+        let mut a = SizeSampler::new(&SizeDistribution::InternetMix, Some(3), 1);
+        let mut b = SizeSampler::new(&SizeDistribution::InternetMix, Some(3), 1);
+        assert!((0..1000).all(|_| a.sample() == b.sample()));
     }
 
-    fn raw_worker(
-        _thread_id: usize,
-        _addr: SocketAddr,
-        _config: EngineConfig,
-        state: Arc<AtomicBool>,
-        _packets_sent: Arc<AtomicU64>,
-        _bytes_sent: Arc<AtomicU64>,
-        errors: Arc<AtomicU64>,
-        _rate_limit: Arc<AtomicU64>,
-    ) {
-        // Raw socket implementation (requires elevated privileges)
-        while state.load(Ordering::Relaxed) {
-            errors.fetch_add(1, Ordering::Relaxed);
-            thread::sleep(Duration::from_secs(1));
+    #[test]
+    fn test_engine_rejects_invalid_size_distribution() {
+        for buckets in [vec![], vec![(0, 1)], vec![(64, 0)], vec![(70_000, 1)]] {
+            let config = EngineConfig {
+                size_distribution: Some(SizeDistribution::Buckets(buckets)),
+                ..Default::default()
+            };
+            assert!(matches!(
+                FloodEngine::new(config),
+                Err(EngineError::InvalidConfig(_))
+            ));
         }
     }
-}
 
-impl Drop for FloodEngine {
-    fn drop(&mut self) {
-        self.state.store(false, Ordering::SeqCst);
-        for handle in self.threads.drain(..) {
-            let _ = handle.join();
-        }
+    #[test]
+    fn test_size_distribution_byte_accounting() {
+        let (_sink, mut engine) = loopback_engine(
+            1,
+            EngineConfig {
+                size_distribution: Some(SizeDistribution::Buckets(vec![(100, 1), (300, 1)])),
+                seed: Some(5),
+                ..Default::default()
+            },
+        );
+        engine.start().unwrap();
+        wait_for_packets(&engine, 0);
+        engine.stop().unwrap();
+
+        let stats = engine.get_stats();
+        assert!(stats.packets_sent > 0);
+        let avg = stats.bytes_sent as f64 / stats.packets_sent as f64;
+        assert!(avg > 150.0 && avg < 250.0, "average payload {}", avg);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use proptest::prelude::*;
-    use std::time::Duration;
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_tcp_fastopen_connections() {
+        use std::io::Read;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let done = Arc::new(AtomicBool::new(false));
+        let received = Arc::new(AtomicU64::new(0));
+
+        let server = {
+            let done = Arc::clone(&done);
+            let received = Arc::clone(&received);
+            std::thread::spawn(move || {
+                let mut conns = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !done.load(Ordering::Relaxed) {
+                    if let Ok((conn, _)) = listener.accept() {
+                        conn.set_nonblocking(true).unwrap();
+                        conns.push(conn);
+                    }
+                    for conn in conns.iter_mut() {
+                        if let Ok(n) = conn.read(&mut buf) {
+                            received.fetch_add(n as u64, Ordering::Relaxed);
+                        }
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port,
+            threads: 1,
+            packet_size: 64,
+            protocol: Protocol::TCP,
+            rate_limit: Some(1000),
+            tcp_fastopen: true,
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        engine.stop().unwrap();
+        done.store(true, Ordering::Relaxed);
+        server.join().unwrap();
 
+        // Negotiation depends on the server-side sysctl, the TFO path itself must work
+        assert!(engine.get_tfo_connections() > 0);
+        assert!(engine.get_tfo_negotiated() <= engine.get_tfo_connections());
+        assert!(received.load(Ordering::Relaxed) > 0);
+    }
+
+    /// Compare socket counts on loopback: `cargo test bench_sockets -- --ignored --nocapture`
     #[test]
-    fn test_engine_config_default() {
-        let config = EngineConfig::default();
-        assert_eq!(config.threads, 4);
-        assert_eq!(config.packet_size, 1472);
-        assert_eq!(config.protocol, Protocol::UDP);
-        assert_eq!(config.port, 80);
-        assert!(config.rate_limit.is_none());
-        assert!(config.duration.is_none());
-        assert!(!config.use_raw_sockets);
+    #[ignore]
+    fn bench_sockets_per_thread_loopback() {
+        let base = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9,
+            threads: 2,
+            ..Default::default()
+        };
+        let results =
+            benchmark_sockets_per_thread(&base, &[1, 2, 4, 8], Duration::from_millis(500)).unwrap();
+        for (sockets, stats) in results {
+            println!("sockets_per_thread={:<2} pps={}", sockets, stats.pps);
+        }
     }
 
     #[test]
@@ -765,9 +6931,8 @@ mod tests {
             ..Default::default()
         };
         let engine = FloodEngine::new(config);
-        assert!(engine.is_err());
-        match engine.unwrap_err() {
-            EngineError::InvalidTarget(_) => {}
+        match engine {
+            Err(EngineError::InvalidTarget(_)) => {}
             _ => panic!("Expected InvalidTarget error"),
         }
     }
@@ -844,14 +7009,157 @@ mod tests {
                 ..Default::default()
             };
             let engine = FloodEngine::new(config);
-            assert!(
+            let needs_privilege = protocol == Protocol::ICMP;
+            assert_eq!(
                 engine.is_ok(),
-                "Failed to create engine for protocol {:?}",
+                !needs_privilege || has_raw_socket_privilege(),
+                "Unexpected result creating engine for protocol {:?}",
                 protocol
             );
         }
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_interface_binding() {
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sink.set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let config = loopback_config(
+            &sink,
+            1,
+            EngineConfig {
+                rate_limit: Some(1000),
+                interface: Some("lo".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let mut engine = FloodEngine::new(EngineConfig {
+            interface: Some("netstress-none0".to_string()),
+            ..config.clone()
+        })
+        .unwrap();
+        assert!(matches!(
+            engine.start(),
+            Err(EngineError::InvalidConfig(e)) if e.contains("netstress-none0") && e.contains("lo")
+        ));
+
+        let mut engine = FloodEngine::new(config.clone()).unwrap();
+        match engine.start() {
+            Ok(()) => {}
+            // SO_BINDTODEVICE needs CAP_NET_RAW before Linux 5.7
+            Err(EngineError::InsufficientPrivileges(_)) => return,
+            Err(e) => panic!("start failed: {}", e),
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        engine.stop().unwrap();
+        let mut buf = [0u8; 2048];
+        assert!(sink.recv(&mut buf).is_ok());
+
+        // Bound to another interface, loopback traffic never arrives
+        let names = crate::linux_optimizations::interface_names();
+        let Some(other) = names.iter().find(|name| name.as_str() != "lo") else {
+            return;
+        };
+        while sink.recv(&mut buf).is_ok() {}
+        let mut engine = FloodEngine::new(EngineConfig {
+            interface: Some(other.clone()),
+            ..config
+        })
+        .unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        engine.stop().unwrap();
+        assert!(
+            sink.recv(&mut buf).is_err(),
+            "traffic bound to {} reached lo",
+            other
+        );
+    }
+
+    #[test]
+    fn test_icmp_sends_echo_requests() {
+        // Needs CAP_NET_RAW
+        if !has_raw_socket_privilege() {
+            return;
+        }
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 0,
+            protocol: Protocol::ICMP,
+            threads: 1,
+            packet_size: 64,
+            rate_limit: Some(1000),
+            ..Default::default()
+        };
+        let mut engine = FloodEngine::new(config).unwrap();
+        engine.start().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        engine.stop().unwrap();
+
+        let stats = engine.get_stats();
+        assert!(stats.packets_sent > 0);
+        assert_eq!(stats.errors, 0);
+        // 64-byte packet minus the 20-byte IP header the kernel adds
+        assert_eq!(stats.bytes_sent, stats.packets_sent * 44);
+        // 1000 PPS for ~200ms, with slack for the rate window
+        assert!(stats.packets_sent < 1000, "sent {}", stats.packets_sent);
+    }
+
+    #[test]
+    fn test_cpu_pin_order() {
+        assert_eq!(
+            interleave_nodes(&[vec![0, 1, 2], vec![4, 5]]),
+            vec![0, 4, 1, 5, 2]
+        );
+        let order = cpu_pin_order(true);
+        assert!(!order.is_empty());
+
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            pin_threads: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            FloodEngine::new(config).unwrap().cpu_order.len(),
+            order.len()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pin_current_thread_reads_back() {
+        use nix::sched::sched_getaffinity;
+        use nix::unistd::Pid;
+
+        let cpu = cpu_pin_order(false)[0];
+        std::thread::spawn(move || {
+            pin_current_thread(cpu).unwrap();
+            let set = sched_getaffinity(Pid::from_raw(0)).unwrap();
+            for other in 0..nix::sched::CpuSet::count() {
+                assert_eq!(set.is_set(other).unwrap(), other == cpu);
+            }
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_raw_protocols_require_privilege() {
+        for protocol in [Protocol::ICMP, Protocol::RAW] {
+            assert!(check_protocol_privileges(protocol, true).is_ok());
+            match check_protocol_privileges(protocol, false) {
+                Err(e @ EngineError::InsufficientPrivileges(_)) => {
+                    assert!(e.to_string().contains("requires elevated privileges"));
+                }
+                _ => panic!("Expected InsufficientPrivileges for {:?}", protocol),
+            }
+        }
+        assert!(check_protocol_privileges(Protocol::UDP, false).is_ok());
+        assert!(check_protocol_privileges(Protocol::TCP, false).is_ok());
+    }
+
     #[test]
     fn test_engine_stats_after_start_stop() {
         let config = EngineConfig {
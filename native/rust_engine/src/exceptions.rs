@@ -0,0 +1,92 @@
+//! Python exception types raised by the bindings
+//! Everything derives from `NetStressError`, itself a `RuntimeError` as all errors used to be
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+use crate::backend;
+use crate::engine::EngineError;
+use crate::safety;
+
+create_exception!(
+    netstress_engine,
+    NetStressError,
+    PyRuntimeError,
+    "Base class of every netstress_engine error."
+);
+create_exception!(
+    netstress_engine,
+    InvalidTargetError,
+    NetStressError,
+    "The target host could not be parsed or resolved."
+);
+create_exception!(
+    netstress_engine,
+    BackendError,
+    NetStressError,
+    "A socket, privilege or backend failure in the operating system."
+);
+create_exception!(
+    netstress_engine,
+    SafetyError,
+    NetStressError,
+    "A safety control refused the operation."
+);
+create_exception!(
+    netstress_engine,
+    AlreadyRunningError,
+    NetStressError,
+    "The engine is already running."
+);
+
+/// Python exception for `e`, its message prefixed with `context`
+pub(crate) fn engine_error(context: &str, e: EngineError) -> PyErr {
+    let message = format!("{}: {}", context, e);
+    raise(&e, message)
+}
+
+impl From<EngineError> for PyErr {
+    fn from(e: EngineError) -> Self {
+        raise(&e, e.to_string())
+    }
+}
+
+/// `message` as the exception class matching `e`
+fn raise(e: &EngineError, message: String) -> PyErr {
+    match e {
+        EngineError::InvalidTarget(_) => InvalidTargetError::new_err(message),
+        EngineError::AlreadyRunning => AlreadyRunningError::new_err(message),
+        EngineError::SocketError(_)
+        | EngineError::InsufficientPrivileges(_)
+        | EngineError::PayloadExceedsMtu { .. }
+        | EngineError::ThreadError(_) => BackendError::new_err(message),
+        EngineError::NotRunning
+        | EngineError::InvalidConfig(_)
+        | EngineError::ProfileError(_)
+        | EngineError::CaptureError(_) => NetStressError::new_err(message),
+    }
+}
+
+impl From<safety::SafetyError> for PyErr {
+    fn from(e: safety::SafetyError) -> Self {
+        SafetyError::new_err(e.to_string())
+    }
+}
+
+impl From<backend::BackendError> for PyErr {
+    fn from(e: backend::BackendError) -> Self {
+        BackendError::new_err(e.to_string())
+    }
+}
+
+/// Add the exception classes to the module
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("NetStressError", py.get_type::<NetStressError>())?;
+    m.add("InvalidTargetError", py.get_type::<InvalidTargetError>())?;
+    m.add("BackendError", py.get_type::<BackendError>())?;
+    m.add("SafetyError", py.get_type::<SafetyError>())?;
+    m.add("AlreadyRunningError", py.get_type::<AlreadyRunningError>())?;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+//! Host resource governor for `EngineConfig::max_cpu_percent`
+//!
+//! Sampled once per `GOVERNOR_INTERVAL` by a background thread, never from the
+//! send loop. While the host is over a limit the governor caps the engine's
+//! rate and lowers the cap each sample; once usage is back under the limits
+//! it raises the cap again until it no longer binds.
+
+use parking_lot::Mutex;
+use std::time::Duration;
+
+/// Time between resource samples
+pub(crate) const GOVERNOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fraction of the rate kept after each over-limit sample
+const GOVERNOR_BACKOFF: f64 = 0.75;
+/// Growth of the cap after each sample comfortably under the limits
+const GOVERNOR_RECOVERY: f64 = 1.25;
+/// Share of a limit usage must fall below before the cap is raised again
+const GOVERNOR_HEADROOM: f64 = 0.9;
+
+/// One reading of local resource use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    /// Busy share of all CPUs since the previous sample, 0-100
+    pub cpu_percent: f64,
+    /// Resident memory of this process
+    pub rss_bytes: u64,
+}
+
+/// Source of `ResourceUsage` readings, swappable for tests
+pub trait ResourceSampler: Send + Sync {
+    /// Usage since the previous call, `None` when it cannot be measured
+    fn sample(&self) -> Option<ResourceUsage>;
+}
+
+/// System-wide CPU from `/proc/stat` and this process's RSS from
+/// `/proc/self/status`
+///
+/// The first sample only sets the CPU baseline. Other platforms report
+/// nothing, so the governor never throttles there.
+#[derive(Default)]
+pub struct SystemSampler {
+    /// Busy and total jiffies at the previous sample
+    last: Mutex<Option<(u64, u64)>>,
+}
+
+impl SystemSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ResourceSampler for SystemSampler {
+    #[cfg(target_os = "linux")]
+    fn sample(&self) -> Option<ResourceUsage> {
+        let stat = std::fs::read_to_string("/proc/stat").ok()?;
+        let (busy, total) = cpu_jiffies(stat.lines().next()?)?;
+        let previous = self.last.lock().replace((busy, total));
+        let (last_busy, last_total) = previous?;
+        let elapsed = total.saturating_sub(last_total).max(1);
+        let cpu_percent = busy.saturating_sub(last_busy) as f64 * 100.0 / elapsed as f64;
+
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let rss_kb = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(ResourceUsage {
+            cpu_percent,
+            rss_bytes: rss_kb * 1024,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn sample(&self) -> Option<ResourceUsage> {
+        let _ = &self.last;
+        None
+    }
+}
+
+/// Busy and total jiffies from the aggregate `cpu` line of `/proc/stat`
+#[cfg(target_os = "linux")]
+fn cpu_jiffies(line: &str) -> Option<(u64, u64)> {
+    let fields: Vec<u64> = line
+        .strip_prefix("cpu ")?
+        .split_whitespace()
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+    // user nice system idle iowait irq softirq steal; guest time is already in user
+    let total: u64 = fields.iter().take(8).sum();
+    let idle = fields.get(3)? + fields.get(4).copied().unwrap_or(0);
+    Some((total - idle, total))
+}
+
+/// Decides the rate cap from successive resource samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceGovernor {
+    max_cpu_percent: Option<f64>,
+    max_rss_bytes: Option<u64>,
+    /// Current cap in pps, `None` while not throttling
+    cap: Option<u64>,
+}
+
+impl ResourceGovernor {
+    pub fn new(max_cpu_percent: Option<f64>, max_rss_bytes: Option<u64>) -> Self {
+        Self {
+            max_cpu_percent,
+            max_rss_bytes,
+            cap: None,
+        }
+    }
+
+    /// Current cap in pps, `None` while not throttling
+    pub fn cap(&self) -> Option<u64> {
+        self.cap
+    }
+
+    /// Fold in one sample taken while the engine sent `pps` against a rate
+    /// limit of `limit` (0 for unlimited), returning the new cap
+    pub fn update(&mut self, usage: ResourceUsage, pps: u64, limit: u64) -> Option<u64> {
+        let over = |share: f64| {
+            self.max_cpu_percent
+                .is_some_and(|max| usage.cpu_percent > max * share)
+                || self
+                    .max_rss_bytes
+                    .is_some_and(|max| usage.rss_bytes as f64 > max as f64 * share)
+        };
+        if over(1.0) {
+            // Start from what is actually being sent, not from an unreached limit
+            let base = self.cap.unwrap_or(match limit {
+                0 => pps,
+                limit => pps.min(limit),
+            });
+            self.cap = Some(((base as f64 * GOVERNOR_BACKOFF) as u64).max(1));
+        } else if let Some(cap) = self.cap.filter(|_| !over(GOVERNOR_HEADROOM)) {
+            let raised = ((cap as f64 * GOVERNOR_RECOVERY).ceil() as u64).max(cap + 1);
+            // Released once it would no longer hold the engine back
+            let binding = match limit {
+                0 => raised <= pps.saturating_mul(2),
+                limit => raised < limit,
+            };
+            self.cap = binding.then_some(raised);
+        }
+        self.cap
+    }
+}
+
+/// `limit` pps with the governor's `cap` applied; 0 means unlimited for both
+pub(crate) fn capped_rate(limit: u64, cap: u64) -> u64 {
+    match (limit, cap) {
+        (limit, 0) => limit,
+        (0, cap) => cap,
+        (limit, cap) => limit.min(cap),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Replays scripted CPU readings
+    struct ScriptedSampler(Mutex<VecDeque<f64>>);
+
+    impl ResourceSampler for ScriptedSampler {
+        fn sample(&self) -> Option<ResourceUsage> {
+            self.0.lock().pop_front().map(|cpu_percent| ResourceUsage {
+                cpu_percent,
+                rss_bytes: 0,
+            })
+        }
+    }
+
+    #[test]
+    fn test_governor_backs_off_and_recovers() {
+        let sampler = ScriptedSampler(Mutex::new(VecDeque::from(vec![
+            50.0, 95.0, 95.0, 75.0, 50.0, 50.0, 50.0, 50.0,
+        ])));
+        let mut governor = ResourceGovernor::new(Some(80.0), None);
+        let limit = 10_000;
+        let mut pps = limit;
+        let mut caps = Vec::new();
+        while let Some(usage) = sampler.sample() {
+            let cap = governor.update(usage, pps, limit);
+            pps = capped_rate(limit, cap.unwrap_or(0));
+            caps.push(cap);
+        }
+
+        assert_eq!(
+            caps,
+            vec![
+                None,
+                Some(7_500),
+                Some(5_625),
+                // Under the limit but not by enough to raise it
+                Some(5_625),
+                Some(7_032),
+                Some(8_790),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_governor_caps_unlimited_rate_and_rss() {
+        let mut governor = ResourceGovernor::new(None, Some(1 << 20));
+        let heavy = ResourceUsage {
+            cpu_percent: 100.0,
+            rss_bytes: 2 << 20,
+        };
+        let light = ResourceUsage {
+            rss_bytes: 0,
+            ..heavy
+        };
+        // Unlimited: backs off from the measured rate
+        assert_eq!(governor.update(heavy, 4_000, 0), Some(3_000));
+        assert_eq!(governor.update(light, 3_000, 0), Some(3_750));
+        // Released once the cap is well above what is being sent
+        assert_eq!(governor.update(light, 1_000, 0), None);
+
+        assert_eq!(capped_rate(0, 0), 0);
+        assert_eq!(capped_rate(500, 0), 500);
+        assert_eq!(capped_rate(0, 300), 300);
+        assert_eq!(capped_rate(500, 300), 300);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_system_sampler_reads_proc() {
+        let sampler = SystemSampler::new();
+        assert_eq!(sampler.sample(), None);
+        std::thread::sleep(Duration::from_millis(20));
+        let usage = sampler.sample().unwrap();
+        assert!((0.0..=100.0).contains(&usage.cpu_percent));
+        assert!(usage.rss_bytes > 0);
+        assert_eq!(cpu_jiffies("cpu  10 0 5 80 5 0 0 0 0 0"), Some((15, 100)));
+    }
+}
@@ -5,15 +5,23 @@ mod atomic_stats;
 mod audit;
 mod backend;
 mod backend_selector;
+mod campaign;
+mod control;
 mod engine;
+mod exceptions;
+mod governor;
 mod packet;
+mod pcap;
 mod pool;
+mod profile;
 mod protocol_builder;
 mod queue;
 mod rate_limiter;
+mod responses;
 mod safety;
 mod simd;
 mod stats;
+mod target_health;
 
 #[cfg(target_os = "linux")]
 mod linux_optimizations;
@@ -28,18 +36,54 @@ use parking_lot::RwLock;
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
 use pyo3::types::PyModule;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-pub use atomic_stats::{AtomicStats, StatsCollector, StatsSnapshot, ThreadStats};
-pub use audit::{AuditEntry, AuditEventType, AuditLogger, ChainVerificationResult};
-pub use backend_selector::{BackendSelector, CapabilityReport};
-pub use engine::{EngineConfig, EngineState, FloodEngine};
-pub use packet::{PacketBuilder, PacketFlags, Protocol};
-pub use pool::PacketPool;
-pub use protocol_builder::{BatchPacketGenerator, FragmentConfig, ProtocolBuilder, SpoofConfig};
-pub use safety::{EmergencyStop, SafetyController, SafetyError, TargetAuthorization};
-pub use stats::Stats;
+use exceptions::engine_error;
+
+pub use atomic_stats::{
+    prometheus_text, AtomicLogHistogram, AtomicStats, CollectorRegistry, Histogram,
+    HistogramSnapshot, MetricsSnapshot, PpsHistogram, StatsCollector, StatsSnapshot, ThreadStats,
+};
+pub use audit::{
+    verify_all_segments, AuditBackpressure, AuditEntry, AuditEventType, AuditLogger, ChainBreak,
+    ChainVerificationResult, DecryptedExport, SyslogFacility,
+};
+pub use backend::{Recommendation, RecommendationSeverity};
+pub use backend_selector::{
+    BackendChangeListener, BackendSelector, BackendTransition, BenchmarkResult, CapabilityReport,
+    ReprobeHandle,
+};
+pub use campaign::Campaign;
+pub use control::{ControlHandle, ControlServer};
+pub use engine::{
+    available_memory, benchmark_sockets_per_thread, resolve_destinations, resolve_target,
+    AddressFamily, CaptureStats, DestinationStats, EngineConfig, EngineError, EngineState,
+    EngineStateHandle, FloodEngine, PacketSource, PayloadFactory, PortMode, RampConfig, RampCurve,
+    SendErrorKind, SizeDistribution, WorkDistribution,
+};
+pub use governor::{ResourceGovernor, ResourceSampler, ResourceUsage, SystemSampler};
+pub use packet::{
+    PacketBuilder, PacketFlags, Protocol, ETHERTYPE_IPV4, ETHERTYPE_IPV6, ETHERTYPE_QINQ,
+    ETHERTYPE_VLAN,
+};
+pub use pcap::{
+    parse_capture, read_capture, udp_frame_between, udp_payload, PcapError, PcapPacket, PcapWriter,
+};
+pub use pool::{ClassStats, PacketPool, PoolStats, PooledBuffer};
+pub use profile::PROFILE_VERSION;
+pub use protocol_builder::{
+    BatchPacketGenerator, ChecksumMode, FragmentConfig, PacketSizeDistribution, ProtocolBuilder,
+    SpoofConfig, GRE_PROTO_IPV4, GRE_PROTO_IPV6, GRE_PROTO_TEB, VXLAN_PORT,
+};
+pub use responses::ResponseStats;
+pub use safety::{
+    CounterSource, EmergencyStop, ReverseDns, SafetyController, SafetyError, SafetyWatch,
+    SystemReverseDns, TargetAuthorization,
+};
+pub use stats::{Stats, StatsDelta};
+pub use target_health::{HealthPolicy, TargetHealthSnapshot, TargetHealthTracker};
 // Note: StatsSnapshot is already exported from atomic_stats
 
 #[cfg(target_os = "linux")]
@@ -92,71 +136,376 @@ mod dpdk_stub {
 #[cfg(not(feature = "dpdk"))]
 use dpdk_stub::*;
 
+/// `PacketEngine` target: one host, or several `host:port` endpoints
+#[derive(FromPyObject)]
+enum TargetSpec {
+    Host(String),
+    Endpoints(Vec<String>),
+}
+
+/// `host:port` for a listed endpoint, adding `port` when it names none
+///
+/// IPv6 literals may come bare (`::1`) or bracketed, with or without a port.
+fn endpoint_with_port(endpoint: String, port: u16) -> String {
+    if endpoint.parse::<SocketAddr>().is_ok() {
+        return endpoint;
+    }
+    let host = endpoint
+        .strip_prefix('[')
+        .and_then(|e| e.strip_suffix(']'))
+        .unwrap_or(&endpoint);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return SocketAddr::new(ip, port).to_string();
+    }
+    match endpoint.rsplit_once(':') {
+        Some((_, p)) if p.parse::<u16>().is_ok() => endpoint,
+        _ => format!("{}:{}", endpoint, port),
+    }
+}
+
 /// Python-exposed PacketEngine class
 #[pyclass]
 pub struct PacketEngine {
     target: String,
     port: u16,
+    /// `target="..."` label of this engine's metrics
+    label: String,
     engine: Arc<RwLock<FloodEngine>>,
     stats: Arc<RwLock<Stats>>,
+    /// Shared with the workers and listed in the process-wide metrics registry
+    collector: Arc<StatsCollector>,
+    /// Read without the engine lock so `Stopping` is visible while `stop()` joins
+    state: EngineStateHandle,
+    /// Thread calling the `on_stats` callback
+    stats_timer: parking_lot::Mutex<Option<StatsTimer>>,
 }
 
-#[pymethods]
 impl PacketEngine {
-    #[new]
-    #[pyo3(signature = (target, port, threads=4, packet_size=1472))]
-    fn new(target: String, port: u16, threads: usize, packet_size: usize) -> PyResult<Self> {
-        let config = EngineConfig {
-            target: target.clone(),
-            port,
-            threads,
-            packet_size,
-            ..Default::default()
+    fn with_config(config: EngineConfig) -> PyResult<Self> {
+        let (target, port) = (config.target.clone(), config.port);
+        let (target, label) = if config.destinations.is_empty() {
+            let label = metrics_label(&target, port);
+            (target, label)
+        } else {
+            let endpoints = config.destinations.join(",");
+            (endpoints.clone(), endpoints)
         };
 
-        let engine = FloodEngine::new(config)
-            .map_err(|e| PyRuntimeError::new_err(format!("Failed to create engine: {}", e)))?;
+        let engine =
+            FloodEngine::new(config).map_err(|e| engine_error("Failed to create engine", e))?;
+        let collector = Arc::clone(engine.collector());
+        CollectorRegistry::global().register(label.clone(), &collector);
 
         Ok(Self {
             target,
             port,
+            label,
+            collector,
+            state: engine.state_handle(),
             engine: Arc::new(RwLock::new(engine)),
             stats: Arc::new(RwLock::new(Stats::new())),
+            stats_timer: parking_lot::Mutex::new(None),
         })
     }
+}
+
+#[pymethods]
+impl PacketEngine {
+    #[new]
+    #[pyo3(signature = (target, port, threads=4, packet_size=1472, sockets_per_thread=None, drop_fraction=None, seed=None, dont_fragment=false, clamp_to_mtu=false, duration_secs=None, pin_threads=false, numa_aware=false, payload_factory=None, payload_ring=256, dry_run=false, clamp_to_interface_mtu=false, gso_segments=None, zerocopy=false, pcap_path=None, replay_speed=None, ttl=None, dscp=None, ecn=None, autoscale=false, max_threads=None, port_range=None, port_mode="sequential", collect_responses=false, response_timeout_secs=1.0, max_cpu_percent=None))]
+    #[allow(clippy::too_many_arguments)] // mirrors the Python keyword arguments
+    fn new(
+        target: TargetSpec,
+        port: u16,
+        threads: usize,
+        packet_size: usize,
+        sockets_per_thread: Option<usize>,
+        drop_fraction: Option<f64>,
+        seed: Option<u64>,
+        dont_fragment: bool,
+        clamp_to_mtu: bool,
+        duration_secs: Option<f64>,
+        pin_threads: bool,
+        numa_aware: bool,
+        payload_factory: Option<PyObject>,
+        payload_ring: usize,
+        dry_run: bool,
+        clamp_to_interface_mtu: bool,
+        gso_segments: Option<u16>,
+        zerocopy: bool,
+        pcap_path: Option<String>,
+        replay_speed: Option<f64>,
+        ttl: Option<u8>,
+        dscp: Option<u8>,
+        ecn: Option<u8>,
+        autoscale: bool,
+        max_threads: Option<usize>,
+        port_range: Option<(u16, u16)>,
+        port_mode: &str,
+        collect_responses: bool,
+        response_timeout_secs: f64,
+        max_cpu_percent: Option<f64>,
+    ) -> PyResult<Self> {
+        let payload_factory = payload_factory
+            .map(|callable| materialize_payloads(&callable, payload_ring))
+            .transpose()?;
+        // Listed endpoints without a port use `port`
+        let (target, destinations) = match target {
+            TargetSpec::Host(host) => (host, Vec::new()),
+            TargetSpec::Endpoints(endpoints) if endpoints.is_empty() => {
+                return Err(PyRuntimeError::new_err("target list is empty"));
+            }
+            TargetSpec::Endpoints(endpoints) => {
+                let endpoints = endpoints
+                    .into_iter()
+                    .map(|e| endpoint_with_port(e, port))
+                    .collect();
+                (String::new(), endpoints)
+            }
+        };
+        let defaults = EngineConfig::default();
+        let config = EngineConfig {
+            target,
+            port,
+            destinations,
+            threads,
+            packet_size,
+            sockets_per_thread: sockets_per_thread.unwrap_or(defaults.sockets_per_thread),
+            drop_fraction,
+            seed,
+            dont_fragment,
+            clamp_to_mtu,
+            clamp_to_interface_mtu,
+            duration: duration_secs
+                .map(Duration::try_from_secs_f64)
+                .transpose()
+                .map_err(|e| PyRuntimeError::new_err(format!("Invalid duration_secs: {}", e)))?,
+            pin_threads,
+            numa_aware,
+            payload_factory,
+            dry_run,
+            gso_segments,
+            zerocopy,
+            source: pcap_path.map_or(PacketSource::Synthetic, |p| PacketSource::Pcap(p.into())),
+            replay_timing: replay_speed,
+            ttl,
+            dscp,
+            ecn,
+            autoscale,
+            max_threads,
+            port_range,
+            port_mode: parse_port_mode(port_mode)?,
+            collect_responses,
+            response_timeout: Duration::try_from_secs_f64(response_timeout_secs).map_err(|e| {
+                PyRuntimeError::new_err(format!("Invalid response_timeout_secs: {}", e))
+            })?,
+            max_cpu_percent,
+            ..defaults
+        };
+        Self::with_config(config)
+    }
+
+    /// Create an engine from a JSON config object, as written by `to_config`
+    ///
+    /// Unknown fields are an error unless `strict` is false, in which case
+    /// they are skipped.
+    #[staticmethod]
+    #[pyo3(signature = (json, strict=true))]
+    fn from_config(json: &str, strict: bool) -> PyResult<Self> {
+        Self::with_config(EngineConfig::from_json_with(json, strict)?)
+    }
+
+    /// This engine's configuration as a JSON object
+    fn to_config(&self) -> PyResult<String> {
+        self.engine
+            .read()
+            .config()
+            .to_json()
+            .map_err(|e| engine_error("Failed to serialize config", e))
+    }
 
     /// Start the packet engine
     fn start(&self) -> PyResult<()> {
         let mut engine = self.engine.write();
         engine
             .start()
-            .map_err(|e| PyRuntimeError::new_err(format!("Failed to start: {}", e)))
+            .map_err(|e| engine_error("Failed to start", e))
+    }
+
+    /// Throttle workers with the controller's rate limit and halt them on its
+    /// emergency stop; applies from the next `start`
+    fn set_safety(&self, controller: PyRef<'_, PySafetyController>) {
+        self.engine
+            .write()
+            .set_safety(Arc::clone(&controller.inner));
+    }
+
+    /// Write every `sample_every`th sent packet to a pcap file, at most
+    /// `max_packets`; applies from the next `start` and ends with `stop`
+    #[pyo3(signature = (path, max_packets, sample_every=100))]
+    fn enable_capture(&self, path: &str, max_packets: u64, sample_every: u64) -> PyResult<()> {
+        self.engine
+            .write()
+            .enable_capture(path, max_packets, sample_every)
+            .map_err(|e| engine_error("Failed to enable capture", e))
+    }
+
+    /// Written and dropped sample counts of the current or last capture
+    fn capture_stats(&self) -> PyResult<Option<PyObject>> {
+        let Some(stats) = self.engine.read().capture_stats() else {
+            return Ok(None);
+        };
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("written", stats.written)?;
+            dict.set_item("dropped", stats.dropped)?;
+            Ok(Some(dict.into()))
+        })
+    }
+
+    /// Packets, bytes and health per resolved endpoint when created with a
+    /// list of targets, as a list of dicts in endpoint order
+    fn get_destination_stats(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let list = pyo3::types::PyList::empty(py);
+            for destination in self.engine.read().destination_stats() {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("address", destination.addr.to_string())?;
+                dict.set_item("packets", destination.packets_sent)?;
+                dict.set_item("bytes", destination.bytes_sent)?;
+                dict.set_item("healthy", destination.healthy)?;
+                dict.set_item("ejections", destination.ejections)?;
+                list.append(dict)?;
+            }
+            Ok(list.into())
+        })
+    }
+
+    /// Per-worker counters as a list of dicts ordered by thread id
+    fn get_per_thread_stats(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let list = pyo3::types::PyList::empty(py);
+            for slot in self.engine.read().per_thread_stats() {
+                let snapshot = slot.snapshot();
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("thread_id", slot.thread_id)?;
+                dict.set_item("packets", snapshot.packets_sent)?;
+                dict.set_item("bytes", snapshot.bytes_sent)?;
+                dict.set_item("errors", snapshot.errors)?;
+                list.append(dict)?;
+            }
+            Ok(list.into())
+        })
+    }
+
+    /// Prometheus metrics for this engine, labelled `target="host:port"`
+    fn prometheus_metrics(&self) -> String {
+        prometheus_text(
+            "netstress",
+            &[(Some(&self.label), &self.collector.metrics_snapshot())],
+        )
+    }
+
+    /// This engine's collector snapshot as JSON
+    fn realtime_stats_json(&self) -> String {
+        self.collector.json_metrics()
+    }
+
+    /// Lifecycle state name: Idle, Running, Stopping or Stopped
+    fn state(&self) -> &'static str {
+        self.state.get().as_str()
+    }
+
+    /// Publish workers' local counters so the next get_stats() is exact
+    fn flush_stats(&self) -> bool {
+        self.engine.read().flush_stats()
+    }
+
+    /// Estimated memory (bytes) this engine's configuration needs
+    fn estimate_memory(&self) -> usize {
+        self.engine.read().config().estimate_memory()
     }
 
     /// Stop the packet engine
     fn stop(&self) -> PyResult<()> {
         let mut engine = self.engine.write();
-        engine
-            .stop()
-            .map_err(|e| PyRuntimeError::new_err(format!("Failed to stop: {}", e)))
+        engine.stop().map_err(|e| engine_error("Failed to stop", e))
+    }
+
+    /// Packets, bytes and errors since an earlier `get_stats()` snapshot or its
+    /// `to_dict()`, with the rates between
+    fn rate_since(&self, previous: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let previous = match previous.downcast::<PyStatsSnapshot>() {
+            Ok(snapshot) => snapshot.get().fields.bind(previous.py()).clone(),
+            Err(_) => previous.downcast::<pyo3::types::PyDict>()?.clone(),
+        };
+        let count = |key: &str| -> PyResult<u64> {
+            previous
+                .get_item(key)?
+                .map(|v| v.extract::<u64>())
+                .transpose()
+                .map(Option::unwrap_or_default)
+        };
+        let secs: f64 = previous
+            .get_item("duration_secs")?
+            .ok_or_else(|| PyRuntimeError::new_err("previous snapshot has no duration_secs"))?
+            .extract()?;
+        let earlier = stats::StatsSnapshot {
+            packets_sent: count("packets_sent")?,
+            bytes_sent: count("bytes_sent")?,
+            errors: count("errors")?,
+            // Negative or NaN durations count from the start of the run
+            duration: Duration::try_from_secs_f64(secs).unwrap_or_default(),
+            ..Default::default()
+        };
+        let delta = self.engine.read().get_stats().delta(&earlier);
+
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("packets", delta.packets)?;
+            dict.set_item("bytes", delta.bytes)?;
+            dict.set_item("errors", delta.errors)?;
+            dict.set_item("interval_secs", delta.interval.as_secs_f64())?;
+            dict.set_item("packets_per_second", delta.pps)?;
+            dict.set_item("bytes_per_second", delta.bps)?;
+            Ok(dict.into())
+        })
     }
 
     /// Get current statistics
     fn get_stats(&self) -> PyResult<PyObject> {
-        Python::with_gil(|py| {
-            let engine = self.engine.read();
-            let snapshot = engine.get_stats();
+        Python::with_gil(|py| engine_stats(py, &self.engine.read()))
+    }
 
-            let dict = pyo3::types::PyDict::new_bound(py);
-            dict.set_item("packets_sent", snapshot.packets_sent)?;
-            dict.set_item("bytes_sent", snapshot.bytes_sent)?;
-            dict.set_item("packets_per_second", snapshot.pps)?;
-            dict.set_item("bytes_per_second", snapshot.bps)?;
-            dict.set_item("errors", snapshot.errors)?;
-            dict.set_item("duration_secs", snapshot.duration.as_secs_f64())?;
+    /// Replies matched this run and their round-trip percentiles in seconds,
+    /// `None` unless created with `collect_responses=True`
+    fn response_stats(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let Some(stats) = self.engine.read().response_stats() else {
+            return Ok(None);
+        };
+        let dict = pyo3::types::PyDict::new(py);
+        dict.set_item("responses", stats.responses)?;
+        dict.set_item("unmatched", stats.unmatched)?;
+        dict.set_item("timed_out", stats.timed_out)?;
+        for (key, latency) in [
+            ("p50_latency_secs", stats.p50_latency),
+            ("p95_latency_secs", stats.p95_latency),
+            ("p99_latency_secs", stats.p99_latency),
+        ] {
+            dict.set_item(key, latency.map(|l| l.as_secs_f64()))?;
+        }
+        Ok(Some(dict.into()))
+    }
 
-            Ok(dict.into())
-        })
+    /// Rate in pps the CPU governor is holding the engine to, `None` while it
+    /// is not throttling
+    fn governed_rate(&self) -> Option<u64> {
+        self.engine.read().governed_rate()
+    }
+
+    /// JSON summary of the latest run, `"complete": false` while it is still going
+    fn final_report(&self) -> String {
+        self.engine.read().final_report()
     }
 
     /// Check if engine is running
@@ -165,6 +514,72 @@ impl PacketEngine {
         engine.is_running()
     }
 
+    /// Call `callback(stats)` with the `get_stats()` snapshot every `interval_secs`
+    /// while the engine runs; `None` clears it
+    ///
+    /// Replaces any earlier callback. The timer ends when the engine stops;
+    /// exceptions raised by the callback are logged and the timer keeps going.
+    #[pyo3(signature = (callback, interval_secs=1.0))]
+    fn on_stats(
+        &self,
+        py: Python<'_>,
+        callback: Option<PyObject>,
+        interval_secs: f64,
+    ) -> PyResult<()> {
+        let interval = Duration::try_from_secs_f64(interval_secs)
+            .ok()
+            .filter(|interval| !interval.is_zero())
+            .ok_or_else(|| {
+                PyRuntimeError::new_err(format!("Invalid interval_secs: {}", interval_secs))
+            })?;
+        if let Some(callback) = &callback {
+            if !callback.bind(py).is_callable() {
+                return Err(pyo3::exceptions::PyTypeError::new_err(
+                    "on_stats callback must be callable",
+                ));
+            }
+        }
+
+        let previous = self.stats_timer.lock().take();
+        if let Some(timer) = previous {
+            timer.cancel(py);
+        }
+        if let Some(callback) = callback {
+            let timer = StatsTimer::spawn(
+                Arc::clone(&self.engine),
+                self.state.clone(),
+                callback,
+                interval,
+            )?;
+            *self.stats_timer.lock() = Some(timer);
+        }
+        Ok(())
+    }
+
+    /// `with PacketEngine(...) as e:` starts the engine; a failed start leaves
+    /// no threads behind, and entering a running engine raises
+    /// `AlreadyRunningError`
+    fn __enter__(slf: PyRef<'_, Self>) -> PyResult<PyRef<'_, Self>> {
+        slf.start()?;
+        Ok(slf)
+    }
+
+    /// Stop the engine when the `with` block ends, exceptions included; they
+    /// are never suppressed
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &self,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        match self.engine.write().stop() {
+            // Already stopped inside the block, or by the `duration` watchdog
+            Ok(()) | Err(EngineError::NotRunning) => Ok(false),
+            Err(e) => Err(engine_error("Failed to stop", e)),
+        }
+    }
+
     /// Set target rate (packets per second)
     fn set_rate(&self, pps: u64) -> PyResult<()> {
         let mut engine = self.engine.write();
@@ -172,15 +587,498 @@ impl PacketEngine {
         Ok(())
     }
 
+    /// Ramp the rate in `step` pps increments of `step_secs` each, up to the
+    /// rate limit, and return the highest rate before errors climbed
+    ///
+    /// `step` defaults to a tenth of the rate limit. Runs the engine itself, so
+    /// call it while stopped; the GIL is released meanwhile.
+    #[pyo3(signature = (step_secs=1.0, step=None))]
+    fn calibrate(&self, py: Python<'_>, step_secs: f64, step: Option<u64>) -> PyResult<u64> {
+        let step_duration = Duration::try_from_secs_f64(step_secs)
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid step duration: {}", e)))?;
+        py.allow_threads(|| {
+            let mut engine = self.engine.write();
+            let step = step.unwrap_or((engine.get_rate_limit() / 10).max(1));
+            engine.calibrate(step_duration, step)
+        })
+        .map_err(|e| engine_error("Calibration failed", e))
+    }
+
+    /// Move the rate from `start` to `end` pps over `secs`, then hold `end`;
+    /// `curve` is "linear" or "exponential"
+    #[pyo3(signature = (start, end, secs, curve="linear"))]
+    fn set_ramp(&self, start: u64, end: u64, secs: f64, curve: &str) -> PyResult<()> {
+        let duration = Duration::try_from_secs_f64(secs)
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid ramp duration: {}", e)))?;
+        let ramp = RampConfig {
+            start_pps: start,
+            end_pps: end,
+            duration,
+            curve: parse_ramp_curve(curve)?,
+        };
+        self.engine
+            .write()
+            .set_ramp(ramp)
+            .map_err(|e| engine_error("Failed to set ramp", e))
+    }
+
+    /// Apply `[(secs_from_start, pps), ...]` in turn, holding the last rate;
+    /// `interpolate` moves linearly between points instead of stepping
+    #[pyo3(signature = (points, interpolate=false))]
+    fn set_rate_schedule(&self, points: Vec<(f64, u64)>, interpolate: bool) -> PyResult<()> {
+        let points = points
+            .into_iter()
+            .map(|(secs, pps)| {
+                Duration::try_from_secs_f64(secs)
+                    .map(|offset| (offset, pps))
+                    .map_err(|e| PyRuntimeError::new_err(format!("Invalid offset {}: {}", secs, e)))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        self.engine
+            .write()
+            .set_rate_schedule(points, interpolate)
+            .map_err(|e| engine_error("Failed to set rate schedule", e))
+    }
+
+    /// Limit new TCP/HTTP connections per second, 0 for unlimited
+    fn set_conn_rate(&self, cps: u64) {
+        self.engine.write().set_conn_rate(cps);
+    }
+
+    /// Change the number of worker threads without restarting
+    fn set_thread_count(&self, threads: usize) -> PyResult<()> {
+        let mut engine = self.engine.write();
+        engine
+            .set_thread_count(threads)
+            .map_err(|e| engine_error("Failed to set thread count", e))
+    }
+
+    /// Workers currently sending; below the spawned count while autoscale holds some back
+    fn scaled_thread_count(&self) -> usize {
+        self.engine.read().scaled_thread_count()
+    }
+
     /// Get target info
     fn __repr__(&self) -> String {
         format!("PacketEngine(target='{}', port={})", self.target, self.port)
     }
 }
 
+/// Python-exposed Campaign: several targets under one global rate budget
+#[pyclass]
+pub struct PyCampaign {
+    inner: RwLock<Campaign>,
+    /// Engine settings shared by every target added
+    template: EngineConfig,
+}
+
+#[pymethods]
+impl PyCampaign {
+    #[new]
+    #[pyo3(signature = (budget_pps, threads=2, packet_size=1472, protocol="udp", dry_run=false))]
+    fn new(
+        budget_pps: u64,
+        threads: usize,
+        packet_size: usize,
+        protocol: &str,
+        dry_run: bool,
+    ) -> PyResult<Self> {
+        let campaign =
+            Campaign::new(budget_pps).map_err(|e| engine_error("Failed to create campaign", e))?;
+        Ok(Self {
+            inner: RwLock::new(campaign),
+            template: EngineConfig {
+                threads,
+                packet_size,
+                protocol: parse_protocol(protocol)?,
+                dry_run,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Add a target with relative `weight`; returns its id for `remove_target`
+    #[pyo3(signature = (target, port, weight=1.0))]
+    fn add_target(&self, target: String, port: u16, weight: f64) -> PyResult<usize> {
+        let config = EngineConfig {
+            target,
+            port,
+            ..self.template.clone()
+        };
+        self.inner
+            .write()
+            .add_target(config, weight)
+            .map_err(|e| engine_error("Failed to add target", e))
+    }
+
+    /// Stop and remove a target; the others take over its share of the budget
+    fn remove_target(&self, id: usize) -> PyResult<()> {
+        self.inner
+            .write()
+            .remove_target(id)
+            .map(|_| ())
+            .map_err(|e| engine_error("Failed to remove target", e))
+    }
+
+    /// Change the global budget (packets per second)
+    fn set_budget(&self, budget_pps: u64) -> PyResult<()> {
+        self.inner
+            .write()
+            .set_budget(budget_pps)
+            .map_err(|e| engine_error("Failed to set budget", e))
+    }
+
+    /// Govern every target's engine with the controller
+    fn set_safety(&self, controller: PyRef<'_, PySafetyController>) {
+        self.inner.write().set_safety(Arc::clone(&controller.inner));
+    }
+
+    fn start(&self) -> PyResult<()> {
+        self.inner
+            .write()
+            .start()
+            .map_err(|e| engine_error("Failed to start", e))
+    }
+
+    fn stop(&self) -> PyResult<()> {
+        self.inner
+            .write()
+            .stop()
+            .map_err(|e| engine_error("Failed to stop", e))
+    }
+
+    fn is_running(&self) -> bool {
+        self.inner.read().is_running()
+    }
+
+    /// Targets as a list of dicts with id, weight and their pps share
+    fn targets(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let list = pyo3::types::PyList::empty(py);
+            for (id, weight, rate) in self.inner.read().targets() {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("id", id)?;
+                dict.set_item("weight", weight)?;
+                dict.set_item("rate_pps", rate)?;
+                list.append(dict)?;
+            }
+            Ok(list.into())
+        })
+    }
+
+    /// Totals across all targets since `start`
+    fn get_stats(&self) -> PyResult<PyObject> {
+        let campaign = self.inner.read();
+        let snapshot = campaign.get_stats();
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("packets_sent", snapshot.packets_sent)?;
+            dict.set_item("bytes_sent", snapshot.bytes_sent)?;
+            dict.set_item("packets_per_second", snapshot.pps)?;
+            dict.set_item("bytes_per_second", snapshot.bps)?;
+            dict.set_item("errors", snapshot.errors)?;
+            dict.set_item("packets_dropped", snapshot.packets_dropped)?;
+            dict.set_item("duration_secs", snapshot.duration.as_secs_f64())?;
+            dict.set_item("budget_pps", campaign.budget())?;
+            dict.set_item("targets", campaign.targets().len())?;
+            Ok(dict.into())
+        })
+    }
+}
+
+/// `PacketEngine.get_stats()` snapshot for `engine`
+fn engine_stats(py: Python<'_>, engine: &FloodEngine) -> PyResult<PyObject> {
+    let snapshot = engine.get_stats();
+
+    let dict = pyo3::types::PyDict::new(py);
+    dict.set_item("packets_sent", snapshot.packets_sent)?;
+    dict.set_item("bytes_sent", snapshot.bytes_sent)?;
+    dict.set_item("packets_per_second", snapshot.pps)?;
+    dict.set_item("bytes_per_second", snapshot.bps)?;
+    dict.set_item("errors", snapshot.errors)?;
+    dict.set_item("packets_dropped", snapshot.packets_dropped)?;
+    dict.set_item("packets_intended", snapshot.packets_intended())?;
+    dict.set_item("duration_secs", snapshot.duration.as_secs_f64())?;
+    dict.set_item("sockets_per_thread", engine.sockets_per_thread())?;
+    dict.set_item("open_sockets", engine.get_open_sockets())?;
+    dict.set_item("state", engine.state().as_str())?;
+    dict.set_item("oversized_errors", snapshot.oversized_errors)?;
+    dict.set_item("path_mtu", snapshot.path_mtu)?;
+    dict.set_item("peak_pps", snapshot.peak_pps)?;
+    dict.set_item("p50_pps", snapshot.p50_pps)?;
+    dict.set_item("p95_pps", snapshot.p95_pps)?;
+    dict.set_item("p99_pps", snapshot.p99_pps)?;
+    dict.set_item(
+        "payload_error",
+        engine.payload_error().map(|e| e.to_string()),
+    )?;
+
+    let breakdown = pyo3::types::PyDict::new(py);
+    for (kind, count) in engine.error_breakdown() {
+        breakdown.set_item(kind.as_str(), count)?;
+    }
+    dict.set_item("error_breakdown", breakdown)?;
+
+    // Empty for a single target
+    let (healthy, ejected): (Vec<_>, Vec<_>) = engine
+        .destination_stats()
+        .into_iter()
+        .partition(|destination| destination.healthy);
+    let addresses =
+        |list: Vec<DestinationStats>| list.iter().map(|d| d.addr.to_string()).collect::<Vec<_>>();
+    dict.set_item("healthy_destinations", addresses(healthy))?;
+    dict.set_item("ejected_destinations", addresses(ejected))?;
+
+    PyStatsSnapshot::wrap(py, &snapshot, dict)
+}
+
+/// Stats as attributes, returned by `get_stats` and `start_flood`
+///
+/// The full set of keys that used to come back as a dict is still there
+/// through `to_dict()`, item access and `get`.
+#[pyclass(name = "StatsSnapshot", frozen)]
+pub struct PyStatsSnapshot {
+    #[pyo3(get)]
+    packets_sent: u64,
+    #[pyo3(get)]
+    bytes_sent: u64,
+    #[pyo3(get)]
+    pps: u64,
+    #[pyo3(get)]
+    bps: u64,
+    #[pyo3(get)]
+    errors: u64,
+    #[pyo3(get)]
+    duration_secs: f64,
+    fields: Py<pyo3::types::PyDict>,
+}
+
+impl PyStatsSnapshot {
+    fn wrap(
+        py: Python<'_>,
+        snapshot: &stats::StatsSnapshot,
+        fields: Bound<'_, pyo3::types::PyDict>,
+    ) -> PyResult<PyObject> {
+        let object = Self {
+            packets_sent: snapshot.packets_sent,
+            bytes_sent: snapshot.bytes_sent,
+            pps: snapshot.pps,
+            bps: snapshot.bps,
+            errors: snapshot.errors,
+            duration_secs: snapshot.duration.as_secs_f64(),
+            fields: fields.unbind(),
+        };
+        Ok(Py::new(py, object)?.into_any())
+    }
+}
+
+#[pymethods]
+impl PyStatsSnapshot {
+    /// Every field as a new dict
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(self.fields.bind(py).copy()?.into_any().unbind())
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    fn get(
+        &self,
+        py: Python<'_>,
+        key: &str,
+        default: Option<PyObject>,
+    ) -> PyResult<Option<PyObject>> {
+        Ok(self
+            .fields
+            .bind(py)
+            .get_item(key)?
+            .map(Bound::unbind)
+            .or(default))
+    }
+
+    fn __getitem__(&self, py: Python<'_>, key: &str) -> PyResult<PyObject> {
+        self.fields
+            .bind(py)
+            .get_item(key)?
+            .map(Bound::unbind)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __contains__(&self, py: Python<'_>, key: &str) -> PyResult<bool> {
+        self.fields.bind(py).contains(key)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "StatsSnapshot(packets_sent={}, bytes_sent={}, pps={}, bps={}, errors={}, \
+             duration_secs={:.3})",
+            self.packets_sent, self.bytes_sent, self.pps, self.bps, self.errors, self.duration_secs
+        )
+    }
+}
+
+/// Thread behind `PacketEngine.on_stats`
+struct StatsTimer {
+    cancel: Arc<(parking_lot::Mutex<bool>, parking_lot::Condvar)>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+impl StatsTimer {
+    /// Call `callback` every `interval` while `state` is Running, until
+    /// cancelled or the engine stops
+    fn spawn(
+        engine: Arc<RwLock<FloodEngine>>,
+        state: EngineStateHandle,
+        callback: PyObject,
+        interval: Duration,
+    ) -> PyResult<Self> {
+        let cancel = Arc::new((parking_lot::Mutex::new(false), parking_lot::Condvar::new()));
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            std::thread::Builder::new()
+                .name("stats-callback".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    let mut next = Instant::now() + interval;
+                    loop {
+                        {
+                            let mut guard = cancelled.lock();
+                            if !*guard {
+                                wake.wait_until(&mut guard, next);
+                            }
+                            if *guard {
+                                return;
+                            }
+                        }
+                        // A slow callback skips ticks instead of firing a burst
+                        next = (next + interval).max(Instant::now());
+                        match state.get() {
+                            EngineState::Idle => continue,
+                            EngineState::Stopping | EngineState::Stopped => return,
+                            EngineState::Running => {}
+                        }
+                        Python::with_gil(|py| {
+                            // Cleared while this tick waited for the GIL
+                            if *cancelled.lock() {
+                                return;
+                            }
+                            let result = engine_stats(py, &engine.read())
+                                .and_then(|stats| callback.call1(py, (stats,)));
+                            if let Err(e) = result {
+                                tracing::warn!("on_stats callback raised: {}", e);
+                            }
+                        });
+                    }
+                })
+                .map_err(|e| PyRuntimeError::new_err(format!("Failed to start timer: {}", e)))?
+        };
+        Ok(Self { cancel, handle })
+    }
+
+    fn signal(&self) {
+        let (cancelled, wake) = &*self.cancel;
+        *cancelled.lock() = true;
+        wake.notify_all();
+    }
+
+    /// Stop the thread, joining without the GIL a tick may be waiting for
+    fn cancel(self, py: Python<'_>) {
+        self.signal();
+        py.allow_threads(|| {
+            let _ = self.handle.join();
+        });
+    }
+}
+
+impl Drop for PacketEngine {
+    /// Garbage-collecting a running engine stops it instead of leaking its threads
+    fn drop(&mut self) {
+        // The timer holds its own reference to the engine; let it exit on its own
+        if let Some(timer) = self.stats_timer.get_mut().take() {
+            timer.signal();
+        }
+        let mut engine = self.engine.write();
+        if engine.is_running() {
+            let _ = engine.stop();
+        }
+    }
+}
+
+fn parse_ramp_curve(curve: &str) -> PyResult<RampCurve> {
+    match curve.to_lowercase().as_str() {
+        "linear" => Ok(RampCurve::Linear),
+        "exponential" => Ok(RampCurve::Exponential),
+        _ => Err(PyRuntimeError::new_err(format!(
+            "Unknown ramp curve: {}",
+            curve
+        ))),
+    }
+}
+
+fn parse_port_mode(mode: &str) -> PyResult<PortMode> {
+    match mode.to_lowercase().as_str() {
+        "sequential" => Ok(PortMode::Sequential),
+        "random" => Ok(PortMode::Random),
+        _ => Err(PyRuntimeError::new_err(format!(
+            "Unknown port mode: {}",
+            mode
+        ))),
+    }
+}
+
+fn parse_protocol(protocol: &str) -> PyResult<Protocol> {
+    match protocol.to_lowercase().as_str() {
+        "udp" => Ok(Protocol::UDP),
+        "tcp" => Ok(Protocol::TCP),
+        "icmp" => Ok(Protocol::ICMP),
+        "http" => Ok(Protocol::HTTP),
+        _ => Err(PyRuntimeError::new_err(format!(
+            "Unknown protocol: {}",
+            protocol
+        ))),
+    }
+}
+
+/// Estimate engine memory (bytes) for a configuration before creating it
+#[pyfunction]
+#[pyo3(signature = (threads=4, packet_size=1472, protocol="udp", sockets_per_thread=None))]
+fn estimate_memory(
+    threads: usize,
+    packet_size: usize,
+    protocol: &str,
+    sockets_per_thread: Option<usize>,
+) -> PyResult<usize> {
+    let defaults = EngineConfig::default();
+    let config = EngineConfig {
+        threads,
+        packet_size,
+        protocol: parse_protocol(protocol)?,
+        sockets_per_thread: sockets_per_thread.unwrap_or(defaults.sockets_per_thread),
+        ..defaults
+    };
+    Ok(config.estimate_memory())
+}
+
+/// Call a Python payload callable for indices 0..ring up front
+///
+/// Workers cycle through the resulting payloads, so the GIL is never taken
+/// on the send path.
+fn materialize_payloads(callable: &PyObject, ring: usize) -> PyResult<PayloadFactory> {
+    if ring == 0 {
+        return Err(PyRuntimeError::new_err("payload_ring must be at least 1"));
+    }
+    let payloads: Vec<Vec<u8>> = Python::with_gil(|py| {
+        (0..ring)
+            .map(|i| callable.call1(py, (i,))?.extract::<Vec<u8>>(py))
+            .collect::<PyResult<_>>()
+    })?;
+    Ok(PayloadFactory::new(move |i| {
+        payloads[i % payloads.len()].clone()
+    }))
+}
+
 /// High-level flood function exposed to Python
 #[pyfunction]
-#[pyo3(signature = (target, port, duration=60, rate=100000, threads=4, packet_size=1472, protocol="udp"))]
+#[pyo3(signature = (target, port, duration=60, rate=100000, threads=4, packet_size=1472, protocol="udp", tcp_fastopen=false))]
+#[allow(clippy::too_many_arguments)] // mirrors the Python keyword arguments
 fn start_flood(
     target: &str,
     port: u16,
@@ -189,19 +1087,9 @@ fn start_flood(
     threads: usize,
     packet_size: usize,
     protocol: &str,
+    tcp_fastopen: bool,
 ) -> PyResult<PyObject> {
-    let proto = match protocol.to_lowercase().as_str() {
-        "udp" => Protocol::UDP,
-        "tcp" => Protocol::TCP,
-        "icmp" => Protocol::ICMP,
-        "http" => Protocol::HTTP,
-        _ => {
-            return Err(PyRuntimeError::new_err(format!(
-                "Unknown protocol: {}",
-                protocol
-            )))
-        }
-    };
+    let proto = parse_protocol(protocol)?;
 
     let config = EngineConfig {
         target: target.to_string(),
@@ -210,26 +1098,153 @@ fn start_flood(
         packet_size,
         protocol: proto,
         rate_limit: Some(rate),
+        tcp_fastopen,
         ..Default::default()
     };
 
-    let mut engine = FloodEngine::new(config)
-        .map_err(|e| PyRuntimeError::new_err(format!("Failed to create engine: {}", e)))?;
+    let mut engine =
+        FloodEngine::new(config).map_err(|e| engine_error("Failed to create engine", e))?;
 
     engine
         .start()
-        .map_err(|e| PyRuntimeError::new_err(format!("Failed to start: {}", e)))?;
+        .map_err(|e| engine_error("Failed to start", e))?;
 
     // Run for specified duration
     std::thread::sleep(Duration::from_secs(duration));
 
     engine
         .stop()
-        .map_err(|e| PyRuntimeError::new_err(format!("Failed to stop: {}", e)))?;
+        .map_err(|e| engine_error("Failed to stop", e))?;
 
-    // Return final stats
-    Python::with_gil(|py| {
-        let snapshot = engine.get_stats();
+    flood_result(&engine)
+}
+
+/// Start a flood and return at once with a `PyFloodHandle` to poll or stop it
+///
+/// The engine stops itself after `duration` seconds.
+#[pyfunction]
+#[pyo3(signature = (target, port, duration=60, rate=100000, threads=4, packet_size=1472, protocol="udp", tcp_fastopen=false))]
+#[allow(clippy::too_many_arguments)] // mirrors the Python keyword arguments
+fn start_flood_async(
+    target: &str,
+    port: u16,
+    duration: u64,
+    rate: u64,
+    threads: usize,
+    packet_size: usize,
+    protocol: &str,
+    tcp_fastopen: bool,
+) -> PyResult<PyFloodHandle> {
+    let config = EngineConfig {
+        target: target.to_string(),
+        port,
+        threads,
+        packet_size,
+        protocol: parse_protocol(protocol)?,
+        rate_limit: Some(rate),
+        tcp_fastopen,
+        duration: Some(Duration::from_secs(duration)),
+        ..Default::default()
+    };
+
+    let mut engine =
+        FloodEngine::new(config).map_err(|e| engine_error("Failed to create engine", e))?;
+    engine
+        .start()
+        .map_err(|e| engine_error("Failed to start", e))?;
+
+    Ok(PyFloodHandle {
+        engine: parking_lot::Mutex::new(engine),
+    })
+}
+
+/// Handle to a flood started by `start_flood_async`
+#[pyclass]
+pub struct PyFloodHandle {
+    engine: parking_lot::Mutex<FloodEngine>,
+}
+
+#[pymethods]
+impl PyFloodHandle {
+    /// Stats so far, in the same shape as `start_flood`'s result plus `running`
+    fn poll_stats(&self, py: Python<'_>) -> PyResult<PyObject> {
+        // Flushing and a concurrent `stop` both block; wait for them without the GIL
+        let (totals, running) = py.allow_threads(|| {
+            let engine = self.engine.lock();
+            engine.flush_stats();
+            (FloodTotals::of(&engine), engine.is_running())
+        });
+        let dict = totals.to_dict(py)?;
+        dict.set_item("running", running)?;
+        Ok(dict.into())
+    }
+
+    /// Whether the flood is still sending
+    fn is_running(&self) -> bool {
+        self.engine.lock().is_running()
+    }
+
+    /// Stop the flood early and return the final stats; after the duration
+    /// has passed it just returns them
+    fn stop(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let totals = py.allow_threads(|| {
+            let mut engine = self.engine.lock();
+            match engine.stop() {
+                Ok(()) | Err(EngineError::NotRunning) => Ok(FloodTotals::of(&engine)),
+                Err(e) => Err(e),
+            }
+        });
+        totals
+            .map_err(|e| engine_error("Failed to stop", e))?
+            .to_snapshot(py)
+    }
+}
+
+/// Run a saved profile to completion and return the final stats
+#[pyfunction]
+fn run_profile(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let mut engine =
+        FloodEngine::from_profile(path).map_err(|e| engine_error("Failed to create engine", e))?;
+    let duration = engine
+        .config()
+        .duration
+        .ok_or_else(|| PyRuntimeError::new_err("Profile does not set a duration"))?;
+
+    // Other Python threads keep running for the length of the profile
+    py.allow_threads(|| {
+        engine
+            .start()
+            .map_err(|e| engine_error("Failed to start", e))?;
+        std::thread::sleep(duration);
+        engine.stop().map_err(|e| engine_error("Failed to stop", e))
+    })?;
+
+    flood_result(&engine)
+}
+
+/// Final stats of a completed run as a `StatsSnapshot`
+fn flood_result(engine: &FloodEngine) -> PyResult<PyObject> {
+    Python::with_gil(|py| FloodTotals::of(engine).to_snapshot(py))
+}
+
+/// Counters reported for a flood, read without holding the GIL
+struct FloodTotals {
+    snapshot: stats::StatsSnapshot,
+    tfo_connections: u64,
+    tfo_negotiated: u64,
+}
+
+impl FloodTotals {
+    fn of(engine: &FloodEngine) -> Self {
+        Self {
+            snapshot: engine.get_stats(),
+            tfo_connections: engine.get_tfo_connections(),
+            tfo_negotiated: engine.get_tfo_negotiated(),
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, pyo3::types::PyDict>> {
+        let snapshot = &self.snapshot;
         let dict = pyo3::types::PyDict::new_bound(py);
         dict.set_item("packets_sent", snapshot.packets_sent)?;
         dict.set_item("bytes_sent", snapshot.bytes_sent)?;
@@ -237,13 +1252,118 @@ fn start_flood(
         dict.set_item("average_bps", snapshot.bps)?;
         dict.set_item("errors", snapshot.errors)?;
         dict.set_item("duration_secs", snapshot.duration.as_secs_f64())?;
-        Ok(dict.into())
-    })
+        dict.set_item("tfo_connections", self.tfo_connections)?;
+        dict.set_item("tfo_negotiated", self.tfo_negotiated)?;
+        dict.set_item("path_mtu", snapshot.path_mtu)?;
+        dict.set_item("oversized_errors", snapshot.oversized_errors)?;
+        Ok(dict)
+    }
+
+    fn to_snapshot(&self, py: Python<'_>) -> PyResult<PyObject> {
+        PyStatsSnapshot::wrap(py, &self.snapshot, self.to_dict(py)?)
+    }
+}
+
+/// Application protocols `build_payload` knows, with their transport and
+/// their required and optional parameters
+const APP_PROTOCOLS: &[(&str, Protocol, &[&str], &[&str])] = &[
+    ("http", Protocol::TCP, &["host"], &["path"]),
+    ("http_post", Protocol::TCP, &["host"], &["path", "body"]),
+    ("http2", Protocol::TCP, &["authority"], &["path"]),
+    ("tls", Protocol::TCP, &["server_name"], &[]),
+    ("dns", Protocol::UDP, &["domain"], &[]),
+];
+
+/// Application-layer bytes for `protocol`, built from the keys of `params`
+///
+/// Missing required keys and keys the protocol does not take are errors.
+fn app_payload(
+    protocol: &str,
+    params: Option<&Bound<'_, pyo3::types::PyDict>>,
+) -> PyResult<Vec<u8>> {
+    let (name, _, required, optional) = APP_PROTOCOLS
+        .iter()
+        .find(|p| p.0 == protocol)
+        .ok_or_else(|| PyRuntimeError::new_err(format!("Unknown protocol: {}", protocol)))?;
+
+    if let Some(params) = params {
+        for key in params.keys() {
+            let key: String = key.extract()?;
+            if !required.contains(&key.as_str()) && !optional.contains(&key.as_str()) {
+                return Err(PyRuntimeError::new_err(format!(
+                    "{} payload does not take '{}'",
+                    name, key
+                )));
+            }
+        }
+    }
+    let param = |key: &str| {
+        params
+            .map(|p| p.get_item(key))
+            .transpose()
+            .map(Option::flatten)
+    };
+    for key in required.iter() {
+        if param(key)?.is_none() {
+            return Err(PyRuntimeError::new_err(format!(
+                "{} payload requires '{}'",
+                name, key
+            )));
+        }
+    }
+    let str_param = |key: &str, default: &str| -> PyResult<String> {
+        match param(key)? {
+            Some(value) => value.extract(),
+            None => Ok(default.to_string()),
+        }
+    };
+
+    let built = match *name {
+        "http" => Ok(protocol_builder::http_get_payload(
+            &str_param("host", "")?,
+            &str_param("path", "/")?,
+        )),
+        "http_post" => {
+            let body: Vec<u8> = match param("body")? {
+                Some(value) => value.extract()?,
+                None => Vec::new(),
+            };
+            Ok(protocol_builder::http_post_payload(
+                &str_param("host", "")?,
+                &str_param("path", "/")?,
+                &body,
+            ))
+        }
+        "http2" => {
+            protocol_builder::http2_preface(&str_param("authority", "")?, &str_param("path", "/")?)
+        }
+        "tls" => protocol_builder::tls_client_hello(&str_param("server_name", "")?),
+        _ => protocol_builder::dns_query_payload(&str_param("domain", "")?),
+    };
+    built.map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
+}
+
+/// Build only the application-layer payload of `protocol`
+///
+/// `params` holds the protocol's fields: `host` (and optionally `path`, plus
+/// `body` for http_post) for http, `authority` for http2, `server_name` for
+/// tls and `domain` for dns.
+#[pyfunction]
+#[pyo3(signature = (protocol, params=None))]
+fn build_payload(
+    protocol: &str,
+    params: Option<&Bound<'_, pyo3::types::PyDict>>,
+) -> PyResult<Vec<u8>> {
+    app_payload(&protocol.to_lowercase(), params)
 }
 
 /// Build a custom packet
+///
+/// Application protocols (see `build_payload`) take their fields from
+/// `params` and are carried over their transport, TCP or UDP.
 #[pyfunction]
-#[pyo3(signature = (src_ip, dst_ip, src_port, dst_port, protocol="udp", payload=None))]
+#[pyo3(signature = (src_ip, dst_ip, src_port, dst_port, protocol="udp", payload=None, params=None))]
+#[allow(clippy::too_many_arguments)] // mirrors the Python keyword arguments
 fn build_packet(
     src_ip: &str,
     dst_ip: &str,
@@ -251,16 +1371,28 @@ fn build_packet(
     dst_port: u16,
     protocol: &str,
     payload: Option<&[u8]>,
+    params: Option<&Bound<'_, pyo3::types::PyDict>>,
 ) -> PyResult<Vec<u8>> {
-    let proto = match protocol.to_lowercase().as_str() {
-        "udp" => Protocol::UDP,
-        "tcp" => Protocol::TCP,
-        "icmp" => Protocol::ICMP,
+    let protocol = protocol.to_lowercase();
+    let (proto, app) = match protocol.as_str() {
+        "udp" => (Protocol::UDP, None),
+        "tcp" => (Protocol::TCP, None),
+        "icmp" => (Protocol::ICMP, None),
         _ => {
-            return Err(PyRuntimeError::new_err(format!(
-                "Unknown protocol: {}",
-                protocol
-            )))
+            let transport = APP_PROTOCOLS
+                .iter()
+                .find(|p| p.0 == protocol)
+                .map(|p| p.1)
+                .ok_or_else(|| {
+                    PyRuntimeError::new_err(format!("Unknown protocol: {}", protocol))
+                })?;
+            if payload.is_some() {
+                return Err(PyRuntimeError::new_err(format!(
+                    "{} packets build their payload from params",
+                    protocol
+                )));
+            }
+            (transport, Some(app_payload(&protocol, params)?))
         }
     };
 
@@ -271,10 +1403,9 @@ fn build_packet(
         .dst_port(dst_port)
         .protocol(proto);
 
-    let builder = if let Some(data) = payload {
-        builder.payload(data)
-    } else {
-        builder
+    let builder = match (app.as_deref(), payload) {
+        (Some(data), _) | (None, Some(data)) => builder.payload(data),
+        (None, None) => builder,
     };
 
     builder
@@ -312,10 +1443,18 @@ fn get_capabilities() -> PyResult<PyObject> {
                 .unwrap_or(1),
         )?;
 
+        dict.set_item("raw_socket_privilege", backend::has_raw_socket_privilege())?;
+
         Ok(dict.into())
     })
 }
 
+/// Check whether raw sockets (ICMP/raw modes) can be opened by this process
+#[pyfunction]
+fn has_raw_socket_privilege() -> bool {
+    backend::has_raw_socket_privilege()
+}
+
 /// Get current statistics snapshot
 #[pyfunction]
 fn get_stats() -> PyResult<PyObject> {
@@ -350,6 +1489,37 @@ fn build_udp_packet(
         .map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
 }
 
+/// Build a UDP datagram and split it into IP fragments
+#[pyfunction]
+#[pyo3(signature = (dst_ip, dst_port, payload, fragment_size=576, spoof_cidr=None))]
+fn build_udp_fragmented(
+    dst_ip: &str,
+    dst_port: u16,
+    payload: &[u8],
+    fragment_size: u16,
+    spoof_cidr: Option<&str>,
+) -> PyResult<Vec<Vec<u8>>> {
+    let mut builder = protocol_builder::ProtocolBuilder::new();
+
+    if let Some(cidr) = spoof_cidr {
+        builder = builder
+            .with_spoofing(cidr)
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid CIDR: {}", e)))?;
+    }
+
+    let config = FragmentConfig {
+        enabled: true,
+        fragment_size,
+        ..Default::default()
+    };
+    builder
+        .build_udp(dst_ip, dst_port, payload)
+        .and_then(|datagram| {
+            protocol_builder::ProtocolBuilder::build_fragmented(&datagram, &config)
+        })
+        .map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
+}
+
 /// Build TCP SYN packet with optional spoofing
 #[pyfunction]
 #[pyo3(signature = (dst_ip, dst_port, spoof_cidr=None))]
@@ -424,6 +1594,52 @@ fn build_dns_query(dst_ip: &str, domain: &str, spoof_cidr: Option<&str>) -> PyRe
         .map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
 }
 
+/// Wrap an IP packet in VXLAN over UDP/IPv4 with optional outer spoofing
+#[pyfunction]
+#[pyo3(signature = (inner, vni, outer_src, outer_dst, spoof_cidr=None))]
+fn encapsulate_vxlan(
+    inner: &[u8],
+    vni: u32,
+    outer_src: &str,
+    outer_dst: &str,
+    spoof_cidr: Option<&str>,
+) -> PyResult<Vec<u8>> {
+    let mut builder = protocol_builder::ProtocolBuilder::new();
+
+    if let Some(cidr) = spoof_cidr {
+        builder = builder
+            .with_spoofing(cidr)
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid CIDR: {}", e)))?;
+    }
+
+    builder
+        .encapsulate_vxlan(inner, vni, outer_src, outer_dst)
+        .map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
+}
+
+/// Wrap a packet in GRE over IPv4 with optional outer spoofing
+#[pyfunction]
+#[pyo3(signature = (inner, protocol_type, outer_src, outer_dst, spoof_cidr=None))]
+fn encapsulate_gre(
+    inner: &[u8],
+    protocol_type: u16,
+    outer_src: &str,
+    outer_dst: &str,
+    spoof_cidr: Option<&str>,
+) -> PyResult<Vec<u8>> {
+    let mut builder = protocol_builder::ProtocolBuilder::new();
+
+    if let Some(cidr) = spoof_cidr {
+        builder = builder
+            .with_spoofing(cidr)
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid CIDR: {}", e)))?;
+    }
+
+    builder
+        .encapsulate_gre(inner, protocol_type, outer_src, outer_dst)
+        .map_err(|e| PyRuntimeError::new_err(format!("Build failed: {}", e)))
+}
+
 /// Generate batch of packets for high-throughput scenarios
 #[pyfunction]
 #[pyo3(signature = (dst_ip, dst_port, protocol, payload_size, count, spoof_cidr=None))]
@@ -460,6 +1676,57 @@ fn generate_packet_batch(
     Ok(gen.generate_batch(count))
 }
 
+/// Buffers at least this large are summed or filled without holding the GIL
+const GIL_RELEASE_BYTES: usize = 64 * 1024;
+
+/// Run `f` with the GIL released if it touches `len` bytes or more
+fn without_gil_if_large<T, F>(py: Python<'_>, len: usize, f: F) -> T
+where
+    F: pyo3::marker::Ungil + FnOnce() -> T,
+    T: pyo3::marker::Ungil,
+{
+    if len >= GIL_RELEASE_BYTES {
+        py.allow_threads(f)
+    } else {
+        f()
+    }
+}
+
+/// Internet checksum (RFC 1071) of `data`; 0xFFFF for empty input
+#[pyfunction]
+fn checksum(py: Python<'_>, data: &[u8]) -> u16 {
+    without_gil_if_large(py, data.len(), || simd::checksum_simd(data))
+}
+
+/// Checksum over an even-length pseudo-header followed by `payload`
+///
+/// Pass the 12-byte IPv4 or 40-byte IPv6 pseudo-header and the UDP or TCP
+/// segment (with its checksum field zeroed) to get the transport checksum.
+#[pyfunction]
+fn ip_checksum_with_pseudo(py: Python<'_>, header: &[u8], payload: &[u8]) -> PyResult<u16> {
+    if !header.len().is_multiple_of(2) {
+        return Err(PyRuntimeError::new_err(format!(
+            "Pseudo-header must be an even number of bytes, got {}",
+            header.len()
+        )));
+    }
+    Ok(without_gil_if_large(
+        py,
+        header.len() + payload.len(),
+        || simd::checksum_simd_with_pseudo(header, payload),
+    ))
+}
+
+/// `size` bytes, each set to `pattern`
+#[pyfunction]
+fn fill_payload(py: Python<'_>, size: usize, pattern: u8) -> Vec<u8> {
+    without_gil_if_large(py, size, || {
+        let mut payload = vec![0u8; size];
+        simd::fill_payload_simd(&mut payload, pattern);
+        payload
+    })
+}
+
 /// Get detailed capability report
 #[pyfunction]
 fn get_capability_report() -> PyResult<PyObject> {
@@ -494,18 +1761,64 @@ fn get_available_backends() -> PyResult<Vec<String>> {
         .collect())
 }
 
-/// Get real-time statistics in JSON format
+/// Benchmark every available backend and return the fastest one's name along
+/// with the measured packets per second (`None` for backends that failed)
+#[pyfunction]
+#[pyo3(signature = (probe_packets=10000))]
+fn select_fastest_backend(
+    probe_packets: usize,
+) -> PyResult<(String, std::collections::HashMap<String, Option<f64>>)> {
+    let selector = backend_selector::BackendSelector::new();
+    let result = selector
+        .benchmark_and_select(probe_packets)
+        .map_err(|e| exceptions::BackendError::new_err(format!("Benchmark failed: {}", e)))?;
+    let rates = result
+        .rates
+        .iter()
+        .map(|(backend, rate)| (backend.name().to_string(), *rate))
+        .collect();
+    Ok((result.selected.name().to_string(), rates))
+}
+
+/// Get real-time statistics of every live engine as JSON keyed by `host:port`
 #[pyfunction]
 fn get_realtime_stats_json() -> PyResult<String> {
-    let collector = atomic_stats::StatsCollector::new();
-    Ok(collector.json_metrics())
+    Ok(CollectorRegistry::global().json_metrics())
 }
 
-/// Get Prometheus-format metrics
+/// Get Prometheus-format metrics for every live engine, labelled by target
 #[pyfunction]
 fn get_prometheus_metrics() -> PyResult<String> {
-    let collector = atomic_stats::StatsCollector::new();
-    Ok(collector.prometheus_metrics())
+    Ok(CollectorRegistry::global().prometheus_metrics())
+}
+
+/// `host:port` label for an engine's metrics, bracketing IPv6 literals
+fn metrics_label(target: &str, port: u16) -> String {
+    if target.contains(':') && !target.starts_with('[') {
+        format!("[{}]:{}", target, port)
+    } else {
+        format!("{}:{}", target, port)
+    }
+}
+
+/// Convert structured recommendations into a list of dicts
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+fn recommendations_to_py(
+    py: Python<'_>,
+    recommendations: &[backend::Recommendation],
+) -> PyResult<PyObject> {
+    let list = pyo3::types::PyList::empty(py);
+    for rec in recommendations {
+        let dict = pyo3::types::PyDict::new(py);
+        dict.set_item("id", &rec.id)?;
+        dict.set_item("severity", rec.severity.name())?;
+        dict.set_item("message", &rec.message)?;
+        dict.set_item("current_value", &rec.current_value)?;
+        dict.set_item("suggested_value", &rec.suggested_value)?;
+        dict.set_item("sysctl_or_action", &rec.sysctl_or_action)?;
+        list.append(dict)?;
+    }
+    Ok(list.into())
 }
 
 /// Get Linux optimization report (Linux only)
@@ -531,6 +1844,7 @@ fn get_linux_optimization_report() -> PyResult<PyObject> {
         dict.set_item("has_io_uring", caps.has_io_uring)?;
         dict.set_item("has_sendmmsg", caps.has_sendmmsg)?;
         dict.set_item("has_raw_socket", caps.has_raw_socket)?;
+        dict.set_item("has_raw_socket_privilege", caps.has_raw_socket_privilege)?;
 
         // Enabled features
         dict.set_item("enabled_features", optimizer.enabled_features())?;
@@ -546,6 +1860,10 @@ fn get_linux_optimization_report() -> PyResult<PyObject> {
             "performance_recommendations",
             optimizer.get_performance_recommendations(),
         )?;
+        dict.set_item(
+            "recommendations",
+            recommendations_to_py(py, &optimizer.get_recommendations())?,
+        )?;
 
         Ok(dict.into())
     })
@@ -597,6 +1915,10 @@ fn get_windows_optimization_report() -> PyResult<PyObject> {
             "performance_recommendations",
             optimizer.get_performance_recommendations(),
         )?;
+        dict.set_item(
+            "recommendations",
+            recommendations_to_py(py, &optimizer.get_recommendations())?,
+        )?;
 
         // Windows version info
         let (major, minor) = optimizer.get_windows_version();
@@ -658,6 +1980,10 @@ fn get_macos_optimization_report() -> PyResult<PyObject> {
             "performance_recommendations",
             optimizer.get_performance_recommendations(),
         )?;
+        dict.set_item(
+            "recommendations",
+            recommendations_to_py(py, &optimizer.get_recommendations())?,
+        )?;
 
         // macOS version info
         let (major, minor) = optimizer.get_darwin_version();
@@ -683,10 +2009,190 @@ fn get_macos_optimization_report() -> PyResult<PyObject> {
     })
 }
 
+/// Deadline for an authorization lasting `ttl_secs`, `None` when permanent
+fn ttl_deadline(ttl_secs: Option<f64>) -> PyResult<Option<Instant>> {
+    ttl_secs
+        .map(|secs| {
+            Duration::try_from_secs_f64(secs)
+                .map(|ttl| Instant::now() + ttl)
+                .map_err(|_| PyRuntimeError::new_err(format!("Invalid ttl_secs: {}", secs)))
+        })
+        .transpose()
+}
+
+/// Python-exposed BackendSelector, keeping its fallback history across calls
+#[pyclass]
+pub struct PyBackendSelector {
+    inner: Arc<backend_selector::BackendSelector>,
+}
+
+#[pymethods]
+impl PyBackendSelector {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(backend_selector::BackendSelector::new()),
+        }
+    }
+
+    /// Name of the active backend
+    fn current_backend(&self) -> String {
+        self.inner.current_backend().name().to_string()
+    }
+
+    /// Names of the backends available on this system, in priority order
+    fn available_backends(&self) -> Vec<String> {
+        self.inner
+            .available_backends()
+            .iter()
+            .map(|b| b.name().to_string())
+            .collect()
+    }
+
+    /// Re-detect system capabilities, falling back if the active backend vanished
+    fn refresh_capabilities(&self) -> PyResult<()> {
+        self.inner.refresh_capabilities().map_err(PyErr::from)
+    }
+
+    /// Recent backend transitions as dicts with from, to, reason and timestamp
+    fn fallback_history(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.inner
+            .fallback_history()
+            .into_iter()
+            .map(|transition| {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("from", transition.from.name())?;
+                dict.set_item("to", transition.to.name())?;
+                dict.set_item("reason", transition.reason)?;
+                let timestamp = transition
+                    .at
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or(0.0);
+                dict.set_item("timestamp", timestamp)?;
+                Ok(dict.into())
+            })
+            .collect()
+    }
+}
+
+/// Python-exposed TokenBucket, for pacing work outside the engine
+#[pyclass]
+pub struct PyTokenBucket {
+    inner: rate_limiter::TokenBucket,
+}
+
+#[pymethods]
+impl PyTokenBucket {
+    /// `rate` tokens per second, holding up to `burst` (`rate` when 0)
+    #[new]
+    #[pyo3(signature = (rate, burst=0))]
+    fn new(rate: u64, burst: u64) -> Self {
+        Self {
+            inner: rate_limiter::TokenBucket::new(rate, burst),
+        }
+    }
+
+    /// Take `count` tokens if available; never blocks
+    #[pyo3(signature = (count=1))]
+    fn try_acquire(&self, count: u64) -> bool {
+        self.inner.try_acquire(count)
+    }
+
+    /// Take `count` tokens, waiting without the GIL; returns the seconds waited
+    #[pyo3(signature = (count=1))]
+    fn acquire(&self, py: Python<'_>, count: u64) -> PyResult<f64> {
+        // The bucket never holds more than `burst`, so this would wait forever
+        if self.inner.is_enabled() && count > self.inner.burst() {
+            return Err(PyRuntimeError::new_err(format!(
+                "Cannot acquire {} tokens from a bucket of {}",
+                count,
+                self.inner.burst()
+            )));
+        }
+        Ok(py.allow_threads(|| self.inner.acquire(count)).as_secs_f64())
+    }
+
+    /// Change the rate; 0 disables limiting
+    fn set_rate(&self, rate: u64) {
+        self.inner.set_rate(rate);
+    }
+
+    /// Whole tokens available now
+    fn available(&self) -> u64 {
+        self.inner.available()
+    }
+
+    /// Configured rate in tokens per second
+    fn current_rate(&self) -> u64 {
+        self.inner.rate()
+    }
+}
+
+/// Python-exposed SlidingWindowLimiter, for pacing work outside the engine
+#[pyclass]
+pub struct PySlidingWindowLimiter {
+    inner: rate_limiter::SlidingWindowLimiter,
+}
+
+#[pymethods]
+impl PySlidingWindowLimiter {
+    /// At most `rate_per_second` events, counted over the last `window_ms`
+    #[new]
+    #[pyo3(signature = (rate_per_second, window_ms=1000))]
+    fn new(rate_per_second: u64, window_ms: u64) -> PyResult<Self> {
+        check_window_rate(rate_per_second, window_ms.max(1))?;
+        Ok(Self {
+            inner: rate_limiter::SlidingWindowLimiter::new(rate_per_second, window_ms),
+        })
+    }
+
+    /// Record one event if the window has room; never blocks
+    fn try_acquire(&self) -> bool {
+        self.inner.try_record()
+    }
+
+    /// Record one event, waiting without the GIL; returns the seconds waited
+    fn acquire(&self, py: Python<'_>) -> f64 {
+        py.allow_threads(|| self.inner.acquire()).as_secs_f64()
+    }
+
+    /// Change the rate; 0 disables limiting
+    fn set_rate(&self, rate_per_second: u64) -> PyResult<()> {
+        check_window_rate(rate_per_second, self.inner.window_ms())?;
+        self.inner.set_rate(rate_per_second);
+        Ok(())
+    }
+
+    /// Events still allowed in the current window
+    fn available(&self) -> u64 {
+        self.inner.available()
+    }
+
+    /// Events per second over the window
+    fn current_rate(&self) -> u64 {
+        self.inner.current_rate()
+    }
+}
+
+/// Refuse a positive rate too low for one event per window, which would block
+/// `acquire` forever
+fn check_window_rate(rate_per_second: u64, window_ms: u64) -> PyResult<()> {
+    if rate_per_second > 0 && rate_per_second.saturating_mul(window_ms) / 1000 == 0 {
+        return Err(PyRuntimeError::new_err(format!(
+            "Rate {} per second allows no events in a {} ms window",
+            rate_per_second, window_ms
+        )));
+    }
+    Ok(())
+}
+
 /// Python-exposed SafetyController
 #[pyclass]
 pub struct PySafetyController {
-    inner: safety::SafetyController,
+    inner: Arc<safety::SafetyController>,
+    /// Active error-ratio watch, replaced by each `watch` call
+    watch: parking_lot::Mutex<Option<safety::SafetyWatch>>,
 }
 
 #[pymethods]
@@ -695,7 +2201,8 @@ impl PySafetyController {
     #[pyo3(signature = (max_pps=0))]
     fn new(max_pps: u64) -> Self {
         Self {
-            inner: safety::SafetyController::new(max_pps),
+            inner: Arc::new(safety::SafetyController::new(max_pps)),
+            watch: parking_lot::Mutex::new(None),
         }
     }
 
@@ -703,25 +2210,70 @@ impl PySafetyController {
     #[staticmethod]
     fn permissive() -> Self {
         Self {
-            inner: safety::SafetyController::permissive(),
+            inner: Arc::new(safety::SafetyController::permissive()),
+            watch: parking_lot::Mutex::new(None),
         }
     }
 
-    /// Authorize an IP address
-    fn authorize_ip(&self, ip: &str) -> PyResult<()> {
+    /// Authorize an IP address, for `ttl_secs` seconds when given
+    #[pyo3(signature = (ip, ttl_secs=None))]
+    fn authorize_ip(&self, ip: &str, ttl_secs: Option<f64>) -> PyResult<()> {
         let addr: std::net::IpAddr = ip
             .parse()
             .map_err(|_| PyRuntimeError::new_err(format!("Invalid IP: {}", ip)))?;
-        self.inner.authorization.authorize_ip(addr);
+        match ttl_deadline(ttl_secs)? {
+            Some(until) => self.inner.authorization.authorize_ip_until(addr, until),
+            None => self.inner.authorization.authorize_ip(addr),
+        }
         Ok(())
     }
 
-    /// Authorize a CIDR range
-    fn authorize_cidr(&self, cidr: &str) -> PyResult<()> {
+    /// Authorize a CIDR range, for `ttl_secs` seconds when given
+    #[pyo3(signature = (cidr, ttl_secs=None))]
+    fn authorize_cidr(&self, cidr: &str, ttl_secs: Option<f64>) -> PyResult<()> {
+        let result = match ttl_deadline(ttl_secs)? {
+            Some(until) => self.inner.authorization.authorize_cidr_until(cidr, until),
+            None => self.inner.authorization.authorize_cidr(cidr),
+        };
+        result.map_err(PyErr::from)
+    }
+
+    /// Remove an exact IP authorization; returns whether one existed
+    fn revoke_ip(&self, ip: &str) -> PyResult<bool> {
+        let addr: std::net::IpAddr = ip
+            .parse()
+            .map_err(|_| PyRuntimeError::new_err(format!("Invalid IP: {}", ip)))?;
+        Ok(self.inner.authorization.revoke_ip(addr))
+    }
+
+    /// Remove a CIDR authorization; returns whether one existed
+    fn revoke_cidr(&self, cidr: &str) -> PyResult<bool> {
+        self.inner
+            .authorization
+            .revoke_cidr(cidr)
+            .map_err(PyErr::from)
+    }
+
+    /// Block a CIDR range, overriding any authorization
+    fn deny_cidr(&self, cidr: &str) -> PyResult<()> {
         self.inner
             .authorization
-            .authorize_cidr(cidr)
-            .map_err(|e| PyRuntimeError::new_err(format!("{}", e)))
+            .deny_cidr(cidr)
+            .map_err(PyErr::from)
+    }
+
+    /// In strict mode, reject IPs whose reverse DNS falls under `suffixes`;
+    /// lookups slower than `timeout_secs` reject the target
+    #[pyo3(signature = (suffixes, timeout_secs=2.0))]
+    fn set_reverse_dns_check(&self, suffixes: Vec<String>, timeout_secs: f64) -> PyResult<()> {
+        let timeout = Duration::try_from_secs_f64(timeout_secs).map_err(|_| {
+            PyRuntimeError::new_err(format!("Invalid timeout_secs: {}", timeout_secs))
+        })?;
+        let suffixes: Vec<&str> = suffixes.iter().map(String::as_str).collect();
+        self.inner
+            .authorization
+            .set_reverse_dns_check(Arc::new(safety::SystemReverseDns::new(timeout)), &suffixes);
+        Ok(())
     }
 
     /// Authorize a domain
@@ -784,12 +2336,57 @@ impl PySafetyController {
 
     /// Perform all safety checks
     fn check_all(&self, target: &str) -> PyResult<()> {
-        self.inner
-            .check_all(target)
-            .map_err(|e| PyRuntimeError::new_err(format!("{}", e)))
+        self.inner.check_all(target).map_err(PyErr::from)
+    }
+
+    /// Emergency-stop when more than `error_ratio_threshold` of the engine's
+    /// sends fail over `window_secs`; replaces any previous watch
+    fn watch(
+        &self,
+        engine: PyRef<'_, PacketEngine>,
+        error_ratio_threshold: f64,
+        window_secs: f64,
+    ) -> PyResult<()> {
+        let window = Duration::try_from_secs_f64(window_secs).map_err(|_| {
+            PyRuntimeError::new_err(format!("Invalid window_secs: {}", window_secs))
+        })?;
+        let stats: Arc<dyn safety::CounterSource> = engine.collector.clone();
+        let watch = self
+            .inner
+            .watch(stats, error_ratio_threshold, window)
+            .map_err(PyErr::from)?;
+        *self.watch.lock() = Some(watch);
+        Ok(())
+    }
+
+    /// Stop the error-ratio watch
+    fn unwatch(&self) {
+        self.watch.lock().take();
     }
 }
 
+/// Audit encryption key from Python bytes
+fn audit_key(key: &[u8]) -> PyResult<[u8; 32]> {
+    key.try_into().map_err(|_| {
+        PyRuntimeError::new_err(format!("Audit key must be 32 bytes, got {}", key.len()))
+    })
+}
+
+/// Chain verification result as a Python dict
+fn chain_result_dict(result: audit::ChainVerificationResult) -> PyResult<PyObject> {
+    Python::with_gil(|py| {
+        let dict = pyo3::types::PyDict::new(py);
+        dict.set_item("valid", result.valid)?;
+        dict.set_item("entries_checked", result.entries_checked)?;
+        dict.set_item("first_invalid", result.first_invalid)?;
+        dict.set_item("error", result.error)?;
+        dict.set_item("break_type", result.break_type.map(|kind| kind.as_str()))?;
+        dict.set_item("expected_hash", result.expected_hash)?;
+        dict.set_item("actual_hash", result.actual_hash)?;
+        Ok(dict.into())
+    })
+}
+
 /// Python-exposed AuditLogger
 #[pyclass]
 pub struct PyAuditLogger {
@@ -806,15 +2403,128 @@ impl PyAuditLogger {
     }
 
     /// Create with file output
+    ///
+    /// With `queue_capacity`, writes happen on a background thread and log
+    /// calls return immediately; a full queue blocks unless `drop_when_full`.
+    #[staticmethod]
+    #[pyo3(signature = (path, queue_capacity=None, drop_when_full=false))]
+    fn with_file(
+        path: &str,
+        queue_capacity: Option<usize>,
+        drop_when_full: bool,
+    ) -> PyResult<Self> {
+        let map_err = |e: std::io::Error| {
+            PyRuntimeError::new_err(format!("Failed to create audit log: {}", e))
+        };
+        let mut logger = audit::AuditLogger::with_file(path).map_err(map_err)?;
+        if let Some(capacity) = queue_capacity {
+            let backpressure = if drop_when_full {
+                audit::AuditBackpressure::Drop
+            } else {
+                audit::AuditBackpressure::Block
+            };
+            logger = logger.into_async(capacity, backpressure).map_err(map_err)?;
+        }
+        Ok(Self {
+            inner: Arc::new(logger),
+        })
+    }
+
+    /// Wait for queued events to reach the file
+    fn flush(&self) -> PyResult<()> {
+        self.inner
+            .flush()
+            .map_err(|e| PyRuntimeError::new_err(format!("Failed to flush audit log: {}", e)))
+    }
+
+    /// Events dropped because the background queue was full
+    fn dropped_events(&self) -> u64 {
+        self.inner.dropped_events()
+    }
+
+    /// Create with file output encrypted per entry under a 32-byte key
     #[staticmethod]
-    fn with_file(path: &str) -> PyResult<Self> {
-        let logger = audit::AuditLogger::with_file(path)
+    fn with_encrypted_file(path: &str, key: &[u8]) -> PyResult<Self> {
+        let logger = audit::AuditLogger::with_encrypted_file(path, audit_key(key)?)
             .map_err(|e| PyRuntimeError::new_err(format!("Failed to create audit log: {}", e)))?;
         Ok(Self {
             inner: Arc::new(logger),
         })
     }
 
+    /// Decrypt the encrypted file: dict with json, recovered and complete
+    fn decrypt_export(&self, key: &[u8]) -> PyResult<PyObject> {
+        let export = self
+            .inner
+            .decrypt_export(&audit_key(key)?)
+            .map_err(|e| PyRuntimeError::new_err(format!("Failed to decrypt audit log: {}", e)))?;
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new(py);
+            dict.set_item("json", export.json)?;
+            dict.set_item("recovered", export.recovered)?;
+            dict.set_item("complete", export.complete)?;
+            Ok(dict.into())
+        })
+    }
+
+    /// Create with RFC 5424 syslog output, e.g. `("local4", "tcp://siem:6514")`
+    #[staticmethod]
+    fn with_syslog(facility: &str, remote_addr: &str) -> PyResult<Self> {
+        let facility = audit::SyslogFacility::from_name(facility).ok_or_else(|| {
+            PyRuntimeError::new_err(format!("Unknown syslog facility: {}", facility))
+        })?;
+        let logger = audit::AuditLogger::with_syslog(facility, remote_addr)
+            .map_err(|e| PyRuntimeError::new_err(format!("Failed to create audit log: {}", e)))?;
+        Ok(Self {
+            inner: Arc::new(logger),
+        })
+    }
+
+    /// Create with output to the systemd journal (Linux only)
+    #[staticmethod]
+    fn with_journald() -> PyResult<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let logger = audit::AuditLogger::with_journald().map_err(|e| {
+                PyRuntimeError::new_err(format!("Failed to create audit log: {}", e))
+            })?;
+            Ok(Self {
+                inner: Arc::new(logger),
+            })
+        }
+        #[cfg(not(target_os = "linux"))]
+        Err(PyRuntimeError::new_err(
+            "journald is only available on Linux",
+        ))
+    }
+
+    /// Entries the syslog/journald sink had to drop
+    fn remote_dropped(&self) -> u64 {
+        self.inner.remote_dropped()
+    }
+
+    /// Create with segmented file output rotated by size (bytes) or age (seconds)
+    #[staticmethod]
+    fn with_rotating_file(path: &str, max_size: u64, max_age_secs: u64) -> PyResult<Self> {
+        let logger = audit::AuditLogger::with_rotating_file(
+            path,
+            max_size,
+            Duration::from_secs(max_age_secs),
+        )
+        .map_err(|e| PyRuntimeError::new_err(format!("Failed to create audit log: {}", e)))?;
+        Ok(Self {
+            inner: Arc::new(logger),
+        })
+    }
+
+    /// Verify the hash chain across all rotated segments in a directory
+    #[staticmethod]
+    fn verify_all_segments(dir: &str) -> PyResult<PyObject> {
+        let result = audit::verify_all_segments(dir)
+            .map_err(|e| PyRuntimeError::new_err(format!("Failed to read segments: {}", e)))?;
+        chain_result_dict(result)
+    }
+
     /// Log engine start
     fn log_engine_start(&self, target: &str, config: &str) {
         self.inner.log_engine_start(target, config);
@@ -847,15 +2557,12 @@ impl PyAuditLogger {
 
     /// Verify chain integrity
     fn verify_chain(&self) -> PyResult<PyObject> {
-        Python::with_gil(|py| {
-            let result = self.inner.verify_chain();
-            let dict = pyo3::types::PyDict::new_bound(py);
-            dict.set_item("valid", result.valid)?;
-            dict.set_item("entries_checked", result.entries_checked)?;
-            dict.set_item("first_invalid", result.first_invalid)?;
-            dict.set_item("error", result.error)?;
-            Ok(dict.into())
-        })
+        chain_result_dict(self.inner.verify_chain())
+    }
+
+    /// Verify entries with sequence numbers from `start` to `end` inclusive
+    fn verify_range(&self, start: u64, end: u64) -> PyResult<PyObject> {
+        chain_result_dict(self.inner.verify_range(start, end))
     }
 
     /// Export to JSON
@@ -875,25 +2582,44 @@ fn netstress_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Core classes
     m.add_class::<PacketEngine>()?;
     m.add_class::<PySafetyController>()?;
+    m.add_class::<PyTokenBucket>()?;
+    m.add_class::<PySlidingWindowLimiter>()?;
     m.add_class::<PyAuditLogger>()?;
+    m.add_class::<PyBackendSelector>()?;
+    m.add_class::<PyCampaign>()?;
+    m.add_class::<PyFloodHandle>()?;
+    m.add_class::<PyStatsSnapshot>()?;
+    exceptions::register(m)?;
 
     // Core functions
     m.add_function(wrap_pyfunction!(start_flood, m)?)?;
+    m.add_function(wrap_pyfunction!(start_flood_async, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_memory, m)?)?;
+    m.add_function(wrap_pyfunction!(run_profile, m)?)?;
     m.add_function(wrap_pyfunction!(build_packet, m)?)?;
     m.add_function(wrap_pyfunction!(get_capabilities, m)?)?;
+    m.add_function(wrap_pyfunction!(has_raw_socket_privilege, m)?)?;
     m.add_function(wrap_pyfunction!(get_stats, m)?)?;
 
     // Protocol builder functions
     m.add_function(wrap_pyfunction!(build_udp_packet, m)?)?;
+    m.add_function(wrap_pyfunction!(build_udp_fragmented, m)?)?;
     m.add_function(wrap_pyfunction!(build_tcp_syn, m)?)?;
     m.add_function(wrap_pyfunction!(build_icmp_echo, m)?)?;
+    m.add_function(wrap_pyfunction!(build_payload, m)?)?;
     m.add_function(wrap_pyfunction!(build_http_get, m)?)?;
     m.add_function(wrap_pyfunction!(build_dns_query, m)?)?;
+    m.add_function(wrap_pyfunction!(encapsulate_vxlan, m)?)?;
+    m.add_function(wrap_pyfunction!(encapsulate_gre, m)?)?;
     m.add_function(wrap_pyfunction!(generate_packet_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(checksum, m)?)?;
+    m.add_function(wrap_pyfunction!(ip_checksum_with_pseudo, m)?)?;
+    m.add_function(wrap_pyfunction!(fill_payload, m)?)?;
 
     // Backend selection functions
     m.add_function(wrap_pyfunction!(get_capability_report, m)?)?;
     m.add_function(wrap_pyfunction!(get_available_backends, m)?)?;
+    m.add_function(wrap_pyfunction!(select_fastest_backend, m)?)?;
 
     // Statistics functions
     m.add_function(wrap_pyfunction!(get_realtime_stats_json, m)?)?;
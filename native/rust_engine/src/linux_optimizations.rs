@@ -1,10 +1,17 @@
 //! Linux-specific optimizations and advanced features
 //! Enables DPDK, AF_XDP, io_uring, and sendmmsg for maximum performance
 
-use crate::backend::{Backend, BackendError, BackendType, SystemCapabilities};
+use crate::backend::{
+    Backend, BackendError, BackendType, Recommendation, RecommendationSeverity, SystemCapabilities,
+};
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
 use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::{debug, info, warn};
 
+/// Socket buffer ceiling the engine requests (SO_SNDBUF/SO_RCVBUF)
+const RECOMMENDED_SOCKET_BUFFER: u64 = 64 * 1024 * 1024;
+
 /// Linux optimization manager
 pub struct LinuxOptimizer {
     capabilities: SystemCapabilities,
@@ -155,31 +162,113 @@ impl LinuxOptimizer {
 
     /// Get performance recommendations
     pub fn get_performance_recommendations(&self) -> Vec<String> {
+        self.get_recommendations()
+            .iter()
+            .map(|r| r.message.clone())
+            .collect()
+    }
+
+    /// Get structured performance recommendations
+    pub fn get_recommendations(&self) -> Vec<Recommendation> {
         let mut recommendations = Vec::new();
 
         if !self.capabilities.has_dpdk {
-            recommendations.push("Consider installing DPDK for maximum performance".to_string());
+            recommendations.push(Recommendation::new(
+                "install_dpdk",
+                RecommendationSeverity::Info,
+                "Consider installing DPDK for maximum performance",
+                "install DPDK and reserve hugepages",
+            ));
         }
 
         if !self.capabilities.has_af_xdp {
-            recommendations
-                .push("Consider enabling AF_XDP for kernel-bypass networking".to_string());
+            recommendations.push(Recommendation::new(
+                "enable_af_xdp",
+                RecommendationSeverity::Info,
+                "Consider enabling AF_XDP for kernel-bypass networking",
+                "install libbpf and build with the af_xdp feature",
+            ));
         }
 
         if !self.capabilities.has_io_uring {
-            recommendations
-                .push("Consider upgrading to Linux 5.1+ for io_uring support".to_string());
+            recommendations.push(
+                Recommendation::new(
+                    "upgrade_kernel_io_uring",
+                    RecommendationSeverity::Low,
+                    "Consider upgrading to Linux 5.1+ for io_uring support",
+                    "upgrade kernel",
+                )
+                .with_values(
+                    format!(
+                        "{}.{}",
+                        self.capabilities.kernel_version.0, self.capabilities.kernel_version.1
+                    ),
+                    "5.1",
+                ),
+            );
         }
 
         if self.capabilities.cpu_count < 4 {
             recommendations.push(
-                "Consider using a system with more CPU cores for better performance".to_string(),
+                Recommendation::new(
+                    "more_cpu_cores",
+                    RecommendationSeverity::Medium,
+                    "Consider using a system with more CPU cores for better performance",
+                    "add CPU cores",
+                )
+                .with_values(self.capabilities.cpu_count, 4),
             );
         }
 
-        recommendations.push("Use CPU affinity to pin threads to specific cores".to_string());
-        recommendations.push("Increase socket buffer sizes for high throughput".to_string());
-        recommendations.push("Disable interrupt coalescing on network interfaces".to_string());
+        recommendations.push(Recommendation::new(
+            "cpu_affinity",
+            RecommendationSeverity::Info,
+            "Use CPU affinity to pin threads to specific cores",
+            "pin worker threads with sched_setaffinity",
+        ));
+
+        let mut buffer_recommendations = Vec::new();
+        for (id, key) in [
+            ("raise_wmem_max", "net.core.wmem_max"),
+            ("raise_rmem_max", "net.core.rmem_max"),
+        ] {
+            match read_sysctl(key) {
+                Some(current) if current < RECOMMENDED_SOCKET_BUFFER => {
+                    buffer_recommendations.push(
+                        Recommendation::new(
+                            id,
+                            RecommendationSeverity::High,
+                            format!(
+                                "Increase {} from {} to {} for high throughput",
+                                key, current, RECOMMENDED_SOCKET_BUFFER
+                            ),
+                            key,
+                        )
+                        .with_values(current, RECOMMENDED_SOCKET_BUFFER),
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    buffer_recommendations.push(
+                        Recommendation::new(
+                            id,
+                            RecommendationSeverity::Medium,
+                            "Increase socket buffer sizes for high throughput",
+                            key,
+                        )
+                        .with_values("unknown", RECOMMENDED_SOCKET_BUFFER),
+                    );
+                }
+            }
+        }
+        recommendations.extend(buffer_recommendations);
+
+        recommendations.push(Recommendation::new(
+            "disable_interrupt_coalescing",
+            RecommendationSeverity::Low,
+            "Disable interrupt coalescing on network interfaces",
+            "ethtool -C <iface> rx-usecs 0 tx-usecs 0",
+        ));
 
         recommendations
     }
@@ -224,10 +313,131 @@ impl Default for LinuxOptimizer {
     }
 }
 
+/// Read a numeric sysctl value through /proc/sys
+fn read_sysctl(key: &str) -> Option<u64> {
+    let path = format!("/proc/sys/{}", key.replace('.', "/"));
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// CPUs of each online NUMA node, ordered by node id
+///
+/// Empty when the kernel exposes no node topology.
+pub fn numa_node_cpus() -> Vec<Vec<usize>> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<(usize, Vec<usize>)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let list = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            Some((id, parse_cpu_list(&list)))
+        })
+        .collect();
+    nodes.sort_by_key(|(id, _)| *id);
+    nodes.into_iter().map(|(_, cpus)| cpus).collect()
+}
+
+/// MTU of the interface the kernel routes `dst` through
+///
+/// Connects a UDP socket to learn the source address the route picks, then reads
+/// `/sys/class/net/<iface>/mtu` for the interface carrying that address.
+pub fn interface_mtu(dst: IpAddr) -> Option<usize> {
+    let bind: IpAddr = if dst.is_ipv4() {
+        Ipv4Addr::UNSPECIFIED.into()
+    } else {
+        Ipv6Addr::UNSPECIFIED.into()
+    };
+    let socket = UdpSocket::bind((bind, 0)).ok()?;
+    socket.connect((dst, 9)).ok()?;
+    let local = socket.local_addr().ok()?.ip();
+    let name = interface_with_addr(local)?;
+    std::fs::read_to_string(format!("/sys/class/net/{}/mtu", name))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Names of the network interfaces in `/sys/class/net`, sorted
+pub fn interface_names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Name of the interface that has `ip` assigned
+fn interface_with_addr(ip: IpAddr) -> Option<String> {
+    let mut ifap: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifap) } != 0 {
+        return None;
+    }
+
+    let mut found = None;
+    let mut cursor = ifap;
+    while !cursor.is_null() {
+        let ifa = unsafe { &*cursor };
+        cursor = ifa.ifa_next;
+        if ifa.ifa_addr.is_null() {
+            continue;
+        }
+        let addr = unsafe {
+            match (*ifa.ifa_addr).sa_family as libc::c_int {
+                libc::AF_INET => {
+                    let sin = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                    IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)))
+                }
+                libc::AF_INET6 => {
+                    let sin6 = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                    IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr))
+                }
+                _ => continue,
+            }
+        };
+        if addr == ip {
+            found = unsafe { CStr::from_ptr(ifa.ifa_name) }
+                .to_str()
+                .ok()
+                .map(String::from);
+            break;
+        }
+    }
+
+    unsafe { libc::freeifaddrs(ifap) };
+    found
+}
+
+/// Parse a kernel CPU list such as `0-3,8-11`
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => cpus.extend(part.parse::<usize>().ok()),
+        }
+    }
+    cpus
+}
+
 /// Enhanced Linux capability detection
 fn detect_linux_capabilities() -> SystemCapabilities {
     let mut caps = SystemCapabilities::default();
     caps.has_raw_socket = true;
+    caps.has_raw_socket_privilege = crate::backend::has_raw_socket_privilege();
 
     // Get CPU count
     caps.cpu_count = std::thread::available_parallelism()
@@ -335,6 +545,23 @@ fn check_dpdk_support() -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_interface_mtu_loopback() {
+        let lo: usize = std::fs::read_to_string("/sys/class/net/lo/mtu")
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        assert_eq!(interface_mtu("127.0.0.1".parse().unwrap()), Some(lo));
+    }
+
+    #[test]
+    fn test_interface_names_include_loopback() {
+        let names = interface_names();
+        assert!(names.iter().any(|name| name == "lo"), "{:?}", names);
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
     #[test]
     fn test_linux_optimizer_creation() {
         let optimizer = LinuxOptimizer::new();
@@ -348,10 +575,52 @@ mod tests {
         assert!(caps.cpu_count > 0);
     }
 
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3,8-9\n"), vec![0, 1, 2, 3, 8, 9]);
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+        assert!(parse_cpu_list("").is_empty());
+    }
+
     #[test]
     fn test_performance_recommendations() {
         let optimizer = LinuxOptimizer::new();
         let recommendations = optimizer.get_performance_recommendations();
         assert!(!recommendations.is_empty());
     }
+
+    #[test]
+    fn test_structured_recommendations_match_strings() {
+        let optimizer = LinuxOptimizer::new();
+        let structured = optimizer.get_recommendations();
+        let strings = optimizer.get_performance_recommendations();
+        assert_eq!(structured.len(), strings.len());
+        for (rec, text) in structured.iter().zip(&strings) {
+            assert_eq!(&rec.message, text);
+            assert!(!rec.id.is_empty());
+            assert!(!rec.sysctl_or_action.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_buffer_recommendation_carries_sysctl() {
+        let optimizer = LinuxOptimizer::new();
+        if let Some(current) = read_sysctl("net.core.wmem_max") {
+            let rec = optimizer
+                .get_recommendations()
+                .into_iter()
+                .find(|r| r.id == "raise_wmem_max");
+            if current < RECOMMENDED_SOCKET_BUFFER {
+                let rec = rec.expect("wmem_max recommendation missing");
+                assert_eq!(rec.sysctl_or_action, "net.core.wmem_max");
+                assert_eq!(rec.current_value, Some(current.to_string()));
+                assert_eq!(
+                    rec.suggested_value,
+                    Some(RECOMMENDED_SOCKET_BUFFER.to_string())
+                );
+            } else {
+                assert!(rec.is_none());
+            }
+        }
+    }
 }
@@ -1,15 +1,50 @@
 //! macOS-specific backend implementations
 //! Uses kqueue for efficient event handling and BSD socket optimizations
 
-use crate::backend::{Backend, BackendError, BackendStats, BackendType};
+use crate::backend::{
+    Backend, BackendError, BackendStats, BackendType, Recommendation, RecommendationSeverity,
+};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tracing::{debug, error, info, warn};
 
+/// Socket buffer ceiling needed for the engine's large SO_SNDBUF requests
+const RECOMMENDED_MAX_SOCKBUF: u64 = 64 * 1024 * 1024;
+
+/// Messages per `sendmsg_x` call; the kernel caps this (`kern.ipc.maxsendmsgx`)
+/// at an undocumented value, so larger batches are split
+const SENDMSG_X_MAX_BATCH: usize = 64;
+
+/// `struct msghdr_x` from XNU's <sys/socket.h>
+#[repr(C)]
+struct MsgHdrX {
+    msg_name: *mut libc::c_void,
+    msg_namelen: libc::socklen_t,
+    msg_iov: *mut libc::iovec,
+    msg_iovlen: libc::c_int,
+    msg_control: *mut libc::c_void,
+    msg_controllen: libc::socklen_t,
+    msg_flags: libc::c_int,
+    msg_datalen: libc::size_t,
+}
+
+type SendmsgX =
+    unsafe extern "C" fn(libc::c_int, *const MsgHdrX, libc::c_uint, libc::c_int) -> libc::ssize_t;
+
+/// `sendmsg_x` is private API, so look it up at runtime instead of linking it
+fn sendmsg_x_fn() -> Option<SendmsgX> {
+    static SENDMSG_X: OnceLock<Option<SendmsgX>> = OnceLock::new();
+    *SENDMSG_X.get_or_init(|| {
+        let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"sendmsg_x".as_ptr()) };
+        (!symbol.is_null())
+            .then(|| unsafe { std::mem::transmute::<*mut libc::c_void, SendmsgX>(symbol) })
+    })
+}
+
 /// macOS capabilities detection
 #[derive(Debug, Clone, Default)]
 pub struct MacOSCapabilities {
@@ -217,6 +252,89 @@ impl KqueueBackend {
         Ok(false)
     }
 
+    /// Send a batch with `sendmsg_x`, `SENDMSG_X_MAX_BATCH` messages per call
+    ///
+    /// A short count resumes from the first unsent message. Returns `None`
+    /// when `sendmsg_x` is missing or rejected so the caller can fall back.
+    fn send_batch_sendmsg_x(
+        &mut self,
+        packets: &[&[u8]],
+        dest: SocketAddr,
+    ) -> Option<Result<usize, BackendError>> {
+        let sendmsg_x = sendmsg_x_fn()?;
+        let mut addr = match dest {
+            SocketAddr::V4(v4) => {
+                let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+                addr.sin_family = libc::AF_INET as libc::sa_family_t;
+                addr.sin_port = v4.port().to_be();
+                addr.sin_addr.s_addr = u32::from_ne_bytes(v4.ip().octets());
+                addr
+            }
+            SocketAddr::V6(_) => {
+                return Some(Err(BackendError::SendFailed(
+                    "IPv6 not supported yet".into(),
+                )));
+            }
+        };
+
+        let mut iovecs: Vec<libc::iovec> = packets
+            .iter()
+            .map(|p| libc::iovec {
+                iov_base: p.as_ptr() as *mut libc::c_void,
+                iov_len: p.len(),
+            })
+            .collect();
+        let msgs: Vec<MsgHdrX> = iovecs
+            .iter_mut()
+            .map(|iov| MsgHdrX {
+                msg_name: &mut addr as *mut libc::sockaddr_in as *mut libc::c_void,
+                msg_namelen: std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                msg_iov: iov,
+                msg_iovlen: 1,
+                msg_control: std::ptr::null_mut(),
+                msg_controllen: 0,
+                msg_flags: 0,
+                msg_datalen: 0,
+            })
+            .collect();
+
+        let mut offset = 0;
+        while offset < msgs.len() {
+            let chunk = &msgs[offset..(offset + SENDMSG_X_MAX_BATCH).min(msgs.len())];
+            let sent = unsafe { sendmsg_x(self.socket_fd, chunk.as_ptr(), chunk.len() as _, 0) };
+
+            if sent > 0 {
+                offset += sent as usize;
+                continue;
+            }
+
+            let errno = unsafe { *libc::__error() };
+            match errno {
+                libc::EINTR => continue,
+                libc::EAGAIN => match self.wait_for_write_ready(1) {
+                    Ok(true) => continue,
+                    _ => break,
+                },
+                // Unsupported: let the caller use the scalar loop
+                libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL if offset == 0 => return None,
+                _ => {
+                    self.stats
+                        .errors
+                        .fetch_add((msgs.len() - offset) as u64, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
+
+        let bytes: u64 = packets[..offset].iter().map(|p| p.len() as u64).sum();
+        self.stats
+            .packets_sent
+            .fetch_add(offset as u64, Ordering::Relaxed);
+        self.stats.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        self.stats.batch_count.fetch_add(1, Ordering::Relaxed);
+        Some(Ok(offset))
+    }
+
     /// Send batch using kqueue for event notification
     fn send_batch_kqueue(
         &mut self,
@@ -371,9 +489,12 @@ impl Backend for KqueueBackend {
             return Err(BackendError::NotInitialized);
         }
 
-        // Use kqueue-optimized batch sending
+        // One sendmsg_x per chunk; the kqueue send loop where it is unavailable
         let mut backend = unsafe { &mut *(self as *const Self as *mut Self) };
-        backend.send_batch_kqueue(packets, dest)
+        match backend.send_batch_sendmsg_x(packets, dest) {
+            Some(result) => result,
+            None => backend.send_batch_kqueue(packets, dest),
+        }
     }
 
     fn cleanup(&mut self) -> Result<(), BackendError> {
@@ -441,27 +562,77 @@ impl MacOSOptimizer {
 
     /// Get performance recommendations
     pub fn get_performance_recommendations(&self) -> Vec<String> {
+        self.get_recommendations()
+            .iter()
+            .map(|r| r.message.clone())
+            .collect()
+    }
+
+    /// Get structured performance recommendations
+    pub fn get_recommendations(&self) -> Vec<Recommendation> {
         let mut recommendations = Vec::new();
 
         if self.capabilities.has_kqueue {
-            recommendations.push("Use kqueue backend for optimal performance".into());
+            recommendations.push(Recommendation::new(
+                "use_kqueue",
+                RecommendationSeverity::Medium,
+                "Use kqueue backend for optimal performance",
+                "select the kqueue backend",
+            ));
         }
 
         if self.capabilities.has_so_reuseport {
-            recommendations.push("SO_REUSEPORT available for load balancing".into());
+            recommendations.push(Recommendation::new(
+                "so_reuseport",
+                RecommendationSeverity::Info,
+                "SO_REUSEPORT available for load balancing",
+                "setsockopt SO_REUSEPORT",
+            ));
         }
 
         if self.capabilities.has_sendfile {
-            recommendations.push("sendfile() available for zero-copy transfers".into());
+            recommendations.push(Recommendation::new(
+                "sendfile",
+                RecommendationSeverity::Info,
+                "sendfile() available for zero-copy transfers",
+                "use sendfile()",
+            ));
         }
 
-        recommendations.push(format!(
-            "Detected {} CPU cores for parallel processing",
-            self.capabilities.cpu_count
+        if let Some(current) = read_sysctl("kern.ipc.maxsockbuf") {
+            if current < RECOMMENDED_MAX_SOCKBUF {
+                recommendations.push(
+                    Recommendation::new(
+                        "raise_maxsockbuf",
+                        RecommendationSeverity::High,
+                        format!(
+                            "Increase kern.ipc.maxsockbuf from {} to {} for high throughput",
+                            current, RECOMMENDED_MAX_SOCKBUF
+                        ),
+                        "kern.ipc.maxsockbuf",
+                    )
+                    .with_values(current, RECOMMENDED_MAX_SOCKBUF),
+                );
+            }
+        }
+
+        recommendations.push(Recommendation::new(
+            "thread_count",
+            RecommendationSeverity::Info,
+            format!(
+                "Detected {} CPU cores for parallel processing",
+                self.capabilities.cpu_count
+            ),
+            "set engine threads to the CPU count",
         ));
 
         if self.capabilities.darwin_version.0 >= 20 {
-            recommendations.push("Modern macOS version with optimized networking stack".into());
+            recommendations.push(Recommendation::new(
+                "modern_darwin",
+                RecommendationSeverity::Info,
+                "Modern macOS version with optimized networking stack",
+                "none",
+            ));
         }
 
         recommendations
@@ -499,6 +670,16 @@ impl MacOSOptimizer {
     }
 }
 
+/// Read a numeric sysctl value via the sysctl utility
+fn read_sysctl(key: &str) -> Option<u64> {
+    let output = std::process::Command::new("sysctl")
+        .arg("-n")
+        .arg(key)
+        .output()
+        .ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
 /// Detect macOS system capabilities
 pub fn detect_macos_capabilities() -> MacOSCapabilities {
     let mut caps = MacOSCapabilities::default();
@@ -558,6 +739,77 @@ mod tests {
         assert!(!recommendations.is_empty());
     }
 
+    #[test]
+    fn test_structured_recommendations() {
+        let optimizer = MacOSOptimizer::new();
+        let structured = optimizer.get_recommendations();
+        assert_eq!(
+            structured.len(),
+            optimizer.get_performance_recommendations().len()
+        );
+        assert!(structured.iter().all(|r| !r.sysctl_or_action.is_empty()));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_sendmsg_x_batch_delivered() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+            .unwrap();
+        let dest = receiver.local_addr().unwrap();
+
+        let mut backend = KqueueBackend::new();
+        backend.init().unwrap();
+
+        let payloads: Vec<[u8; 2]> = (0..128u16).map(|i| i.to_be_bytes()).collect();
+        let packets: Vec<&[u8]> = payloads.iter().map(|p| &p[..]).collect();
+        assert_eq!(backend.send_batch(&packets, dest).unwrap(), 128);
+
+        let mut seen = [false; 128];
+        let mut buf = [0u8; 16];
+        for _ in 0..128 {
+            receiver.recv(&mut buf).unwrap();
+            seen[u16::from_be_bytes([buf[0], buf[1]]) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    /// Compare sendmsg_x batches with the scalar send loop
+    /// (`cargo test bench_sendmsg_x -- --ignored --nocapture`)
+    #[cfg(target_os = "macos")]
+    #[test]
+    #[ignore]
+    fn bench_sendmsg_x_vs_scalar() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = receiver.local_addr().unwrap();
+        let payload = [0u8; 64];
+        let packets = vec![&payload[..]; 1024];
+
+        let mut backend = KqueueBackend::new();
+        backend.init().unwrap();
+        let rounds = 200;
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let _ = backend.send_batch_sendmsg_x(&packets, dest);
+        }
+        let batched = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let _ = backend.send_batch_kqueue(&packets, dest);
+        }
+        let scalar = start.elapsed();
+
+        let total = (rounds * packets.len()) as f64;
+        println!(
+            "sendmsg_x: {:.0} pps, scalar: {:.0} pps",
+            total / batched.as_secs_f64(),
+            total / scalar.as_secs_f64()
+        );
+    }
+
     #[test]
     fn test_kqueue_backend_creation() {
         let backend = KqueueBackend::new();
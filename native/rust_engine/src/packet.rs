@@ -1,10 +1,13 @@
 //! Packet building module
 //! High-performance packet construction with zero-copy where possible
 
-use std::net::{IpAddr, Ipv4Addr};
+use crate::simd::{checksum_simd, checksum_simd_with_pseudo};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Protocol {
     UDP,
     TCP,
@@ -102,17 +105,42 @@ pub enum PacketError {
     BuildError(String),
 }
 
+/// TCP timestamp option with padding, present on most kernel-built segments
+const TCP_TIMESTAMP_OPTION_LEN: usize = 12;
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_IPV6: u16 = 0x86DD;
+/// 802.1Q customer tag, the only tag of a single-tagged frame
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+/// 802.1ad service tag, the outer tag of a QinQ frame
+pub const ETHERTYPE_QINQ: u16 = 0x88A8;
+/// Shortest Ethernet frame without the FCS; shorter frames are zero padded
+const MIN_ETHERNET_FRAME: usize = 60;
+
+/// Ethernet II header requested with `PacketBuilder::ethernet`
+#[derive(Debug, Clone, Copy)]
+struct EthernetHeader {
+    src: [u8; 6],
+    dst: [u8; 6],
+    ethertype: u16,
+}
+
 /// High-performance packet builder
 pub struct PacketBuilder {
-    src_ip: Option<Ipv4Addr>,
-    dst_ip: Option<Ipv4Addr>,
+    src_ip: Option<IpAddr>,
+    dst_ip: Option<IpAddr>,
     src_port: u16,
     dst_port: u16,
     protocol: Protocol,
     flags: PacketFlags,
     payload: Vec<u8>,
     ttl: u8,
+    dscp: u8,
+    ecn: u8,
     id: u16,
+    ethernet: Option<EthernetHeader>,
+    /// 802.1Q tag control words, outermost first
+    vlans: Vec<u16>,
 }
 
 impl Default for PacketBuilder {
@@ -132,17 +160,23 @@ impl PacketBuilder {
             flags: PacketFlags::default(),
             payload: Vec::new(),
             ttl: 64,
+            dscp: 0,
+            ecn: 0,
             id: rand::random(),
+            ethernet: None,
+            vlans: Vec::new(),
         }
     }
 
+    /// Source address, IPv4 or IPv6 (brackets around v6 literals are accepted)
     pub fn src_ip(mut self, ip: &str) -> Self {
-        self.src_ip = ip.parse().ok();
+        self.src_ip = parse_ip(ip);
         self
     }
 
+    /// Destination address, IPv4 or IPv6; selects the IP header version
     pub fn dst_ip(mut self, ip: &str) -> Self {
-        self.dst_ip = ip.parse().ok();
+        self.dst_ip = parse_ip(ip);
         self
     }
 
@@ -171,22 +205,127 @@ impl PacketBuilder {
         self
     }
 
+    /// IPv4 TTL or IPv6 hop limit
     pub fn ttl(mut self, ttl: u8) -> Self {
         self.ttl = ttl;
         self
     }
 
+    /// Differentiated services code point; only the low 6 bits are used
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        self.dscp = dscp & 0x3F;
+        self
+    }
+
+    /// Explicit congestion notification codepoint; only the low 2 bits are used
+    pub fn ecn(mut self, ecn: u8) -> Self {
+        self.ecn = ecn & 0x03;
+        self
+    }
+
+    /// IPv4 ToS / IPv6 traffic class byte: DSCP in the top 6 bits, ECN below
+    pub fn traffic_class(&self) -> u8 {
+        (self.dscp << 2) | self.ecn
+    }
+
     pub fn id(mut self, id: u16) -> Self {
         self.id = id;
         self
     }
 
-    /// Build the packet
-    pub fn build(self) -> Result<Vec<u8>, PacketError> {
-        let src_ip = self.src_ip.unwrap_or(Ipv4Addr::new(0, 0, 0, 0));
+    /// Wrap the packet in an Ethernet II frame
+    ///
+    /// `ethertype` names the packet after any VLAN tags, usually
+    /// `ETHERTYPE_IPV4` or `ETHERTYPE_IPV6`. Frames are padded to the 60-byte
+    /// minimum.
+    pub fn ethernet(mut self, src_mac: [u8; 6], dst_mac: [u8; 6], ethertype: u16) -> Self {
+        self.ethernet = Some(EthernetHeader {
+            src: src_mac,
+            dst: dst_mac,
+            ethertype,
+        });
+        self
+    }
+
+    /// Add an 802.1Q tag with VLAN id `vid` (12 bits) and priority `pcp` (3 bits)
+    ///
+    /// Call twice for QinQ, outer tag first: it is sent with the 802.1ad
+    /// TPID and the inner one with 802.1Q. Needs `ethernet`.
+    pub fn vlan(mut self, vid: u16, pcp: u8) -> Self {
+        self.vlans
+            .push((u16::from(pcp & 0x07) << 13) | (vid & 0x0FFF));
+        self
+    }
+
+    /// Largest payload that fits a `mtu`-byte IP packet for `protocol`
+    ///
+    /// Counts the IPv4 (20) or IPv6 (40) header plus the transport header.
+    /// TCP and HTTP also reserve the 12-byte timestamp option kernels add to
+    /// every segment, so the result matches the effective MSS.
+    pub fn max_payload_for_mtu(mtu: usize, protocol: Protocol, ipv6: bool) -> usize {
+        let ip_header = if ipv6 { 40 } else { 20 };
+        let transport_header = match protocol {
+            Protocol::UDP | Protocol::ICMP => 8,
+            Protocol::TCP | Protocol::HTTP => 20 + TCP_TIMESTAMP_OPTION_LEN,
+            Protocol::RAW => return mtu,
+        };
+        mtu.saturating_sub(ip_header + transport_header)
+    }
+
+    /// Build the packet, framed in Ethernet when `ethernet` was set
+    pub fn build(mut self) -> Result<Vec<u8>, PacketError> {
+        let ethernet = self.ethernet.take();
+        let vlans = std::mem::take(&mut self.vlans);
+        if vlans.len() > 2 {
+            return Err(PacketError::BuildError(format!(
+                "{} VLAN tags; at most two (QinQ) are supported",
+                vlans.len()
+            )));
+        }
+        let packet = self.build_packet()?;
+        match ethernet {
+            Some(header) => Ok(Self::frame(header, &vlans, &packet)),
+            None if !vlans.is_empty() => Err(PacketError::BuildError(
+                "VLAN tags need an Ethernet header".into(),
+            )),
+            None => Ok(packet),
+        }
+    }
+
+    fn frame(header: EthernetHeader, vlans: &[u16], packet: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity((14 + 4 * vlans.len() + packet.len()).max(60));
+        frame.extend_from_slice(&header.dst);
+        frame.extend_from_slice(&header.src);
+        for (i, tci) in vlans.iter().enumerate() {
+            let tpid = if vlans.len() > 1 && i == 0 {
+                ETHERTYPE_QINQ
+            } else {
+                ETHERTYPE_VLAN
+            };
+            frame.extend_from_slice(&tpid.to_be_bytes());
+            frame.extend_from_slice(&tci.to_be_bytes());
+        }
+        frame.extend_from_slice(&header.ethertype.to_be_bytes());
+        frame.extend_from_slice(packet);
+        frame.resize(frame.len().max(MIN_ETHERNET_FRAME), 0);
+        frame
+    }
+
+    fn build_packet(self) -> Result<Vec<u8>, PacketError> {
         let dst_ip = self
             .dst_ip
             .ok_or_else(|| PacketError::InvalidIp("No destination IP".into()))?;
+        let src_ip = match (self.src_ip, dst_ip) {
+            (Some(src), dst) if src.is_ipv4() != dst.is_ipv4() => {
+                return Err(PacketError::InvalidIp(format!(
+                    "source {} and destination {} are different address families",
+                    src, dst
+                )))
+            }
+            (Some(src), _) => src,
+            (None, IpAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (None, IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
 
         match self.protocol {
             Protocol::UDP => self.build_udp(src_ip, dst_ip),
@@ -198,6 +337,52 @@ impl PacketBuilder {
     }
 
     fn build_ip_header(
+        &self,
+        src: IpAddr,
+        dst: IpAddr,
+        protocol: u8,
+        payload_len: usize,
+    ) -> Vec<u8> {
+        match (src, dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                self.build_ipv4_header(src, dst, protocol, payload_len)
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                self.build_ipv6_header(src, dst, protocol, payload_len)
+            }
+            // build() rejects mixed families before any header is built
+            _ => unreachable!("mixed address families"),
+        }
+    }
+
+    fn build_ipv6_header(
+        &self,
+        src: Ipv6Addr,
+        dst: Ipv6Addr,
+        next_header: u8,
+        payload_len: usize,
+    ) -> Vec<u8> {
+        let mut header = vec![0u8; 40];
+
+        // Version (6) and traffic class; flow label left at zero
+        let traffic_class = self.traffic_class();
+        header[0] = 0x60 | (traffic_class >> 4);
+        header[1] = (traffic_class & 0x0F) << 4;
+        // Payload length
+        header[4] = ((payload_len >> 8) & 0xFF) as u8;
+        header[5] = (payload_len & 0xFF) as u8;
+        // Next header
+        header[6] = next_header;
+        // Hop limit
+        header[7] = self.ttl;
+        // Source and destination addresses
+        header[8..24].copy_from_slice(&src.octets());
+        header[24..40].copy_from_slice(&dst.octets());
+
+        header
+    }
+
+    fn build_ipv4_header(
         &self,
         src: Ipv4Addr,
         dst: Ipv4Addr,
@@ -210,7 +395,7 @@ impl PacketBuilder {
         // Version (4) + IHL (5)
         header[0] = 0x45;
         // DSCP + ECN
-        header[1] = 0x00;
+        header[1] = self.traffic_class();
         // Total length
         header[2] = ((total_len >> 8) & 0xFF) as u8;
         header[3] = (total_len & 0xFF) as u8;
@@ -240,7 +425,7 @@ impl PacketBuilder {
         header
     }
 
-    fn build_udp(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Result<Vec<u8>, PacketError> {
+    fn build_udp(&self, src: IpAddr, dst: IpAddr) -> Result<Vec<u8>, PacketError> {
         let udp_len = 8 + self.payload.len();
         let mut udp_header = vec![0u8; 8];
 
@@ -253,9 +438,18 @@ impl PacketBuilder {
         // Length
         udp_header[4] = ((udp_len >> 8) & 0xFF) as u8;
         udp_header[5] = (udp_len & 0xFF) as u8;
-        // Checksum (optional for UDP over IPv4)
+        // Checksum (optional for UDP over IPv4, mandatory over IPv6)
         udp_header[6] = 0x00;
         udp_header[7] = 0x00;
+        if dst.is_ipv6() {
+            let checksum = match Self::transport_checksum(&udp_header, &self.payload, src, dst, 17)
+            {
+                0 => 0xFFFF,
+                c => c,
+            };
+            udp_header[6] = ((checksum >> 8) & 0xFF) as u8;
+            udp_header[7] = (checksum & 0xFF) as u8;
+        }
 
         let ip_header = self.build_ip_header(src, dst, 17, udp_len);
 
@@ -266,7 +460,7 @@ impl PacketBuilder {
         Ok(packet)
     }
 
-    fn build_tcp(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Result<Vec<u8>, PacketError> {
+    fn build_tcp(&self, src: IpAddr, dst: IpAddr) -> Result<Vec<u8>, PacketError> {
         let tcp_header_len = 20;
         let mut tcp_header = vec![0u8; tcp_header_len];
 
@@ -296,7 +490,7 @@ impl PacketBuilder {
         tcp_header[19] = 0x00;
 
         // Calculate TCP checksum with pseudo-header
-        let checksum = Self::tcp_checksum(&tcp_header, &self.payload, src, dst);
+        let checksum = Self::transport_checksum(&tcp_header, &self.payload, src, dst, 6);
         tcp_header[16] = ((checksum >> 8) & 0xFF) as u8;
         tcp_header[17] = (checksum & 0xFF) as u8;
 
@@ -309,11 +503,11 @@ impl PacketBuilder {
         Ok(packet)
     }
 
-    fn build_icmp(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Result<Vec<u8>, PacketError> {
+    fn build_icmp(&self, src: IpAddr, dst: IpAddr) -> Result<Vec<u8>, PacketError> {
         let mut icmp_header = vec![0u8; 8];
 
-        // Type (8 = Echo Request)
-        icmp_header[0] = 8;
+        // Type (8 = Echo Request, 128 for ICMPv6)
+        icmp_header[0] = if dst.is_ipv6() { 128 } else { 8 };
         // Code
         icmp_header[1] = 0;
         // Checksum (calculated later)
@@ -326,14 +520,22 @@ impl PacketBuilder {
         icmp_header[6] = 0;
         icmp_header[7] = 1;
 
-        // Calculate ICMP checksum
-        let mut data = icmp_header.clone();
-        data.extend(&self.payload);
-        let checksum = Self::ip_checksum(&data);
+        // Calculate ICMP checksum (ICMPv6 covers the pseudo-header too)
+        let (next_header, checksum) = if dst.is_ipv6() {
+            (
+                58,
+                Self::transport_checksum(&icmp_header, &self.payload, src, dst, 58),
+            )
+        } else {
+            // ICMPv4 has no pseudo-header: sum the message as one buffer
+            let mut data = icmp_header.clone();
+            data.extend(&self.payload);
+            (1, Self::ip_checksum(&data))
+        };
         icmp_header[2] = ((checksum >> 8) & 0xFF) as u8;
         icmp_header[3] = (checksum & 0xFF) as u8;
 
-        let ip_header = self.build_ip_header(src, dst, 1, 8 + self.payload.len());
+        let ip_header = self.build_ip_header(src, dst, next_header, 8 + self.payload.len());
 
         let mut packet = ip_header;
         packet.extend(icmp_header);
@@ -342,12 +544,16 @@ impl PacketBuilder {
         Ok(packet)
     }
 
-    fn build_http(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Result<Vec<u8>, PacketError> {
+    fn build_http(&self, src: IpAddr, dst: IpAddr) -> Result<Vec<u8>, PacketError> {
         // HTTP is just TCP with HTTP payload
+        let host = match dst {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => format!("[{}]", ip),
+        };
         let http_payload = if self.payload.is_empty() {
             format!(
                 "GET / HTTP/1.1\r\nHost: {}\r\nUser-Agent: NetStress/1.0\r\nAccept: */*\r\nConnection: keep-alive\r\n\r\n",
-                dst
+                host
             ).into_bytes()
         } else {
             self.payload.clone()
@@ -372,45 +578,63 @@ impl PacketBuilder {
             flags: self.flags,
             payload: Vec::new(),
             ttl: self.ttl,
+            dscp: self.dscp,
+            ecn: self.ecn,
             id: self.id,
+            ethernet: None,
+            vlans: Vec::new(),
         }
     }
 
     fn ip_checksum(data: &[u8]) -> u16 {
-        let mut sum: u32 = 0;
-        let mut i = 0;
-
-        while i < data.len() - 1 {
-            sum += ((data[i] as u32) << 8) | (data[i + 1] as u32);
-            i += 2;
-        }
-
-        if i < data.len() {
-            sum += (data[i] as u32) << 8;
-        }
+        checksum_simd(data)
+    }
 
-        while sum >> 16 != 0 {
-            sum = (sum & 0xFFFF) + (sum >> 16);
+    /// Checksum over the IPv4 or IPv6 pseudo-header plus the transport segment
+    fn transport_checksum(
+        header: &[u8],
+        payload: &[u8],
+        src: IpAddr,
+        dst: IpAddr,
+        protocol: u8,
+    ) -> u16 {
+        let len = header.len() + payload.len();
+
+        // Pseudo-header followed by the transport header; the payload is summed in place
+        let mut pseudo = Vec::with_capacity(40 + header.len());
+        match (src, dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                pseudo.extend_from_slice(&src.octets());
+                pseudo.extend_from_slice(&dst.octets());
+                pseudo.push(0);
+                pseudo.push(protocol);
+                pseudo.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            (src, dst) => {
+                pseudo.extend_from_slice(&ipv6_octets(src));
+                pseudo.extend_from_slice(&ipv6_octets(dst));
+                pseudo.extend_from_slice(&(len as u32).to_be_bytes());
+                pseudo.extend_from_slice(&[0, 0, 0, protocol]);
+            }
         }
+        pseudo.extend_from_slice(header);
 
-        !sum as u16
+        checksum_simd_with_pseudo(&pseudo, payload)
     }
+}
 
-    fn tcp_checksum(tcp_header: &[u8], payload: &[u8], src: Ipv4Addr, dst: Ipv4Addr) -> u16 {
-        let tcp_len = tcp_header.len() + payload.len();
-
-        // Build pseudo-header
-        let mut pseudo = Vec::with_capacity(12 + tcp_len);
-        pseudo.extend_from_slice(&src.octets());
-        pseudo.extend_from_slice(&dst.octets());
-        pseudo.push(0);
-        pseudo.push(6); // TCP protocol
-        pseudo.push(((tcp_len >> 8) & 0xFF) as u8);
-        pseudo.push((tcp_len & 0xFF) as u8);
-        pseudo.extend_from_slice(tcp_header);
-        pseudo.extend_from_slice(payload);
+/// Parse an IPv4 or IPv6 literal, allowing `[v6]` brackets
+fn parse_ip(ip: &str) -> Option<IpAddr> {
+    ip.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
 
-        Self::ip_checksum(&pseudo)
+fn ipv6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
     }
 }
 
@@ -446,8 +670,8 @@ impl PacketTemplates {
     /// Generate an ICMP echo request
     pub fn icmp_echo(dst_ip: &str, size: usize) -> Result<Vec<u8>, PacketError> {
         let payload = vec![0x00; size.saturating_sub(28)];
+        // Source left unset so it matches the destination's family
         PacketBuilder::new()
-            .src_ip("0.0.0.0")
             .dst_ip(dst_ip)
             .protocol(Protocol::ICMP)
             .payload(&payload)
@@ -484,6 +708,54 @@ mod tests {
         assert_eq!(&packet[16..20], &[192, 168, 1, 2]);
     }
 
+    #[test]
+    fn test_udp_packet_ipv6() {
+        let packet = PacketBuilder::new()
+            .src_ip("::1")
+            .dst_ip("[::1]")
+            .src_port(12345)
+            .dst_port(9)
+            .protocol(Protocol::UDP)
+            .payload(b"test")
+            .build()
+            .unwrap();
+
+        assert_eq!(packet.len(), 40 + 8 + 4);
+        assert_eq!(packet[0] >> 4, 6); // IPv6
+        assert_eq!(u16::from_be_bytes([packet[4], packet[5]]), 12); // payload length
+        assert_eq!(packet[6], 17); // next header: UDP
+        assert_eq!(packet[7], 64); // hop limit
+        assert_eq!(&packet[24..40], &Ipv6Addr::LOCALHOST.octets());
+        assert_eq!(u16::from_be_bytes([packet[42], packet[43]]), 9);
+
+        // Mandatory checksum verifies over the pseudo-header
+        assert_ne!(&packet[46..48], &[0, 0]);
+        let src = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(
+            PacketBuilder::transport_checksum(&packet[40..], &[], src, src, 17),
+            0
+        );
+    }
+
+    #[test]
+    fn test_ipv6_icmp_and_family_mismatch() {
+        let packet = PacketBuilder::new()
+            .dst_ip("2001:db8::1")
+            .protocol(Protocol::ICMP)
+            .build()
+            .unwrap();
+        assert_eq!(packet[6], 58); // ICMPv6
+        assert_eq!(packet[40], 128); // Echo Request
+        assert_eq!(&packet[8..24], &Ipv6Addr::UNSPECIFIED.octets());
+
+        let mixed = PacketBuilder::new()
+            .src_ip("10.0.0.1")
+            .dst_ip("::1")
+            .protocol(Protocol::UDP)
+            .build();
+        assert!(matches!(mixed, Err(PacketError::InvalidIp(_))));
+    }
+
     #[test]
     fn test_tcp_syn() {
         let packet = PacketBuilder::new()
@@ -574,6 +846,80 @@ mod tests {
         assert_eq!(packet[8], 32);
     }
 
+    #[test]
+    fn test_dscp_ecn_and_ttl_in_headers() {
+        let v4 = PacketBuilder::new()
+            .dst_ip("10.0.0.2")
+            .dst_port(53)
+            .ttl(7)
+            .dscp(46) // EF
+            .ecn(1)
+            .payload(b"qos")
+            .build()
+            .unwrap();
+        assert_eq!(v4[1], 0xB9);
+        assert_eq!(v4[8], 7);
+        assert_eq!(PacketBuilder::ip_checksum(&v4[..20]), 0);
+
+        let v6 = PacketBuilder::new()
+            .dst_ip("2001:db8::2")
+            .dst_port(53)
+            .ttl(3)
+            .dscp(0xFF) // masked to 63
+            .ecn(2)
+            .payload(b"qos")
+            .build()
+            .unwrap();
+        // Traffic class 0xFE straddles the version nibble
+        assert_eq!(v6[0], 0x6F);
+        assert_eq!(v6[1], 0xE0);
+        assert_eq!(v6[7], 3);
+    }
+
+    #[test]
+    fn test_ethernet_frame_layouts() {
+        let src = [0x02, 0, 0, 0, 0, 0x01];
+        let dst = [0x02, 0, 0, 0, 0, 0x02];
+        let builder = || {
+            PacketBuilder::new()
+                .dst_ip("10.0.0.2")
+                .dst_port(9)
+                .payload(b"l2")
+                .ethernet(src, dst, ETHERTYPE_IPV4)
+        };
+
+        // Untagged: 14-byte header, 30-byte packet padded to 60
+        let frame = builder().build().unwrap();
+        assert_eq!(&frame[0..6], &dst);
+        assert_eq!(&frame[6..12], &src);
+        assert_eq!(&frame[12..14], &[0x08, 0x00]);
+        assert_eq!(frame[14], 0x45);
+        assert_eq!(frame.len(), 60);
+        assert!(frame[44..].iter().all(|&b| b == 0));
+
+        // Single tag: 802.1Q TPID, PCP 5 / VID 100, then the EtherType
+        let frame = builder().vlan(100, 5).build().unwrap();
+        assert_eq!(&frame[12..18], &[0x81, 0x00, 0xA0, 0x64, 0x08, 0x00]);
+        assert_eq!(frame[18], 0x45);
+        assert_eq!(frame.len(), 60);
+
+        // QinQ: 802.1ad outer tag, 802.1Q inner tag, then the EtherType
+        let frame = builder().vlan(10, 0).vlan(20, 3).build().unwrap();
+        assert_eq!(
+            &frame[12..22],
+            &[0x88, 0xA8, 0x00, 0x0A, 0x81, 0x00, 0x60, 0x14, 0x08, 0x00]
+        );
+        assert_eq!(frame[22], 0x45);
+
+        // Large packets are not padded
+        let big = builder().payload(&[0xAB; 100]).build().unwrap();
+        assert_eq!(big.len(), 14 + 20 + 8 + 100);
+
+        assert!(builder().vlan(1, 0).vlan(2, 0).vlan(3, 0).build().is_err());
+        let untagged = PacketBuilder::new().dst_ip("10.0.0.2").vlan(1, 0).build();
+        assert!(untagged.is_err());
+    }
+
     #[test]
     fn test_packet_templates_udp_flood() {
         let packet = PacketTemplates::udp_flood("8.8.8.8", 53, 1024).unwrap();
@@ -749,4 +1095,48 @@ mod tests {
             prop_assert_eq!(packet[8], ttl); // TTL field in IP header
         }
     }
+
+    #[test]
+    fn test_max_payload_for_mtu() {
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1500, Protocol::UDP, false),
+            1472
+        );
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1500, Protocol::TCP, false),
+            1448
+        );
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1500, Protocol::ICMP, false),
+            1472
+        );
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1500, Protocol::RAW, false),
+            1500
+        );
+
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(9000, Protocol::UDP, false),
+            8972
+        );
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(9000, Protocol::HTTP, false),
+            8948
+        );
+
+        // IPv6 minimum link MTU
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1280, Protocol::UDP, true),
+            1232
+        );
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(1280, Protocol::TCP, true),
+            1208
+        );
+
+        assert_eq!(
+            PacketBuilder::max_payload_for_mtu(20, Protocol::UDP, false),
+            0
+        );
+    }
 }
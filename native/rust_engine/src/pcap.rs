@@ -0,0 +1,531 @@
+//! Packet capture files
+//! Reads pcap and pcapng captures for replay and writes classic pcap for inspection
+//!
+//! Both pcap byte orders and microsecond/nanosecond timestamps are accepted.
+//! pcapng files may hold several interfaces with different link types and
+//! timestamp resolutions; every enhanced and simple packet block is returned.
+
+use crate::simd::{checksum_simd, checksum_simd_with_pseudo};
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+/// BSD loopback: 4-byte host-order address family
+pub const LINKTYPE_NULL: u32 = 0;
+/// Ethernet II, optionally 802.1Q tagged
+pub const LINKTYPE_ETHERNET: u32 = 1;
+/// Bare IPv4/IPv6 packets
+pub const LINKTYPE_RAW: u32 = 101;
+/// Linux "cooked" capture (`any` interface)
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+/// Linux "cooked" capture v2
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+/// Snapshot length written to pcap headers
+const WRITE_SNAPLEN: u32 = 262_144;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const IPPROTO_UDP: u8 = 17;
+
+#[derive(Debug, Error)]
+pub enum PcapError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a pcap or pcapng file")]
+    UnknownFormat,
+    #[error("Truncated capture at byte {0}")]
+    Truncated(usize),
+    #[error("Malformed capture: {0}")]
+    Malformed(String),
+}
+
+/// One captured frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcapPacket {
+    /// Capture time since the Unix epoch
+    pub timestamp: Duration,
+    /// Link-layer type of `data` (`LINKTYPE_*`)
+    pub linktype: u32,
+    /// Captured bytes, starting at the link-layer header
+    pub data: Vec<u8>,
+}
+
+/// Read every packet of a pcap or pcapng file
+pub fn read_capture<P: AsRef<Path>>(path: P) -> Result<Vec<PcapPacket>, PcapError> {
+    parse_capture(&std::fs::read(path)?)
+}
+
+/// Parse an in-memory pcap or pcapng capture
+pub fn parse_capture(bytes: &[u8]) -> Result<Vec<PcapPacket>, PcapError> {
+    let magic = read_u32(bytes, 0, false).ok_or(PcapError::UnknownFormat)?;
+    if magic == PCAPNG_SECTION_HEADER {
+        return parse_pcapng(bytes);
+    }
+    parse_pcap(bytes)
+}
+
+fn parse_pcap(bytes: &[u8]) -> Result<Vec<PcapPacket>, PcapError> {
+    let (big_endian, nanos) = match read_u32(bytes, 0, false) {
+        Some(PCAP_MAGIC_MICROS) => (false, false),
+        Some(PCAP_MAGIC_NANOS) => (false, true),
+        Some(m) if m.swap_bytes() == PCAP_MAGIC_MICROS => (true, false),
+        Some(m) if m.swap_bytes() == PCAP_MAGIC_NANOS => (true, true),
+        _ => return Err(PcapError::UnknownFormat),
+    };
+    let linktype = read_u32(bytes, 20, big_endian).ok_or(PcapError::Truncated(bytes.len()))?;
+
+    let mut packets = Vec::new();
+    let mut offset = PCAP_HEADER_LEN;
+    while offset < bytes.len() {
+        let field = |at: usize| read_u32(bytes, offset + at, big_endian);
+        let (Some(secs), Some(frac), Some(len)) = (field(0), field(4), field(8)) else {
+            return Err(PcapError::Truncated(offset));
+        };
+        let start = offset + PCAP_RECORD_HEADER_LEN;
+        let data = bytes
+            .get(start..start + len as usize)
+            .ok_or(PcapError::Truncated(offset))?;
+
+        let frac = if nanos {
+            frac
+        } else {
+            frac.saturating_mul(1000)
+        };
+        packets.push(PcapPacket {
+            timestamp: Duration::new(secs as u64, frac),
+            linktype,
+            data: data.to_vec(),
+        });
+        offset = start + len as usize;
+    }
+    Ok(packets)
+}
+
+/// Link type and timestamp units per second of a pcapng interface
+struct Interface {
+    linktype: u32,
+    units_per_sec: u64,
+}
+
+fn parse_pcapng(bytes: &[u8]) -> Result<Vec<PcapPacket>, PcapError> {
+    let mut packets = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut big_endian = false;
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let block_type = read_u32(bytes, offset, big_endian).ok_or(PcapError::Truncated(offset))?;
+        if block_type == PCAPNG_SECTION_HEADER {
+            // Each section sets its own byte order and interface list
+            big_endian = match read_u32(bytes, offset + 8, false) {
+                Some(PCAPNG_BYTE_ORDER_MAGIC) => false,
+                Some(m) if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return Err(PcapError::Malformed("bad byte-order magic".into())),
+            };
+            interfaces.clear();
+        }
+
+        let block_len =
+            read_u32(bytes, offset + 4, big_endian).ok_or(PcapError::Truncated(offset))? as usize;
+        if block_len < 12 || !block_len.is_multiple_of(4) {
+            return Err(PcapError::Malformed(format!("block length {}", block_len)));
+        }
+        let body = bytes
+            .get(offset + 8..offset + block_len - 4)
+            .ok_or(PcapError::Truncated(offset))?;
+
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                let linktype =
+                    read_u16(body, 0, big_endian).ok_or(PcapError::Truncated(offset))? as u32;
+                let units_per_sec = interface_tsresol(body.get(8..).unwrap_or(&[]), big_endian);
+                interfaces.push(Interface {
+                    linktype,
+                    units_per_sec,
+                });
+            }
+            PCAPNG_ENHANCED_PACKET => {
+                let field = |at: usize| read_u32(body, at, big_endian);
+                let (Some(id), Some(high), Some(low), Some(len)) =
+                    (field(0), field(4), field(8), field(12))
+                else {
+                    return Err(PcapError::Truncated(offset));
+                };
+                let interface = interfaces.get(id as usize).ok_or_else(|| {
+                    PcapError::Malformed(format!("packet for unknown interface {}", id))
+                })?;
+                let data = body
+                    .get(20..20 + len as usize)
+                    .ok_or(PcapError::Truncated(offset))?;
+                let units = ((high as u64) << 32) | low as u64;
+                packets.push(PcapPacket {
+                    timestamp: units_to_duration(units, interface.units_per_sec),
+                    linktype: interface.linktype,
+                    data: data.to_vec(),
+                });
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                // No timestamp; always belongs to the first interface
+                let interface = interfaces.first().ok_or_else(|| {
+                    PcapError::Malformed("simple packet before any interface".into())
+                })?;
+                let len = read_u32(body, 0, big_endian).ok_or(PcapError::Truncated(offset))?;
+                let data = &body[4..];
+                packets.push(PcapPacket {
+                    timestamp: Duration::ZERO,
+                    linktype: interface.linktype,
+                    data: data[..data.len().min(len as usize)].to_vec(),
+                });
+            }
+            _ => {}
+        }
+        offset += block_len;
+    }
+    Ok(packets)
+}
+
+/// Timestamp units per second from an interface's `if_tsresol` option (default µs)
+fn interface_tsresol(mut options: &[u8], big_endian: bool) -> u64 {
+    while let (Some(code), Some(len)) = (
+        read_u16(options, 0, big_endian),
+        read_u16(options, 2, big_endian),
+    ) {
+        if code == 0 {
+            break;
+        }
+        if code == PCAPNG_OPTION_TSRESOL && len >= 1 {
+            if let Some(&resol) = options.get(4) {
+                let exponent = (resol & 0x7f) as u32;
+                return if resol & 0x80 == 0 {
+                    10u64.checked_pow(exponent).unwrap_or(1_000_000)
+                } else {
+                    2u64.checked_pow(exponent).unwrap_or(1_000_000)
+                };
+            }
+        }
+        let padded = (len as usize).div_ceil(4) * 4;
+        options = options.get(4 + padded..).unwrap_or(&[]);
+    }
+    1_000_000
+}
+
+fn units_to_duration(units: u64, units_per_sec: u64) -> Duration {
+    let secs = units / units_per_sec;
+    let rem = units % units_per_sec;
+    let nanos = (rem as u128 * 1_000_000_000 / units_per_sec as u128) as u32;
+    Duration::new(secs, nanos)
+}
+
+/// The UDP payload of a captured frame, `None` for anything but a complete UDP datagram
+///
+/// Replay only needs what the application sent; addresses and ports in the
+/// capture are discarded.
+pub fn udp_payload(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    let (ethertype, ip) = match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = read_u16(frame, 12, true)?;
+            let mut start = 14;
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                ethertype = read_u16(frame, start + 2, true)?;
+                start += 4;
+            }
+            (ethertype, frame.get(start..)?)
+        }
+        LINKTYPE_LINUX_SLL => (read_u16(frame, 14, true)?, frame.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (read_u16(frame, 0, true)?, frame.get(20..)?),
+        LINKTYPE_RAW | 12 | 14 | LINKTYPE_NULL => {
+            let ip = if linktype == LINKTYPE_NULL {
+                frame.get(4..)?
+            } else {
+                frame
+            };
+            match ip.first()? >> 4 {
+                4 => (ETHERTYPE_IPV4, ip),
+                6 => (ETHERTYPE_IPV6, ip),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let udp = match ethertype {
+        ETHERTYPE_IPV4 => {
+            let header_len = ((ip.first()? & 0x0f) as usize) * 4;
+            let flags_fragment = read_u16(ip, 6, true)?;
+            // Only unfragmented datagrams carry the whole payload
+            if ip.get(9)? != &IPPROTO_UDP || flags_fragment & 0x3fff != 0 {
+                return None;
+            }
+            ip.get(header_len..)?
+        }
+        ETHERTYPE_IPV6 => {
+            let mut next = *ip.get(6)?;
+            let mut rest = ip.get(40..)?;
+            // Hop-by-hop, routing and destination options precede the UDP header
+            while matches!(next, 0 | 43 | 60) {
+                let len = (*rest.get(1)? as usize + 1) * 8;
+                next = *rest.first()?;
+                rest = rest.get(len..)?;
+            }
+            if next != IPPROTO_UDP {
+                return None;
+            }
+            rest
+        }
+        _ => return None,
+    };
+
+    let udp_len = read_u16(udp, 4, true)? as usize;
+    udp.get(8..udp_len.clamp(8, udp.len()))
+}
+
+/// Writer for classic little-endian, microsecond pcap files
+pub struct PcapWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Write the file header for frames of `linktype`
+    pub fn new(mut out: W, linktype: u32) -> io::Result<Self> {
+        let mut header = [0u8; PCAP_HEADER_LEN];
+        header[0..4].copy_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        header[4..6].copy_from_slice(&2u16.to_le_bytes());
+        header[6..8].copy_from_slice(&4u16.to_le_bytes());
+        header[16..20].copy_from_slice(&WRITE_SNAPLEN.to_le_bytes());
+        header[20..24].copy_from_slice(&linktype.to_le_bytes());
+        out.write_all(&header)?;
+        Ok(Self { out })
+    }
+
+    /// Append one frame captured at `timestamp` (since the Unix epoch)
+    pub fn write_packet(&mut self, timestamp: Duration, data: &[u8]) -> io::Result<()> {
+        let captured = data.len().min(WRITE_SNAPLEN as usize);
+        let mut record = [0u8; PCAP_RECORD_HEADER_LEN];
+        record[0..4].copy_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+        record[4..8].copy_from_slice(&timestamp.subsec_micros().to_le_bytes());
+        record[8..12].copy_from_slice(&(captured as u32).to_le_bytes());
+        record[12..16].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        self.out.write_all(&record)?;
+        self.out.write_all(&data[..captured])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize, big_endian: bool) -> Option<u16> {
+    let raw: [u8; 2] = bytes.get(at..at + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(raw)
+    } else {
+        u16::from_le_bytes(raw)
+    })
+}
+
+fn read_u32(bytes: &[u8], at: usize, big_endian: bool) -> Option<u32> {
+    let raw: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(raw)
+    } else {
+        u32::from_le_bytes(raw)
+    })
+}
+
+/// Ethernet + IPv4 + UDP frame around `payload`, as a capture would hold it
+/// Ethernet frame carrying `payload` as a UDP datagram from `src` to `dst`
+///
+/// MAC addresses are zero. The IP family follows `dst`; a `src` of the other
+/// family is replaced by the unspecified address. IPv4 header and UDP
+/// checksums are valid, so dissectors show the datagram as sent.
+pub fn udp_frame_between(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (8 + payload.len()) as u16;
+    let mut udp = Vec::with_capacity(udp_len as usize);
+    udp.extend_from_slice(&src.port().to_be_bytes());
+    udp.extend_from_slice(&dst.port().to_be_bytes());
+    udp.extend_from_slice(&udp_len.to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+
+    let mut frame = vec![0u8; 14];
+    match dst.ip() {
+        IpAddr::V4(dst_ip) => {
+            let src_ip = match src.ip() {
+                IpAddr::V4(ip) => ip,
+                IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+            };
+            let mut header = [0u8; 20];
+            header[0] = 0x45;
+            header[2..4].copy_from_slice(&(20 + udp_len).to_be_bytes());
+            header[6] = 0x40;
+            header[8] = 64;
+            header[9] = IPPROTO_UDP;
+            header[12..16].copy_from_slice(&src_ip.octets());
+            header[16..20].copy_from_slice(&dst_ip.octets());
+            let checksum = checksum_simd(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+            let mut pseudo = [0u8; 12];
+            pseudo[0..8].copy_from_slice(&header[12..20]);
+            pseudo[9] = IPPROTO_UDP;
+            pseudo[10..12].copy_from_slice(&udp_len.to_be_bytes());
+            set_udp_checksum(&mut udp, &pseudo);
+
+            frame[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+            frame.extend_from_slice(&header);
+        }
+        IpAddr::V6(dst_ip) => {
+            let src_ip = match src.ip() {
+                IpAddr::V6(ip) => ip,
+                IpAddr::V4(_) => Ipv6Addr::UNSPECIFIED,
+            };
+            let mut header = [0u8; 40];
+            header[0] = 0x60;
+            header[4..6].copy_from_slice(&udp_len.to_be_bytes());
+            header[6] = IPPROTO_UDP;
+            header[7] = 64;
+            header[8..24].copy_from_slice(&src_ip.octets());
+            header[24..40].copy_from_slice(&dst_ip.octets());
+
+            let mut pseudo = [0u8; 40];
+            pseudo[0..32].copy_from_slice(&header[8..40]);
+            pseudo[34..36].copy_from_slice(&udp_len.to_be_bytes());
+            pseudo[39] = IPPROTO_UDP;
+            set_udp_checksum(&mut udp, &pseudo);
+
+            frame[12..14].copy_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+            frame.extend_from_slice(&header);
+        }
+    }
+    frame.extend_from_slice(&udp);
+    frame
+}
+
+fn set_udp_checksum(udp: &mut [u8], pseudo: &[u8]) {
+    // Zero means "no checksum" on the wire, so a computed zero is sent as 0xFFFF
+    let checksum = match checksum_simd_with_pseudo(pseudo, udp) {
+        0 => 0xFFFF,
+        c => c,
+    };
+    udp[6..8].copy_from_slice(&checksum.to_be_bytes());
+}
+
+#[cfg(test)]
+pub(crate) fn udp_frame(payload: &[u8]) -> Vec<u8> {
+    udp_frame_between(
+        SocketAddr::from(([192, 0, 2, 1], 4000)),
+        SocketAddr::from(([192, 0, 2, 2], 53)),
+        payload,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcap_round_trip() {
+        let mut writer = PcapWriter::new(Vec::new(), LINKTYPE_ETHERNET).unwrap();
+        let frames: Vec<Vec<u8>> = [&b"one"[..], b"two", b"three"]
+            .iter()
+            .map(|p| udp_frame(p))
+            .collect();
+        for (i, frame) in frames.iter().enumerate() {
+            writer
+                .write_packet(Duration::new(1_700_000_000, i as u32 * 1_000), frame)
+                .unwrap();
+        }
+
+        let packets = parse_capture(&writer.into_inner()).unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[2].timestamp, Duration::new(1_700_000_000, 2_000));
+        let payloads: Vec<&[u8]> = packets
+            .iter()
+            .map(|p| udp_payload(p.linktype, &p.data).unwrap())
+            .collect();
+        assert_eq!(payloads, [&b"one"[..], b"two", b"three"]);
+    }
+
+    #[test]
+    fn test_udp_frame_checksums() {
+        let v4 = udp_frame(b"payload");
+        assert_eq!(checksum_simd(&v4[14..34]), 0);
+        let pseudo = [&v4[26..34], &[0, IPPROTO_UDP], &v4[38..40]].concat();
+        assert_eq!(checksum_simd_with_pseudo(&pseudo, &v4[34..]), 0);
+
+        let src = SocketAddr::from((Ipv6Addr::LOCALHOST, 4000));
+        let dst = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 53));
+        let v6 = udp_frame_between(src, dst, b"payload");
+        assert_eq!(udp_payload(LINKTYPE_ETHERNET, &v6), Some(&b"payload"[..]));
+        let mut pseudo = v6[22..54].to_vec();
+        pseudo.extend_from_slice(&[0, 0, v6[58], v6[59], 0, 0, 0, IPPROTO_UDP]);
+        assert_eq!(checksum_simd_with_pseudo(&pseudo, &v6[54..]), 0);
+    }
+
+    #[test]
+    fn test_pcapng_nanosecond_interface() {
+        fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+            let len = (12 + body.len().div_ceil(4) * 4) as u32;
+            let mut out = Vec::new();
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(body);
+            out.resize(len as usize - 4, 0);
+            out.extend_from_slice(&len.to_le_bytes());
+            out
+        }
+
+        let mut shb = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        shb.extend_from_slice(&[1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        let mut idb = vec![LINKTYPE_RAW as u8, 0, 0, 0, 0, 0, 4, 0];
+        idb.extend_from_slice(&[PCAPNG_OPTION_TSRESOL as u8, 0, 1, 0, 9, 0, 0, 0]);
+
+        let frame = udp_frame(b"payload");
+        let ip = &frame[14..];
+        let units: u64 = 1_500_000_000;
+        let mut epb = Vec::new();
+        for field in [
+            0,
+            (units >> 32) as u32,
+            units as u32,
+            ip.len() as u32,
+            ip.len() as u32,
+        ] {
+            epb.extend_from_slice(&field.to_le_bytes());
+        }
+        epb.extend_from_slice(ip);
+
+        let mut file = block(PCAPNG_SECTION_HEADER, &shb);
+        file.extend(block(PCAPNG_INTERFACE_DESCRIPTION, &idb));
+        file.extend(block(PCAPNG_ENHANCED_PACKET, &epb));
+
+        let packets = parse_capture(&file).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].timestamp, Duration::from_millis(1500));
+        assert_eq!(
+            udp_payload(packets[0].linktype, &packets[0].data),
+            Some(&b"payload"[..])
+        );
+    }
+}
@@ -1,9 +1,11 @@
 //! Packet pool module
 //! Pre-allocated packet buffers for zero-allocation sending
 
-use std::sync::Arc;
-use parking_lot::Mutex;
 use crossbeam::queue::ArrayQueue;
+use parking_lot::Mutex;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Pre-allocated packet buffer
 #[derive(Clone)]
@@ -58,49 +60,269 @@ impl PacketBuffer {
     }
 }
 
+/// Free list for one buffer size
+struct SizeClass {
+    size: usize,
+    free: ArrayQueue<PacketBuffer>,
+    /// Buffers of this class handed out and not yet returned
+    outstanding: AtomicUsize,
+}
+
+/// Utilization of one size class, from `PacketPool::stats`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassStats {
+    /// Buffer size of the class
+    pub size: usize,
+    /// Buffers the class keeps
+    pub capacity: usize,
+    /// Buffers sitting in the free list
+    pub available: usize,
+    /// Buffers handed out, including ones allocated by growth
+    pub in_use: usize,
+}
+
+impl ClassStats {
+    /// Fraction of the class's buffers in use
+    pub fn utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.in_use as f64 / self.capacity as f64
+    }
+}
+
+/// Snapshot of a `PacketPool`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    /// One entry per size class, smallest first
+    pub classes: Vec<ClassStats>,
+    /// Requests larger than every class, served by one-off allocations
+    pub non_pooled: u64,
+}
+
 /// Lock-free packet pool for high-performance allocation
+///
+/// Buffers are kept in size classes; a request is served from the smallest
+/// class that fits it.
 pub struct PacketPool {
-    pool: ArrayQueue<PacketBuffer>,
-    packet_size: usize,
+    /// Sorted by size, ascending
+    classes: Vec<SizeClass>,
+    /// Zero buffer contents when they come back (safer, slower)
+    zero_on_return: bool,
+    /// Allocate a fresh buffer instead of failing when a class is empty
+    grow: bool,
+    /// Pooled buffers currently held by a `PooledBuffer` guard
+    guarded: AtomicUsize,
+    non_pooled: AtomicU64,
 }
 
 impl PacketPool {
-    /// Create a new packet pool
+    /// Create a new packet pool with a single size class
     pub fn new(capacity: usize, packet_size: usize) -> Self {
-        let pool = ArrayQueue::new(capacity);
-        
-        // Pre-allocate buffers
-        for _ in 0..capacity {
-            let _ = pool.push(PacketBuffer::new(packet_size));
+        Self::with_classes(&[packet_size], capacity)
+    }
+
+    /// Create a pool keeping `per_class` buffers of each size in `classes`
+    pub fn with_classes(classes: &[usize], per_class: usize) -> Self {
+        let mut sizes = classes.to_vec();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        let classes = sizes
+            .into_iter()
+            .map(|size| {
+                // Pre-allocate buffers
+                let free = ArrayQueue::new(per_class.max(1));
+                for _ in 0..per_class {
+                    let _ = free.push(PacketBuffer::new(size));
+                }
+                SizeClass {
+                    size,
+                    free,
+                    outstanding: AtomicUsize::new(0),
+                }
+            })
+            .collect();
+
+        Self {
+            classes,
+            zero_on_return: false,
+            grow: false,
+            guarded: AtomicUsize::new(0),
+            non_pooled: AtomicU64::new(0),
         }
+    }
 
-        Self { pool, packet_size }
+    /// Zero returned buffers so no packet data outlives its use
+    pub fn with_zero_on_return(mut self, enabled: bool) -> Self {
+        self.zero_on_return = enabled;
+        self
     }
 
-    /// Acquire a packet buffer from the pool
-    pub fn acquire(&self) -> Option<PacketBuffer> {
-        self.pool.pop()
+    /// Allocate when a class is empty instead of returning `None`; extra
+    /// buffers are dropped on return once the class is full again
+    pub fn with_growth(mut self, enabled: bool) -> Self {
+        self.grow = enabled;
+        self
+    }
+
+    /// Acquire a buffer of at least `len` bytes that returns itself to the
+    /// pool on drop. It derefs to exactly `len` bytes.
+    ///
+    /// Requests larger than every class get a one-off allocation that is not
+    /// pooled (see `PooledBuffer::is_pooled`). `None` means the class that
+    /// fits is empty and growth is off.
+    pub fn acquire(&self, len: usize) -> Option<PooledBuffer<'_>> {
+        let Some(class) = self.classes.iter().find(|class| class.size >= len) else {
+            self.non_pooled.fetch_add(1, Ordering::Relaxed);
+            let mut buffer = PacketBuffer::new(len);
+            buffer.len = len;
+            return Some(PooledBuffer {
+                pool: self,
+                buffer: Some(buffer),
+                pooled: false,
+            });
+        };
+
+        let mut buffer = match class.free.pop() {
+            Some(buffer) => buffer,
+            None if self.grow => PacketBuffer::new(class.size),
+            None => return None,
+        };
+        class.outstanding.fetch_add(1, Ordering::Relaxed);
+        self.guarded.fetch_add(1, Ordering::Relaxed);
+        buffer.len = len;
+        Some(PooledBuffer {
+            pool: self,
+            buffer: Some(buffer),
+            pooled: true,
+        })
     }
 
     /// Return a packet buffer to the pool
+    ///
+    /// Buffers that match no size class (non-pooled allocations) are dropped.
     pub fn release(&self, mut buffer: PacketBuffer) {
+        let Some(class) = self
+            .classes
+            .iter()
+            .find(|class| class.size == buffer.capacity())
+        else {
+            return;
+        };
+        class.outstanding.fetch_sub(1, Ordering::Relaxed);
+        if self.zero_on_return {
+            buffer.data.fill(0);
+        }
         buffer.clear();
-        let _ = self.pool.push(buffer);
+        let _ = class.free.push(buffer);
+    }
+
+    /// Buffers taken out with `PooledBuffer::detach` and never released
+    ///
+    /// A guard passed to `mem::forget` still counts as held, not leaked.
+    pub fn leaked_count(&self) -> usize {
+        let outstanding: usize = self
+            .classes
+            .iter()
+            .map(|class| class.outstanding.load(Ordering::Relaxed))
+            .sum();
+        outstanding.saturating_sub(self.guarded.load(Ordering::Relaxed))
+    }
+
+    /// Per-class utilization and the number of non-pooled allocations
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            classes: self
+                .classes
+                .iter()
+                .map(|class| ClassStats {
+                    size: class.size,
+                    capacity: class.free.capacity(),
+                    available: class.free.len(),
+                    in_use: class.outstanding.load(Ordering::Relaxed),
+                })
+                .collect(),
+            non_pooled: self.non_pooled.load(Ordering::Relaxed),
+        }
     }
 
     /// Get number of available buffers
     pub fn available(&self) -> usize {
-        self.pool.len()
+        self.classes.iter().map(|class| class.free.len()).sum()
     }
 
     /// Get pool capacity
     pub fn capacity(&self) -> usize {
-        self.pool.capacity()
+        self.classes.iter().map(|class| class.free.capacity()).sum()
     }
 
     /// Check if pool is empty
     pub fn is_empty(&self) -> bool {
-        self.pool.is_empty()
+        self.classes.iter().all(|class| class.free.is_empty())
+    }
+}
+
+/// Buffer borrowed from a `PacketPool`, returned automatically when dropped
+///
+/// Derefs to the requested length.
+pub struct PooledBuffer<'a> {
+    pool: &'a PacketPool,
+    buffer: Option<PacketBuffer>,
+    /// False for one-off allocations larger than every size class
+    pooled: bool,
+}
+
+impl PooledBuffer<'_> {
+    /// Whether the buffer came from a size class and will be reused
+    pub fn is_pooled(&self) -> bool {
+        self.pooled
+    }
+
+    /// Size of the underlying allocation (the class size for pooled buffers)
+    pub fn capacity(&self) -> usize {
+        self.buffer.as_ref().map_or(0, PacketBuffer::capacity)
+    }
+
+    /// Take the buffer out of the guard; it must go back through `PacketPool::release`
+    pub fn detach(mut self) -> PacketBuffer {
+        if self.pooled {
+            self.pool.guarded.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.buffer.take().expect("buffer present until drop")
+    }
+
+    /// Underlying buffer, for `set_data` and friends
+    pub fn buffer_mut(&mut self) -> &mut PacketBuffer {
+        self.buffer.as_mut().expect("buffer present until drop")
+    }
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.buffer
+            .as_ref()
+            .expect("buffer present until drop")
+            .as_slice()
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.buffer_mut().as_mut_slice()
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            if self.pooled {
+                self.pool.guarded.fetch_sub(1, Ordering::Relaxed);
+                self.pool.release(buffer);
+            }
+        }
     }
 }
 
@@ -229,11 +451,79 @@ mod tests {
         let pool = PacketPool::new(10, 1500);
         assert_eq!(pool.available(), 10);
         
-        let buf = pool.acquire().unwrap();
+        let buf = pool.acquire(1500).unwrap();
         assert_eq!(pool.available(), 9);
         
-        pool.release(buf);
+        pool.release(buf.detach());
         assert_eq!(pool.available(), 10);
+        assert_eq!(pool.leaked_count(), 0);
+    }
+
+    #[test]
+    fn test_pooled_buffer_returns_on_drop() {
+        let pool = PacketPool::new(1, 64);
+        {
+            let mut buf = pool.acquire(64).unwrap();
+            assert_eq!(buf.len(), 64);
+            buf[..5].copy_from_slice(b"hello");
+            assert!(pool.acquire(64).is_none(), "pool of one is exhausted");
+        }
+        assert_eq!(pool.available(), 1);
+
+        // Same buffer comes back, contents kept without zero_on_return
+        let buf = pool.acquire(64).unwrap();
+        assert_eq!(&buf[..5], b"hello");
+        drop(buf);
+
+        let pool = PacketPool::new(1, 64).with_zero_on_return(true);
+        pool.acquire(64).unwrap()[..5].copy_from_slice(b"hello");
+        assert!(pool.acquire(64).unwrap().iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_packet_pool_growth_and_leaks() {
+        let pool = PacketPool::new(1, 64).with_growth(true);
+        let first = pool.acquire(64).unwrap();
+        let second = pool.acquire(64).expect("grows when empty");
+        assert_eq!(pool.leaked_count(), 0);
+
+        let detached = first.detach();
+        assert_eq!(pool.leaked_count(), 1);
+        drop(detached);
+        assert_eq!(pool.leaked_count(), 1);
+
+        drop(second);
+        assert_eq!(pool.available(), 1);
+        assert_eq!(pool.leaked_count(), 1);
+    }
+
+    #[test]
+    fn test_size_classes() {
+        let pool = PacketPool::with_classes(&[1500, 64, 9000, 512], 4);
+        assert_eq!(pool.capacity(), 16);
+
+        let small = pool.acquire(100).unwrap();
+        assert!(small.is_pooled());
+        assert_eq!(small.capacity(), 512);
+        assert_eq!(small.len(), 100);
+
+        let huge = pool.acquire(20_000).unwrap();
+        assert!(!huge.is_pooled());
+        assert_eq!(huge.len(), 20_000);
+
+        let stats = pool.stats();
+        let sizes: Vec<usize> = stats.classes.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![64, 512, 1500, 9000]);
+        assert_eq!(stats.classes[1].in_use, 1);
+        assert_eq!(stats.classes[1].available, 3);
+        assert_eq!(stats.classes[1].utilization(), 0.25);
+        assert_eq!(stats.non_pooled, 1);
+
+        drop(small);
+        drop(huge);
+        assert_eq!(pool.available(), 16);
+        assert!(pool.stats().classes.iter().all(|c| c.in_use == 0));
+        assert_eq!(pool.leaked_count(), 0);
     }
 
     #[test]
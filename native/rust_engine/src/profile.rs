@@ -0,0 +1,281 @@
+//! Reproducible run profiles
+//! Saves a fully specified `EngineConfig` to a versioned JSON file and reloads it elsewhere
+//!
+//! A profile looks like `{"version":1,"config":{"target":"...","protocol":"udp",...}}`.
+//! Fields missing from an older profile take their defaults; unknown fields and
+//! profiles written by a newer schema version are rejected rather than dropped.
+//!
+//! `to_json`/`from_json` read and write the bare config object without the
+//! version envelope, for hand-written scenario files.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::engine::{EngineConfig, EngineError, FloodEngine};
+
+/// Schema version written by `save_profile`
+pub const PROFILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    version: u32,
+    config: EngineConfig,
+}
+
+impl EngineConfig {
+    /// Write this configuration as a versioned profile
+    pub fn save_profile<P: AsRef<Path>>(&self, path: P) -> Result<(), EngineError> {
+        let profile = Profile {
+            version: PROFILE_VERSION,
+            config: self.clone(),
+        };
+        let json = serde_json::to_string_pretty(&profile)
+            .map_err(|e| EngineError::ProfileError(e.to_string()))?;
+        std::fs::write(path.as_ref(), json)
+            .map_err(|e| EngineError::ProfileError(format!("{}: {}", path.as_ref().display(), e)))
+    }
+
+    /// Read a configuration saved with `save_profile`
+    pub fn load_profile<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+        let json = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            EngineError::ProfileError(format!("{}: {}", path.as_ref().display(), e))
+        })?;
+        Self::from_profile_json(&json)
+    }
+
+    fn from_profile_json(json: &str) -> Result<Self, EngineError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| EngineError::ProfileError(e.to_string()))?;
+
+        // Check the version before the body so newer fields are reported as such
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| EngineError::ProfileError("missing profile version".to_string()))?;
+        if version > PROFILE_VERSION as u64 {
+            return Err(EngineError::ProfileError(format!(
+                "profile schema version {} is newer than supported version {}",
+                version, PROFILE_VERSION
+            )));
+        }
+
+        let profile: Profile =
+            serde_json::from_value(value).map_err(|e| EngineError::ProfileError(e.to_string()))?;
+        Ok(profile.config)
+    }
+}
+
+impl EngineConfig {
+    /// This configuration as a bare JSON object
+    pub fn to_json(&self) -> Result<String, EngineError> {
+        serde_json::to_string_pretty(self).map_err(|e| EngineError::InvalidConfig(e.to_string()))
+    }
+
+    /// Parse a bare JSON config object, rejecting unknown fields
+    ///
+    /// Missing fields take their defaults. Errors name the offending field.
+    pub fn from_json(json: &str) -> Result<Self, EngineError> {
+        Self::from_json_with(json, true)
+    }
+
+    /// Parse a bare JSON config object; unless `strict`, top-level fields
+    /// this version does not know are skipped with a warning
+    pub fn from_json_with(json: &str, strict: bool) -> Result<Self, EngineError> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| EngineError::InvalidConfig(e.to_string()))?;
+        let fields = value.as_object_mut().ok_or_else(|| {
+            EngineError::InvalidConfig("config must be a JSON object".to_string())
+        })?;
+
+        if !strict {
+            let known = serde_json::to_value(EngineConfig::default())
+                .map_err(|e| EngineError::InvalidConfig(e.to_string()))?;
+            fields.retain(|name, _| {
+                let keep = known.get(name).is_some();
+                if !keep {
+                    tracing::warn!("ignoring unknown config field {:?}", name);
+                }
+                keep
+            });
+        }
+
+        serde_json::from_value(value.clone()).map_err(|e| {
+            // serde reports the problem but not where; retry field by field to find it
+            let culprit = value.as_object().and_then(|fields| {
+                fields.iter().find(|(name, field)| {
+                    let single = serde_json::json!({ name.as_str(): field });
+                    serde_json::from_value::<EngineConfig>(single).is_err()
+                })
+            });
+            match culprit {
+                Some((name, _)) => EngineError::InvalidConfig(format!("field `{}`: {}", name, e)),
+                None => EngineError::InvalidConfig(e.to_string()),
+            }
+        })
+    }
+}
+
+impl FloodEngine {
+    /// Create an engine from a saved profile
+    pub fn from_profile<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+        FloodEngine::new(EngineConfig::load_profile(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::SizeDistribution;
+    use crate::packet::Protocol;
+    use std::time::Duration;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "netstress_profile_{}_{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let config = EngineConfig {
+            target: "127.0.0.1".to_string(),
+            port: 9000,
+            threads: 2,
+            protocol: Protocol::TCP,
+            rate_limit: Some(5000),
+            duration: Some(Duration::from_secs(30)),
+            drop_fraction: Some(0.1),
+            seed: Some(42),
+            size_distribution: Some(SizeDistribution::Buckets(vec![(64, 3), (1400, 1)])),
+            ..Default::default()
+        };
+
+        let path = temp_path("round_trip");
+        config.save_profile(&path).unwrap();
+        let loaded = EngineConfig::load_profile(&path).unwrap();
+        let engine = FloodEngine::from_profile(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded, config);
+        assert_eq!(engine.config(), &config);
+    }
+
+    #[test]
+    fn test_older_profile_uses_defaults() {
+        let config = EngineConfig::from_profile_json(
+            r#"{"version":1,"config":{"target":"10.0.0.1","protocol":"udp"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.target, "10.0.0.1");
+        assert_eq!(config.threads, EngineConfig::default().threads);
+    }
+
+    #[test]
+    fn test_newer_or_unknown_profile_is_rejected() {
+        let newer = EngineConfig::from_profile_json(
+            r#"{"version":99,"config":{"target":"10.0.0.1","future_field":true}}"#,
+        );
+        assert!(matches!(newer, Err(EngineError::ProfileError(ref e)) if e.contains("newer")));
+
+        let unknown = EngineConfig::from_profile_json(
+            r#"{"version":1,"config":{"target":"10.0.0.1","future_field":true}}"#,
+        );
+        assert!(
+            matches!(unknown, Err(EngineError::ProfileError(ref e)) if e.contains("future_field"))
+        );
+
+        assert!(EngineConfig::from_profile_json(r#"{"config":{}}"#).is_err());
+    }
+
+    #[test]
+    fn test_json_config_round_trip() {
+        use crate::engine::{
+            AddressFamily, PacketSource, PortMode, RampConfig, RampCurve, WorkDistribution,
+        };
+
+        let config = EngineConfig {
+            target: "::1".to_string(),
+            port: 5353,
+            destinations: vec!["127.0.0.1:9".to_string(), "[::1]:9".to_string()],
+            port_range: Some((9000, 9004)),
+            port_mode: PortMode::Random,
+            threads: 3,
+            packet_size: 512,
+            protocol: Protocol::HTTP,
+            rate_limit: Some(20_000),
+            conn_rate_limit: Some(100),
+            duration: Some(Duration::from_millis(2_500)),
+            use_raw_sockets: true,
+            sockets_per_thread: 2,
+            drop_fraction: Some(0.25),
+            seed: Some(7),
+            tcp_fastopen: true,
+            size_distribution: Some(SizeDistribution::Buckets(vec![(64, 1), (1200, 2)])),
+            refuse_over_memory: true,
+            address_family: AddressFamily::V6,
+            interface: Some("eth1".to_string()),
+            dont_fragment: true,
+            clamp_to_mtu: true,
+            ttl: Some(32),
+            dscp: Some(46),
+            ecn: Some(1),
+            clamp_to_interface_mtu: true,
+            pin_threads: true,
+            numa_aware: true,
+            payload_factory: None,
+            dry_run: true,
+            work_distribution: WorkDistribution::Stealing,
+            gso_segments: Some(16),
+            source: PacketSource::Pcap("capture.pcap".into()),
+            replay_timing: Some(2.0),
+            zerocopy: true,
+            autoscale: true,
+            max_threads: Some(8),
+            ramp: Some(RampConfig {
+                start_pps: 100,
+                end_pps: 10_000,
+                duration: Duration::from_secs(5),
+                curve: RampCurve::Exponential,
+            }),
+            rate_schedule: vec![(Duration::from_secs(1), 500), (Duration::from_secs(2), 900)],
+            interpolate_schedule: true,
+            record_send_timing: true,
+            embed_sequence: true,
+            sequence_offset: 16,
+            sequence_per_worker: true,
+            collect_responses: true,
+            response_timeout: Duration::from_millis(250),
+            max_cpu_percent: Some(85.0),
+            max_rss_bytes: Some(1 << 30),
+        };
+
+        let json = config.to_json().unwrap();
+        assert_eq!(EngineConfig::from_json(&json).unwrap(), config);
+        assert_eq!(
+            EngineConfig::from_json("{}").unwrap(),
+            EngineConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_json_config_errors_name_the_field() {
+        let err = EngineConfig::from_json(r#"{"target":"10.0.0.1","port":70000}"#).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidConfig(ref e) if e.starts_with("field `port`")));
+
+        let err = EngineConfig::from_json(r#"{"protocol":"smtp"}"#).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidConfig(ref e) if e.contains("`protocol`")));
+
+        // Unknown fields only pass in lenient mode
+        let json = r#"{"target":"10.0.0.1","future_field":true}"#;
+        let err = EngineConfig::from_json(json).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidConfig(ref e) if e.contains("future_field")));
+        let config = EngineConfig::from_json_with(json, false).unwrap();
+        assert_eq!(config.target, "10.0.0.1");
+
+        assert!(EngineConfig::from_json("[1, 2]").is_err());
+        assert!(EngineConfig::from_json("{").is_err());
+    }
+}
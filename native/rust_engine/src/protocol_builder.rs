@@ -2,7 +2,9 @@
 //! Implements advanced packet construction for stress testing
 
 use crate::packet::{PacketBuilder, PacketFlags, Protocol, PacketError};
-use crate::simd::checksum_simd;
+use crate::simd::{
+    checksum_simd, checksum_simd_with_pseudo, checksum_update, checksum_update_words,
+};
 use rand::Rng;
 use std::net::Ipv4Addr;
 
@@ -103,6 +105,45 @@ impl Default for FragmentConfig {
     }
 }
 
+/// How TCP/UDP checksums are filled in. ICMP checksums are always computed,
+/// since raw ICMP sockets send them as given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumMode {
+    /// Leave the field zero for the kernel or NIC to fill (checksum offload)
+    Hardware,
+    /// Compute over the pseudo-header; a UDP result of 0 is sent as 0xFFFF
+    #[default]
+    Software,
+    /// Send zero. For UDP over IPv4 this means "no checksum" (RFC 768)
+    Zero,
+}
+
+impl ChecksumMode {
+    /// Offload through standard kernel sockets, compute for raw sockets
+    pub fn for_socket(raw: bool) -> Self {
+        if raw {
+            ChecksumMode::Software
+        } else {
+            ChecksumMode::Hardware
+        }
+    }
+}
+
+/// IANA-assigned VXLAN UDP port (RFC 7348)
+pub const VXLAN_PORT: u16 = 4789;
+/// GRE protocol type for an encapsulated IPv4 packet
+pub const GRE_PROTO_IPV4: u16 = 0x0800;
+/// GRE protocol type for an encapsulated IPv6 packet
+pub const GRE_PROTO_IPV6: u16 = 0x86DD;
+/// GRE protocol type for an encapsulated Ethernet frame (transparent bridging)
+pub const GRE_PROTO_TEB: u16 = 0x6558;
+
+const IPPROTO_GRE: u8 = 47;
+/// VXLAN flags with only the I bit set: the VNI is valid
+const VXLAN_FLAGS: u8 = 0x08;
+/// GRE flags word with the C bit set: checksum and reserved1 fields present
+const GRE_FLAG_CHECKSUM: u16 = 0x8000;
+
 /// Enhanced protocol builder with spoofing and fragmentation
 pub struct ProtocolBuilder {
     /// Spoofing configuration
@@ -113,6 +154,8 @@ pub struct ProtocolBuilder {
     ttl: u8,
     /// IP identification counter
     id_counter: u16,
+    /// TCP/UDP checksum handling
+    checksum: ChecksumMode,
 }
 
 impl Default for ProtocolBuilder {
@@ -128,6 +171,7 @@ impl ProtocolBuilder {
             fragment: FragmentConfig::default(),
             ttl: 64,
             id_counter: rand::random(),
+            checksum: ChecksumMode::default(),
         }
     }
 
@@ -157,6 +201,12 @@ impl ProtocolBuilder {
         self
     }
 
+    /// Set how TCP/UDP checksums are filled in
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.checksum = mode;
+        self
+    }
+
     /// Build UDP packet with optional spoofing
     pub fn build_udp(
         &mut self,
@@ -188,6 +238,19 @@ impl ProtocolBuilder {
         &mut self,
         dst_ip: &str,
         dst_port: u16,
+    ) -> Result<Vec<u8>, PacketError> {
+        self.build_tcp_packet(dst_ip, dst_port, PacketFlags::syn(), rand::random(), 0)
+    }
+
+    /// Build TCP packet with arbitrary flags and sequence/ack numbers.
+    /// A set URG flag gets an urgent pointer of 1 since a zero pointer is invalid.
+    pub fn build_tcp_packet(
+        &mut self,
+        dst_ip: &str,
+        dst_port: u16,
+        flags: PacketFlags,
+        seq: u32,
+        ack: u32,
     ) -> Result<Vec<u8>, PacketError> {
         let src_ip = if self.spoof.enabled {
             self.spoof.random_ip()
@@ -201,7 +264,7 @@ impl ProtocolBuilder {
         let dst: Ipv4Addr = dst_ip.parse()
             .map_err(|_| PacketError::InvalidIp(dst_ip.into()))?;
         
-        self.build_tcp_packet(src_ip, dst, src_port, dst_port, PacketFlags::syn(), &[])
+        self.build_tcp_segment(src_ip, dst, src_port, dst_port, flags, seq, ack, &[])
     }
 
     /// Build TCP ACK packet
@@ -222,8 +285,17 @@ impl ProtocolBuilder {
         
         let dst: Ipv4Addr = dst_ip.parse()
             .map_err(|_| PacketError::InvalidIp(dst_ip.into()))?;
-        
-        self.build_tcp_packet(src_ip, dst, src_port, dst_port, PacketFlags::ack(), payload)
+
+        self.build_tcp_segment(
+            src_ip,
+            dst,
+            src_port,
+            dst_port,
+            PacketFlags::ack(),
+            rand::random(),
+            0,
+            payload,
+        )
     }
 
     /// Build TCP RST packet
@@ -232,19 +304,7 @@ impl ProtocolBuilder {
         dst_ip: &str,
         dst_port: u16,
     ) -> Result<Vec<u8>, PacketError> {
-        let src_ip = if self.spoof.enabled {
-            self.spoof.random_ip()
-        } else {
-            Ipv4Addr::new(0, 0, 0, 0)
-        };
-        
-        let src_port: u16 = rand::random();
-        self.id_counter = self.id_counter.wrapping_add(1);
-        
-        let dst: Ipv4Addr = dst_ip.parse()
-            .map_err(|_| PacketError::InvalidIp(dst_ip.into()))?;
-        
-        self.build_tcp_packet(src_ip, dst, src_port, dst_port, PacketFlags::rst(), &[])
+        self.build_tcp_packet(dst_ip, dst_port, PacketFlags::rst(), rand::random(), 0)
     }
 
     /// Build ICMP echo request
@@ -275,19 +335,8 @@ impl ProtocolBuilder {
         host: &str,
         path: &str,
     ) -> Result<Vec<u8>, PacketError> {
-        let http_payload = format!(
-            "GET {} HTTP/1.1\r\n\
-             Host: {}\r\n\
-             User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36\r\n\
-             Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
-             Accept-Language: en-US,en;q=0.5\r\n\
-             Accept-Encoding: gzip, deflate\r\n\
-             Connection: keep-alive\r\n\
-             \r\n",
-            path, host
-        );
-        
-        self.build_tcp_ack(dst_ip, dst_port, http_payload.as_bytes())
+        let payload = http_get_payload(host, path);
+        self.build_tcp_ack(dst_ip, dst_port, &payload)
     }
 
     /// Build HTTP POST request packet
@@ -299,20 +348,30 @@ impl ProtocolBuilder {
         path: &str,
         body: &[u8],
     ) -> Result<Vec<u8>, PacketError> {
-        let http_payload = format!(
-            "POST {} HTTP/1.1\r\n\
-             Host: {}\r\n\
-             User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36\r\n\
-             Content-Type: application/x-www-form-urlencoded\r\n\
-             Content-Length: {}\r\n\
-             Connection: keep-alive\r\n\
-             \r\n",
-            path, host, body.len()
-        );
-        
-        let mut payload = http_payload.into_bytes();
-        payload.extend_from_slice(body);
-        
+        let payload = http_post_payload(host, path, body);
+        self.build_tcp_ack(dst_ip, dst_port, &payload)
+    }
+
+    /// Build HTTP/2 connection preface packet (preface, SETTINGS and a GET HEADERS frame)
+    pub fn build_http2_preface(
+        &mut self,
+        dst_ip: &str,
+        dst_port: u16,
+        authority: &str,
+        path: &str,
+    ) -> Result<Vec<u8>, PacketError> {
+        let payload = http2_preface(authority, path)?;
+        self.build_tcp_ack(dst_ip, dst_port, &payload)
+    }
+
+    /// Build TLS ClientHello packet with SNI
+    pub fn build_tls_client_hello(
+        &mut self,
+        dst_ip: &str,
+        dst_port: u16,
+        server_name: &str,
+    ) -> Result<Vec<u8>, PacketError> {
+        let payload = tls_client_hello(server_name)?;
         self.build_tcp_ack(dst_ip, dst_port, &payload)
     }
 
@@ -322,44 +381,106 @@ impl ProtocolBuilder {
         dst_ip: &str,
         domain: &str,
     ) -> Result<Vec<u8>, PacketError> {
-        let mut dns_payload = Vec::with_capacity(512);
-        
-        // Transaction ID
-        let txid: u16 = rand::random();
-        dns_payload.extend_from_slice(&txid.to_be_bytes());
-        
-        // Flags: Standard query
-        dns_payload.extend_from_slice(&[0x01, 0x00]);
-        
-        // Questions: 1
-        dns_payload.extend_from_slice(&[0x00, 0x01]);
-        
-        // Answer RRs: 0
-        dns_payload.extend_from_slice(&[0x00, 0x00]);
-        
-        // Authority RRs: 0
-        dns_payload.extend_from_slice(&[0x00, 0x00]);
-        
-        // Additional RRs: 0
-        dns_payload.extend_from_slice(&[0x00, 0x00]);
-        
-        // Query name (domain)
-        for label in domain.split('.') {
-            dns_payload.push(label.len() as u8);
-            dns_payload.extend_from_slice(label.as_bytes());
+        let payload = dns_query_payload(domain)?;
+        self.build_udp(dst_ip, 53, &payload)
+    }
+
+    /// Wrap the IP packet `inner` in Ethernet, VXLAN, UDP and IPv4 headers
+    ///
+    /// VXLAN carries Ethernet frames, so `inner` gets an Ethernet header with
+    /// zeroed MACs and the EtherType of its IP version. The outer UDP source
+    /// port is derived from the inner headers (RFC 7348 flow entropy), and
+    /// the outer UDP checksum follows the builder's `ChecksumMode`. With
+    /// spoofing enabled the outer source comes from the spoofed range instead
+    /// of `outer_src`.
+    pub fn encapsulate_vxlan(
+        &mut self,
+        inner: &[u8],
+        vni: u32,
+        outer_src: &str,
+        outer_dst: &str,
+    ) -> Result<Vec<u8>, PacketError> {
+        if vni >= 1 << 24 {
+            return Err(PacketError::BuildError(format!(
+                "VNI {} exceeds 24 bits",
+                vni
+            )));
         }
-        dns_payload.push(0x00); // Null terminator
-        
-        // Query type: A (1)
-        dns_payload.extend_from_slice(&[0x00, 0x01]);
-        
-        // Query class: IN (1)
-        dns_payload.extend_from_slice(&[0x00, 0x01]);
-        
-        self.build_udp(dst_ip, 53, &dns_payload)
+        let ethertype = match inner.first().map(|b| b >> 4) {
+            Some(4) => GRE_PROTO_IPV4,
+            Some(6) => GRE_PROTO_IPV6,
+            _ => {
+                return Err(PacketError::BuildError(
+                    "inner packet is not IPv4 or IPv6".into(),
+                ))
+            }
+        };
+        let (src, dst) = self.outer_addresses(outer_src, outer_dst)?;
+
+        let mut payload = Vec::with_capacity(8 + 14 + inner.len());
+        payload.extend_from_slice(&[VXLAN_FLAGS, 0, 0, 0]);
+        payload.extend_from_slice(&(vni << 8).to_be_bytes());
+        payload.extend_from_slice(&[0u8; 12]);
+        payload.extend_from_slice(&ethertype.to_be_bytes());
+        payload.extend_from_slice(inner);
+
+        let entropy = checksum_simd(&inner[..inner.len().min(40)]);
+        let src_port = 0xC000 | (entropy & 0x3FFF);
+        self.id_counter = self.id_counter.wrapping_add(1);
+        self.build_udp_packet(src, dst, src_port, VXLAN_PORT, &payload)
+    }
+
+    /// Wrap `inner` in GRE (RFC 2784) and IPv4 headers
+    ///
+    /// `protocol_type` names the inner payload, e.g. `GRE_PROTO_IPV4` or
+    /// `GRE_PROTO_TEB` for an Ethernet frame. Under `ChecksumMode::Software`
+    /// the optional GRE checksum is included; other modes omit it. Spoofing
+    /// applies to the outer source as in `encapsulate_vxlan`.
+    pub fn encapsulate_gre(
+        &mut self,
+        inner: &[u8],
+        protocol_type: u16,
+        outer_src: &str,
+        outer_dst: &str,
+    ) -> Result<Vec<u8>, PacketError> {
+        let (src, dst) = self.outer_addresses(outer_src, outer_dst)?;
+
+        let with_checksum = self.checksum == ChecksumMode::Software;
+        let header_len = if with_checksum { 8 } else { 4 };
+        let mut gre = Vec::with_capacity(header_len + inner.len());
+        let flags = if with_checksum { GRE_FLAG_CHECKSUM } else { 0 };
+        gre.extend_from_slice(&flags.to_be_bytes());
+        gre.extend_from_slice(&protocol_type.to_be_bytes());
+        if with_checksum {
+            gre.extend_from_slice(&[0u8; 4]);
+        }
+        gre.extend_from_slice(inner);
+        if with_checksum {
+            let checksum = checksum_simd(&gre);
+            gre[4..6].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        self.id_counter = self.id_counter.wrapping_add(1);
+        let mut packet = self.build_ip_header(src, dst, IPPROTO_GRE, gre.len(), 0x4000);
+        packet.extend_from_slice(&gre);
+        Ok(packet)
     }
 
     // Internal packet building methods
+
+    /// Outer tunnel endpoints, the source replaced when spoofing
+    fn outer_addresses(&self, src: &str, dst: &str) -> Result<(Ipv4Addr, Ipv4Addr), PacketError> {
+        let dst: Ipv4Addr = dst
+            .parse()
+            .map_err(|_| PacketError::InvalidIp(dst.into()))?;
+        let src = if self.spoof.enabled {
+            self.spoof.random_ip()
+        } else {
+            src.parse()
+                .map_err(|_| PacketError::InvalidIp(src.into()))?
+        };
+        Ok((src, dst))
+    }
     
     fn build_ip_header(
         &self,
@@ -415,6 +536,10 @@ impl ProtocolBuilder {
         udp_header[6] = 0x00; // Checksum optional for IPv4
         udp_header[7] = 0x00;
         
+        let checksum = self.l4_checksum(17, &udp_header, payload, src, dst);
+        udp_header[6] = ((checksum >> 8) & 0xFF) as u8;
+        udp_header[7] = (checksum & 0xFF) as u8;
+
         let ip_header = self.build_ip_header(src, dst, 17, udp_len, 0x4000); // Don't fragment
         
         let mut packet = ip_header;
@@ -424,13 +549,16 @@ impl ProtocolBuilder {
         Ok(packet)
     }
 
-    fn build_tcp_packet(
+    #[allow(clippy::too_many_arguments)]
+    fn build_tcp_segment(
         &self,
         src: Ipv4Addr,
         dst: Ipv4Addr,
         src_port: u16,
         dst_port: u16,
         flags: PacketFlags,
+        seq: u32,
+        ack: u32,
         payload: &[u8],
     ) -> Result<Vec<u8>, PacketError> {
         let tcp_header_len = 20;
@@ -442,11 +570,10 @@ impl ProtocolBuilder {
         tcp_header[3] = (dst_port & 0xFF) as u8;
         
         // Sequence number
-        let seq: u32 = rand::random();
         tcp_header[4..8].copy_from_slice(&seq.to_be_bytes());
         
         // Acknowledgment number
-        tcp_header[8..12].copy_from_slice(&0u32.to_be_bytes());
+        tcp_header[8..12].copy_from_slice(&ack.to_be_bytes());
         
         // Data offset (5 words) + reserved
         tcp_header[12] = 0x50;
@@ -462,12 +589,16 @@ impl ProtocolBuilder {
         tcp_header[16] = 0x00;
         tcp_header[17] = 0x00;
         
-        // Urgent pointer
-        tcp_header[18] = 0x00;
-        tcp_header[19] = 0x00;
+        // Urgent pointer: only meaningful with URG, which requires it non-zero
+        let urgent: u16 = if flags.urg {
+            payload.len().clamp(1, u16::MAX as usize) as u16
+        } else {
+            0
+        };
+        tcp_header[18..20].copy_from_slice(&urgent.to_be_bytes());
         
         // Calculate TCP checksum
-        let checksum = self.tcp_checksum(&tcp_header, payload, src, dst);
+        let checksum = self.l4_checksum(6, &tcp_header, payload, src, dst);
         tcp_header[16] = ((checksum >> 8) & 0xFF) as u8;
         tcp_header[17] = (checksum & 0xFF) as u8;
         
@@ -523,69 +654,379 @@ impl ProtocolBuilder {
         dst_port: u16,
         payload: &[u8],
     ) -> Result<Vec<u8>, PacketError> {
-        let frag_size = self.fragment.fragment_size as usize;
-        let mut fragments = Vec::new();
+        let datagram = self.build_udp_packet(src, dst, src_port, dst_port, payload)?;
+        let fragments = Self::build_fragmented(&datagram, &self.fragment)?;
         
-        // Build UDP header for first fragment
-        let udp_len = 8 + payload.len();
-        let mut udp_header = vec![0u8; 8];
-        udp_header[0] = ((src_port >> 8) & 0xFF) as u8;
-        udp_header[1] = (src_port & 0xFF) as u8;
-        udp_header[2] = ((dst_port >> 8) & 0xFF) as u8;
-        udp_header[3] = (dst_port & 0xFF) as u8;
-        udp_header[4] = ((udp_len >> 8) & 0xFF) as u8;
-        udp_header[5] = (udp_len & 0xFF) as u8;
+        Ok(fragments.concat())
+    }
+
+    /// Split an IPv4 packet into fragments carrying at most
+    /// `config.fragment_size` bytes of data each (rounded down to a multiple of 8).
+    /// Every fragment repeats the original header with DF cleared, its own
+    /// offset and MF bit, and a fresh header checksum. Packets that already fit
+    /// are returned unchanged.
+    pub fn build_fragmented(
+        packet: &[u8],
+        config: &FragmentConfig,
+    ) -> Result<Vec<Vec<u8>>, PacketError> {
+        if packet.len() < 20 || packet[0] >> 4 != 4 {
+            return Err(PacketError::BuildError("not an IPv4 packet".into()));
+        }
+        let header_len = ((packet[0] & 0x0F) as usize) * 4;
+        let total_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        if header_len < 20 || total_len < header_len || total_len > packet.len() {
+            return Err(PacketError::BuildError("malformed IPv4 header".into()));
+        }
         
-        // First fragment includes UDP header
-        let mut first_payload = udp_header;
-        let first_data_len = (frag_size - 8).min(payload.len());
-        first_payload.extend_from_slice(&payload[..first_data_len]);
-        
-        // MF (More Fragments) flag set
-        let ip_header = self.build_ip_header(src, dst, 17, first_payload.len(), 0x2000);
-        let mut first_frag = ip_header;
-        first_frag.extend(first_payload);
-        fragments.extend(first_frag);
-        
-        // Subsequent fragments
-        let mut offset = first_data_len;
-        while offset < payload.len() {
-            let chunk_len = frag_size.min(payload.len() - offset);
-            let frag_offset = (8 + offset) / 8; // Fragment offset in 8-byte units
-            
-            let flags_offset = if offset + chunk_len < payload.len() {
-                0x2000 | (frag_offset as u16) // MF set
-            } else {
-                frag_offset as u16 // Last fragment
-            };
-            
-            let ip_header = self.build_ip_header(src, dst, 17, chunk_len, flags_offset);
-            fragments.extend(ip_header);
-            fragments.extend_from_slice(&payload[offset..offset + chunk_len]);
+        let chunk_size = (config.fragment_size as usize / 8) * 8;
+        if chunk_size == 0 {
+            return Err(PacketError::BuildError(format!(
+                "fragment size {} is below 8 bytes",
+                config.fragment_size
+            )));
+        }
+        
+        let header = &packet[..header_len];
+        let data = &packet[header_len..total_len];
+        if data.len() <= chunk_size {
+            return Ok(vec![packet[..total_len].to_vec()]);
+        }
+        
+        // Preserve an existing offset/MF so fragmenting a fragment stays valid
+        let orig_flags = u16::from_be_bytes([packet[6], packet[7]]);
+        let base_offset = (orig_flags & 0x1FFF) as usize;
+        let orig_mf = orig_flags & 0x2000;
+
+        let mut fragments = Vec::with_capacity(data.len().div_ceil(chunk_size));
+        for (i, chunk) in data.chunks(chunk_size).enumerate() {
+            let is_last = (i + 1) * chunk_size >= data.len();
+            let offset = base_offset + i * chunk_size / 8;
+            if offset > 0x1FFF {
+                return Err(PacketError::PayloadTooLarge(data.len()));
+            }
+            let mf = if is_last { orig_mf } else { 0x2000 };
+            let flags_offset = mf | offset as u16;
             
-            offset += chunk_len;
+            let mut frag = Vec::with_capacity(header_len + chunk.len());
+            frag.extend_from_slice(header);
+            frag.extend_from_slice(chunk);
+            frag[2..4].copy_from_slice(&((header_len + chunk.len()) as u16).to_be_bytes());
+            frag[6..8].copy_from_slice(&flags_offset.to_be_bytes());
+            frag[10] = 0x00;
+            frag[11] = 0x00;
+            let checksum = checksum_simd(&frag[..header_len]);
+            frag[10..12].copy_from_slice(&checksum.to_be_bytes());
+            fragments.push(frag);
         }
         
         Ok(fragments)
     }
 
-    fn tcp_checksum(&self, tcp_header: &[u8], payload: &[u8], src: Ipv4Addr, dst: Ipv4Addr) -> u16 {
-        let tcp_len = tcp_header.len() + payload.len();
+    /// TCP/UDP checksum field value for the configured `ChecksumMode`
+    fn l4_checksum(
+        &self,
+        protocol: u8,
+        header: &[u8],
+        payload: &[u8],
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+    ) -> u16 {
+        match self.checksum {
+            ChecksumMode::Hardware | ChecksumMode::Zero => 0,
+            ChecksumMode::Software => {
+                let checksum = Self::transport_checksum(protocol, header, payload, src, dst);
+                // A computed UDP checksum of zero is transmitted as all ones
+                if protocol == 17 && checksum == 0 {
+                    0xFFFF
+                } else {
+                    checksum
+                }
+            }
+        }
+    }
+
+    fn transport_checksum(
+        protocol: u8,
+        header: &[u8],
+        payload: &[u8],
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+    ) -> u16 {
+        let len = header.len() + payload.len();
         
-        let mut pseudo = Vec::with_capacity(12 + tcp_len);
+        let mut pseudo = Vec::with_capacity(12 + len);
         pseudo.extend_from_slice(&src.octets());
         pseudo.extend_from_slice(&dst.octets());
         pseudo.push(0);
-        pseudo.push(6); // TCP
-        pseudo.push(((tcp_len >> 8) & 0xFF) as u8);
-        pseudo.push((tcp_len & 0xFF) as u8);
-        pseudo.extend_from_slice(tcp_header);
+        pseudo.push(protocol);
+        pseudo.push(((len >> 8) & 0xFF) as u8);
+        pseudo.push((len & 0xFF) as u8);
+        pseudo.extend_from_slice(header);
         pseudo.extend_from_slice(payload);
         
         checksum_simd(&pseudo)
     }
 }
 
+/// HTTP/2 client connection preface (RFC 9113 section 3.4)
+pub const HTTP2_CLIENT_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Default SETTINGS_MAX_FRAME_SIZE; a HEADERS frame must fit in one frame
+const HTTP2_MAX_FRAME_SIZE: usize = 16_384;
+
+const HTTP2_FRAME_HEADERS: u8 = 0x01;
+const HTTP2_FRAME_SETTINGS: u8 = 0x04;
+const HTTP2_FLAG_END_STREAM: u8 = 0x01;
+const HTTP2_FLAG_END_HEADERS: u8 = 0x04;
+
+/// Append a 9-byte HTTP/2 frame header followed by the payload
+fn push_http2_frame(out: &mut Vec<u8>, frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+    let len = payload.len() as u32;
+    out.extend_from_slice(&len.to_be_bytes()[1..]); // 24-bit length
+    out.push(frame_type);
+    out.push(flags);
+    out.extend_from_slice(&(stream_id & 0x7FFF_FFFF).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+/// Append an HPACK integer with an N-bit prefix (RFC 7541 section 5.1)
+fn push_hpack_int(out: &mut Vec<u8>, first_byte: u8, prefix_bits: u8, mut value: usize) {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    if value < max_prefix {
+        out.push(first_byte | value as u8);
+        return;
+    }
+    out.push(first_byte | max_prefix as u8);
+    value -= max_prefix;
+    while value >= 0x80 {
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Append a literal header field without indexing, name taken from the static table
+fn push_hpack_literal(out: &mut Vec<u8>, name_index: usize, value: &str) {
+    push_hpack_int(out, 0x00, 4, name_index);
+    push_hpack_int(out, 0x00, 7, value.len()); // no Huffman coding
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Generate an HTTP/1.1 GET request for `path` on `host`
+pub fn http_get_payload(host: &str, path: &str) -> Vec<u8> {
+    format!(
+        "GET {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36\r\n\
+         Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
+         Accept-Language: en-US,en;q=0.5\r\n\
+         Accept-Encoding: gzip, deflate\r\n\
+         Connection: keep-alive\r\n\
+         \r\n",
+        path, host
+    )
+    .into_bytes()
+}
+
+/// Generate an HTTP/1.1 form POST of `body` to `path` on `host`
+pub fn http_post_payload(host: &str, path: &str, body: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "POST {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36\r\n\
+         Content-Type: application/x-www-form-urlencoded\r\n\
+         Content-Length: {}\r\n\
+         Connection: keep-alive\r\n\
+         \r\n",
+        path,
+        host,
+        body.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(body);
+    out
+}
+
+/// Generate a recursive DNS query for the A record of `domain`
+pub fn dns_query_payload(domain: &str) -> Result<Vec<u8>, PacketError> {
+    let name = domain.strip_suffix('.').unwrap_or(domain);
+    // RFC 1035: labels of 1 to 63 bytes, at most 255 bytes encoded
+    if name.is_empty()
+        || name.len() + 2 > 255
+        || name
+            .split('.')
+            .any(|label| label.is_empty() || label.len() > 63)
+    {
+        return Err(PacketError::BuildError(format!(
+            "invalid DNS name {:?}",
+            domain
+        )));
+    }
+
+    let mut out = Vec::with_capacity(12 + name.len() + 6);
+    let txid: u16 = rand::random();
+    out.extend_from_slice(&txid.to_be_bytes());
+    out.extend_from_slice(&[0x01, 0x00]); // Flags: standard query, recursion desired
+    out.extend_from_slice(&[0x00, 0x01]); // Questions: 1
+    out.extend_from_slice(&[0x00, 0x00]); // Answer RRs: 0
+    out.extend_from_slice(&[0x00, 0x00]); // Authority RRs: 0
+    out.extend_from_slice(&[0x00, 0x00]); // Additional RRs: 0
+    for label in name.split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0x00); // Root label
+    out.extend_from_slice(&[0x00, 0x01]); // Query type: A
+    out.extend_from_slice(&[0x00, 0x01]); // Query class: IN
+    Ok(out)
+}
+
+/// Generate the HTTP/2 client preface, an empty SETTINGS frame and a
+/// HEADERS frame for `GET https://{authority}{path}` on stream 1
+pub fn http2_preface(authority: &str, path: &str) -> Result<Vec<u8>, PacketError> {
+    if authority.is_empty() {
+        return Err(PacketError::BuildError("empty :authority".into()));
+    }
+    if !path.starts_with('/') {
+        return Err(PacketError::BuildError(format!("invalid :path {:?}", path)));
+    }
+
+    // Header block: static table entries where they match exactly
+    let mut block = Vec::with_capacity(16 + authority.len() + path.len());
+    block.push(0x82); // :method: GET
+    block.push(0x87); // :scheme: https
+    if path == "/" {
+        block.push(0x84); // :path: /
+    } else {
+        push_hpack_literal(&mut block, 4, path);
+    }
+    push_hpack_literal(&mut block, 1, authority);
+
+    if block.len() > HTTP2_MAX_FRAME_SIZE {
+        return Err(PacketError::PayloadTooLarge(block.len()));
+    }
+
+    let mut out = Vec::with_capacity(HTTP2_CLIENT_PREFACE.len() + 18 + block.len());
+    out.extend_from_slice(HTTP2_CLIENT_PREFACE);
+    push_http2_frame(&mut out, HTTP2_FRAME_SETTINGS, 0, 0, &[]);
+    push_http2_frame(
+        &mut out,
+        HTTP2_FRAME_HEADERS,
+        HTTP2_FLAG_END_STREAM | HTTP2_FLAG_END_HEADERS,
+        1,
+        &block,
+    );
+    Ok(out)
+}
+
+/// Append `body` prefixed by its length as a big-endian u16
+fn push_u16_prefixed(out: &mut Vec<u8>, body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    out.extend_from_slice(body);
+}
+
+/// Append a TLS extension (type, u16 length, body)
+fn push_tls_extension(out: &mut Vec<u8>, ext_type: u16, body: &[u8]) {
+    out.extend_from_slice(&ext_type.to_be_bytes());
+    push_u16_prefixed(out, body);
+}
+
+/// Generate a TLS record carrying a ClientHello that offers TLS 1.3 and 1.2
+/// with the given SNI host name
+pub fn tls_client_hello(server_name: &str) -> Result<Vec<u8>, PacketError> {
+    // RFC 6066: host_name is at most 255 bytes of ASCII, no trailing dot
+    if server_name.is_empty()
+        || server_name.len() > 255
+        || server_name.ends_with('.')
+        || !server_name.is_ascii()
+        || server_name.parse::<std::net::IpAddr>().is_ok()
+    {
+        return Err(PacketError::BuildError(format!(
+            "invalid SNI host name {:?}",
+            server_name
+        )));
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut extensions = Vec::with_capacity(128 + server_name.len());
+
+    // server_name: list of one host_name entry
+    let mut sni_entry = Vec::with_capacity(3 + server_name.len());
+    sni_entry.push(0x00); // host_name
+    push_u16_prefixed(&mut sni_entry, server_name.as_bytes());
+    let mut sni = Vec::with_capacity(2 + sni_entry.len());
+    push_u16_prefixed(&mut sni, &sni_entry);
+    push_tls_extension(&mut extensions, 0x0000, &sni);
+
+    // supported_groups: x25519, secp256r1, secp384r1
+    push_tls_extension(
+        &mut extensions,
+        0x000a,
+        &[0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18],
+    );
+    // ec_point_formats: uncompressed
+    push_tls_extension(&mut extensions, 0x000b, &[0x01, 0x00]);
+    // signature_algorithms: ecdsa_secp256r1_sha256, rsa_pss_rsae_sha256, rsa_pkcs1_sha256,
+    // ecdsa_secp384r1_sha384, rsa_pss_rsae_sha384, rsa_pkcs1_sha384
+    push_tls_extension(
+        &mut extensions,
+        0x000d,
+        &[
+            0x00, 0x0c, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08, 0x05, 0x05, 0x01,
+        ],
+    );
+    // supported_versions: TLS 1.3, TLS 1.2
+    push_tls_extension(&mut extensions, 0x002b, &[0x04, 0x03, 0x04, 0x03, 0x03]);
+    // psk_key_exchange_modes: psk_dhe_ke
+    push_tls_extension(&mut extensions, 0x002d, &[0x01, 0x01]);
+    // key_share: one x25519 share
+    let mut key_share = Vec::with_capacity(38);
+    key_share.extend_from_slice(&[0x00, 0x24, 0x00, 0x1d, 0x00, 0x20]);
+    let key: [u8; 32] = rng.gen();
+    key_share.extend_from_slice(&key);
+    push_tls_extension(&mut extensions, 0x0033, &key_share);
+
+    let mut hello = Vec::with_capacity(128 + extensions.len());
+    hello.extend_from_slice(&[0x03, 0x03]); // legacy_version: TLS 1.2
+    let random: [u8; 32] = rng.gen();
+    hello.extend_from_slice(&random);
+    let session_id: [u8; 32] = rng.gen();
+    hello.push(session_id.len() as u8);
+    hello.extend_from_slice(&session_id);
+    push_u16_prefixed(
+        &mut hello,
+        &[
+            0x13, 0x01, 0x13, 0x02, 0x13, 0x03, // TLS 1.3 AEAD suites
+            0xc0, 0x2b, 0xc0, 0x2f, 0xc0, 0x2c, 0xc0, 0x30, // ECDHE-{ECDSA,RSA}-AES-GCM
+        ],
+    );
+    hello.extend_from_slice(&[0x01, 0x00]); // compression: null
+    push_u16_prefixed(&mut hello, &extensions);
+
+    // Handshake header: type 1 (ClientHello), 24-bit length
+    let mut record = Vec::with_capacity(9 + hello.len());
+    record.push(0x16); // handshake
+    record.extend_from_slice(&[0x03, 0x01]); // record version: TLS 1.0 for compatibility
+    record.extend_from_slice(&((hello.len() + 4) as u16).to_be_bytes());
+    record.push(0x01);
+    record.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+    record.extend_from_slice(&hello);
+    Ok(record)
+}
+
+/// Largest IPv4 packet the batch generator produces (Ethernet MTU)
+const BATCH_MTU: usize = 1500;
+
+/// Total IP packet size distribution for `BatchPacketGenerator::with_size_distribution`.
+/// Unlike the engine's `SizeDistribution` buckets this is bounded by a min/max range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketSizeDistribution {
+    /// Every size between min and max is equally likely
+    Uniform,
+    /// Simple IMIX: min, 576 and max bytes weighted 7:4:1
+    InternetMix,
+}
+
 /// Batch packet generator for high-throughput scenarios
 pub struct BatchPacketGenerator {
     builder: ProtocolBuilder,
@@ -593,6 +1034,10 @@ pub struct BatchPacketGenerator {
     dst_port: u16,
     protocol: Protocol,
     payload_size: usize,
+    /// First built packet, reused by `next_into` with per-packet fields rewritten
+    template: Option<Vec<u8>>,
+    /// Per-packet total size range and distribution
+    sizes: Option<(usize, usize, PacketSizeDistribution)>,
 }
 
 impl BatchPacketGenerator {
@@ -603,27 +1048,79 @@ impl BatchPacketGenerator {
             dst_port,
             protocol,
             payload_size,
+            template: None,
+            sizes: None,
+        }
+    }
+
+    /// Vary each packet's total IP length between `min` and `max`. The range is
+    /// clamped to the protocol's header length and the MTU. Only UDP, ICMP and
+    /// RAW packets carry the fill payload, so TCP and HTTP keep their size.
+    pub fn with_size_distribution(
+        mut self,
+        min: usize,
+        max: usize,
+        distribution: PacketSizeDistribution,
+    ) -> Self {
+        let header_len = self.header_len();
+        let max = max.clamp(header_len, BATCH_MTU);
+        let min = min.clamp(header_len, max);
+        self.sizes = Some((min, max, distribution));
+        self.template = None;
+        self
+    }
+
+    /// Bytes in front of the fill payload for the resizable protocols
+    fn header_len(&self) -> usize {
+        match self.protocol {
+            Protocol::UDP | Protocol::ICMP => 28,
+            _ => 0,
         }
     }
 
+    /// Payload length of the next packet
+    fn next_payload_len(&self) -> usize {
+        let Some((min, max, distribution)) = self.sizes else {
+            return self.payload_size;
+        };
+        let mut rng = rand::thread_rng();
+        let size = match distribution {
+            PacketSizeDistribution::Uniform => rng.gen_range(min..=max),
+            PacketSizeDistribution::InternetMix => match rng.gen_range(0..12) {
+                0..=6 => min,
+                7..=10 => 576usize.clamp(min, max),
+                _ => max,
+            },
+        };
+        size - self.header_len()
+    }
+
     /// Enable spoofing
     pub fn with_spoofing(mut self, cidr: &str) -> Result<Self, PacketError> {
         self.builder = self.builder.with_spoofing(cidr)?;
         Ok(self)
     }
 
+    /// Set how TCP/UDP checksums are filled in
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.builder = self.builder.with_checksum_mode(mode);
+        self.template = None;
+        self
+    }
+
     /// Generate a batch of packets
     pub fn generate_batch(&mut self, count: usize) -> Vec<Vec<u8>> {
-        let payload = vec![0xAA; self.payload_size];
+        let payload = vec![0xAA; self.max_payload_len()];
         let mut packets = Vec::with_capacity(count);
         
         for _ in 0..count {
+            let payload = &payload[..self.next_payload_len()];
             let packet = match self.protocol {
-                Protocol::UDP => self.builder.build_udp(&self.dst_ip, self.dst_port, &payload),
+                Protocol::UDP => self.builder.build_udp(&self.dst_ip, self.dst_port, payload),
                 Protocol::TCP => self.builder.build_tcp_syn(&self.dst_ip, self.dst_port),
-                Protocol::ICMP => self.builder.build_icmp_echo(&self.dst_ip, &payload),
+                Protocol::ICMP => self.builder.build_icmp_echo(&self.dst_ip, payload),
                 Protocol::HTTP => self.builder.build_http_get(&self.dst_ip, self.dst_port, &self.dst_ip, "/"),
-                Protocol::RAW => Ok(payload.clone()),
+                Protocol::RAW => Ok(payload.to_vec()),
             };
             
             if let Ok(p) = packet {
@@ -633,6 +1130,174 @@ impl BatchPacketGenerator {
         
         packets
     }
+
+    fn max_payload_len(&self) -> usize {
+        match self.sizes {
+            Some((_, max, _)) => max - self.header_len(),
+            None => self.payload_size,
+        }
+    }
+
+    /// Write the next packet into `buf` without allocating and return its length.
+    /// The first call builds a template; later calls copy it and rewrite the IP id,
+    /// spoofed source, source port, TCP sequence and the affected checksums.
+    pub fn next_into(&mut self, buf: &mut [u8]) -> Result<usize, PacketError> {
+        if self.template.is_none() {
+            let payload = vec![0xAA; self.max_payload_len()];
+            let packet = match self.protocol {
+                Protocol::UDP => self
+                    .builder
+                    .build_udp(&self.dst_ip, self.dst_port, &payload)?,
+                Protocol::TCP => self.builder.build_tcp_syn(&self.dst_ip, self.dst_port)?,
+                Protocol::ICMP => self.builder.build_icmp_echo(&self.dst_ip, &payload)?,
+                Protocol::HTTP => {
+                    self.builder
+                        .build_http_get(&self.dst_ip, self.dst_port, &self.dst_ip, "/")?
+                }
+                Protocol::RAW => payload,
+            };
+            self.template = Some(packet);
+        }
+        // The template carries the largest payload; shorter packets are a prefix of it
+        let len = match self.protocol {
+            Protocol::UDP | Protocol::ICMP | Protocol::RAW => {
+                self.header_len() + self.next_payload_len()
+            }
+            _ => self.template.as_ref().map_or(0, Vec::len),
+        };
+        let template = self.template.as_deref().unwrap_or_default();
+
+        if len > buf.len() {
+            return Err(PacketError::PayloadTooLarge(len));
+        }
+        let packet = &mut buf[..len];
+        packet.copy_from_slice(&template[..len]);
+        if self.protocol != Protocol::RAW {
+            self.rewrite_headers(packet, len != template.len());
+        }
+
+        Ok(len)
+    }
+
+    /// Write packets back to back into `buf`, one every `stride` bytes, storing
+    /// each length in `lens`. Stops when `lens` or `buf` runs out and returns
+    /// the number of packets written.
+    pub fn generate_into(
+        &mut self,
+        buf: &mut [u8],
+        stride: usize,
+        lens: &mut [usize],
+    ) -> Result<usize, PacketError> {
+        if stride == 0 {
+            return Err(PacketError::BuildError("stride must be non-zero".into()));
+        }
+
+        let mut written = 0;
+        for (slot, len) in buf.chunks_exact_mut(stride).zip(lens.iter_mut()) {
+            *len = self.next_into(slot)?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    /// Rewrite the per-packet fields of a copied template, patching checksums
+    /// incrementally. `resized` packets are a truncated template, so checksums
+    /// covering the payload are recomputed in full.
+    fn rewrite_headers(&mut self, packet: &mut [u8], resized: bool) {
+        let ihl = ((packet[0] & 0x0F) as usize) * 4;
+        let builder = &mut self.builder;
+        builder.id_counter = builder.id_counter.wrapping_add(1);
+
+        let (ip_header, l4) = packet.split_at_mut(ihl);
+        let src = if builder.spoof.enabled {
+            builder.spoof.random_ip().octets()
+        } else {
+            [ip_header[12], ip_header[13], ip_header[14], ip_header[15]]
+        };
+        // Source address words also feed the TCP/UDP pseudo-header
+        let src_edits = [
+            put_word(ip_header, 12, u16::from_be_bytes([src[0], src[1]])),
+            put_word(ip_header, 14, u16::from_be_bytes([src[2], src[3]])),
+        ];
+        let ip_edits = [
+            put_word(ip_header, 2, (ihl + l4.len()) as u16),
+            put_word(ip_header, 4, builder.id_counter),
+            src_edits[0],
+            src_edits[1],
+        ];
+        update_checksum_field(ip_header, 10, &ip_edits);
+
+        let software = builder.checksum == ChecksumMode::Software;
+        match ip_header[9] {
+            17 => {
+                let udp_len = l4.len() as u16;
+                put_word(l4, 4, udp_len);
+                let edits = [src_edits[0], src_edits[1], put_word(l4, 0, rand::random())];
+                if software {
+                    let checksum = if resized {
+                        l4[6] = 0x00;
+                        l4[7] = 0x00;
+                        pseudo_header_checksum(ip_header, l4)
+                    } else {
+                        checksum_update_words(u16::from_be_bytes([l4[6], l4[7]]), &edits)
+                    };
+                    // A computed UDP checksum of zero is transmitted as all ones
+                    let checksum = if checksum == 0 { 0xFFFF } else { checksum };
+                    l4[6..8].copy_from_slice(&checksum.to_be_bytes());
+                }
+            }
+            6 => {
+                let seq: u32 = rand::random();
+                let edits = [
+                    src_edits[0],
+                    src_edits[1],
+                    put_word(l4, 0, rand::random()),
+                    put_word(l4, 4, (seq >> 16) as u16),
+                    put_word(l4, 6, seq as u16),
+                ];
+                if software {
+                    update_checksum_field(l4, 16, &edits);
+                }
+            }
+            1 => {
+                let (old_id, new_id) = put_word(l4, 4, builder.id_counter);
+                let checksum = if resized {
+                    l4[2] = 0x00;
+                    l4[3] = 0x00;
+                    checksum_simd(l4)
+                } else {
+                    checksum_update(u16::from_be_bytes([l4[2], l4[3]]), old_id, new_id)
+                };
+                l4[2..4].copy_from_slice(&checksum.to_be_bytes());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Store a big-endian word at `at`, returning `(old, new)` for checksum updates
+fn put_word(buf: &mut [u8], at: usize, new: u16) -> (u16, u16) {
+    let old = u16::from_be_bytes([buf[at], buf[at + 1]]);
+    buf[at..at + 2].copy_from_slice(&new.to_be_bytes());
+    (old, new)
+}
+
+/// Patch the checksum stored at `at` for the given word edits
+fn update_checksum_field(buf: &mut [u8], at: usize, edits: &[(u16, u16)]) {
+    let (old, _) = put_word(buf, at, 0);
+    put_word(buf, at, checksum_update_words(old, edits));
+}
+
+/// TCP/UDP checksum over the IPv4 pseudo-header and `segment`, without
+/// copying the segment into a scratch buffer
+fn pseudo_header_checksum(ip_header: &[u8], segment: &[u8]) -> u16 {
+    let mut pseudo = [0u8; 12];
+    pseudo[..8].copy_from_slice(&ip_header[12..20]);
+    pseudo[9] = ip_header[9];
+    pseudo[10..12].copy_from_slice(&(segment.len() as u16).to_be_bytes());
+
+    checksum_simd_with_pseudo(&pseudo, segment)
 }
 
 #[cfg(test)]
@@ -684,4 +1349,535 @@ mod tests {
         let packets = gen.generate_batch(10);
         assert_eq!(packets.len(), 10);
     }
+
+    #[test]
+    fn test_http2_preface_bytes() {
+        let out = http2_preface("example.com", "/").unwrap();
+        assert_eq!(&out[..24], b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+        // Empty SETTINGS frame on stream 0
+        assert_eq!(&out[24..33], &[0, 0, 0, 0x04, 0, 0, 0, 0, 0]);
+        // HEADERS frame on stream 1 with END_STREAM | END_HEADERS
+        let block = [&[0x82, 0x87, 0x84, 0x01, 11][..], b"example.com"].concat();
+        assert_eq!(
+            &out[33..42],
+            &[0, 0, block.len() as u8, 0x01, 0x05, 0, 0, 0, 1]
+        );
+        assert_eq!(&out[42..], &block[..]);
+    }
+
+    #[test]
+    fn test_http2_headers_length_encoding() {
+        // 300-byte path: 24-bit frame length > 255 and a multi-byte HPACK length
+        let path = format!("/{}", "a".repeat(299));
+        let out = http2_preface("h", &path).unwrap();
+        let frame = &out[33..];
+        let len = u32::from_be_bytes([0, frame[0], frame[1], frame[2]]) as usize;
+        assert_eq!(len, frame.len() - 9);
+        let block = &frame[9..];
+        assert_eq!(&block[..4], &[0x82, 0x87, 0x04, 0x7F]);
+        // 300 - 127 = 173 = 0b1_0101101 -> [0xAD, 0x01]
+        assert_eq!(&block[4..6], &[0xAD, 0x01]);
+        assert_eq!(&block[6..306], path.as_bytes());
+
+        assert!(http2_preface("h", &format!("/{}", "a".repeat(20_000))).is_err());
+        assert!(http2_preface("h", "no-slash").is_err());
+    }
+
+    /// Walk a ClientHello record and return the SNI host name
+    fn parse_client_hello_sni(rec: &[u8]) -> String {
+        let take = |buf: &[u8], at: usize| u16::from_be_bytes([buf[at], buf[at + 1]]) as usize;
+        assert_eq!(rec[0], 0x16);
+        assert_eq!(take(rec, 3), rec.len() - 5);
+        let hs = &rec[5..];
+        assert_eq!(hs[0], 0x01);
+        assert_eq!(
+            u32::from_be_bytes([0, hs[1], hs[2], hs[3]]) as usize,
+            hs.len() - 4
+        );
+        let body = &hs[4..];
+        let mut at = 2 + 32;
+        at += 1 + body[at] as usize; // session id
+        at += 2 + take(body, at); // cipher suites
+        at += 1 + body[at] as usize; // compression methods
+        let ext_end = at + 2 + take(body, at);
+        assert_eq!(ext_end, body.len());
+        at += 2;
+        let mut sni = None;
+        while at < ext_end {
+            let ext_type = take(body, at);
+            let ext_len = take(body, at + 2);
+            let ext = &body[at + 4..at + 4 + ext_len];
+            if ext_type == 0 {
+                assert_eq!(take(ext, 0), ext.len() - 2);
+                assert_eq!(ext[2], 0x00);
+                let name_len = take(ext, 3);
+                assert_eq!(name_len, ext.len() - 5);
+                sni = Some(String::from_utf8(ext[5..].to_vec()).unwrap());
+            }
+            at += 4 + ext_len;
+        }
+        assert_eq!(at, ext_end);
+        sni.expect("no SNI extension")
+    }
+
+    #[test]
+    fn test_tls_client_hello_parses() {
+        let rec = tls_client_hello("example.com").unwrap();
+        assert_eq!(parse_client_hello_sni(&rec), "example.com");
+
+        // Long host name pushes the SNI lengths past a single byte
+        let long = format!(
+            "{}.{}.{}.{}.example",
+            "a".repeat(63),
+            "b".repeat(63),
+            "c".repeat(63),
+            "d".repeat(55)
+        );
+        assert_eq!(long.len(), 255);
+        let rec = tls_client_hello(&long).unwrap();
+        assert_eq!(parse_client_hello_sni(&rec), long);
+
+        assert!(tls_client_hello("").is_err());
+        assert!(tls_client_hello(&"a".repeat(256)).is_err());
+        assert!(tls_client_hello("10.0.0.1").is_err());
+    }
+
+    #[test]
+    fn test_http_payloads() {
+        let get = http_get_payload("example.com", "/index.html");
+        let text = std::str::from_utf8(&get).unwrap();
+        assert!(text.starts_with("GET /index.html HTTP/1.1\r\nHost: example.com\r\n"));
+        assert!(text.ends_with("\r\n\r\n"));
+
+        let post = http_post_payload("example.com", "/form", b"a=1&b=2");
+        let text = std::str::from_utf8(&post).unwrap();
+        assert!(text.starts_with("POST /form HTTP/1.1\r\nHost: example.com\r\n"));
+        assert!(text.contains("Content-Length: 7\r\n"));
+        assert!(text.ends_with("\r\n\r\na=1&b=2"));
+    }
+
+    #[test]
+    fn test_dns_query_payload() {
+        let query = dns_query_payload("www.example.com.").unwrap();
+        assert_eq!(&query[2..12], &[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..29], b"\x03www\x07example\x03com\x00");
+        assert_eq!(&query[29..], &[0x00, 0x01, 0x00, 0x01]);
+
+        assert!(dns_query_payload("").is_err());
+        assert!(dns_query_payload("a..b").is_err());
+        assert!(dns_query_payload(&format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn test_build_tls_client_hello_with_spoofing() {
+        let mut builder = ProtocolBuilder::new().with_spoofing("10.0.0.0/8").unwrap();
+        let packet = builder
+            .build_tls_client_hello("192.168.1.1", 443, "example.com")
+            .unwrap();
+        assert_eq!(packet[12], 10);
+        // 20-byte IP header + 20-byte TCP header, then the TLS record
+        assert_eq!(parse_client_hello_sni(&packet[40..]), "example.com");
+
+        let packet = builder
+            .build_http2_preface("192.168.1.1", 443, "example.com", "/")
+            .unwrap();
+        assert_eq!(&packet[40..64], HTTP2_CLIENT_PREFACE);
+    }
+
+    /// Re-sum the TCP segment over its pseudo-header; a valid checksum folds to zero
+    fn tcp_checksum_valid(packet: &[u8]) -> bool {
+        let segment = &packet[20..];
+        let mut pseudo = Vec::with_capacity(12 + segment.len());
+        pseudo.extend_from_slice(&packet[12..20]);
+        pseudo.extend_from_slice(&[0, 6]);
+        pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+        pseudo.extend_from_slice(segment);
+        checksum_simd(&pseudo) == 0
+    }
+
+    #[test]
+    fn test_build_tcp_packet_flags() {
+        let mut builder = ProtocolBuilder::new().with_spoofing("10.0.0.0/8").unwrap();
+
+        let syn = builder
+            .build_tcp_packet("192.168.1.1", 80, PacketFlags::syn(), 1000, 0)
+            .unwrap();
+        assert_eq!(syn[33], 0x02);
+        assert_eq!(&syn[24..28], &1000u32.to_be_bytes());
+        assert_eq!(&syn[38..40], &[0, 0]);
+        assert!(tcp_checksum_valid(&syn));
+
+        let ack = builder
+            .build_tcp_packet("192.168.1.1", 80, PacketFlags::ack(), 7, 0xDEAD_BEEF)
+            .unwrap();
+        assert_eq!(ack[33], 0x10);
+        assert_eq!(&ack[24..28], &7u32.to_be_bytes());
+        assert_eq!(&ack[28..32], &0xDEAD_BEEFu32.to_be_bytes());
+        assert!(tcp_checksum_valid(&ack));
+
+        let xmas = PacketFlags {
+            fin: true,
+            psh: true,
+            urg: true,
+            ..Default::default()
+        };
+        let xmas = builder
+            .build_tcp_packet("192.168.1.1", 80, xmas, 42, 0)
+            .unwrap();
+        assert_eq!(xmas[33], 0x29);
+        assert_ne!(
+            &xmas[38..40],
+            &[0, 0],
+            "URG needs a non-zero urgent pointer"
+        );
+        assert!(tcp_checksum_valid(&xmas));
+    }
+
+    #[test]
+    fn test_build_fragmented_reassembles() {
+        let mut builder = ProtocolBuilder::new();
+        let payload: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let datagram = builder.build_udp("192.168.1.1", 9, &payload).unwrap();
+        let config = FragmentConfig {
+            enabled: true,
+            fragment_size: 100,
+            ..Default::default()
+        };
+
+        let frags = ProtocolBuilder::build_fragmented(&datagram, &config).unwrap();
+        // 1008 bytes of UDP data in 96-byte chunks
+        assert_eq!(frags.len(), 11);
+
+        let mut reassembled = vec![0u8; datagram.len() - 20];
+        for (i, frag) in frags.iter().enumerate() {
+            assert_eq!(
+                checksum_simd(&frag[..20]),
+                0,
+                "header checksum of fragment {}",
+                i
+            );
+            assert_eq!(u16::from_be_bytes([frag[2], frag[3]]) as usize, frag.len());
+            assert_eq!(&frag[4..6], &datagram[4..6], "fragments share the IP id");
+            let flags = u16::from_be_bytes([frag[6], frag[7]]);
+            assert_eq!(flags & 0x4000, 0, "DF must be cleared");
+            let more = flags & 0x2000 != 0;
+            assert_eq!(
+                more,
+                i + 1 < frags.len(),
+                "only the last fragment clears MF"
+            );
+            let offset = (flags & 0x1FFF) as usize * 8;
+            if more {
+                assert_eq!((frag.len() - 20) % 8, 0);
+            }
+            reassembled[offset..offset + frag.len() - 20].copy_from_slice(&frag[20..]);
+        }
+        assert_eq!(&reassembled[..], &datagram[20..]);
+
+        // Small packets pass through untouched
+        let small = builder.build_udp("192.168.1.1", 9, b"x").unwrap();
+        assert_eq!(
+            ProtocolBuilder::build_fragmented(&small, &config).unwrap(),
+            vec![small]
+        );
+
+        let tiny = FragmentConfig {
+            fragment_size: 7,
+            ..config
+        };
+        assert!(ProtocolBuilder::build_fragmented(&datagram, &tiny).is_err());
+    }
+
+    mod alloc_counter {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        /// Counts allocations made by the current thread
+        pub struct CountingAlloc;
+
+        unsafe impl GlobalAlloc for CountingAlloc {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        pub fn allocations() -> usize {
+            ALLOCATIONS.with(|n| n.get())
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: alloc_counter::CountingAlloc = alloc_counter::CountingAlloc;
+
+    #[test]
+    fn test_generate_into_rewrites_each_packet() {
+        for protocol in [Protocol::UDP, Protocol::TCP, Protocol::ICMP, Protocol::HTTP] {
+            let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, protocol, 64)
+                .with_spoofing("10.0.0.0/8")
+                .unwrap();
+            let mut buf = vec![0u8; 64 * 512];
+            let mut lens = [0usize; 64];
+            assert_eq!(gen.generate_into(&mut buf, 512, &mut lens).unwrap(), 64);
+
+            let packets: Vec<&[u8]> = buf
+                .chunks(512)
+                .zip(lens)
+                .map(|(slot, len)| &slot[..len])
+                .collect();
+            for packet in &packets {
+                assert_eq!(checksum_simd(&packet[..20]), 0);
+                assert_eq!(packet[12], 10);
+                if protocol == Protocol::TCP || protocol == Protocol::HTTP {
+                    assert!(tcp_checksum_valid(packet));
+                }
+                if protocol == Protocol::ICMP {
+                    assert_eq!(checksum_simd(&packet[20..]), 0);
+                }
+                if protocol == Protocol::UDP {
+                    assert!(udp_checksum_valid(packet));
+                }
+            }
+            // IP ids advance per packet even though every slot came from one template
+            assert_ne!(&packets[0][4..6], &packets[1][4..6]);
+        }
+
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 1000);
+        assert!(matches!(
+            gen.next_into(&mut [0u8; 100]),
+            Err(PacketError::PayloadTooLarge(1028))
+        ));
+    }
+
+    #[test]
+    fn test_generate_into_allocations() {
+        const COUNT: usize = 1000;
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 64);
+        gen.next_into(&mut [0u8; 128]).unwrap(); // build the template
+
+        let before = alloc_counter::allocations();
+        let batch = gen.generate_batch(COUNT);
+        let batch_allocs = alloc_counter::allocations() - before;
+        assert_eq!(batch.len(), COUNT);
+
+        let mut buf = vec![0u8; COUNT * 128];
+        let mut lens = vec![0usize; COUNT];
+        let before = alloc_counter::allocations();
+        assert_eq!(gen.generate_into(&mut buf, 128, &mut lens).unwrap(), COUNT);
+        let arena_allocs = alloc_counter::allocations() - before;
+
+        assert!(
+            batch_allocs >= COUNT,
+            "generate_batch allocated {} times",
+            batch_allocs
+        );
+        assert_eq!(arena_allocs, 0);
+    }
+
+    #[test]
+    fn test_size_distribution_histogram() {
+        const COUNT: usize = 12_000;
+        let tolerance = COUNT / 40; // 2.5 percentage points
+
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 0)
+            .with_size_distribution(64, 1500, PacketSizeDistribution::InternetMix);
+        let packets = gen.generate_batch(COUNT);
+        let count_len = |len: usize| packets.iter().filter(|p| p.len() == len).count();
+        assert!(count_len(64).abs_diff(COUNT * 7 / 12) < tolerance);
+        assert!(count_len(576).abs_diff(COUNT * 4 / 12) < tolerance);
+        assert!(count_len(1500).abs_diff(COUNT / 12) < tolerance);
+        assert_eq!(count_len(64) + count_len(576) + count_len(1500), COUNT);
+
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 0)
+            .with_size_distribution(100, 500, PacketSizeDistribution::Uniform);
+        let mut quarters = [0usize; 4];
+        for packet in gen.generate_batch(COUNT) {
+            assert!((100..=500).contains(&packet.len()));
+            quarters[((packet.len() - 100) * 4 / 401).min(3)] += 1;
+        }
+        for quarter in quarters {
+            assert!(quarter.abs_diff(COUNT / 4) < tolerance, "{:?}", quarters);
+        }
+
+        // Range is clamped to the UDP header length and the MTU
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 0)
+            .with_size_distribution(0, 9000, PacketSizeDistribution::Uniform);
+        assert!(gen
+            .generate_batch(1000)
+            .iter()
+            .all(|p| (28..=1500).contains(&p.len())));
+    }
+
+    #[test]
+    fn test_size_distribution_next_into() {
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 0)
+            .with_size_distribution(64, 1500, PacketSizeDistribution::Uniform);
+        let mut buf = [0u8; 1500];
+        for _ in 0..100 {
+            let len = gen.next_into(&mut buf).unwrap();
+            assert!((64..=1500).contains(&len));
+            assert_eq!(u16::from_be_bytes([buf[2], buf[3]]) as usize, len);
+            assert_eq!(u16::from_be_bytes([buf[24], buf[25]]) as usize, len - 20);
+            assert_eq!(checksum_simd(&buf[..20]), 0);
+        }
+    }
+
+    /// Re-sum a UDP datagram over its pseudo-header
+    fn udp_checksum_valid(packet: &[u8]) -> bool {
+        let mut pseudo = packet[12..20].to_vec();
+        pseudo.extend_from_slice(&[0, 17]);
+        pseudo.extend_from_slice(&((packet.len() - 20) as u16).to_be_bytes());
+        pseudo.extend_from_slice(&packet[20..]);
+        checksum_simd(&pseudo) == 0
+    }
+
+    #[test]
+    fn test_encapsulate_vxlan() {
+        let inner = PacketBuilder::new()
+            .src_ip("192.168.0.1")
+            .dst_ip("192.168.0.2")
+            .src_port(1000)
+            .dst_port(2000)
+            .payload(b"overlay")
+            .build()
+            .unwrap();
+        let mut builder = ProtocolBuilder::new();
+        let packet = builder
+            .encapsulate_vxlan(&inner, 0x12_3456, "10.0.0.1", "10.0.0.2")
+            .unwrap();
+
+        // Outer IPv4/UDP as any decoder sees it
+        assert_eq!(checksum_simd(&packet[..20]), 0);
+        assert_eq!(&packet[12..20], &[10, 0, 0, 1, 10, 0, 0, 2]);
+        assert!(udp_checksum_valid(&packet));
+        assert_eq!(u16::from_be_bytes([packet[22], packet[23]]), VXLAN_PORT);
+        assert!(u16::from_be_bytes([packet[20], packet[21]]) >= 0xC000);
+        let vxlan = crate::pcap::udp_payload(crate::pcap::LINKTYPE_RAW, &packet).unwrap();
+
+        // VXLAN header: I flag, 24-bit VNI, reserved bytes zero
+        assert_eq!(&vxlan[..8], &[0x08, 0, 0, 0, 0x12, 0x34, 0x56, 0]);
+        let frame = &vxlan[8..];
+        assert_eq!(&frame[12..14], &[0x08, 0x00]);
+        assert_eq!(&frame[14..], &inner[..]);
+        let inner_payload = crate::pcap::udp_payload(crate::pcap::LINKTYPE_ETHERNET, frame);
+        assert_eq!(inner_payload, Some(&b"overlay"[..]));
+
+        assert!(builder
+            .encapsulate_vxlan(&inner, 1 << 24, "10.0.0.1", "10.0.0.2")
+            .is_err());
+        assert!(builder
+            .encapsulate_vxlan(&[0u8; 28], 1, "10.0.0.1", "10.0.0.2")
+            .is_err());
+
+        // Spoofing rewrites only the outer source
+        let mut spoofed = ProtocolBuilder::new()
+            .with_spoofing("172.16.0.0/12")
+            .unwrap();
+        let packet = spoofed
+            .encapsulate_vxlan(&inner, 7, "10.0.0.1", "10.0.0.2")
+            .unwrap();
+        assert_eq!(packet[12], 172);
+        assert!(udp_checksum_valid(&packet));
+        assert_eq!(&packet[packet.len() - inner.len()..], &inner[..]);
+    }
+
+    #[test]
+    fn test_encapsulate_gre() {
+        let inner = PacketBuilder::new()
+            .dst_ip("192.168.0.2")
+            .dst_port(2000)
+            .payload(b"tunnelled")
+            .build()
+            .unwrap();
+
+        let mut builder = ProtocolBuilder::new();
+        let packet = builder
+            .encapsulate_gre(&inner, GRE_PROTO_IPV4, "10.0.0.1", "10.0.0.2")
+            .unwrap();
+        assert_eq!(packet[9], IPPROTO_GRE);
+        assert_eq!(
+            u16::from_be_bytes([packet[2], packet[3]]) as usize,
+            packet.len()
+        );
+        assert_eq!(checksum_simd(&packet[..20]), 0);
+        // C bit, version 0, IPv4 payload; checksum covers GRE header and payload
+        assert_eq!(&packet[20..24], &[0x80, 0x00, 0x08, 0x00]);
+        assert_eq!(checksum_simd(&packet[20..]), 0);
+        assert_eq!(&packet[28..], &inner[..]);
+
+        let mut offload = ProtocolBuilder::new().with_checksum_mode(ChecksumMode::Hardware);
+        let packet = offload
+            .encapsulate_gre(&inner, GRE_PROTO_TEB, "10.0.0.1", "10.0.0.2")
+            .unwrap();
+        assert_eq!(&packet[20..24], &[0x00, 0x00, 0x65, 0x58]);
+        assert_eq!(&packet[24..], &inner[..]);
+    }
+
+    #[test]
+    fn test_checksum_modes() {
+        let src = Ipv4Addr::new(10, 0, 0, 1);
+        let dst = Ipv4Addr::new(192, 168, 1, 1);
+
+        let software = ProtocolBuilder::new();
+        let udp = software
+            .build_udp_packet(src, dst, 1234, 53, b"data")
+            .unwrap();
+        assert_ne!(&udp[26..28], &[0, 0]);
+        assert!(udp_checksum_valid(&udp));
+        let tcp = software
+            .build_tcp_segment(src, dst, 1234, 80, PacketFlags::syn(), 1, 0, &[])
+            .unwrap();
+        assert!(tcp_checksum_valid(&tcp));
+
+        for mode in [ChecksumMode::Hardware, ChecksumMode::Zero] {
+            let builder = ProtocolBuilder::new().with_checksum_mode(mode);
+            let udp = builder
+                .build_udp_packet(src, dst, 1234, 53, b"data")
+                .unwrap();
+            assert_eq!(&udp[26..28], &[0, 0]);
+            let tcp = builder
+                .build_tcp_segment(src, dst, 1234, 80, PacketFlags::syn(), 1, 0, &[])
+                .unwrap();
+            assert_eq!(&tcp[36..38], &[0, 0]);
+            // The IP header checksum is never offloaded
+            assert_eq!(checksum_simd(&tcp[..20]), 0);
+        }
+
+        // Pick a payload word that makes the computed UDP checksum zero; it must
+        // go out as 0xFFFF rather than the "no checksum" value
+        let probe = software
+            .build_udp_packet(src, dst, 1234, 53, &[0, 0])
+            .unwrap();
+        let udp = software
+            .build_udp_packet(src, dst, 1234, 53, &probe[26..28])
+            .unwrap();
+        assert_eq!(&udp[26..28], &[0xFF, 0xFF]);
+        assert!(udp_checksum_valid(&udp));
+
+        assert_eq!(ChecksumMode::for_socket(true), ChecksumMode::Software);
+        assert_eq!(ChecksumMode::for_socket(false), ChecksumMode::Hardware);
+    }
+
+    #[test]
+    fn test_generate_into_honours_checksum_mode() {
+        let mut buf = [0u8; 256];
+        let mut gen = BatchPacketGenerator::new("192.168.1.1", 80, Protocol::UDP, 32)
+            .with_spoofing("10.0.0.0/8")
+            .unwrap();
+        let len = gen.next_into(&mut buf).unwrap();
+        assert!(udp_checksum_valid(&buf[..len]));
+
+        let mut gen = gen.with_checksum_mode(ChecksumMode::Hardware);
+        let len = gen.next_into(&mut buf).unwrap();
+        assert_eq!(&buf[26..28], &[0, 0]);
+        assert_eq!(len, 60);
+    }
 }
@@ -2,14 +2,27 @@
 //! Uses crossbeam for MPMC queues with batch operations
 
 use crossbeam::queue::{ArrayQueue, SegQueue};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Empty polls a blocking consumer spins through before parking
+const SPIN_LIMIT: usize = 64;
 
 /// Lock-free MPMC bounded queue with batch operations
 pub struct PacketQueue<T> {
     queue: ArrayQueue<T>,
     enqueued: AtomicUsize,
     dequeued: AtomicUsize,
+    /// Items evicted by `push_overwrite` before anyone popped them
+    overwritten: AtomicUsize,
+    /// Times a blocking pop found the queue empty
+    empty_polls: AtomicUsize,
+    /// Consumers parked (or about to park) in `pop_batch_blocking`
+    waiters: AtomicUsize,
+    park_lock: Mutex<()>,
+    not_empty: Condvar,
 }
 
 impl<T> PacketQueue<T> {
@@ -19,6 +32,11 @@ impl<T> PacketQueue<T> {
             queue: ArrayQueue::new(capacity),
             enqueued: AtomicUsize::new(0),
             dequeued: AtomicUsize::new(0),
+            overwritten: AtomicUsize::new(0),
+            empty_polls: AtomicUsize::new(0),
+            waiters: AtomicUsize::new(0),
+            park_lock: Mutex::new(()),
+            not_empty: Condvar::new(),
         }
     }
 
@@ -28,12 +46,29 @@ impl<T> PacketQueue<T> {
         match self.queue.push(item) {
             Ok(()) => {
                 self.enqueued.fetch_add(1, Ordering::Relaxed);
+                self.wake_consumers();
                 Ok(())
             }
             Err(item) => Err(item),
         }
     }
 
+    /// Push a single item, evicting the oldest one if the queue is full
+    ///
+    /// Returns the evicted item so the caller can account for it.
+    #[inline]
+    pub fn push_overwrite(&self, item: T) -> Option<T> {
+        // force_push evicts and inserts as one operation, so a consumer popping
+        // concurrently can never leave us without a slot
+        let evicted = self.queue.force_push(item);
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+        if evicted.is_some() {
+            self.overwritten.fetch_add(1, Ordering::Relaxed);
+        }
+        self.wake_consumers();
+        evicted
+    }
+
     /// Pop a single item (non-blocking)
     #[inline]
     pub fn pop(&self) -> Option<T> {
@@ -54,9 +89,25 @@ impl<T> PacketQueue<T> {
             }
         }
         self.enqueued.fetch_add(count, Ordering::Relaxed);
+        if count > 0 {
+            self.wake_consumers();
+        }
         count
     }
 
+    /// Wake consumers parked in `pop_batch_blocking`
+    #[inline]
+    fn wake_consumers(&self) {
+        // Pairs with the fence in `pop_batch_blocking`: either it sees our item
+        // or we see its registration
+        fence(Ordering::SeqCst);
+        if self.waiters.load(Ordering::Relaxed) > 0 {
+            // Holding the lock means a registered consumer is already waiting
+            let _guard = self.park_lock.lock();
+            self.not_empty.notify_all();
+        }
+    }
+
     /// Pop multiple items in batch
     pub fn pop_batch(&self, max: usize) -> Vec<T> {
         let mut items = Vec::with_capacity(max);
@@ -70,6 +121,37 @@ impl<T> PacketQueue<T> {
         items
     }
 
+    /// Pop up to `max` items, waiting up to `timeout` for the first one
+    ///
+    /// Spins briefly, then parks until a push wakes it. Returns as soon as
+    /// anything was collected; an empty result means the timeout elapsed.
+    pub fn pop_batch_blocking(&self, max: usize, timeout: Duration) -> Vec<T> {
+        let deadline = Instant::now() + timeout;
+        let mut spins = 0;
+        loop {
+            let items = self.pop_batch(max);
+            if !items.is_empty() || max == 0 || Instant::now() >= deadline {
+                return items;
+            }
+            self.empty_polls.fetch_add(1, Ordering::Relaxed);
+
+            if spins < SPIN_LIMIT {
+                spins += 1;
+                std::hint::spin_loop();
+                continue;
+            }
+
+            let mut guard = self.park_lock.lock();
+            self.waiters.fetch_add(1, Ordering::SeqCst);
+            // Re-check after registering so a push in between is never missed
+            fence(Ordering::SeqCst);
+            if self.queue.is_empty() {
+                self.not_empty.wait_until(&mut guard, deadline);
+            }
+            self.waiters.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
 
     /// Get current length (approximate)
     pub fn len(&self) -> usize {
@@ -100,6 +182,16 @@ impl<T> PacketQueue<T> {
     pub fn total_dequeued(&self) -> usize {
         self.dequeued.load(Ordering::Relaxed)
     }
+
+    /// Get number of items evicted by `push_overwrite`
+    pub fn total_overwritten(&self) -> usize {
+        self.overwritten.load(Ordering::Relaxed)
+    }
+
+    /// Get number of times a blocking pop found the queue empty
+    pub fn total_empty_polls(&self) -> usize {
+        self.empty_polls.load(Ordering::Relaxed)
+    }
 }
 
 /// Lock-free unbounded queue for variable workloads
@@ -164,6 +256,21 @@ impl<T> WorkStealingQueue<T> {
         )
     }
 
+    /// Create `n` queues where each one can steal from every other
+    ///
+    /// The returned stealers are in queue order, for draining from outside.
+    pub fn group(n: usize) -> (Vec<Self>, Vec<crossbeam::deque::Stealer<T>>) {
+        let (mut queues, stealers): (Vec<Self>, Vec<_>) = (0..n).map(|_| Self::new()).unzip();
+        for (i, queue) in queues.iter_mut().enumerate() {
+            for (j, stealer) in stealers.iter().enumerate() {
+                if i != j {
+                    queue.add_stealer(stealer.clone());
+                }
+            }
+        }
+        (queues, stealers)
+    }
+
     pub fn add_stealer(&mut self, stealer: crossbeam::deque::Stealer<T>) {
         self.stealers.push(stealer);
     }
@@ -180,14 +287,17 @@ impl<T> WorkStealingQueue<T> {
             return Some(item);
         }
         
-        // Try stealing from others
-        for stealer in &self.stealers {
-            if let crossbeam::deque::Steal::Success(item) = stealer.steal() {
-                return Some(item);
+        // Try stealing from others, retrying while a steal lost a race
+        loop {
+            let steal: crossbeam::deque::Steal<T> = self
+                .stealers
+                .iter()
+                .map(|stealer| stealer.steal())
+                .collect();
+            if !steal.is_retry() {
+                return steal.success();
             }
         }
-        
-        None
     }
 
     pub fn is_empty(&self) -> bool {
@@ -254,6 +364,91 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_push_overwrite_drops_oldest() {
+        let queue = PacketQueue::new(3);
+        for i in 0..3 {
+            assert_eq!(queue.push_overwrite(i), None);
+        }
+        assert_eq!(queue.push_overwrite(3), Some(0));
+        assert_eq!(queue.push_overwrite(4), Some(1));
+        assert_eq!(queue.pop_batch(10), vec![2, 3, 4]);
+        assert_eq!(queue.total_overwritten(), 2);
+    }
+
+    #[test]
+    fn test_push_overwrite_concurrent_accounting() {
+        let queue = Arc::new(PacketQueue::new(16));
+        let mut handles = vec![];
+
+        for i in 0..4 {
+            let q = Arc::clone(&queue);
+            handles.push(thread::spawn(move || {
+                for j in 0..10_000 {
+                    let _ = q.push_overwrite(i * 10_000 + j);
+                }
+            }));
+        }
+        for _ in 0..2 {
+            let q = Arc::clone(&queue);
+            handles.push(thread::spawn(move || {
+                for _ in 0..10_000 {
+                    let _ = q.pop();
+                }
+            }));
+        }
+        for h in handles {
+            h.join().unwrap();
+        }
+        while queue.pop().is_some() {}
+
+        assert_eq!(queue.total_enqueued(), 40_000);
+        assert_eq!(
+            queue.total_dequeued() + queue.total_overwritten(),
+            queue.total_enqueued()
+        );
+    }
+
+    #[test]
+    fn test_pop_batch_blocking_parks() {
+        const ITEMS: usize = 10;
+
+        let queue = Arc::new(PacketQueue::new(64));
+        let producer = {
+            let q = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..ITEMS {
+                    thread::sleep(Duration::from_millis(20));
+                    q.push(i).unwrap();
+                }
+            })
+        };
+
+        let mut received = Vec::new();
+        while received.len() < ITEMS {
+            let batch = queue.pop_batch_blocking(16, Duration::from_secs(5));
+            assert!(!batch.is_empty(), "timed out with a producer still pushing");
+            received.extend(batch);
+        }
+        producer.join().unwrap();
+        assert_eq!(received, (0..ITEMS).collect::<Vec<_>>());
+
+        // Each wait spins a little and then parks; a busy loop would poll millions of times
+        let polls = queue.total_empty_polls();
+        assert!(
+            polls <= ITEMS * (SPIN_LIMIT + 8),
+            "consumer polled an empty queue {} times",
+            polls
+        );
+
+        // Nothing arrives: the timeout ends the wait
+        let start = Instant::now();
+        assert!(queue
+            .pop_batch_blocking(4, Duration::from_millis(30))
+            .is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
     #[test]
     fn test_unbounded_queue() {
         let queue = UnboundedPacketQueue::new();
@@ -265,4 +460,55 @@ mod tests {
         assert_eq!(queue.pop(), Some(1));
         assert_eq!(queue.len(), 1);
     }
+
+    #[test]
+    fn test_work_stealing_evens_skewed_load() {
+        use std::collections::HashSet;
+        use std::sync::Barrier;
+        use std::time::Duration;
+
+        const THREADS: usize = 4;
+        const TASKS: usize = 400;
+
+        let (queues, stealers) = WorkStealingQueue::group(THREADS);
+        // All work lands on the first deque
+        for task in 0..TASKS {
+            queues[0].push(task);
+        }
+
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let handles: Vec<_> = queues
+            .into_iter()
+            .map(|queue| {
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    let mut done = Vec::new();
+                    while let Some(task) = queue.pop() {
+                        thread::sleep(Duration::from_micros(200));
+                        done.push(task);
+                    }
+                    done
+                })
+            })
+            .collect();
+        let done: Vec<Vec<usize>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        // Every worker took a real share, not just the one that was handed the load
+        for (i, tasks) in done.iter().enumerate() {
+            assert!(
+                tasks.len() >= TASKS / (THREADS * 4),
+                "worker {} completed only {} of {}",
+                i,
+                tasks.len(),
+                TASKS
+            );
+        }
+
+        // Each task ran exactly once and nothing is left behind
+        let all: Vec<usize> = done.into_iter().flatten().collect();
+        assert_eq!(all.len(), TASKS);
+        assert_eq!(all.iter().collect::<HashSet<_>>().len(), TASKS);
+        assert!(stealers.iter().all(|s| s.is_empty()));
+    }
 }
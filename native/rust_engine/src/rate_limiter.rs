@@ -1,19 +1,29 @@
 //! Precision rate limiting with nanosecond timing
 //! Implements token bucket algorithm for accurate rate control
+//!
+//! `TokenBucket` keeps its balance in milli-tokens (`TOKEN_SCALE` per token) so
+//! refills between closely spaced calls still add fractional tokens.
 
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 
+/// Milli-tokens per whole token
+const TOKEN_SCALE: u64 = 1000;
+/// A rate of one token per second accrues `TOKEN_SCALE` milli-tokens per this many ns
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
 /// High-precision token bucket rate limiter
 pub struct TokenBucket {
     /// Tokens per second (rate limit)
     rate: AtomicU64,
     /// Maximum burst size
     burst: AtomicU64,
-    /// Current available tokens (scaled by 1000 for precision)
+    /// Current available tokens in milli-tokens
     tokens: AtomicU64,
-    /// Last refill timestamp (nanoseconds since start)
+    /// Time up to which elapsed nanoseconds have been converted into tokens
     last_refill: AtomicU64,
+    /// Sub-milli-token remainder of the last refill, in milli-tokens * 1e-9
+    refill_carry: AtomicU64,
     /// Start time for nanosecond calculations
     start: Instant,
     /// Whether rate limiting is enabled
@@ -31,8 +41,9 @@ impl TokenBucket {
         Self {
             rate: AtomicU64::new(rate),
             burst: AtomicU64::new(burst),
-            tokens: AtomicU64::new(burst * 1000), // Start with full bucket, scaled
+            tokens: AtomicU64::new(burst.saturating_mul(TOKEN_SCALE)), // Start with full bucket
             last_refill: AtomicU64::new(0),
+            refill_carry: AtomicU64::new(0),
             start: Instant::now(),
             enabled: AtomicBool::new(rate > 0),
         }
@@ -45,6 +56,7 @@ impl TokenBucket {
             burst: AtomicU64::new(0),
             tokens: AtomicU64::new(u64::MAX),
             last_refill: AtomicU64::new(0),
+            refill_carry: AtomicU64::new(0),
             start: Instant::now(),
             enabled: AtomicBool::new(false),
         }
@@ -54,28 +66,40 @@ impl TokenBucket {
     /// Returns true if tokens were acquired, false if rate limited
     #[inline]
     pub fn try_acquire(&self, count: u64) -> bool {
+        self.try_acquire_at(count, self.start.elapsed().as_nanos() as u64)
+    }
+
+    /// `try_acquire` against a caller-supplied clock in nanoseconds since `start`
+    #[inline]
+    fn try_acquire_at(&self, count: u64, now_ns: u64) -> bool {
         if !self.enabled.load(Ordering::Relaxed) {
             return true;
         }
 
-        self.refill();
-
-        let needed = count * 1000; // Scale for precision
-        let current = self.tokens.load(Ordering::Relaxed);
-
-        if current >= needed {
-            // Try to consume tokens atomically
-            self.tokens.fetch_sub(needed, Ordering::Relaxed);
-            true
-        } else {
-            false
+        self.refill_at(now_ns);
+
+        let needed = count.saturating_mul(TOKEN_SCALE);
+        let mut current = self.tokens.load(Ordering::Relaxed);
+        // Subtract only if the balance still covers the request, so concurrent
+        // callers cannot both spend the same tokens
+        while current >= needed {
+            match self.tokens.compare_exchange_weak(
+                current,
+                current - needed,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
         }
+        false
     }
 
     /// Acquire tokens, blocking if necessary
     /// Returns the time waited
     pub fn acquire(&self, count: u64) -> Duration {
-        if !self.enabled.load(Ordering::Relaxed) {
+        if !self.enabled.load(Ordering::Relaxed) || self.try_acquire(count) {
             return Duration::ZERO;
         }
 
@@ -88,13 +112,14 @@ impl TokenBucket {
                 return Duration::ZERO;
             }
 
-            let needed = count * 1000;
+            let needed = count.saturating_mul(TOKEN_SCALE);
             let current = self.tokens.load(Ordering::Relaxed);
             let deficit = needed.saturating_sub(current);
 
-            // Wait time = deficit / (rate * 1000) seconds
-            // Convert to nanoseconds for precision
-            let wait_ns = (deficit * 1_000_000_000) / (rate * 1000);
+            // Wait time = deficit / (rate * TOKEN_SCALE) seconds
+            let per_sec = rate as u128 * TOKEN_SCALE as u128;
+            let wait_ns =
+                ((deficit as u128 * NANOS_PER_SEC) / per_sec).min(u64::MAX as u128) as u64;
 
             if wait_ns > 0 {
                 std::thread::sleep(Duration::from_nanos(wait_ns.min(1_000_000)));
@@ -110,26 +135,50 @@ impl TokenBucket {
     /// Refill tokens based on elapsed time
     #[inline]
     fn refill(&self) {
-        let now_ns = self.start.elapsed().as_nanos() as u64;
-        let last = self.last_refill.load(Ordering::Relaxed);
-        let elapsed_ns = now_ns.saturating_sub(last);
+        self.refill_at(self.start.elapsed().as_nanos() as u64);
+    }
 
-        if elapsed_ns == 0 {
+    /// Convert the nanoseconds since `last_refill` into milli-tokens
+    ///
+    /// Adds `rate * elapsed_ns * TOKEN_SCALE / 1e9` milli-tokens. The part of
+    /// the product too small to make a whole milli-token is kept in
+    /// `refill_carry` for the next call rather than lost, so many small refills
+    /// add up to the same balance as one large one. A full bucket drops the
+    /// carry, as tokens past `burst` are not kept.
+    fn refill_at(&self, now_ns: u64) {
+        let last = self.last_refill.load(Ordering::Acquire);
+        let elapsed_ns = now_ns.saturating_sub(last);
+        let rate = self.rate.load(Ordering::Relaxed);
+        if elapsed_ns == 0 || rate == 0 {
             return;
         }
 
-        // Calculate tokens to add: rate * elapsed_time
-        // tokens = rate * (elapsed_ns / 1_000_000_000) * 1000 (scaled)
-        let rate = self.rate.load(Ordering::Relaxed);
-        let new_tokens = (rate * elapsed_ns) / 1_000_000; // Simplified calculation
-
-        if new_tokens > 0 {
-            let burst = self.burst.load(Ordering::Relaxed) * 1000;
-            let current = self.tokens.load(Ordering::Relaxed);
-            let new_total = (current + new_tokens).min(burst);
+        // Only the caller that advances the clock credits the tokens
+        if self
+            .last_refill
+            .compare_exchange(last, now_ns, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
 
-            self.tokens.store(new_total, Ordering::Relaxed);
-            self.last_refill.store(now_ns, Ordering::Relaxed);
+        let accrued = elapsed_ns as u128 * rate as u128 * TOKEN_SCALE as u128
+            + self.refill_carry.swap(0, Ordering::AcqRel) as u128;
+        let new_tokens = (accrued / NANOS_PER_SEC).min(u64::MAX as u128) as u64;
+        let carry = (accrued % NANOS_PER_SEC) as u64;
+
+        let burst = self
+            .burst
+            .load(Ordering::Relaxed)
+            .saturating_mul(TOKEN_SCALE);
+        let previous = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |t| {
+                Some(t.saturating_add(new_tokens).min(burst))
+            })
+            .unwrap_or(burst);
+        if previous.saturating_add(new_tokens) < burst {
+            self.refill_carry.store(carry, Ordering::Release);
         }
     }
 
@@ -164,7 +213,7 @@ impl TokenBucket {
     /// Get available tokens
     pub fn available(&self) -> u64 {
         self.refill();
-        self.tokens.load(Ordering::Relaxed) / 1000
+        self.tokens.load(Ordering::Relaxed) / TOKEN_SCALE
     }
 
     /// Check if rate limiting is enabled
@@ -175,21 +224,36 @@ impl TokenBucket {
     /// Reset the rate limiter
     pub fn reset(&self) {
         let burst = self.burst.load(Ordering::Relaxed);
-        self.tokens.store(burst * 1000, Ordering::SeqCst);
-        self.last_refill.store(0, Ordering::SeqCst);
+        self.tokens
+            .store(burst.saturating_mul(TOKEN_SCALE), Ordering::SeqCst);
+        self.refill_carry.store(0, Ordering::SeqCst);
+        self.last_refill
+            .store(self.start.elapsed().as_nanos() as u64, Ordering::SeqCst);
     }
 }
 
+/// Sub-buckets per sliding window; rate resolution is one bucket
+const WINDOW_BUCKETS: usize = 10;
+/// Epoch of a bucket that has never been used
+const EMPTY_EPOCH: u64 = u64::MAX;
+
 /// Sliding window rate limiter for more accurate rate measurement
+///
+/// The window is split into `WINDOW_BUCKETS` fixed sub-buckets holding event
+/// counts, so recording and querying cost O(buckets) regardless of rate. Each
+/// bucket remembers which time slice (epoch) it counts; a bucket whose epoch
+/// has fallen out of the window is zeroed lazily the next time it is reused.
 pub struct SlidingWindowLimiter {
     /// Window size in milliseconds
     window_ms: u64,
+    /// Width of one sub-bucket in milliseconds
+    bucket_ms: u64,
     /// Maximum count per window
     max_count: AtomicU64,
-    /// Timestamps of recent events (circular buffer)
-    timestamps: Vec<AtomicU64>,
-    /// Current write position
-    write_pos: AtomicU64,
+    /// Events counted in each sub-bucket
+    counts: [AtomicU64; WINDOW_BUCKETS],
+    /// Time slice (`now_ms / bucket_ms`) each sub-bucket currently counts
+    epochs: [AtomicU64; WINDOW_BUCKETS],
     /// Start time
     start: Instant,
     /// Enabled flag
@@ -198,14 +262,15 @@ pub struct SlidingWindowLimiter {
 
 impl SlidingWindowLimiter {
     pub fn new(rate_per_second: u64, window_ms: u64) -> Self {
+        let window_ms = window_ms.max(1);
         let max_count = (rate_per_second * window_ms) / 1000;
-        let buffer_size = max_count.max(1000) as usize;
 
         Self {
             window_ms,
+            bucket_ms: (window_ms / WINDOW_BUCKETS as u64).max(1),
             max_count: AtomicU64::new(max_count),
-            timestamps: (0..buffer_size).map(|_| AtomicU64::new(0)).collect(),
-            write_pos: AtomicU64::new(0),
+            counts: std::array::from_fn(|_| AtomicU64::new(0)),
+            epochs: std::array::from_fn(|_| AtomicU64::new(EMPTY_EPOCH)),
             start: Instant::now(),
             enabled: AtomicBool::new(rate_per_second > 0),
         }
@@ -216,45 +281,43 @@ impl SlidingWindowLimiter {
         if !self.enabled.load(Ordering::Relaxed) {
             return true;
         }
+        self.try_record_at(self.start.elapsed().as_millis() as u64)
+    }
 
-        let now_ms = self.start.elapsed().as_millis() as u64;
-        let window_start = now_ms.saturating_sub(self.window_ms);
-
-        // Count events in window
-        let mut count = 0u64;
-        for ts in &self.timestamps {
-            let t = ts.load(Ordering::Relaxed);
-            if t >= window_start && t <= now_ms {
-                count += 1;
-            }
+    /// Record an event, blocking until the window has room
+    /// Returns the time waited
+    pub fn acquire(&self) -> Duration {
+        if self.try_record() {
+            return Duration::ZERO;
         }
 
-        let max = self.max_count.load(Ordering::Relaxed);
-        if count >= max {
-            return false;
+        let start = Instant::now();
+        while !self.try_record() {
+            // Room only opens when a bucket leaves the window
+            std::thread::sleep(Duration::from_millis(1));
         }
+        start.elapsed()
+    }
 
-        // Record this event
-        let pos = self.write_pos.fetch_add(1, Ordering::Relaxed) as usize % self.timestamps.len();
-        self.timestamps[pos].store(now_ms, Ordering::Relaxed);
+    /// Events still allowed in the current window
+    pub fn available(&self) -> u64 {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return u64::MAX;
+        }
+        let epoch = self.start.elapsed().as_millis() as u64 / self.bucket_ms;
+        self.max_count
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.count_at(epoch))
+    }
 
-        true
+    /// Window size in milliseconds
+    pub fn window_ms(&self) -> u64 {
+        self.window_ms
     }
 
     /// Get current rate (events per second)
     pub fn current_rate(&self) -> u64 {
-        let now_ms = self.start.elapsed().as_millis() as u64;
-        let window_start = now_ms.saturating_sub(self.window_ms);
-
-        let mut count = 0u64;
-        for ts in &self.timestamps {
-            let t = ts.load(Ordering::Relaxed);
-            if t >= window_start && t <= now_ms {
-                count += 1;
-            }
-        }
-
-        (count * 1000) / self.window_ms
+        self.rate_at(self.start.elapsed().as_millis() as u64)
     }
 
     /// Set new rate limit
@@ -263,6 +326,49 @@ impl SlidingWindowLimiter {
         self.max_count.store(max_count, Ordering::SeqCst);
         self.enabled.store(rate_per_second > 0, Ordering::SeqCst);
     }
+
+    fn try_record_at(&self, now_ms: u64) -> bool {
+        let epoch = now_ms / self.bucket_ms;
+        let slot = self.rotate(epoch);
+
+        if self.count_at(epoch) >= self.max_count.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.counts[slot].fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    fn rate_at(&self, now_ms: u64) -> u64 {
+        let counted_ms = WINDOW_BUCKETS as u64 * self.bucket_ms;
+        (self.count_at(now_ms / self.bucket_ms) * 1000) / counted_ms
+    }
+
+    /// Claim the bucket for `epoch`, zeroing it if it still holds an older slice
+    fn rotate(&self, epoch: u64) -> usize {
+        let slot = (epoch % WINDOW_BUCKETS as u64) as usize;
+        let seen = self.epochs[slot].load(Ordering::Acquire);
+        if seen != epoch
+            && self.epochs[slot]
+                .compare_exchange(seen, epoch, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            self.counts[slot].store(0, Ordering::Release);
+        }
+        slot
+    }
+
+    /// Events in the buckets whose epochs fall inside the window ending at `epoch`
+    fn count_at(&self, epoch: u64) -> u64 {
+        self.epochs
+            .iter()
+            .zip(&self.counts)
+            .filter(|(e, _)| {
+                let e = e.load(Ordering::Acquire);
+                e != EMPTY_EPOCH && e <= epoch && epoch - e < WINDOW_BUCKETS as u64
+            })
+            .map(|(_, c)| c.load(Ordering::Relaxed))
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -332,6 +438,48 @@ mod tests {
         assert!(elapsed >= wait_time);
     }
 
+    #[test]
+    fn test_token_bucket_concurrent_acquirers() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        const RATE: u64 = 20_000;
+        const BURST: u64 = 100;
+        let limiter = Arc::new(TokenBucket::new(RATE, BURST));
+        let stop = Arc::new(AtomicBool::new(false));
+        let started = Instant::now();
+
+        let workers: Vec<_> = (0..16)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let mut acquired = 0u64;
+                    while !stop.load(Ordering::Relaxed) {
+                        if limiter.try_acquire(1) {
+                            acquired += 1;
+                        }
+                    }
+                    acquired
+                })
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(250));
+        stop.store(true, Ordering::Relaxed);
+        let total: u64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        let elapsed = started.elapsed().as_secs_f64();
+
+        let allowed = (RATE as f64 * elapsed) as u64 + BURST;
+        assert!(
+            total <= allowed,
+            "acquired {} tokens, allowed {}",
+            total,
+            allowed
+        );
+        assert!(total > 0);
+    }
+
     #[test]
     fn test_token_bucket_multi_token() {
         let limiter = TokenBucket::new(1000, 100);
@@ -368,6 +516,21 @@ mod tests {
         assert_eq!(limiter.burst(), 200);
     }
 
+    #[test]
+    fn test_token_bucket_saturates_huge_values() {
+        // Burst and count saturate to the same balance instead of overflowing
+        let bucket = TokenBucket::new(1, u64::MAX);
+        assert!(bucket.try_acquire(u64::MAX));
+        assert!(!bucket.try_acquire(1_000));
+        bucket.reset();
+        assert!(bucket.try_acquire(u64::MAX / TOKEN_SCALE));
+
+        // The wait for a huge rate is computed without overflowing
+        let fast = TokenBucket::new(u64::MAX, 1);
+        assert!(fast.try_acquire(1));
+        fast.acquire(1);
+    }
+
     #[test]
     fn test_token_bucket_reset() {
         let limiter = TokenBucket::new(1000, 100);
@@ -434,6 +597,48 @@ mod tests {
         assert!(allowed <= 200); // But not more than new limit
     }
 
+    #[test]
+    fn test_sliding_window_acquire_waits_for_room() {
+        let limiter = SlidingWindowLimiter::new(100, 100);
+        assert_eq!(limiter.available(), 10);
+        for _ in 0..10 {
+            assert_eq!(limiter.acquire(), Duration::ZERO);
+        }
+        assert_eq!(limiter.available(), 0);
+
+        // The oldest bucket leaves the 100ms window before there is room again
+        let waited = limiter.acquire();
+        assert!(waited > Duration::ZERO, "waited {:?}", waited);
+        assert!(waited <= Duration::from_millis(150), "waited {:?}", waited);
+
+        limiter.set_rate(0);
+        assert_eq!(limiter.available(), u64::MAX);
+        assert_eq!(limiter.acquire(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_sliding_window_tracks_steady_stream() {
+        // 1s window in 100ms buckets, limit well above the stream
+        let limiter = SlidingWindowLimiter::new(1_000_000, 1000);
+
+        // 5 events every 2ms is 2500 events/s
+        for now_ms in (0..3000).step_by(2) {
+            for _ in 0..5 {
+                assert!(limiter.try_record_at(now_ms));
+            }
+            // Past the first window the rate is within one bucket's worth
+            if now_ms >= 1000 && now_ms % 250 == 0 {
+                let rate = limiter.rate_at(now_ms);
+                assert!(rate.abs_diff(2500) <= 250, "rate {} at {}ms", rate, now_ms);
+            }
+        }
+
+        // Stale buckets drop out once the stream stops, and are zeroed on reuse
+        assert_eq!(limiter.rate_at(4100), 0);
+        assert!(limiter.try_record_at(4100));
+        assert_eq!(limiter.rate_at(4100), 1);
+    }
+
     // Property-based tests
     proptest! {
         #[test]
@@ -447,16 +652,47 @@ mod tests {
             prop_assert_eq!(limiter.burst(), burst);
             prop_assert!(limiter.is_enabled());
 
-            // Should be able to acquire up to burst tokens
+            // The clock stands still, so nothing refills while draining
             let mut acquired = 0;
-            while limiter.try_acquire(1) {
+            while limiter.try_acquire_at(1, 0) {
                 acquired += 1;
                 if acquired > burst * 2 {
                     break; // Safety check
                 }
             }
-
             prop_assert_eq!(acquired, burst);
+
+            // A second later `rate` tokens have accrued, capped at `burst`
+            let mut refilled = 0;
+            while limiter.try_acquire_at(1, 1_000_000_000) {
+                refilled += 1;
+                if refilled > burst * 2 {
+                    break;
+                }
+            }
+            prop_assert_eq!(refilled, rate.min(burst));
+        }
+
+        #[test]
+        fn test_token_bucket_refill_step_invariant(
+            rate in 1u64..1_000_000,
+            step_ns in 1u64..50_000,
+            steps in 1u64..2_000
+        ) {
+            let stepped = TokenBucket::new(rate, u64::MAX / TOKEN_SCALE);
+            let single = TokenBucket::new(rate, u64::MAX / TOKEN_SCALE);
+            stepped.tokens.store(0, Ordering::Relaxed);
+            single.tokens.store(0, Ordering::Relaxed);
+
+            for i in 1..=steps {
+                stepped.refill_at(i * step_ns);
+            }
+            single.refill_at(steps * step_ns);
+
+            prop_assert_eq!(
+                stepped.tokens.load(Ordering::Relaxed),
+                single.tokens.load(Ordering::Relaxed)
+            );
         }
 
         #[test]
@@ -0,0 +1,423 @@
+//! Response collection for `EngineConfig::collect_responses`
+//!
+//! Workers read replies on their own sockets and match them to the requests
+//! they sent: UDP by the DNS transaction id the engine writes over the first
+//! two payload bytes, HTTP in order on each keep-alive connection. Round
+//! trips go into one engine-wide histogram.
+
+use crate::atomic_stats::AtomicLogHistogram;
+use std::collections::VecDeque;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Bytes at the start of a UDP payload replaced by the transaction id
+pub(crate) const TXID_LEN: usize = 2;
+/// Classic DNS reply limit; longer replies are truncated, only the id is read
+const UDP_REPLY_BUF: usize = 512;
+/// Header bytes buffered while looking for the end of an HTTP response head
+const MAX_HTTP_HEAD: usize = 64 * 1024;
+/// Outstanding requests one socket can tell apart by transaction id
+const TXID_SPACE: usize = 1 << 16;
+
+/// Responses counted with `EngineConfig::collect_responses`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseStats {
+    /// Replies matched to an outstanding request
+    pub responses: u64,
+    /// Replies with no outstanding request, including ones that arrived
+    /// after their request timed out
+    pub unmatched: u64,
+    /// Requests with no reply within `response_timeout`
+    pub timed_out: u64,
+    /// Round-trip percentiles of matched replies, `None` before the first
+    pub p50_latency: Option<Duration>,
+    pub p95_latency: Option<Duration>,
+    pub p99_latency: Option<Duration>,
+}
+
+/// Engine-wide counters every worker reports into
+#[derive(Default)]
+pub(crate) struct ResponseCounters {
+    matched: AtomicU64,
+    unmatched: AtomicU64,
+    timed_out: AtomicU64,
+    /// Round-trip nanoseconds of matched replies
+    latency: AtomicLogHistogram,
+}
+
+impl ResponseCounters {
+    fn record_match(&self, sent: Instant, now: Instant) {
+        self.matched.fetch_add(1, Ordering::Relaxed);
+        let rtt = now.saturating_duration_since(sent).as_nanos() as u64;
+        self.latency.record_n(rtt, 1);
+    }
+
+    fn record_unmatched(&self) {
+        self.unmatched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_timeouts(&self, count: u64) {
+        self.timed_out.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn reset(&self) {
+        self.matched.store(0, Ordering::Relaxed);
+        self.unmatched.store(0, Ordering::Relaxed);
+        self.timed_out.store(0, Ordering::Relaxed);
+        self.latency.reset();
+    }
+
+    pub(crate) fn snapshot(&self) -> ResponseStats {
+        let latency = self.latency.snapshot();
+        let percentile = |q| latency.percentile(q).map(Duration::from_nanos);
+        ResponseStats {
+            responses: self.matched.load(Ordering::Relaxed),
+            unmatched: self.unmatched.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+            p50_latency: percentile(0.5),
+            p95_latency: percentile(0.95),
+            p99_latency: percentile(0.99),
+        }
+    }
+}
+
+/// Read whatever is queued on `socket` without blocking
+///
+/// `None` when nothing is queued, the peer closed, or the read failed.
+fn recv_nowait<'a>(socket: &socket2::Socket, buf: &'a mut [MaybeUninit<u8>]) -> Option<&'a [u8]> {
+    #[cfg(unix)]
+    let read = socket.recv_with_flags(buf, libc::MSG_DONTWAIT);
+    #[cfg(not(unix))]
+    let read = {
+        socket.set_nonblocking(true).ok()?;
+        let read = socket.recv(buf);
+        let _ = socket.set_nonblocking(false);
+        read
+    };
+    match read {
+        // SAFETY: recv initialised the first n bytes
+        Ok(n) if n > 0 => Some(unsafe { std::slice::from_raw_parts(buf.as_ptr().cast(), n) }),
+        _ => None,
+    }
+}
+
+/// One UDP socket's outstanding requests
+///
+/// Transaction ids are handed out in send order, so the outstanding ones form
+/// a window starting at `base`; answered slots stay `None` until they reach
+/// the front.
+#[derive(Default)]
+struct TxidWindow {
+    base: u16,
+    sent: VecDeque<Option<Instant>>,
+}
+
+impl TxidWindow {
+    fn next_txid(&self) -> u16 {
+        self.base.wrapping_add(self.sent.len() as u16)
+    }
+
+    fn push(&mut self, now: Instant, counters: &ResponseCounters) {
+        // Every id is outstanding: the oldest can no longer be told apart
+        if self.sent.len() == TXID_SPACE {
+            self.pop_front(counters);
+        }
+        self.sent.push_back(Some(now));
+    }
+
+    fn answer(&mut self, txid: u16, now: Instant, counters: &ResponseCounters) {
+        let slot = txid.wrapping_sub(self.base) as usize;
+        match self.sent.get_mut(slot).and_then(Option::take) {
+            Some(sent) => counters.record_match(sent, now),
+            None => counters.record_unmatched(),
+        }
+    }
+
+    /// Drop answered requests and time out unanswered ones from the front
+    fn expire(&mut self, now: Instant, timeout: Duration, counters: &ResponseCounters) {
+        while let Some(&front) = self.sent.front() {
+            if front.is_some_and(|sent| now.saturating_duration_since(sent) < timeout) {
+                break;
+            }
+            self.pop_front(counters);
+        }
+    }
+
+    fn pop_front(&mut self, counters: &ResponseCounters) {
+        if let Some(Some(_)) = self.sent.pop_front() {
+            counters.record_timeouts(1);
+        }
+        self.base = self.base.wrapping_add(1);
+    }
+}
+
+/// A UDP worker's outstanding requests, one window per socket
+pub(crate) struct UdpResponses {
+    counters: Arc<ResponseCounters>,
+    timeout: Duration,
+    windows: Vec<TxidWindow>,
+    /// Socket whose next id `stamp` wrote, until `record_sent`
+    stamped: Option<usize>,
+    /// Reused for every stamped payload
+    payload: Vec<u8>,
+    recv_buf: Vec<MaybeUninit<u8>>,
+}
+
+impl UdpResponses {
+    pub(crate) fn new(counters: Arc<ResponseCounters>, timeout: Duration, sockets: usize) -> Self {
+        Self {
+            counters,
+            timeout,
+            windows: (0..sockets).map(|_| TxidWindow::default()).collect(),
+            stamped: None,
+            payload: Vec::new(),
+            recv_buf: vec![MaybeUninit::uninit(); UDP_REPLY_BUF],
+        }
+    }
+
+    /// `payload` with the next transaction id of `socket` written over its
+    /// first bytes; payloads too short to hold one go out untracked
+    #[inline]
+    pub(crate) fn stamp<'a>(&'a mut self, socket: usize, payload: &'a [u8]) -> &'a [u8] {
+        if payload.len() < TXID_LEN {
+            self.stamped = None;
+            return payload;
+        }
+        self.payload.clear();
+        self.payload.extend_from_slice(payload);
+        let txid = self.windows[socket].next_txid();
+        self.payload[..TXID_LEN].copy_from_slice(&txid.to_be_bytes());
+        self.stamped = Some(socket);
+        &self.payload
+    }
+
+    /// Start the clock on the payload `stamp` just returned
+    #[inline]
+    pub(crate) fn record_sent(&mut self) {
+        if let Some(socket) = self.stamped.take() {
+            self.windows[socket].push(Instant::now(), &self.counters);
+        }
+    }
+
+    /// Match every reply queued on `socket`, then expire its old requests
+    pub(crate) fn collect(&mut self, index: usize, socket: &socket2::Socket) {
+        let window = &mut self.windows[index];
+        while let Some(reply) = recv_nowait(socket, &mut self.recv_buf) {
+            match reply {
+                [hi, lo, ..] => window.answer(
+                    u16::from_be_bytes([*hi, *lo]),
+                    Instant::now(),
+                    &self.counters,
+                ),
+                _ => self.counters.record_unmatched(),
+            }
+        }
+        window.expire(Instant::now(), self.timeout, &self.counters);
+    }
+}
+
+/// Finds HTTP/1.x response boundaries in a connection's byte stream
+///
+/// Bodies framed by `Content-Length` are skipped by length; anything else
+/// (chunked, close-delimited) is skipped by scanning for the next status line.
+#[derive(Default)]
+struct HttpResponseReader {
+    buf: Vec<u8>,
+    /// Body bytes of the last response still to skip
+    body_left: usize,
+}
+
+impl HttpResponseReader {
+    /// Feed received bytes, returning how many final responses completed
+    fn feed(&mut self, data: &[u8]) -> usize {
+        self.buf.extend_from_slice(data);
+        let mut responses = 0;
+        loop {
+            let skip = self.body_left.min(self.buf.len());
+            self.buf.drain(..skip);
+            self.body_left -= skip;
+            if self.body_left > 0 {
+                break;
+            }
+
+            match find(&self.buf, b"HTTP/") {
+                Some(start) => {
+                    self.buf.drain(..start);
+                }
+                None => {
+                    // Keep a tail that may be the start of a split status line
+                    let keep = self.buf.len().min(4);
+                    self.buf.drain(..self.buf.len() - keep);
+                    break;
+                }
+            }
+            let Some(end) = find(&self.buf, b"\r\n\r\n") else {
+                if self.buf.len() > MAX_HTTP_HEAD {
+                    self.buf.clear();
+                }
+                break;
+            };
+            let head = &self.buf[..end];
+            // "HTTP/1.1 100 Continue" and friends precede the real response
+            if head.get(9) != Some(&b'1') {
+                responses += 1;
+            }
+            self.body_left = content_length(head).unwrap_or(0);
+            self.buf.drain(..end + 4);
+        }
+        responses
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn content_length(head: &[u8]) -> Option<usize> {
+    head.split(|&b| b == b'\n').find_map(|line| {
+        let (name, value) = line.split_at(line.iter().position(|&b| b == b':')?);
+        if !name.trim_ascii().eq_ignore_ascii_case(b"content-length") {
+            return None;
+        }
+        std::str::from_utf8(&value[1..]).ok()?.trim().parse().ok()
+    })
+}
+
+/// Outstanding requests on one keep-alive connection, answered in order
+#[derive(Default)]
+struct HttpConnection {
+    sent: VecDeque<Instant>,
+    /// Requests already counted as timed out whose replies may still arrive
+    expired: usize,
+    reader: HttpResponseReader,
+}
+
+/// An HTTP worker's outstanding requests, one queue per pooled connection
+pub(crate) struct HttpResponses {
+    counters: Arc<ResponseCounters>,
+    timeout: Duration,
+    connections: Vec<HttpConnection>,
+    recv_buf: Vec<MaybeUninit<u8>>,
+}
+
+impl HttpResponses {
+    pub(crate) fn new(
+        counters: Arc<ResponseCounters>,
+        timeout: Duration,
+        connections: usize,
+    ) -> Self {
+        Self {
+            counters,
+            timeout,
+            connections: (0..connections)
+                .map(|_| HttpConnection::default())
+                .collect(),
+            recv_buf: vec![MaybeUninit::uninit(); 16 * 1024],
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_sent(&mut self, connection: usize) {
+        self.connections[connection].sent.push_back(Instant::now());
+    }
+
+    /// The connection was dropped; its unanswered requests never will be
+    pub(crate) fn reset(&mut self, connection: usize) {
+        let conn = std::mem::take(&mut self.connections[connection]);
+        self.counters.record_timeouts(conn.sent.len() as u64);
+    }
+
+    /// Match the replies queued on `stream`, then expire its old requests
+    pub(crate) fn collect(&mut self, connection: usize, stream: &socket2::Socket) {
+        let conn = &mut self.connections[connection];
+        while let Some(data) = recv_nowait(stream, &mut self.recv_buf) {
+            let now = Instant::now();
+            for _ in 0..conn.reader.feed(data) {
+                if conn.expired > 0 {
+                    conn.expired -= 1;
+                    self.counters.record_unmatched();
+                } else if let Some(sent) = conn.sent.pop_front() {
+                    self.counters.record_match(sent, now);
+                } else {
+                    self.counters.record_unmatched();
+                }
+            }
+        }
+        let now = Instant::now();
+        while conn
+            .sent
+            .front()
+            .is_some_and(|&sent| now.saturating_duration_since(sent) >= self.timeout)
+        {
+            conn.sent.pop_front();
+            conn.expired += 1;
+            self.counters.record_timeouts(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_txid_window_matches_and_expires() {
+        let counters = ResponseCounters::default();
+        let timeout = Duration::from_millis(50);
+        let mut window = TxidWindow::default();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(window.next_txid() as usize, window.sent.len());
+            window.push(start, &counters);
+        }
+
+        window.answer(1, start + Duration::from_millis(5), &counters);
+        // Answered twice, and an id never handed out
+        window.answer(1, start, &counters);
+        window.answer(7, start, &counters);
+        window.expire(start + timeout, timeout, &counters);
+        assert!(window.sent.is_empty());
+        assert_eq!(window.base, 3);
+        // A reply to a request that already timed out
+        window.answer(0, start + timeout, &counters);
+
+        let stats = counters.snapshot();
+        assert_eq!(
+            (stats.responses, stats.unmatched, stats.timed_out),
+            (1, 3, 2)
+        );
+        let p50 = stats.p50_latency.unwrap();
+        assert!(p50 >= Duration::from_millis(2) && p50 <= Duration::from_millis(10));
+
+        // Ids wrap once every one is outstanding
+        let mut window = TxidWindow {
+            base: u16::MAX,
+            ..Default::default()
+        };
+        window.push(start, &counters);
+        assert_eq!(window.next_txid(), 0);
+    }
+
+    #[test]
+    fn test_http_reader_splits_responses() {
+        let mut reader = HttpResponseReader::default();
+        let ok = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHTTP/";
+        assert_eq!(reader.feed(ok), 1);
+        // Split mid-head, with a 100 Continue ahead of the real response
+        assert_eq!(
+            reader.feed(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No"),
+            0
+        );
+        assert_eq!(reader.feed(b" Content\r\n\r\n"), 1);
+
+        let chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        assert_eq!(reader.feed(chunked), 1);
+        assert_eq!(
+            reader.feed(b"HTTP/1.0 404 Not Found\r\ncontent-length:0\r\n\r\n"),
+            1
+        );
+        assert!(reader.buf.len() <= 4);
+    }
+}
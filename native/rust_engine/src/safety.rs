@@ -1,14 +1,17 @@
 //! Safety controls and compliance mechanisms
 //! Implements target authorization, rate limiting, and emergency stop
 
-use parking_lot::RwLock;
-use std::collections::HashSet;
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
+use crate::atomic_stats::StatsCollector;
+
 #[derive(Debug, Error)]
 pub enum SafetyError {
     #[error("Target not authorized: {0}")]
@@ -23,12 +26,238 @@ pub enum SafetyError {
     SafetyCheckFailed(String),
 }
 
+/// Authorized CIDR range, optionally limited to a window
+#[derive(Debug, Clone, Copy)]
+struct CidrGrant {
+    network: Ipv4Addr,
+    prefix: u8,
+    /// `None` for a permanent grant
+    expires: Option<Instant>,
+}
+
+/// Whether a grant with this expiry still holds at `now`
+fn grant_live(expires: Option<Instant>, now: Instant) -> bool {
+    expires.is_none_or(|at| now < at)
+}
+
+/// Keep the longer-lived of two grants for the same target
+fn longer_grant(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    Some(a?.max(b?))
+}
+
+/// Documentation (RFC 5737) and reserved (240.0.0.0/4) ranges that strict
+/// mode rejects unless explicitly authorized
+const RESERVED_RANGES: [(Ipv4Addr, u8); 4] = [
+    (Ipv4Addr::new(192, 0, 2, 0), 24),
+    (Ipv4Addr::new(198, 51, 100, 0), 24),
+    (Ipv4Addr::new(203, 0, 113, 0), 24),
+    (Ipv4Addr::new(240, 0, 0, 0), 4),
+];
+
+/// Longest host name `getnameinfo` returns (NI_MAXHOST)
+#[cfg(unix)]
+const MAX_HOST_NAME: usize = 1025;
+
+/// Reverse DNS lookups for the strict-mode PTR check
+pub trait ReverseDns: Send + Sync {
+    /// PTR name for `ip`, `None` when it has none; a timeout is an error
+    fn lookup(&self, ip: IpAddr) -> std::io::Result<Option<String>>;
+}
+
+/// How long a PTR answer is reused before asking the resolver again
+const REVERSE_DNS_TTL: Duration = Duration::from_secs(300);
+
+/// Lookups the system resolver may run at once; stuck ones count until they return
+const MAX_REVERSE_DNS_IN_FLIGHT: usize = 8;
+
+type PtrLookup = dyn Fn(IpAddr) -> std::io::Result<Option<String>> + Send + Sync;
+
+/// Resolver answer kept per IP; errors are kept as their kind and message
+struct CachedPtr {
+    at: Instant,
+    answer: Result<Option<String>, (std::io::ErrorKind, String)>,
+}
+
+#[derive(Default)]
+struct PtrCache {
+    answers: HashMap<IpAddr, CachedPtr>,
+    in_flight: HashSet<IpAddr>,
+}
+
+/// System resolver (`getnameinfo`) bounded by `timeout`
+///
+/// Answers are cached per IP for `REVERSE_DNS_TTL` and concurrent checks of
+/// one IP share a lookup. Failures are not cached, but an IP whose lookup is
+/// still stuck is not looked up again until it returns.
+pub struct SystemReverseDns {
+    timeout: Duration,
+    resolve: Arc<PtrLookup>,
+    cache: Arc<(Mutex<PtrCache>, Condvar)>,
+}
+
+impl SystemReverseDns {
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_resolver(timeout, Arc::new(ptr_lookup))
+    }
+
+    fn with_resolver(timeout: Duration, resolve: Arc<PtrLookup>) -> Self {
+        Self {
+            timeout,
+            resolve,
+            cache: Arc::new((Mutex::new(PtrCache::default()), Condvar::new())),
+        }
+    }
+
+    /// Run a lookup for `ip` on its own thread, recording the answer in the cache
+    fn spawn_lookup(&self, ip: IpAddr) -> std::io::Result<()> {
+        let resolve = Arc::clone(&self.resolve);
+        let cache = Arc::clone(&self.cache);
+        // getnameinfo cannot be cancelled, so a stuck lookup is left behind
+        std::thread::Builder::new()
+            .name("reverse-dns".to_string())
+            .spawn(move || {
+                let answer = resolve(ip).map_err(|e| (e.kind(), e.to_string()));
+                let (lock, answered) = &*cache;
+                let mut cache = lock.lock();
+                cache.in_flight.remove(&ip);
+                let at = Instant::now();
+                cache
+                    .answers
+                    .retain(|_, cached| at.duration_since(cached.at) < REVERSE_DNS_TTL);
+                cache.answers.insert(ip, CachedPtr { at, answer });
+                answered.notify_all();
+            })
+            .map(|_| ())
+    }
+}
+
+impl ReverseDns for SystemReverseDns {
+    fn lookup(&self, ip: IpAddr) -> std::io::Result<Option<String>> {
+        let asked = Instant::now();
+        let deadline = asked + self.timeout;
+        let (lock, answered) = &*self.cache;
+        let mut cache = lock.lock();
+        loop {
+            if let Some(cached) = cache.answers.get(&ip) {
+                match &cached.answer {
+                    Ok(name) if cached.at.elapsed() < REVERSE_DNS_TTL => return Ok(name.clone()),
+                    Err((kind, message)) if cached.at >= asked => {
+                        return Err(std::io::Error::new(*kind, message.clone()))
+                    }
+                    _ => {}
+                }
+            }
+            if !cache.in_flight.contains(&ip) {
+                if cache.in_flight.len() >= MAX_REVERSE_DNS_IN_FLIGHT {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::WouldBlock,
+                        format!(
+                            "{} reverse DNS lookups already in flight",
+                            MAX_REVERSE_DNS_IN_FLIGHT
+                        ),
+                    ));
+                }
+                cache.answers.remove(&ip);
+                cache.in_flight.insert(ip);
+                if let Err(e) = self.spawn_lookup(ip) {
+                    cache.in_flight.remove(&ip);
+                    return Err(e);
+                }
+            }
+            if answered.wait_until(&mut cache, deadline).timed_out() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("no answer within {:?}", self.timeout),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn ptr_lookup(ip: IpAddr) -> std::io::Result<Option<String>> {
+    let addr = socket2::SockAddr::from(std::net::SocketAddr::new(ip, 0));
+    let mut host = [0 as libc::c_char; MAX_HOST_NAME];
+    // SAFETY: addr and host are valid for the lengths passed
+    let rc = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr(),
+            addr.len(),
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    match rc {
+        0 => {
+            // SAFETY: getnameinfo NUL-terminates the name on success
+            let name = unsafe { std::ffi::CStr::from_ptr(host.as_ptr()) };
+            Ok(Some(name.to_string_lossy().into_owned()))
+        }
+        libc::EAI_NONAME => Ok(None),
+        libc::EAI_AGAIN => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "temporary resolver failure",
+        )),
+        code => Err(std::io::Error::other(format!(
+            "getnameinfo failed ({})",
+            code
+        ))),
+    }
+}
+
+#[cfg(not(unix))]
+fn ptr_lookup(_ip: IpAddr) -> std::io::Result<Option<String>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "reverse DNS is not supported on this platform",
+    ))
+}
+
+/// Strict-mode rejection of targets whose PTR falls under given suffixes
+struct ReverseDnsPolicy {
+    resolver: Arc<dyn ReverseDns>,
+    /// Lowercase suffixes without a leading dot
+    disallowed_suffixes: Vec<String>,
+}
+
+impl ReverseDnsPolicy {
+    fn check(&self, ip: IpAddr) -> Result<(), SafetyError> {
+        let name = match self.resolver.lookup(ip) {
+            Ok(Some(name)) => name.trim_end_matches('.').to_lowercase(),
+            Ok(None) => return Ok(()),
+            // Fail closed: an unverifiable target is not authorized
+            Err(e) => {
+                return Err(SafetyError::UnauthorizedTarget(format!(
+                    "reverse DNS lookup for {} failed ({}); rejecting in strict mode",
+                    ip, e
+                )))
+            }
+        };
+        let matched = self
+            .disallowed_suffixes
+            .iter()
+            .find(|suffix| name == **suffix || name.ends_with(&format!(".{}", suffix)));
+        match matched {
+            Some(suffix) => Err(SafetyError::UnauthorizedTarget(format!(
+                "IP {} reverse-resolves to {}, under disallowed domain {}",
+                ip, name, suffix
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Target authorization whitelist
 pub struct TargetAuthorization {
-    /// Authorized IP addresses
-    authorized_ips: RwLock<HashSet<IpAddr>>,
+    /// Authorized IP addresses, with expiry for time-boxed grants
+    authorized_ips: RwLock<HashMap<IpAddr, Option<Instant>>>,
     /// Authorized IP ranges (CIDR)
-    authorized_ranges: RwLock<Vec<(Ipv4Addr, u8)>>,
+    authorized_ranges: RwLock<Vec<CidrGrant>>,
+    /// Denied IP ranges (CIDR), checked before any authorization
+    denied_ranges: RwLock<Vec<(Ipv4Addr, u8)>>,
     /// Authorized domains
     authorized_domains: RwLock<HashSet<String>>,
     /// Allow localhost
@@ -37,6 +266,8 @@ pub struct TargetAuthorization {
     allow_private: AtomicBool,
     /// Strict mode (deny if not explicitly authorized)
     strict_mode: AtomicBool,
+    /// PTR check applied to IP targets in strict mode
+    reverse_dns: RwLock<Option<ReverseDnsPolicy>>,
 }
 
 impl Default for TargetAuthorization {
@@ -48,12 +279,14 @@ impl Default for TargetAuthorization {
 impl TargetAuthorization {
     pub fn new() -> Self {
         Self {
-            authorized_ips: RwLock::new(HashSet::new()),
+            authorized_ips: RwLock::new(HashMap::new()),
             authorized_ranges: RwLock::new(Vec::new()),
+            denied_ranges: RwLock::new(Vec::new()),
             authorized_domains: RwLock::new(HashSet::new()),
             allow_localhost: AtomicBool::new(false),
             allow_private: AtomicBool::new(false),
             strict_mode: AtomicBool::new(true),
+            reverse_dns: RwLock::new(None),
         }
     }
 
@@ -68,27 +301,105 @@ impl TargetAuthorization {
 
     /// Add authorized IP
     pub fn authorize_ip(&self, ip: IpAddr) {
-        self.authorized_ips.write().insert(ip);
+        self.grant_ip(ip, None);
+    }
+
+    /// Authorize an IP until `until`, after which it is rejected again
+    ///
+    /// Expiry uses the monotonic clock, so wall-clock changes neither extend
+    /// nor cut short the window. Re-authorizing keeps the longer grant.
+    pub fn authorize_ip_until(&self, ip: IpAddr, until: Instant) {
+        self.grant_ip(ip, Some(until));
+    }
+
+    fn grant_ip(&self, ip: IpAddr, expires: Option<Instant>) {
+        self.authorized_ips
+            .write()
+            .entry(ip)
+            .and_modify(|current| *current = longer_grant(*current, expires))
+            .or_insert(expires);
     }
 
     /// Add authorized CIDR range
     pub fn authorize_cidr(&self, cidr: &str) -> Result<(), SafetyError> {
-        let parts: Vec<&str> = cidr.split('/').collect();
-        if parts.len() != 2 {
-            return Err(SafetyError::SafetyCheckFailed("Invalid CIDR format".into()));
+        self.grant_cidr(cidr, None)
+    }
+
+    /// Authorize a CIDR range until `until` (see `authorize_ip_until`)
+    pub fn authorize_cidr_until(&self, cidr: &str, until: Instant) -> Result<(), SafetyError> {
+        self.grant_cidr(cidr, Some(until))
+    }
+
+    fn grant_cidr(&self, cidr: &str, expires: Option<Instant>) -> Result<(), SafetyError> {
+        let (ip, prefix) = parse_cidr(cidr)?;
+        let mut ranges = self.authorized_ranges.write();
+        match ranges
+            .iter_mut()
+            .find(|grant| same_cidr((grant.network, grant.prefix), (ip, prefix)))
+        {
+            Some(grant) => grant.expires = longer_grant(grant.expires, expires),
+            None => ranges.push(CidrGrant {
+                network: ip,
+                prefix,
+                expires,
+            }),
         }
+        Ok(())
+    }
 
-        let ip: Ipv4Addr = parts[0]
-            .parse()
-            .map_err(|_| SafetyError::SafetyCheckFailed("Invalid IP".into()))?;
-        let prefix: u8 = parts[1]
-            .parse()
-            .map_err(|_| SafetyError::SafetyCheckFailed("Invalid prefix".into()))?;
+    /// Remove an exact IP authorization, returning whether one existed
+    ///
+    /// Ranges covering the IP still apply; use `deny_cidr` with a /32 to
+    /// block it regardless.
+    pub fn revoke_ip(&self, ip: IpAddr) -> bool {
+        self.authorized_ips.write().remove(&ip).is_some()
+    }
 
-        self.authorized_ranges.write().push((ip, prefix));
+    /// Remove a CIDR authorization added with the same network and prefix
+    pub fn revoke_cidr(&self, cidr: &str) -> Result<bool, SafetyError> {
+        let range = parse_cidr(cidr)?;
+        let mut ranges = self.authorized_ranges.write();
+        let before = ranges.len();
+        ranges.retain(|grant| !same_cidr((grant.network, grant.prefix), range));
+        Ok(ranges.len() != before)
+    }
+
+    /// Block a CIDR range; denies win over every authorization and over the
+    /// localhost/private/permissive defaults
+    pub fn deny_cidr(&self, cidr: &str) -> Result<(), SafetyError> {
+        let range = parse_cidr(cidr)?;
+        let mut denied = self.denied_ranges.write();
+        if !denied.iter().any(|d| same_cidr(*d, range)) {
+            denied.push(range);
+        }
         Ok(())
     }
 
+    /// Drop grants whose window has closed
+    fn prune_expired(&self, now: Instant) {
+        let stale_ip = self
+            .authorized_ips
+            .read()
+            .values()
+            .any(|expires| !grant_live(*expires, now));
+        if stale_ip {
+            self.authorized_ips
+                .write()
+                .retain(|_, expires| grant_live(*expires, now));
+        }
+
+        let stale_range = self
+            .authorized_ranges
+            .read()
+            .iter()
+            .any(|grant| !grant_live(grant.expires, now));
+        if stale_range {
+            self.authorized_ranges
+                .write()
+                .retain(|grant| grant_live(grant.expires, now));
+        }
+    }
+
     /// Add authorized domain
     pub fn authorize_domain(&self, domain: &str) {
         self.authorized_domains
@@ -108,6 +419,29 @@ impl TargetAuthorization {
     }
 
     fn check_ip(&self, ip: IpAddr) -> Result<(), SafetyError> {
+        if let IpAddr::V4(v4) = ip {
+            let denied = self
+                .denied_ranges
+                .read()
+                .iter()
+                .find(|(network, prefix)| ip_in_cidr(v4, *network, *prefix))
+                .copied();
+            if let Some((network, prefix)) = denied {
+                return Err(SafetyError::UnauthorizedTarget(format!(
+                    "IP {} denied by {}/{}",
+                    ip, network, prefix
+                )));
+            }
+        }
+
+        let strict = self.strict_mode.load(Ordering::Relaxed);
+        // Checked even for authorized IPs: it exists to catch a mistyped grant
+        if strict && !ip.is_loopback() {
+            if let Some(policy) = self.reverse_dns.read().as_ref() {
+                policy.check(ip)?;
+            }
+        }
+
         // Check localhost
         if ip.is_loopback() {
             if self.allow_localhost.load(Ordering::Relaxed) {
@@ -132,17 +466,38 @@ impl TargetAuthorization {
             }
         }
 
+        let now = Instant::now();
+        self.prune_expired(now);
+
         // Check explicit authorization
-        if self.authorized_ips.read().contains(&ip) {
+        let explicit = self
+            .authorized_ips
+            .read()
+            .get(&ip)
+            .is_some_and(|expires| grant_live(*expires, now));
+        if explicit {
             return Ok(());
         }
 
         // Check CIDR ranges
         if let IpAddr::V4(v4) = ip {
-            for (range_ip, prefix) in self.authorized_ranges.read().iter() {
-                if ip_in_cidr(v4, *range_ip, *prefix) {
-                    return Ok(());
-                }
+            let in_range = self.authorized_ranges.read().iter().any(|grant| {
+                grant_live(grant.expires, now) && ip_in_cidr(v4, grant.network, grant.prefix)
+            });
+            if in_range {
+                return Ok(());
+            }
+        }
+
+        if let IpAddr::V4(v4) = ip {
+            let reserved = RESERVED_RANGES
+                .iter()
+                .find(|(network, prefix)| ip_in_cidr(v4, *network, *prefix));
+            if let (true, Some((network, prefix))) = (strict, reserved) {
+                return Err(SafetyError::UnauthorizedTarget(format!(
+                    "IP {} is in reserved range {}/{}; authorize it explicitly to target it",
+                    ip, network, prefix
+                )));
             }
         }
 
@@ -166,9 +521,9 @@ impl TargetAuthorization {
 
         // Check wildcard domains
         for auth_domain in self.authorized_domains.read().iter() {
-            if auth_domain.starts_with("*.") {
-                let suffix = &auth_domain[2..];
-                if domain_lower.ends_with(suffix) {
+            // `*.example.com` covers subdomains only, not the base domain
+            if let Some(suffix) = auth_domain.strip_prefix('*') {
+                if suffix.starts_with('.') && domain_lower.ends_with(suffix) {
                     return Ok(());
                 }
             }
@@ -189,6 +544,31 @@ impl TargetAuthorization {
         self.strict_mode.store(strict, Ordering::SeqCst);
     }
 
+    /// In strict mode, reject IP targets whose reverse DNS name falls under
+    /// one of `disallowed_suffixes` (e.g. `"gov"`, `".example.net"`)
+    ///
+    /// A failed or timed-out lookup rejects the target.
+    pub fn set_reverse_dns_check(
+        &self,
+        resolver: Arc<dyn ReverseDns>,
+        disallowed_suffixes: &[&str],
+    ) {
+        let disallowed_suffixes = disallowed_suffixes
+            .iter()
+            .map(|suffix| suffix.trim_matches('.').to_lowercase())
+            .filter(|suffix| !suffix.is_empty())
+            .collect();
+        *self.reverse_dns.write() = Some(ReverseDnsPolicy {
+            resolver,
+            disallowed_suffixes,
+        });
+    }
+
+    /// Remove the reverse DNS check
+    pub fn clear_reverse_dns_check(&self) {
+        *self.reverse_dns.write() = None;
+    }
+
     /// Allow localhost
     pub fn set_allow_localhost(&self, allow: bool) {
         self.allow_localhost.store(allow, Ordering::SeqCst);
@@ -231,6 +611,12 @@ impl SafetyRateLimiter {
     /// Check if sending is allowed
     #[inline]
     pub fn check(&self) -> Result<(), SafetyError> {
+        self.check_n(1)
+    }
+
+    /// Check if sending a batch of `packets` is allowed, counting it if so
+    #[inline]
+    pub fn check_n(&self, packets: u64) -> Result<(), SafetyError> {
         if !self.enabled.load(Ordering::Relaxed) {
             return Ok(());
         }
@@ -254,8 +640,16 @@ impl SafetyRateLimiter {
             return Err(SafetyError::RateLimitExceeded(current));
         }
 
-        self.packets_since_check.fetch_add(1, Ordering::Relaxed);
-        Ok(())
+        // Admit at most one window's share of max_pps, so a window cannot
+        // burst unthrottled before the rate above catches up. A batch larger
+        // than the share still goes out alone in a fresh window.
+        let share = (max / 10).max(1);
+        self.packets_since_check
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pending| {
+                (pending == 0 || pending + packets <= share).then_some(pending + packets)
+            })
+            .map(|_| ())
+            .map_err(|_| SafetyError::RateLimitExceeded(current.max(max)))
     }
 
     /// Set maximum PPS
@@ -264,6 +658,15 @@ impl SafetyRateLimiter {
         self.enabled.store(max > 0, Ordering::SeqCst);
     }
 
+    /// Get maximum PPS, `None` when limiting is disabled
+    pub fn max_pps(&self) -> Option<u64> {
+        if self.enabled.load(Ordering::Relaxed) {
+            Some(self.max_pps.load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
     /// Get current PPS
     pub fn current_pps(&self) -> u64 {
         self.current_pps.load(Ordering::Relaxed)
@@ -407,6 +810,53 @@ impl EmergencyStop {
     }
 }
 
+/// Attempts (packets plus errors) a watch window needs before it can trip
+const WATCH_MIN_ATTEMPTS: u64 = 1000;
+/// Samples taken per watch window
+const WATCH_SAMPLES_PER_WINDOW: u32 = 10;
+
+/// Cumulative packet and error counters sampled by `SafetyController::watch`
+pub trait CounterSource: Send + Sync {
+    /// Packets sent and send errors since the run began
+    fn counts(&self) -> (u64, u64);
+}
+
+impl CounterSource for StatsCollector {
+    fn counts(&self) -> (u64, u64) {
+        let snapshot = self.snapshot();
+        (snapshot.packets_sent, snapshot.errors)
+    }
+}
+
+/// Running error-ratio watch; dropping it stops the watch
+pub struct SafetyWatch {
+    cancel: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for SafetyWatch {
+    fn drop(&mut self) {
+        let (cancelled, wake) = &*self.cancel;
+        *cancelled.lock() = true;
+        wake.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Error ratio over the samples in `window`, once it holds enough attempts
+fn windowed_error_ratio(window: &VecDeque<(u64, u64)>) -> Option<f64> {
+    let (first_packets, first_errors) = *window.front()?;
+    let (last_packets, last_errors) = *window.back()?;
+    let errors = last_errors.saturating_sub(first_errors);
+    let attempts = last_packets.saturating_sub(first_packets) + errors;
+    if attempts < WATCH_MIN_ATTEMPTS {
+        return None;
+    }
+    Some(errors as f64 / attempts as f64)
+}
+
 /// Combined safety controller
 pub struct SafetyController {
     /// Target authorization
@@ -448,6 +898,66 @@ impl SafetyController {
         Ok(())
     }
 
+    /// Trigger the emergency stop when `stats` shows more than
+    /// `error_ratio_threshold` (0.0-1.0) of send attempts failing over `window`
+    ///
+    /// Counters are sampled `WATCH_SAMPLES_PER_WINDOW` times per window, and
+    /// a window with fewer than `WATCH_MIN_ATTEMPTS` attempts never trips,
+    /// so a handful of errors at startup are ignored.
+    pub fn watch(
+        self: &Arc<Self>,
+        stats: Arc<dyn CounterSource>,
+        error_ratio_threshold: f64,
+        window: Duration,
+    ) -> Result<SafetyWatch, SafetyError> {
+        if !(0.0..=1.0).contains(&error_ratio_threshold) {
+            return Err(SafetyError::SafetyCheckFailed(format!(
+                "error ratio threshold must be between 0.0 and 1.0, got {}",
+                error_ratio_threshold
+            )));
+        }
+        let interval = (window / WATCH_SAMPLES_PER_WINDOW).max(Duration::from_millis(1));
+        let cancel = Arc::new((Mutex::new(false), Condvar::new()));
+        let controller = Arc::clone(self);
+
+        let handle = {
+            let cancel = Arc::clone(&cancel);
+            std::thread::Builder::new()
+                .name("safety-watch".to_string())
+                .spawn(move || {
+                    let (cancelled, wake) = &*cancel;
+                    let mut samples = VecDeque::new();
+                    samples.push_back(stats.counts());
+                    let mut guard = cancelled.lock();
+                    while !*guard && !controller.emergency_stop.is_stopped() {
+                        wake.wait_for(&mut guard, interval);
+                        samples.push_back(stats.counts());
+                        while samples.len() > WATCH_SAMPLES_PER_WINDOW as usize + 1 {
+                            samples.pop_front();
+                        }
+                        match windowed_error_ratio(&samples) {
+                            Some(ratio) if ratio > error_ratio_threshold => {
+                                controller.emergency_stop.trigger(&format!(
+                                    "error ratio {:.1}% over {:?} exceeded {:.1}%",
+                                    ratio * 100.0,
+                                    window,
+                                    error_ratio_threshold * 100.0
+                                ));
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                })
+                .map_err(|e| SafetyError::SafetyCheckFailed(e.to_string()))?
+        };
+
+        Ok(SafetyWatch {
+            cancel,
+            handle: Some(handle),
+        })
+    }
+
     /// Quick check (no target validation)
     #[inline]
     pub fn quick_check(&self) -> Result<(), SafetyError> {
@@ -476,6 +986,29 @@ fn is_private_ip(ip: Ipv4Addr) -> bool {
     false
 }
 
+/// Parse `a.b.c.d/prefix`
+fn parse_cidr(cidr: &str) -> Result<(Ipv4Addr, u8), SafetyError> {
+    let parts: Vec<&str> = cidr.split('/').collect();
+    if parts.len() != 2 {
+        return Err(SafetyError::SafetyCheckFailed("Invalid CIDR format".into()));
+    }
+
+    let ip: Ipv4Addr = parts[0]
+        .parse()
+        .map_err(|_| SafetyError::SafetyCheckFailed("Invalid IP".into()))?;
+    let prefix: u8 = parts[1]
+        .parse()
+        .ok()
+        .filter(|prefix| *prefix <= 32)
+        .ok_or_else(|| SafetyError::SafetyCheckFailed("Invalid prefix".into()))?;
+    Ok((ip, prefix))
+}
+
+/// Whether two ranges cover the same addresses (host bits ignored)
+fn same_cidr(a: (Ipv4Addr, u8), b: (Ipv4Addr, u8)) -> bool {
+    a.1 == b.1 && ip_in_cidr(a.0, b.0, b.1)
+}
+
 fn ip_in_cidr(ip: Ipv4Addr, network: Ipv4Addr, prefix: u8) -> bool {
     if prefix > 32 {
         return false;
@@ -499,10 +1032,28 @@ mod tests {
     #[test]
     fn test_target_authorization() {
         let auth = TargetAuthorization::new();
+        auth.authorize_ip("8.8.8.8".parse().unwrap());
+
+        assert!(auth.is_authorized("8.8.8.8").is_ok());
+        assert!(auth.is_authorized("8.8.4.4").is_err());
+    }
+
+    #[test]
+    fn test_grants_do_not_override_localhost_or_private_checks() {
+        let auth = TargetAuthorization::new();
+        auth.authorize_ip("127.0.0.1".parse().unwrap());
         auth.authorize_ip("192.168.1.1".parse().unwrap());
+        auth.authorize_cidr("0.0.0.0/0").unwrap();
 
+        assert!(auth.is_authorized("127.0.0.1").is_err());
+        assert!(auth.is_authorized("192.168.1.1").is_err());
+        assert!(auth.is_authorized("10.0.0.1").is_err());
+        assert!(auth.is_authorized("8.8.8.8").is_ok());
+
+        auth.set_allow_localhost(true);
+        auth.set_allow_private(true);
+        assert!(auth.is_authorized("127.0.0.1").is_ok());
         assert!(auth.is_authorized("192.168.1.1").is_ok());
-        assert!(auth.is_authorized("192.168.1.2").is_err());
     }
 
     #[test]
@@ -536,11 +1087,11 @@ mod tests {
     #[test]
     fn test_cidr_authorization() {
         let auth = TargetAuthorization::new();
-        auth.authorize_cidr("10.0.0.0/8").unwrap();
+        auth.authorize_cidr("11.0.0.0/8").unwrap();
 
-        assert!(auth.is_authorized("10.1.2.3").is_ok());
-        assert!(auth.is_authorized("10.255.255.255").is_ok());
-        assert!(auth.is_authorized("11.0.0.1").is_err());
+        assert!(auth.is_authorized("11.1.2.3").is_ok());
+        assert!(auth.is_authorized("11.255.255.255").is_ok());
+        assert!(auth.is_authorized("12.0.0.1").is_err());
         assert!(auth.is_authorized("9.255.255.255").is_err());
     }
 
@@ -549,16 +1100,16 @@ mod tests {
         let auth = TargetAuthorization::new();
 
         // /24 network
-        auth.authorize_cidr("192.168.1.0/24").unwrap();
-        assert!(auth.is_authorized("192.168.1.1").is_ok());
-        assert!(auth.is_authorized("192.168.1.255").is_ok());
-        assert!(auth.is_authorized("192.168.2.1").is_err());
+        auth.authorize_cidr("45.33.1.0/24").unwrap();
+        assert!(auth.is_authorized("45.33.1.1").is_ok());
+        assert!(auth.is_authorized("45.33.1.255").is_ok());
+        assert!(auth.is_authorized("45.33.2.1").is_err());
 
         // /16 network
-        auth.authorize_cidr("172.16.0.0/16").unwrap();
-        assert!(auth.is_authorized("172.16.1.1").is_ok());
-        assert!(auth.is_authorized("172.16.255.255").is_ok());
-        assert!(auth.is_authorized("172.17.1.1").is_err());
+        auth.authorize_cidr("45.34.0.0/16").unwrap();
+        assert!(auth.is_authorized("45.34.1.1").is_ok());
+        assert!(auth.is_authorized("45.34.255.255").is_ok());
+        assert!(auth.is_authorized("45.35.1.1").is_err());
     }
 
     #[test]
@@ -571,6 +1122,201 @@ mod tests {
         assert!(auth.authorize_cidr("not.an.ip/24").is_err());
     }
 
+    #[test]
+    fn test_time_boxed_authorization_expires() {
+        let controller = SafetyController::new(0);
+        let auth = &controller.authorization;
+        let window = Instant::now() + Duration::from_millis(50);
+        auth.authorize_ip_until("8.8.8.8".parse().unwrap(), window);
+        auth.authorize_cidr_until("1.1.1.0/24", window).unwrap();
+        auth.authorize_ip("9.9.9.9".parse().unwrap());
+
+        assert!(controller.check_all("8.8.8.8").is_ok());
+        assert!(controller.check_all("1.1.1.1").is_ok());
+
+        thread::sleep(Duration::from_millis(80));
+        assert!(controller.check_all("8.8.8.8").is_err());
+        assert!(controller.check_all("1.1.1.1").is_err());
+        assert!(controller.check_all("9.9.9.9").is_ok());
+        // Stale grants are pruned, permanent ones kept
+        assert_eq!(auth.authorized_ips.read().len(), 1);
+        assert!(auth.authorized_ranges.read().is_empty());
+
+        // A permanent grant is not shortened by a later time-boxed one
+        auth.authorize_ip_until("9.9.9.9".parse().unwrap(), Instant::now());
+        assert!(auth.is_authorized("9.9.9.9").is_ok());
+    }
+
+    #[test]
+    fn test_deny_inside_allowed_range() {
+        let auth = TargetAuthorization::new();
+        auth.set_allow_localhost(true);
+        auth.authorize_cidr("45.33.32.0/24").unwrap();
+        auth.deny_cidr("45.33.32.7/32").unwrap();
+        auth.deny_cidr("127.0.0.0/8").unwrap();
+
+        assert!(auth.is_authorized("45.33.32.6").is_ok());
+        assert!(auth.is_authorized("45.33.32.7").is_err());
+        assert!(auth.is_authorized("45.33.32.8").is_ok());
+
+        // Deny beats exact grants and the localhost setting
+        auth.authorize_ip("45.33.32.7".parse().unwrap());
+        assert!(auth.is_authorized("45.33.32.7").is_err());
+        assert!(auth.is_authorized("127.0.0.1").is_err());
+
+        assert!(auth.deny_cidr("45.33.32.7/40").is_err());
+    }
+
+    #[test]
+    fn test_revoke_authorizations() {
+        let auth = TargetAuthorization::new();
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+        auth.authorize_ip(ip);
+        assert!(auth.is_authorized("8.8.8.8").is_ok());
+
+        assert!(auth.revoke_ip(ip));
+        assert!(auth.is_authorized("8.8.8.8").is_err());
+        assert!(!auth.revoke_ip(ip));
+
+        auth.authorize_cidr("1.1.1.0/24").unwrap();
+        assert!(!auth.revoke_cidr("1.1.0.0/16").unwrap());
+        assert!(auth.is_authorized("1.1.1.1").is_ok());
+        assert!(auth.revoke_cidr("1.1.1.0/24").unwrap());
+        assert!(auth.is_authorized("1.1.1.1").is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_reserved_ranges() {
+        let auth = TargetAuthorization::new();
+        for ip in [
+            "192.0.2.1",
+            "198.51.100.20",
+            "203.0.113.254",
+            "240.0.0.1",
+            "255.255.255.254",
+        ] {
+            let err = auth.is_authorized(ip).unwrap_err().to_string();
+            assert!(err.contains("reserved range"), "{}: {}", ip, err);
+        }
+
+        // Explicit grants still work
+        auth.authorize_ip("192.0.2.1".parse().unwrap());
+        auth.authorize_cidr("198.51.100.0/24").unwrap();
+        assert!(auth.is_authorized("192.0.2.1").is_ok());
+        assert!(auth.is_authorized("198.51.100.20").is_ok());
+        assert!(auth.is_authorized("203.0.113.254").is_err());
+
+        auth.set_strict_mode(false);
+        assert!(auth.is_authorized("203.0.113.254").is_ok());
+    }
+
+    /// Canned PTR answers; unknown addresses time out
+    struct MockReverseDns(HashMap<IpAddr, Option<&'static str>>);
+
+    impl ReverseDns for MockReverseDns {
+        fn lookup(&self, ip: IpAddr) -> std::io::Result<Option<String>> {
+            match self.0.get(&ip) {
+                Some(name) => Ok(name.map(str::to_string)),
+                None => Err(std::io::ErrorKind::TimedOut.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_dns_check() {
+        let ip = |s: &str| -> IpAddr { s.parse().unwrap() };
+        let resolver = MockReverseDns(HashMap::from([
+            (ip("8.8.8.8"), Some("dns.google.")),
+            (ip("8.8.4.4"), Some("resolver.agency.GOV.")),
+            (ip("9.9.9.9"), None),
+        ]));
+        let auth = TargetAuthorization::new();
+        for target in ["8.8.8.8", "8.8.4.4", "9.9.9.9", "1.2.3.4"] {
+            auth.authorize_ip(ip(target));
+        }
+        auth.set_reverse_dns_check(Arc::new(resolver), &[".gov", "mil"]);
+
+        assert!(auth.is_authorized("8.8.8.8").is_ok());
+        assert!(auth.is_authorized("9.9.9.9").is_ok());
+        let err = auth.is_authorized("8.8.4.4").unwrap_err().to_string();
+        assert!(
+            err.contains("resolver.agency.gov") && err.contains("gov"),
+            "{}",
+            err
+        );
+        // Fail closed on timeouts
+        let err = auth.is_authorized("1.2.3.4").unwrap_err().to_string();
+        assert!(
+            err.contains("reverse DNS lookup for 1.2.3.4 failed"),
+            "{}",
+            err
+        );
+
+        // Only enforced in strict mode
+        auth.set_strict_mode(false);
+        assert!(auth.is_authorized("8.8.4.4").is_ok());
+        auth.set_strict_mode(true);
+        auth.clear_reverse_dns_check();
+        assert!(auth.is_authorized("1.2.3.4").is_ok());
+    }
+
+    #[test]
+    fn test_system_reverse_dns_caches_and_bounds_lookups() {
+        let ip = |last: u8| IpAddr::from([192, 0, 2, last]);
+        let calls = Arc::new(AtomicU64::new(0));
+        let (release, released) = crossbeam::channel::unbounded::<()>();
+        let counted = Arc::clone(&calls);
+        let dns = SystemReverseDns::with_resolver(
+            Duration::from_millis(20),
+            Arc::new(move |ip: IpAddr| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                // Address .1 answers at once, the rest hang until released
+                if ip != IpAddr::from([192, 0, 2, 1]) {
+                    let _ = released.recv();
+                }
+                Ok(Some(format!("host-{}.example.", ip)))
+            }),
+        );
+
+        // Answers are reused within the TTL
+        assert_eq!(
+            dns.lookup(ip(1)).unwrap().unwrap(),
+            "host-192.0.2.1.example."
+        );
+        assert!(dns.lookup(ip(1)).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A stuck lookup times out and is not started again while it hangs
+        for _ in 0..3 {
+            let err = dns.lookup(ip(2)).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Stuck lookups fill the in-flight budget, then checks fail fast
+        for last in 3..=MAX_REVERSE_DNS_IN_FLIGHT as u8 + 1 {
+            assert!(dns.lookup(ip(last)).is_err());
+        }
+        let err = dns.lookup(ip(100)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            MAX_REVERSE_DNS_IN_FLIGHT as u64 + 1
+        );
+
+        // Once the hung lookups return their answers are served from the cache
+        drop(release);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while dns.lookup(ip(2)).is_err() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(
+            dns.lookup(ip(2)).unwrap().unwrap(),
+            "host-192.0.2.2.example."
+        );
+        assert!(dns.lookup(ip(100)).is_ok());
+    }
+
     #[test]
     fn test_domain_authorization() {
         let auth = TargetAuthorization::new();
@@ -734,6 +1480,54 @@ mod tests {
         assert!(controller.check_all("8.8.8.8").is_ok());
     }
 
+    /// Counters a test drives by hand
+    #[derive(Default)]
+    struct SyntheticCounters {
+        packets: AtomicU64,
+        errors: AtomicU64,
+    }
+
+    impl CounterSource for SyntheticCounters {
+        fn counts(&self) -> (u64, u64) {
+            (
+                self.packets.load(Ordering::SeqCst),
+                self.errors.load(Ordering::SeqCst),
+            )
+        }
+    }
+
+    #[test]
+    fn test_watch_trips_on_rising_errors() {
+        let controller = Arc::new(SafetyController::permissive());
+        let counters = Arc::new(SyntheticCounters::default());
+        let _watch = controller
+            .watch(counters.clone(), 0.5, Duration::from_millis(100))
+            .unwrap();
+
+        // All errors, but too few attempts to judge
+        counters.errors.store(50, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(150));
+        assert!(!controller.emergency_stop.is_stopped());
+
+        // Healthy traffic, then the target disappears and errors climb
+        for step in 1..=100u64 {
+            counters.packets.fetch_add(1000, Ordering::SeqCst);
+            counters.errors.fetch_add(step * 20, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            if controller.emergency_stop.is_stopped() {
+                break;
+            }
+        }
+        assert!(controller.emergency_stop.is_stopped());
+        let reason = controller.emergency_stop.reason().unwrap();
+        assert!(reason.starts_with("error ratio "), "{}", reason);
+        assert!(reason.contains("exceeded 50.0%"), "{}", reason);
+
+        assert!(controller
+            .watch(counters, 1.5, Duration::from_millis(100))
+            .is_err());
+    }
+
     #[test]
     fn test_private_ip_detection() {
         // 10.0.0.0/8
@@ -808,6 +1602,9 @@ mod tests {
         fn test_target_authorization_properties(
             a in 1u8..255, b in 0u8..255, c in 0u8..255, d in 1u8..255
         ) {
+            // Loopback and private targets need allow_localhost/allow_private
+            let v4 = Ipv4Addr::new(a, b, c, d);
+            prop_assume!(!v4.is_loopback() && !is_private_ip(v4));
             let auth = TargetAuthorization::new();
             let ip = format!("{}.{}.{}.{}", a, b, c, d);
             let parsed_ip: IpAddr = ip.parse().unwrap();
@@ -826,14 +1623,17 @@ mod tests {
             prefix in 8u8..30,
             host_d in 1u8..255
         ) {
+            let v4 = Ipv4Addr::new(net_a, net_b, net_c, host_d);
+            prop_assume!(!v4.is_loopback() && !is_private_ip(v4));
             let auth = TargetAuthorization::new();
             let network = format!("{}.{}.{}.0/{}", net_a, net_b, net_c, prefix);
             let host = format!("{}.{}.{}.{}", net_a, net_b, net_c, host_d);
 
             auth.authorize_cidr(&network).unwrap();
 
-            // Host in same /24 should be authorized (for prefix >= 24)
-            if prefix >= 24 {
+            // A host sharing the first 24 bits is inside any range of prefix <= 24;
+            // a longer prefix covers only part of the /24
+            if prefix <= 24 {
                 prop_assert!(auth.is_authorized(&host).is_ok());
             }
         }
@@ -1,6 +1,9 @@
 //! SIMD-accelerated packet operations
-//! Uses portable SIMD for vectorized checksum and packet building
+//! Uses SSE2/AVX2 on x86_64 and NEON on aarch64 for vectorized checksum and packet building
 
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
 /// SIMD-accelerated IP checksum calculation
@@ -18,12 +21,64 @@ pub fn checksum_simd(data: &[u8]) -> u16 {
         }
     }
     
-    #[cfg(not(target_arch = "x86_64"))]
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            unsafe { checksum_neon(data) }
+        } else {
+            checksum_scalar(data)
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
     {
         checksum_scalar(data)
     }
 }
 
+/// Checksum over `pseudo_header` followed by `data`, without concatenating them
+///
+/// The pseudo-header must have even length (IPv4's is 12 bytes, IPv6's 40) so
+/// the two one's-complement sums line up; `data` may be any length.
+#[inline]
+pub fn checksum_simd_with_pseudo(pseudo_header: &[u8], data: &[u8]) -> u16 {
+    debug_assert!(
+        pseudo_header.len().is_multiple_of(2),
+        "pseudo-header must be even length"
+    );
+    let mut sum = (!checksum_simd(pseudo_header)) as u32 + (!checksum_simd(data)) as u32;
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !sum as u16
+}
+
+/// Update a checksum after one 16-bit word changed (RFC 1624 eqn. 3)
+///
+/// `HC' = ~(~HC + ~m + m')` in one's-complement arithmetic, so changing a field
+/// costs O(1) instead of re-summing the packet. Like a full recomputation, the
+/// result is 0x0000 rather than 0xFFFF when the data sums to 0xFFFF; UDP
+/// callers still need to send a zero result as 0xFFFF.
+#[inline]
+pub fn checksum_update(old_csum: u16, old_word: u16, new_word: u16) -> u16 {
+    checksum_update_words(old_csum, &[(old_word, new_word)])
+}
+
+/// `checksum_update` for several changed words, given as `(old, new)` pairs
+#[inline]
+pub fn checksum_update_words(old_csum: u16, changes: &[(u16, u16)]) -> u16 {
+    let mut sum = (!old_csum) as u32;
+    for &(old_word, new_word) in changes {
+        sum += (!old_word) as u32 + new_word as u32;
+        // End-around carry
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !sum as u16
+}
+
 /// Scalar checksum implementation (fallback)
 #[inline]
 pub fn checksum_scalar(data: &[u8]) -> u16 {
@@ -58,21 +113,20 @@ unsafe fn checksum_sse2(data: &[u8]) -> u16 {
     let mut i = 0;
     let len = data.len();
     
-    // Process 16 bytes at a time using SSE2
+    // Process 16 bytes at a time using SSE2. Each big-endian word is
+    // (even byte << 8) + odd byte, so sum the two byte lanes separately.
+    let low_mask = _mm_set1_epi16(0x00FF);
     while i + 16 <= len {
         let chunk = _mm_loadu_si128(data.as_ptr().add(i) as *const __m128i);
         
-        // Unpack and sum
-        let low = _mm_unpacklo_epi8(chunk, _mm_setzero_si128());
-        let high = _mm_unpackhi_epi8(chunk, _mm_setzero_si128());
-        
-        // Horizontal add
-        let sum_vec = _mm_add_epi16(low, high);
-        let sum_vec = _mm_add_epi16(sum_vec, _mm_srli_si128(sum_vec, 8));
-        let sum_vec = _mm_add_epi16(sum_vec, _mm_srli_si128(sum_vec, 4));
-        let sum_vec = _mm_add_epi16(sum_vec, _mm_srli_si128(sum_vec, 2));
-        
-        sum += _mm_extract_epi16(sum_vec, 0) as u64;
+        let even = _mm_sad_epu8(_mm_and_si128(chunk, low_mask), _mm_setzero_si128());
+        let odd = _mm_sad_epu8(_mm_srli_epi16(chunk, 8), _mm_setzero_si128());
+
+        let even_sum =
+            _mm_cvtsi128_si64(even) as u64 + _mm_cvtsi128_si64(_mm_srli_si128(even, 8)) as u64;
+        let odd_sum =
+            _mm_cvtsi128_si64(odd) as u64 + _mm_cvtsi128_si64(_mm_srli_si128(odd, 8)) as u64;
+        sum += (even_sum << 8) + odd_sum;
         i += 16;
     }
     
@@ -102,31 +156,30 @@ unsafe fn checksum_avx2(data: &[u8]) -> u16 {
     let mut i = 0;
     let len = data.len();
     
-    // Process 32 bytes at a time using AVX2
+    // Process 32 bytes at a time using AVX2, summing even (high) and
+    // odd (low) bytes of each big-endian word separately
+    let low_mask = _mm256_set1_epi16(0x00FF);
     while i + 32 <= len {
         let chunk = _mm256_loadu_si256(data.as_ptr().add(i) as *const __m256i);
         
-        // Sum adjacent bytes
-        let sad = _mm256_sad_epu8(chunk, _mm256_setzero_si256());
+        let even = _mm256_sad_epu8(_mm256_and_si256(chunk, low_mask), _mm256_setzero_si256());
+        let odd = _mm256_sad_epu8(_mm256_srli_epi16(chunk, 8), _mm256_setzero_si256());
         
-        // Extract and accumulate
-        sum += _mm256_extract_epi64(sad, 0) as u64;
-        sum += _mm256_extract_epi64(sad, 1) as u64;
-        sum += _mm256_extract_epi64(sad, 2) as u64;
-        sum += _mm256_extract_epi64(sad, 3) as u64;
+        let mut even_sum = 0u64;
+        let mut odd_sum = 0u64;
+        even_sum += _mm256_extract_epi64(even, 0) as u64;
+        even_sum += _mm256_extract_epi64(even, 1) as u64;
+        even_sum += _mm256_extract_epi64(even, 2) as u64;
+        even_sum += _mm256_extract_epi64(even, 3) as u64;
+        odd_sum += _mm256_extract_epi64(odd, 0) as u64;
+        odd_sum += _mm256_extract_epi64(odd, 1) as u64;
+        odd_sum += _mm256_extract_epi64(odd, 2) as u64;
+        odd_sum += _mm256_extract_epi64(odd, 3) as u64;
+        sum += (even_sum << 8) + odd_sum;
         
         i += 32;
     }
     
-    // Process remaining with SSE2
-    while i + 16 <= len {
-        let chunk = _mm_loadu_si128(data.as_ptr().add(i) as *const __m128i);
-        let sad = _mm_sad_epu8(chunk, _mm_setzero_si128());
-        sum += _mm_extract_epi64(sad, 0) as u64;
-        sum += _mm_extract_epi64(sad, 1) as u64;
-        i += 16;
-    }
-    
     // Process remaining bytes
     while i + 1 < len {
         sum += ((data[i] as u64) << 8) | (data[i + 1] as u64);
@@ -145,6 +198,55 @@ unsafe fn checksum_avx2(data: &[u8]) -> u16 {
     !sum as u16
 }
 
+/// NEON accelerated checksum (16 bytes at a time)
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn checksum_neon(data: &[u8]) -> u16 {
+    // Each block adds at most 2 * 0xFFFF to a u32 lane; drain well before overflow
+    const BLOCKS_PER_DRAIN: usize = 16384;
+
+    let mut sum: u64 = 0;
+    let mut i = 0;
+    let len = data.len();
+
+    let mut acc = vdupq_n_u32(0);
+    let mut blocks = 0;
+    while i + 16 <= len {
+        let bytes = vld1q_u8(data.as_ptr().add(i));
+        // Swap bytes within each 16-bit lane so lanes hold the big-endian words
+        let words = vreinterpretq_u16_u8(vrev16q_u8(bytes));
+        // Pairwise widen-add eight u16 words into the four u32 lanes
+        acc = vpadalq_u16(acc, words);
+        i += 16;
+
+        blocks += 1;
+        if blocks == BLOCKS_PER_DRAIN {
+            sum += vaddlvq_u32(acc);
+            acc = vdupq_n_u32(0);
+            blocks = 0;
+        }
+    }
+    // Horizontal reduction of the four u32 lanes into a u64
+    sum += vaddlvq_u32(acc);
+
+    // Process remaining bytes
+    while i + 1 < len {
+        sum += ((data[i] as u64) << 8) | (data[i + 1] as u64);
+        i += 2;
+    }
+
+    if i < len {
+        sum += (data[i] as u64) << 8;
+    }
+
+    // Fold to 16 bits
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    !sum as u16
+}
+
 /// SIMD-accelerated memory fill for packet payloads
 #[inline]
 pub fn fill_payload_simd(buffer: &mut [u8], pattern: u8) {
@@ -157,7 +259,16 @@ pub fn fill_payload_simd(buffer: &mut [u8], pattern: u8) {
         }
     }
     
-    #[cfg(not(target_arch = "x86_64"))]
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            unsafe { fill_neon(buffer, pattern) }
+        } else {
+            buffer.fill(pattern);
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
     {
         buffer.fill(pattern);
     }
@@ -183,6 +294,26 @@ unsafe fn fill_avx2(buffer: &mut [u8], pattern: u8) {
     }
 }
 
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn fill_neon(buffer: &mut [u8], pattern: u8) {
+    let pattern_vec = vdupq_n_u8(pattern);
+    let mut i = 0;
+    let len = buffer.len();
+
+    // Fill 16 bytes at a time
+    while i + 16 <= len {
+        vst1q_u8(buffer.as_mut_ptr().add(i), pattern_vec);
+        i += 16;
+    }
+
+    // Fill remaining
+    while i < len {
+        buffer[i] = pattern;
+        i += 1;
+    }
+}
+
 /// SIMD-accelerated memory copy for packet building
 #[inline]
 pub fn copy_packet_simd(dst: &mut [u8], src: &[u8]) {
@@ -197,7 +328,16 @@ pub fn copy_packet_simd(dst: &mut [u8], src: &[u8]) {
         }
     }
     
-    #[cfg(not(target_arch = "x86_64"))]
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") && len >= 16 {
+            unsafe { copy_neon(&mut dst[..len], &src[..len]) }
+        } else {
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
     {
         dst[..len].copy_from_slice(&src[..len]);
     }
@@ -215,6 +355,26 @@ unsafe fn copy_avx2(dst: &mut [u8], src: &[u8]) {
         _mm256_storeu_si256(dst.as_mut_ptr().add(i) as *mut __m256i, chunk);
         i += 32;
     }
+
+    // Copy remaining
+    while i < len {
+        dst[i] = src[i];
+        i += 1;
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn copy_neon(dst: &mut [u8], src: &[u8]) {
+    let mut i = 0;
+    let len = src.len();
+
+    // Copy 16 bytes at a time
+    while i + 16 <= len {
+        let chunk = vld1q_u8(src.as_ptr().add(i));
+        vst1q_u8(dst.as_mut_ptr().add(i), chunk);
+        i += 16;
+    }
     
     // Copy remaining
     while i < len {
@@ -226,6 +386,48 @@ unsafe fn copy_avx2(dst: &mut [u8], src: &[u8]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_checksum_update_matches_recompute(
+            words in proptest::collection::vec(any::<u16>(), 2..64),
+            index in any::<prop::sample::Index>(),
+            new_word in any::<u16>(),
+        ) {
+            // Keep one word non-zero, as real headers do, so the sum is never all-zero
+            let mut words = words;
+            words[0] |= 0x4500;
+            let index = 1 + index.index(words.len() - 1);
+
+            let bytes = |w: &[u16]| w.iter().flat_map(|w| w.to_be_bytes()).collect::<Vec<u8>>();
+            let old_csum = checksum_scalar(&bytes(&words));
+            let old_word = words[index];
+            words[index] = new_word;
+
+            prop_assert_eq!(checksum_update(old_csum, old_word, new_word), checksum_scalar(&bytes(&words)));
+        }
+    }
+
+    #[test]
+    fn test_checksum_update_words() {
+        let mut header = [
+            0x45, 0x00, 0x00, 0x54, 0x12, 0x34, 0x40, 0x00, 0x40, 0x01, 0x00, 0x00, 0x0a, 0x00,
+            0x00, 0x01, 0xc0, 0xa8, 0x01, 0x01,
+        ];
+        let old_csum = checksum_scalar(&header);
+
+        // Rewrite the source address, as the spoofing path does
+        let changes = [(0x0a00, 0xac10), (0x0001, 0xfffe)];
+        header[12..16].copy_from_slice(&[0xac, 0x10, 0xff, 0xfe]);
+        assert_eq!(
+            checksum_update_words(old_csum, &changes),
+            checksum_scalar(&header)
+        );
+
+        // No-op edits leave the checksum alone
+        assert_eq!(checksum_update(old_csum, 0x1234, 0x1234), old_csum);
+    }
 
     #[test]
     fn test_checksum_scalar() {
@@ -244,6 +446,96 @@ mod tests {
         assert_eq!(scalar, simd);
     }
 
+    #[test]
+    fn test_checksum_with_pseudo_matches_scalar() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(524);
+        for _ in 0..10_000 {
+            let pseudo_len = if rng.gen() { 12 } else { 40 };
+            let pseudo: Vec<u8> = (0..pseudo_len).map(|_| rng.gen()).collect();
+            let data_len = rng.gen_range(0..1500);
+            let data: Vec<u8> = (0..data_len).map(|_| rng.gen()).collect();
+
+            let mut flat = pseudo.clone();
+            flat.extend_from_slice(&data);
+            let scalar = checksum_scalar(&flat);
+            assert_eq!(checksum_simd(&flat), scalar, "flat, data len {}", data_len);
+            assert_eq!(
+                checksum_simd_with_pseudo(&pseudo, &data),
+                scalar,
+                "split, data len {}",
+                data_len
+            );
+        }
+    }
+
+    #[test]
+    fn test_checksum_simd_matches_scalar() {
+        let data: Vec<u8> = (0..1500u32)
+            .map(|i| (i.wrapping_mul(131) ^ (i >> 3)) as u8)
+            .collect();
+        for len in [0, 1, 15, 16, 17, 20, 31, 32, 33, 40, 63, 64, 65, 1499, 1500] {
+            assert_eq!(
+                checksum_simd(&data[..len]),
+                checksum_scalar(&data[..len]),
+                "len {}",
+                len
+            );
+        }
+    }
+
+    fn reference_data(len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_dispatch_matches_scalar_all_sizes() {
+        let data = reference_data(2048);
+        for len in 1..=2048 {
+            assert_eq!(
+                checksum_simd(&data[..len]),
+                checksum_scalar(&data[..len]),
+                "len {}",
+                len
+            );
+
+            let mut filled = vec![0u8; len];
+            fill_payload_simd(&mut filled, 0x5A);
+            assert!(filled.iter().all(|&b| b == 0x5A), "fill len {}", len);
+
+            let mut copied = vec![0u8; len];
+            copy_packet_simd(&mut copied, &data[..len]);
+            assert_eq!(copied, &data[..len], "copy len {}", len);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn test_neon_matches_scalar() {
+        assert!(std::arch::is_aarch64_feature_detected!("neon"));
+        let data = reference_data(2048);
+        for len in 1..=2048 {
+            let neon = unsafe { checksum_neon(&data[..len]) };
+            assert_eq!(neon, checksum_scalar(&data[..len]), "len {}", len);
+
+            let mut filled = vec![0u8; len];
+            unsafe { fill_neon(&mut filled, 0xA5) };
+            assert!(filled.iter().all(|&b| b == 0xA5), "fill len {}", len);
+
+            let mut copied = vec![0u8; len];
+            unsafe { copy_neon(&mut copied, &data[..len]) };
+            assert_eq!(copied, &data[..len], "copy len {}", len);
+        }
+
+        // All-ones input drives every u32 lane to its per-block maximum and runs
+        // past the drain interval; 0xFFFF words fold to 0xFFFF, checksum 0
+        let ones = vec![0xFFu8; 1 << 20];
+        assert_eq!(unsafe { checksum_neon(&ones) }, 0);
+    }
+
     #[test]
     fn test_fill_payload() {
         let mut buffer = vec![0u8; 1500];
@@ -14,9 +14,49 @@ pub struct StatsSnapshot {
     pub duration: Duration,
     pub pps: u64,  // packets per second
     pub bps: u64,  // bytes per second
+    pub packets_dropped: u64, // deliberately skipped by loss injection
+    pub oversized_errors: u64, // sends rejected with EMSGSIZE
+    pub path_mtu: usize,       // discovered path MTU, 0 if unknown
+    pub peak_pps: u64,         // highest per-second rate sampled
+    pub p50_pps: u64,          // per-second rate percentiles
+    pub p95_pps: u64,
+    pub p99_pps: u64,
+}
+
+/// Change between two snapshots of the same run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsDelta {
+    pub packets: u64,
+    pub bytes: u64,
+    pub errors: u64,
+    /// Time between the two snapshots
+    pub interval: Duration,
+    pub pps: f64,
+    pub bps: f64,
 }
 
 impl StatsSnapshot {
+    /// Counters and rates accumulated since `earlier`
+    ///
+    /// A counter reset or a duration that went backwards saturates to zero
+    /// instead of underflowing; a zero interval reports zero rates.
+    pub fn delta(&self, earlier: &StatsSnapshot) -> StatsDelta {
+        let packets = self.packets_sent.saturating_sub(earlier.packets_sent);
+        let bytes = self.bytes_sent.saturating_sub(earlier.bytes_sent);
+        let interval = self.duration.saturating_sub(earlier.duration);
+        let secs = interval.as_secs_f64();
+        let rate = |count: u64| if secs > 0.0 { count as f64 / secs } else { 0.0 };
+
+        StatsDelta {
+            packets,
+            bytes,
+            errors: self.errors.saturating_sub(earlier.errors),
+            interval,
+            pps: rate(packets),
+            bps: rate(bytes),
+        }
+    }
+
     /// Get megabits per second
     pub fn mbps(&self) -> f64 {
         (self.bps as f64 * 8.0) / 1_000_000.0
@@ -27,6 +67,32 @@ impl StatsSnapshot {
         (self.bps as f64 * 8.0) / 1_000_000_000.0
     }
 
+    /// Packets the workers meant to send: sent, failed with an error, or
+    /// deliberately dropped
+    pub fn packets_intended(&self) -> u64 {
+        self.packets_sent + self.errors + self.packets_dropped
+    }
+
+    /// Convert to JSON format
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"packets_sent":{},"bytes_sent":{},"errors":{},"packets_dropped":{},"oversized_errors":{},"path_mtu":{},"duration_secs":{:.3},"pps":{},"bps":{},"peak_pps":{},"p50_pps":{},"p95_pps":{},"p99_pps":{}}}"#,
+            self.packets_sent,
+            self.bytes_sent,
+            self.errors,
+            self.packets_dropped,
+            self.oversized_errors,
+            self.path_mtu,
+            self.duration.as_secs_f64(),
+            self.pps,
+            self.bps,
+            self.peak_pps,
+            self.p50_pps,
+            self.p95_pps,
+            self.p99_pps
+        )
+    }
+
     /// Get success rate
     pub fn success_rate(&self) -> f64 {
         if self.packets_sent == 0 {
@@ -137,6 +203,10 @@ impl Stats {
             duration,
             pps: (packets as f64 / secs) as u64,
             bps: (bytes as f64 / secs) as u64,
+            packets_dropped: 0,
+            oversized_errors: 0,
+            path_mtu: 0,
+            ..Default::default()
         }
     }
 
@@ -246,6 +316,39 @@ mod tests {
         assert_eq!(snapshot.bytes_sent, 100000);
     }
 
+    #[test]
+    fn test_delta_rates() {
+        let earlier = StatsSnapshot {
+            packets_sent: 1_000,
+            bytes_sent: 64_000,
+            errors: 2,
+            duration: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let later = StatsSnapshot {
+            packets_sent: 6_000,
+            bytes_sent: 384_000,
+            errors: 5,
+            duration: Duration::from_millis(12_500),
+            ..Default::default()
+        };
+
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.packets, 5_000);
+        assert_eq!(delta.bytes, 320_000);
+        assert_eq!(delta.errors, 3);
+        assert_eq!(delta.interval, Duration::from_millis(2_500));
+        assert_eq!(delta.pps, 2_000.0);
+        assert_eq!(delta.bps, 128_000.0);
+
+        // Reversed order looks like a counter reset with time running backwards
+        let reset = earlier.delta(&later);
+        assert_eq!(reset, StatsDelta::default());
+
+        // Same instant: no interval, no rate
+        assert_eq!(later.delta(&later).pps, 0.0);
+    }
+
     #[test]
     fn test_success_rate() {
         let snapshot = StatsSnapshot {
@@ -255,4 +358,15 @@ mod tests {
         };
         assert!((snapshot.success_rate() - 90.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_packets_intended_counts_errors() {
+        let snapshot = StatsSnapshot {
+            packets_sent: 80,
+            errors: 10,
+            packets_dropped: 10,
+            ..Default::default()
+        };
+        assert_eq!(snapshot.packets_intended(), 100);
+    }
 }
@@ -0,0 +1,311 @@
+//! Per-target health tracking with outlier ejection
+//! Temporarily removes failing targets from rotation, like a load balancer's outlier detection
+
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Ejection policy for unhealthy targets
+#[derive(Debug, Clone)]
+pub struct HealthPolicy {
+    /// Error ratio (errors / attempts) above which a target is ejected
+    pub error_ratio_threshold: f64,
+    /// Window over which the error ratio is measured
+    pub window: Duration,
+    /// Minimum attempts in a window before the ratio is trusted
+    pub min_samples: u64,
+    /// Base time an ejected target stays out of rotation
+    pub cooldown: Duration,
+    /// Upper bound for the cool-down after repeated ejections
+    pub max_cooldown: Duration,
+    /// Maximum fraction of targets that may be ejected at once
+    pub max_ejected_fraction: f64,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            error_ratio_threshold: 0.5,
+            window: Duration::from_secs(10),
+            min_samples: 100,
+            cooldown: Duration::from_secs(30),
+            max_cooldown: Duration::from_secs(300),
+            max_ejected_fraction: 0.5,
+        }
+    }
+}
+
+struct TargetHealth {
+    target: String,
+    attempts: AtomicU64,
+    errors: AtomicU64,
+    total_attempts: AtomicU64,
+    total_errors: AtomicU64,
+    ejections: AtomicU64,
+    /// Start of the current measurement window (ms since tracker creation)
+    window_start_ms: AtomicU64,
+    /// End of the current ejection (ms since tracker creation), 0 while in rotation
+    ejected_until_ms: AtomicU64,
+}
+
+impl TargetHealth {
+    fn reset_window(&self, now_ms: u64) {
+        self.attempts.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        self.window_start_ms.store(now_ms, Ordering::Relaxed);
+    }
+}
+
+/// Health of a single target at a point in time
+#[derive(Debug, Clone)]
+pub struct TargetHealthSnapshot {
+    pub target: String,
+    pub healthy: bool,
+    pub attempts: u64,
+    pub errors: u64,
+    pub ejections: u64,
+}
+
+/// Tracks send outcomes per target and ejects outliers
+///
+/// Workers report batches with `record`; an ejected target re-enters the
+/// rotation after its cool-down as a re-probe with a fresh window. Each
+/// repeated ejection doubles the cool-down up to `max_cooldown`.
+pub struct TargetHealthTracker {
+    policy: HealthPolicy,
+    targets: Vec<TargetHealth>,
+    epoch: Instant,
+    /// Serializes ejection decisions so the ejection cap holds
+    eject_lock: Mutex<()>,
+}
+
+impl TargetHealthTracker {
+    pub fn new(targets: Vec<String>, policy: HealthPolicy) -> Self {
+        let targets = targets
+            .into_iter()
+            .map(|target| TargetHealth {
+                target,
+                attempts: AtomicU64::new(0),
+                errors: AtomicU64::new(0),
+                total_attempts: AtomicU64::new(0),
+                total_errors: AtomicU64::new(0),
+                ejections: AtomicU64::new(0),
+                window_start_ms: AtomicU64::new(0),
+                ejected_until_ms: AtomicU64::new(0),
+            })
+            .collect();
+
+        Self {
+            policy,
+            targets,
+            epoch: Instant::now(),
+            eject_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Record a batch of send attempts for a target
+    pub fn record(&self, index: usize, attempts: u64, errors: u64) {
+        let Some(health) = self.targets.get(index) else {
+            return;
+        };
+
+        health.total_attempts.fetch_add(attempts, Ordering::Relaxed);
+        health.total_errors.fetch_add(errors, Ordering::Relaxed);
+        if health.ejected_until_ms.load(Ordering::Acquire) != 0 {
+            return;
+        }
+
+        let window_attempts = health.attempts.fetch_add(attempts, Ordering::Relaxed) + attempts;
+        let window_errors = health.errors.fetch_add(errors, Ordering::Relaxed) + errors;
+        let now = self.now_ms();
+
+        let ratio = window_errors as f64 / window_attempts.max(1) as f64;
+        if window_attempts >= self.policy.min_samples && ratio > self.policy.error_ratio_threshold {
+            let _guard = self.eject_lock.lock();
+            if health.ejected_until_ms.load(Ordering::Acquire) == 0 && self.can_eject(now) {
+                let ejections = health.ejections.fetch_add(1, Ordering::Relaxed) + 1;
+                let cooldown = self.cooldown_for(ejections).as_millis() as u64;
+                health
+                    .ejected_until_ms
+                    .store(now + cooldown.max(1), Ordering::Release);
+                return;
+            }
+        }
+
+        let window_ms = self.policy.window.as_millis() as u64;
+        if now.saturating_sub(health.window_start_ms.load(Ordering::Relaxed)) >= window_ms {
+            health.reset_window(now);
+        }
+    }
+
+    /// Whether a target is in rotation, readmitting it once its cool-down expired
+    pub fn is_available(&self, index: usize) -> bool {
+        let Some(health) = self.targets.get(index) else {
+            return false;
+        };
+
+        let until = health.ejected_until_ms.load(Ordering::Acquire);
+        if until == 0 {
+            return true;
+        }
+
+        let now = self.now_ms();
+        if now < until {
+            return false;
+        }
+
+        // Re-probe with a fresh window; only one caller performs the reset
+        if health
+            .ejected_until_ms
+            .compare_exchange(until, 0, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            health.reset_window(now);
+        }
+        true
+    }
+
+    /// Next available target at or after `cursor`, wrapping around
+    pub fn next_available(&self, cursor: usize) -> Option<usize> {
+        let len = self.targets.len();
+        (0..len)
+            .map(|offset| (cursor + offset) % len)
+            .find(|&index| self.is_available(index))
+    }
+
+    /// Targets currently in rotation
+    pub fn healthy_targets(&self) -> Vec<String> {
+        (0..self.targets.len())
+            .filter(|&i| self.is_available(i))
+            .map(|i| self.targets[i].target.clone())
+            .collect()
+    }
+
+    /// Targets currently ejected from rotation
+    pub fn ejected_targets(&self) -> Vec<String> {
+        (0..self.targets.len())
+            .filter(|&i| !self.is_available(i))
+            .map(|i| self.targets[i].target.clone())
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<TargetHealthSnapshot> {
+        (0..self.targets.len())
+            .map(|i| {
+                let health = &self.targets[i];
+                TargetHealthSnapshot {
+                    target: health.target.clone(),
+                    healthy: self.is_available(i),
+                    attempts: health.total_attempts.load(Ordering::Relaxed),
+                    errors: health.total_errors.load(Ordering::Relaxed),
+                    ejections: health.ejections.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    /// Never eject past `max_ejected_fraction`, so a campaign keeps at least one target
+    fn can_eject(&self, now: u64) -> bool {
+        let ejected = self
+            .targets
+            .iter()
+            .filter(|t| now < t.ejected_until_ms.load(Ordering::Acquire))
+            .count();
+        let max = ((self.targets.len() as f64 * self.policy.max_ejected_fraction) as usize)
+            .min(self.targets.len().saturating_sub(1));
+        ejected < max
+    }
+
+    fn cooldown_for(&self, ejections: u64) -> Duration {
+        let factor = 1u32 << (ejections.saturating_sub(1)).min(16);
+        self.policy
+            .cooldown
+            .saturating_mul(factor)
+            .min(self.policy.max_cooldown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> HealthPolicy {
+        HealthPolicy {
+            min_samples: 10,
+            cooldown: Duration::from_millis(50),
+            max_cooldown: Duration::from_millis(200),
+            ..Default::default()
+        }
+    }
+
+    fn targets(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("10.0.0.{}:80", i + 1)).collect()
+    }
+
+    #[test]
+    fn test_failing_target_is_ejected() {
+        let tracker = TargetHealthTracker::new(targets(3), policy());
+
+        tracker.record(0, 100, 0);
+        tracker.record(1, 100, 90);
+        tracker.record(2, 100, 10);
+
+        assert_eq!(tracker.ejected_targets(), vec!["10.0.0.2:80".to_string()]);
+        assert_eq!(tracker.healthy_targets().len(), 2);
+        assert_eq!(tracker.next_available(1), Some(2));
+    }
+
+    #[test]
+    fn test_ejected_target_is_reprobed_after_cooldown() {
+        let tracker = TargetHealthTracker::new(targets(2), policy());
+
+        tracker.record(0, 50, 50);
+        assert!(!tracker.is_available(0));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(tracker.is_available(0));
+
+        // Failing again doubles the cool-down
+        tracker.record(0, 50, 50);
+        assert!(!tracker.is_available(0));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!tracker.is_available(0));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(tracker.is_available(0));
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot[0].ejections, 2);
+        assert_eq!(snapshot[0].errors, 100);
+    }
+
+    #[test]
+    fn test_ejection_is_capped() {
+        let tracker = TargetHealthTracker::new(targets(4), policy());
+
+        for i in 0..4 {
+            tracker.record(i, 100, 100);
+        }
+
+        assert_eq!(tracker.ejected_targets().len(), 2);
+        assert!(tracker.next_available(0).is_some());
+    }
+
+    #[test]
+    fn test_too_few_samples_keep_target() {
+        let tracker = TargetHealthTracker::new(targets(2), policy());
+        tracker.record(0, 5, 5);
+        assert!(tracker.is_available(0));
+    }
+}
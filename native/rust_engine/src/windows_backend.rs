@@ -1,7 +1,9 @@
 //! Windows-specific backend implementations
 //! Provides IOCP (I/O Completion Ports) backend for high-performance async I/O on Windows
 
-use crate::backend::{Backend, BackendError, BackendStats, BackendType};
+use crate::backend::{
+    Backend, BackendError, BackendStats, BackendType, Recommendation, RecommendationSeverity,
+};
 use std::mem;
 use std::net::SocketAddr;
 use std::ptr;
@@ -12,7 +14,7 @@ use thiserror::Error;
 use winapi::ctypes::c_void;
 
 #[cfg(target_os = "windows")]
-use winapi::shared::minwindef::{DWORD, TRUE};
+use winapi::shared::minwindef::{DWORD, FALSE, ULONG};
 #[cfg(target_os = "windows")]
 use winapi::shared::winerror::ERROR_IO_PENDING;
 #[cfg(target_os = "windows")]
@@ -20,19 +22,26 @@ use winapi::shared::ws2def::{AF_INET, SOCKADDR, SOCKADDR_IN, WSABUF};
 #[cfg(target_os = "windows")]
 use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
 #[cfg(target_os = "windows")]
-use winapi::um::ioapiset::{CreateIoCompletionPort, GetQueuedCompletionStatus};
-#[cfg(target_os = "windows")]
-use winapi::um::minwinbase::LPOVERLAPPED;
+use winapi::um::ioapiset::{CreateIoCompletionPort, GetQueuedCompletionStatusEx};
 #[cfg(target_os = "windows")]
-use winapi::um::winbase::INFINITE;
+use winapi::um::minwinbase::OVERLAPPED_ENTRY;
 #[cfg(target_os = "windows")]
 use winapi::um::winnt::{OSVERSIONINFOEXW, OSVERSIONINFOW};
 #[cfg(target_os = "windows")]
 use winapi::um::winsock2::{
-    closesocket, sendto, socket, WSACleanup, WSAGetLastError, WSASend, WSAStartup, INVALID_SOCKET,
-    SOCKET, SOCK_DGRAM, WSADATA, WSAOVERLAPPED,
+    closesocket, sendto, socket, WSACleanup, WSAGetLastError, WSASendTo, WSAStartup,
+    INVALID_SOCKET, SOCKET, SOCK_DGRAM, WSADATA, WSAOVERLAPPED,
 };
 
+/// Overlapped sends an `IOCPBackend` keeps in flight at once
+const IOCP_SLOTS: usize = 256;
+
+/// Completions dequeued per `GetQueuedCompletionStatusEx` call
+const IOCP_COMPLETION_BATCH: usize = 64;
+
+/// Longest a batch waits for its outstanding completions, in milliseconds
+const IOCP_COMPLETION_TIMEOUT_MS: DWORD = 5_000;
+
 #[derive(Debug, Error)]
 pub enum WindowsBackendError {
     #[error("WSA initialization failed: {0}")]
@@ -47,29 +56,49 @@ pub enum WindowsBackendError {
     CompletionPortFailed(i32),
 }
 
-/// IOCP operation types
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum IOCPOperation {
-    Send,
-    Shutdown,
-}
-
-/// IOCP operation context
+/// One reusable overlapped send
+///
+/// `overlapped` comes first so the `OVERLAPPED` pointer a completion carries
+/// is also a pointer to its slot. `buffer` keeps its allocation across posts.
 #[repr(C)]
-struct IOCPContext {
+struct SendSlot {
     overlapped: WSAOVERLAPPED,
-    operation: IOCPOperation,
+    wsabuf: WSABUF,
     buffer: Vec<u8>,
-    bytes_transferred: DWORD,
+    index: usize,
 }
 
-impl IOCPContext {
-    fn new(operation: IOCPOperation, buffer: Vec<u8>) -> Self {
+/// Fixed pool of send slots; boxed so their addresses stay put while in flight
+struct SlotPool {
+    slots: Vec<Box<SendSlot>>,
+    /// Indices of slots not owned by the kernel
+    free: Vec<usize>,
+    /// Posted sends whose completion has not been dequeued yet
+    outstanding: usize,
+}
+
+// Slots hold raw pointers into their own buffers and are only touched under the pool lock
+unsafe impl Send for SlotPool {}
+
+impl SlotPool {
+    fn new(size: usize) -> Self {
+        let slots = (0..size)
+            .map(|index| {
+                Box::new(SendSlot {
+                    overlapped: unsafe { mem::zeroed() },
+                    wsabuf: WSABUF {
+                        len: 0,
+                        buf: ptr::null_mut(),
+                    },
+                    buffer: Vec::new(),
+                    index,
+                })
+            })
+            .collect();
         Self {
-            overlapped: unsafe { mem::zeroed() },
-            operation,
-            buffer,
-            bytes_transferred: 0,
+            slots,
+            free: (0..size).rev().collect(),
+            outstanding: 0,
         }
     }
 }
@@ -103,10 +132,15 @@ impl SafeHandle {
 }
 
 /// Windows IOCP backend for high-performance async I/O
+///
+/// Batches are posted as overlapped `WSASendTo` calls from a fixed slot pool
+/// and harvested with `GetQueuedCompletionStatusEx`; a slot is reposted as
+/// soon as its completion is dequeued.
 #[cfg(target_os = "windows")]
 pub struct IOCPBackend {
     socket: SOCKET,
     completion_port: SafeHandle,
+    pool: parking_lot::Mutex<SlotPool>,
     stats: BackendStatsInner,
     initialized: bool,
     wsa_initialized: bool,
@@ -136,6 +170,7 @@ impl IOCPBackend {
         Self {
             socket: INVALID_SOCKET,
             completion_port: SafeHandle::new(),
+            pool: parking_lot::Mutex::new(SlotPool::new(IOCP_SLOTS)),
             stats: BackendStatsInner::default(),
             initialized: false,
             wsa_initialized: false,
@@ -211,8 +246,15 @@ impl IOCPBackend {
         Ok(())
     }
 
-    /// Send data asynchronously using IOCP
-    fn send_async(&self, data: &[u8], dest: SocketAddr) -> Result<usize, WindowsBackendError> {
+    /// Post every packet as an overlapped `WSASendTo` and wait for all completions
+    ///
+    /// Returns (packets sent, bytes sent). Sends that fail to post or complete
+    /// with an error are counted in the backend's error stats.
+    fn send_overlapped(
+        &self,
+        packets: &[&[u8]],
+        dest: SocketAddr,
+    ) -> Result<(usize, u64), WindowsBackendError> {
         let sockaddr = match dest {
             SocketAddr::V4(v4) => {
                 let mut addr: SOCKADDR_IN = unsafe { mem::zeroed() };
@@ -226,113 +268,110 @@ impl IOCPBackend {
             }
         };
 
-        // Create operation context
-        let mut context = Box::new(IOCPContext::new(IOCPOperation::Send, data.to_vec()));
-        let context_ptr = Box::into_raw(context);
-
-        // Prepare WSABUF
-        let mut wsabuf = WSABUF {
-            len: data.len() as u32,
-            buf: data.as_ptr() as *mut i8,
-        };
+        let mut pool = self.pool.lock();
+        let mut next = 0;
+        let mut sent = 0;
+        let mut bytes = 0u64;
+        let mut errors = 0u64;
+
+        while next < packets.len() || pool.outstanding > 0 {
+            // Refill every free slot before waiting
+            while next < packets.len() {
+                let Some(index) = pool.free.pop() else {
+                    break;
+                };
+                let slot: *mut SendSlot = &mut *pool.slots[index];
+                let posted = unsafe {
+                    (*slot).buffer.clear();
+                    (*slot).buffer.extend_from_slice(packets[next]);
+                    (*slot).overlapped = mem::zeroed();
+                    (*slot).wsabuf = WSABUF {
+                        len: (*slot).buffer.len() as u32,
+                        buf: (*slot).buffer.as_mut_ptr() as *mut i8,
+                    };
+                    let rc = WSASendTo(
+                        self.socket,
+                        &mut (*slot).wsabuf,
+                        1,
+                        ptr::null_mut(),
+                        0,
+                        &sockaddr as *const SOCKADDR_IN as *const SOCKADDR,
+                        mem::size_of::<SOCKADDR_IN>() as i32,
+                        &mut (*slot).overlapped,
+                        None,
+                    );
+                    // Immediate success still queues a completion packet
+                    rc == 0 || WSAGetLastError() == ERROR_IO_PENDING as i32
+                };
+                next += 1;
+
+                if posted {
+                    pool.outstanding += 1;
+                } else {
+                    errors += 1;
+                    pool.free.push(index);
+                }
+            }
 
-        let mut bytes_sent: DWORD = 0;
+            if pool.outstanding == 0 {
+                break;
+            }
 
-        // Perform async send
-        let result = unsafe {
-            WSASend(
-                self.socket,
-                &mut wsabuf,
-                1,
-                &mut bytes_sent,
-                0,
-                &mut (*context_ptr).overlapped as *mut _ as LPOVERLAPPED,
-                None,
-            )
-        };
+            let mut entries: [OVERLAPPED_ENTRY; IOCP_COMPLETION_BATCH] = unsafe { mem::zeroed() };
+            let mut removed: ULONG = 0;
+            let ok = unsafe {
+                GetQueuedCompletionStatusEx(
+                    self.completion_port.get(),
+                    entries.as_mut_ptr(),
+                    IOCP_COMPLETION_BATCH as ULONG,
+                    &mut removed,
+                    IOCP_COMPLETION_TIMEOUT_MS,
+                    FALSE,
+                )
+            };
+            if ok == 0 {
+                // Timed out: the in-flight slots stay owned by the kernel and
+                // are reclaimed by whichever batch dequeues their completion
+                let error = unsafe { winapi::um::errhandlingapi::GetLastError() };
+                self.record(sent, bytes, errors);
+                return Err(WindowsBackendError::CompletionPortFailed(error as i32));
+            }
 
-        if result == 0 {
-            // Operation completed immediately
-            unsafe { Box::from_raw(context_ptr) }; // Clean up context
-            self.stats.packets_sent.fetch_add(1, Ordering::Relaxed);
-            self.stats
-                .bytes_sent
-                .fetch_add(bytes_sent as u64, Ordering::Relaxed);
-            Ok(bytes_sent as usize)
-        } else {
-            let error = unsafe { WSAGetLastError() };
-            if error == ERROR_IO_PENDING as i32 {
-                // Operation is pending, will complete asynchronously
-                // Note: In a real implementation, we'd need to handle completion
-                // For now, we'll wait for completion synchronously
-                self.wait_for_completion(context_ptr)
-            } else {
-                unsafe { Box::from_raw(context_ptr) }; // Clean up context
-                self.stats.errors.fetch_add(1, Ordering::Relaxed);
-                Err(WindowsBackendError::SendFailed(error))
+            for entry in &entries[..removed as usize] {
+                let slot = entry.lpOverlapped as *mut SendSlot;
+                let index = unsafe { (*slot).index };
+                pool.free.push(index);
+                pool.outstanding -= 1;
+
+                // `Internal` carries the NTSTATUS of the send
+                if entry.Internal == 0 {
+                    sent += 1;
+                    bytes += entry.dwNumberOfBytesTransferred as u64;
+                } else {
+                    errors += 1;
+                }
             }
         }
-    }
 
-    /// Wait for IOCP completion (simplified synchronous version)
-    fn wait_for_completion(
-        &self,
-        context_ptr: *mut IOCPContext,
-    ) -> Result<usize, WindowsBackendError> {
-        let mut bytes_transferred: DWORD = 0;
-        let mut completion_key: usize = 0;
-        let mut overlapped: LPOVERLAPPED = ptr::null_mut();
-
-        let result = unsafe {
-            GetQueuedCompletionStatus(
-                self.completion_port.get(),
-                &mut bytes_transferred,
-                &mut completion_key,
-                &mut overlapped,
-                INFINITE, // Wait indefinitely
-            )
-        };
-
-        // Clean up context
-        let context = unsafe { Box::from_raw(context_ptr) };
-
-        if result == TRUE {
-            self.stats.packets_sent.fetch_add(1, Ordering::Relaxed);
-            self.stats
-                .bytes_sent
-                .fetch_add(bytes_transferred as u64, Ordering::Relaxed);
-            Ok(bytes_transferred as usize)
-        } else {
-            self.stats.errors.fetch_add(1, Ordering::Relaxed);
-            let error = unsafe { winapi::um::errhandlingapi::GetLastError() };
-            Err(WindowsBackendError::CompletionPortFailed(error as i32))
-        }
+        self.record(sent, bytes, errors);
+        Ok((sent, bytes))
     }
 
-    /// Send batch using multiple async operations
-    fn send_batch_async(
-        &self,
-        packets: &[&[u8]],
-        dest: SocketAddr,
-    ) -> Result<usize, WindowsBackendError> {
-        let mut sent_count = 0;
-
-        for packet in packets {
-            match self.send_async(packet, dest) {
-                Ok(_) => sent_count += 1,
-                Err(_) => break, // Stop on first error
-            }
-        }
-
+    /// Fold one batch's results into the backend stats
+    fn record(&self, sent: usize, bytes: u64, errors: u64) {
+        self.stats
+            .packets_sent
+            .fetch_add(sent as u64, Ordering::Relaxed);
+        self.stats.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        self.stats.errors.fetch_add(errors, Ordering::Relaxed);
         self.stats.batch_count.fetch_add(1, Ordering::Relaxed);
-        Ok(sent_count)
     }
 }
 
 #[cfg(target_os = "windows")]
 impl Backend for IOCPBackend {
     fn backend_type(&self) -> BackendType {
-        BackendType::RawSocket // We'll add IOCP as a new type later
+        BackendType::IOCP
     }
 
     fn init(&mut self) -> Result<(), BackendError> {
@@ -354,8 +393,11 @@ impl Backend for IOCPBackend {
             return Err(BackendError::NotInitialized);
         }
 
-        self.send_async(data, dest)
-            .map_err(|e| BackendError::SendFailed(e.to_string()))
+        match self.send_overlapped(&[data], dest) {
+            Ok((1, bytes)) => Ok(bytes as usize),
+            Ok(_) => Err(BackendError::SendFailed("overlapped send failed".into())),
+            Err(e) => Err(BackendError::SendFailed(e.to_string())),
+        }
     }
 
     fn send_batch(&self, packets: &[&[u8]], dest: SocketAddr) -> Result<usize, BackendError> {
@@ -363,7 +405,8 @@ impl Backend for IOCPBackend {
             return Err(BackendError::NotInitialized);
         }
 
-        self.send_batch_async(packets, dest)
+        self.send_overlapped(packets, dest)
+            .map(|(sent, _)| sent)
             .map_err(|e| BackendError::SendFailed(e.to_string()))
     }
 
@@ -751,31 +794,86 @@ impl WindowsOptimizer {
     }
 
     pub fn get_performance_recommendations(&self) -> Vec<String> {
+        self.get_recommendations()
+            .iter()
+            .map(|r| r.message.clone())
+            .collect()
+    }
+
+    /// Get structured performance recommendations
+    pub fn get_recommendations(&self) -> Vec<Recommendation> {
         let mut recommendations = Vec::new();
 
         if self.capabilities.has_registered_io {
-            recommendations.push("Use Registered I/O for maximum performance".to_string());
-            recommendations.push("Pre-allocate buffer pools for zero-copy operations".to_string());
+            recommendations.push(Recommendation::new(
+                "use_registered_io",
+                RecommendationSeverity::Medium,
+                "Use Registered I/O for maximum performance",
+                "select the registered_io backend",
+            ));
+            recommendations.push(Recommendation::new(
+                "preallocate_buffer_pools",
+                RecommendationSeverity::Low,
+                "Pre-allocate buffer pools for zero-copy operations",
+                "register RIO buffers up front",
+            ));
         }
 
         if self.capabilities.has_iocp {
-            recommendations.push("Use IOCP for high-concurrency scenarios".to_string());
-            recommendations.push("Configure completion port with optimal thread count".to_string());
-        }
-
-        recommendations.push(format!(
-            "Optimize for {} CPU cores",
-            self.capabilities.cpu_count
+            recommendations.push(Recommendation::new(
+                "use_iocp",
+                RecommendationSeverity::Medium,
+                "Use IOCP for high-concurrency scenarios",
+                "select the iocp backend",
+            ));
+            recommendations.push(Recommendation::new(
+                "iocp_thread_count",
+                RecommendationSeverity::Low,
+                "Configure completion port with optimal thread count",
+                "CreateIoCompletionPort NumberOfConcurrentThreads",
+            ));
+        }
+
+        recommendations.push(Recommendation::new(
+            "thread_count",
+            RecommendationSeverity::Info,
+            format!("Optimize for {} CPU cores", self.capabilities.cpu_count),
+            "set engine threads to the CPU count",
         ));
 
         // Windows-specific socket optimizations
-        recommendations.push("Increase socket buffer sizes (SO_SNDBUF/SO_RCVBUF)".to_string());
-        recommendations.push("Use WSASend/WSARecv for async operations".to_string());
-        recommendations.push("Consider TCP_NODELAY for low-latency scenarios".to_string());
+        recommendations.push(Recommendation::new(
+            "socket_buffers",
+            RecommendationSeverity::Medium,
+            "Increase socket buffer sizes (SO_SNDBUF/SO_RCVBUF)",
+            "HKLM\\SYSTEM\\CurrentControlSet\\Services\\AFD\\Parameters\\DefaultSendWindow",
+        ));
+        recommendations.push(Recommendation::new(
+            "async_wsasend",
+            RecommendationSeverity::Low,
+            "Use WSASend/WSARecv for async operations",
+            "use overlapped WSASend/WSARecv",
+        ));
+        recommendations.push(Recommendation::new(
+            "tcp_nodelay",
+            RecommendationSeverity::Info,
+            "Consider TCP_NODELAY for low-latency scenarios",
+            "setsockopt TCP_NODELAY",
+        ));
 
         // Memory optimizations
-        recommendations.push("Use large pages if available (requires privileges)".to_string());
-        recommendations.push("Pin worker threads to specific CPU cores".to_string());
+        recommendations.push(Recommendation::new(
+            "large_pages",
+            RecommendationSeverity::Info,
+            "Use large pages if available (requires privileges)",
+            "grant SeLockMemoryPrivilege",
+        ));
+        recommendations.push(Recommendation::new(
+            "cpu_affinity",
+            RecommendationSeverity::Info,
+            "Pin worker threads to specific CPU cores",
+            "SetThreadAffinityMask",
+        ));
 
         recommendations
     }
@@ -873,6 +971,31 @@ mod tests {
         assert!(!backend.is_initialized());
     }
 
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_iocp_batch_completions_match_sends() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = receiver.local_addr().unwrap();
+
+        let mut backend = IOCPBackend::new();
+        backend.init().unwrap();
+        assert_eq!(backend.backend_type(), BackendType::IOCP);
+
+        let payloads: Vec<[u8; 4]> = (0..1000u32).map(|i| i.to_be_bytes()).collect();
+        let packets: Vec<&[u8]> = payloads.iter().map(|p| &p[..]).collect();
+        assert_eq!(backend.send_batch(&packets, dest).unwrap(), 1000);
+
+        let stats = backend.stats();
+        assert_eq!(stats.packets_sent, 1000);
+        assert_eq!(stats.bytes_sent, 4000);
+        assert_eq!(stats.errors, 0);
+
+        // Every slot is back in the pool once the batch returns
+        let pool = backend.pool.lock();
+        assert_eq!(pool.outstanding, 0);
+        assert_eq!(pool.free.len(), IOCP_SLOTS);
+    }
+
     #[cfg(target_os = "windows")]
     #[test]
     fn test_windows_optimizer() {
@@ -880,4 +1003,15 @@ mod tests {
         let caps = optimizer.capabilities();
         assert!(caps.cpu_count > 0);
     }
+
+    #[test]
+    fn test_structured_recommendations() {
+        let optimizer = WindowsOptimizer::new();
+        let structured = optimizer.get_recommendations();
+        assert_eq!(
+            structured.len(),
+            optimizer.get_performance_recommendations().len()
+        );
+        assert!(structured.iter().any(|r| r.id == "socket_buffers"));
+    }
 }
@@ -7,6 +7,7 @@ Tests the PyO3 bindings and Rust engine integration
 import pytest
 import sys
 import os
+import socket
 import time
 import json
 from unittest.mock import Mock, patch
@@ -64,7 +65,7 @@ class TestRustPythonBindings:
         
         # Test statistics
         stats = engine.get_stats()
-        assert isinstance(stats, dict)
+        assert isinstance(stats, netstress_engine.StatsSnapshot)
         assert 'packets_sent' in stats
         assert 'bytes_sent' in stats
         assert 'duration_secs' in stats
@@ -101,12 +102,40 @@ class TestRustPythonBindings:
             protocol="udp"
         )
         
-        assert isinstance(result, dict)
+        assert isinstance(result, netstress_engine.StatsSnapshot)
         assert 'packets_sent' in result
         assert 'bytes_sent' in result
         assert 'average_pps' in result
         assert 'duration_secs' in result
 
+    def test_start_flood_async_function(self):
+        """Test that start_flood_async returns at once and can be stopped early"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        started = time.time()
+        handle = netstress_engine.start_flood_async(
+            target="127.0.0.1",
+            port=8080,
+            duration=30,
+            rate=1000,
+            threads=1,
+            packet_size=64,
+        )
+        assert time.time() - started < 1.0
+
+        time.sleep(0.2)
+        first = handle.poll_stats()
+        time.sleep(0.2)
+        second = handle.poll_stats()
+        assert first['running'] and second['running']
+        assert second['packets_sent'] > first['packets_sent']
+
+        result = handle.stop()
+        assert not handle.is_running()
+        assert result['packets_sent'] >= second['packets_sent']
+        assert result['duration_secs'] < 30
+
     def test_build_packet_function(self):
         """Test packet building function"""
         if not RUST_ENGINE_AVAILABLE:
@@ -362,6 +391,441 @@ class TestRustPythonBindings:
                 protocol="invalid_protocol"
             )
 
+    def test_structured_exceptions(self):
+        """Test that engine failures raise the NetStressError hierarchy"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        # Bad host resolves to nothing
+        with pytest.raises(netstress_engine.InvalidTargetError) as excinfo:
+            netstress_engine.PacketEngine("no-such-host.invalid", 8080, 1, 64)
+        assert "Failed to create engine" in str(excinfo.value)
+
+        # Every engine error is still a RuntimeError for existing callers
+        for name in ("InvalidTargetError", "BackendError", "SafetyError", "AlreadyRunningError"):
+            error = getattr(netstress_engine, name)
+            assert issubclass(error, netstress_engine.NetStressError)
+        assert issubclass(netstress_engine.NetStressError, RuntimeError)
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, dry_run=True)
+        engine.start()
+        try:
+            with pytest.raises(netstress_engine.AlreadyRunningError):
+                engine.start()
+        finally:
+            engine.stop()
+
+        controller = netstress_engine.PySafetyController()
+        with pytest.raises(netstress_engine.SafetyError):
+            controller.check_all("203.0.113.1")
+
+    def test_packet_engine_context_manager(self):
+        """Test that a with block always leaves the engine stopped"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        with netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, dry_run=True) as engine:
+            assert engine.is_running()
+        assert not engine.is_running()
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, dry_run=True)
+        with pytest.raises(ValueError):
+            with engine:
+                assert engine.is_running()
+                raise ValueError("inside the block")
+        assert not engine.is_running()
+
+        # Entering a running engine fails without stopping it
+        with engine:
+            with pytest.raises(netstress_engine.AlreadyRunningError):
+                with engine:
+                    pass
+            assert engine.is_running()
+        assert not engine.is_running()
+
+    def test_on_stats_callback(self):
+        """Test that on_stats fires about duration/interval times and survives errors"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, dry_run=True)
+        snapshots = []
+        engine.on_stats(snapshots.append, 0.1)
+        engine.start()
+        time.sleep(1.0)
+        engine.stop()
+        fired = len(snapshots)
+        assert 7 <= fired <= 12
+        assert all('packets_sent' in s for s in snapshots)
+
+        # The timer ends with the engine
+        time.sleep(0.3)
+        assert len(snapshots) == fired
+
+        calls = []
+
+        def failing(stats):
+            calls.append(stats)
+            raise ValueError("callback error")
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, dry_run=True)
+        engine.on_stats(failing, 0.1)
+        engine.start()
+        time.sleep(0.55)
+        engine.on_stats(None)
+        cleared = len(calls)
+        assert cleared >= 3
+        time.sleep(0.3)
+        assert len(calls) == cleared
+        engine.stop()
+
+    def test_stats_snapshot_attributes(self):
+        """Test that stats come back as a typed object with a dict view"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64)
+        engine.start()
+        time.sleep(0.2)
+        engine.stop()
+
+        stats = engine.get_stats()
+        assert isinstance(stats.pps, int)
+        assert stats.packets_sent > 0
+        assert stats.bytes_sent >= stats.packets_sent
+        assert stats.duration_secs > 0
+        assert repr(stats).startswith("StatsSnapshot(packets_sent=")
+
+        as_dict = stats.to_dict()
+        assert isinstance(as_dict, dict)
+        assert as_dict['packets_per_second'] == stats.pps
+        assert as_dict['packets_sent'] == stats['packets_sent'] == stats.packets_sent
+        assert stats.get('missing', 0) == 0
+        with pytest.raises(KeyError):
+            stats['missing']
+
+        # to_dict hands out a copy
+        as_dict['packets_sent'] = -1
+        assert stats.to_dict()['packets_sent'] == stats.packets_sent
+
+    def test_packet_engine_destination_list(self):
+        """Test that a list of endpoints splits the traffic between them"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        sinks = []
+        for _ in range(2):
+            sink = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+            sink.bind(("127.0.0.1", 0))
+            sinks.append(sink)
+        endpoints = ["127.0.0.1:%d" % s.getsockname()[1] for s in sinks]
+
+        engine = netstress_engine.PacketEngine(endpoints, 0, 2, 64)
+        engine.set_rate(400)
+        engine.start()
+        time.sleep(0.5)
+        engine.stop()
+
+        per_destination = engine.get_destination_stats()
+        assert [d['address'] for d in per_destination] == endpoints
+        counts = [d['packets'] for d in per_destination]
+        assert min(counts) > 0
+        assert max(counts) - min(counts) <= max(counts) // 4
+        assert all(d['healthy'] and d['ejections'] == 0 for d in per_destination)
+        stats = engine.get_stats()
+        assert stats['healthy_destinations'] == endpoints
+        assert stats['ejected_destinations'] == []
+        for sink in sinks:
+            sink.close()
+
+        with pytest.raises(RuntimeError):
+            netstress_engine.PacketEngine([], 80)
+
+        # Bare and bracketed IPv6 literals take the default port too
+        for endpoints, addresses in [
+            (["::1", "[::1]:7"], ["[::1]:9000", "[::1]:7"]),
+            (["[::1]"], ["[::1]:9000"]),
+        ]:
+            engine = netstress_engine.PacketEngine(endpoints, 9000)
+            assert [d['address'] for d in engine.get_destination_stats()] == addresses
+
+    def test_packet_engine_port_range(self):
+        """Test that a port range sweep reaches every port and rejects inverted ranges"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        sinks = []
+        for port in range(9000, 9005):
+            sink = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+            try:
+                sink.bind(("127.0.0.1", port))
+            except OSError:
+                for s in sinks + [sink]:
+                    s.close()
+                pytest.skip("ports 9000-9004 are in use")
+            sink.setblocking(False)
+            sinks.append(sink)
+
+        engine = netstress_engine.PacketEngine(
+            "127.0.0.1", 9000, 1, 64, port_range=(9000, 9004), port_mode="sequential"
+        )
+        engine.set_rate(500)
+        engine.start()
+        time.sleep(0.3)
+        engine.stop()
+
+        for sink in sinks:
+            received = 0
+            while True:
+                try:
+                    sink.recv(64)
+                except BlockingIOError:
+                    break
+                received += 1
+            sink.close()
+            assert received > 0
+
+        with pytest.raises(RuntimeError):
+            netstress_engine.PacketEngine("127.0.0.1", 9000, port_range=(9004, 9000))
+        with pytest.raises(RuntimeError):
+            netstress_engine.PacketEngine(
+                "127.0.0.1", 9000, port_range=(9000, 9004), port_mode="spiral"
+            )
+
+    def test_packet_engine_from_config(self):
+        """Test creating an engine from a JSON config and reading it back"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        engine = netstress_engine.PacketEngine(
+            "127.0.0.1", 9000, 2, 256, dry_run=True, port_range=(9000, 9010)
+        )
+        config = engine.to_config()
+        copy = netstress_engine.PacketEngine.from_config(config)
+        assert json.loads(copy.to_config()) == json.loads(config)
+        assert json.loads(config)["port_range"] == [9000, 9010]
+
+        with pytest.raises(netstress_engine.NetStressError, match="field `port`"):
+            netstress_engine.PacketEngine.from_config('{"port": 70000}')
+
+        # Unknown fields are only skipped in lenient mode
+        scenario = '{"target": "127.0.0.1", "dry_run": true, "future_field": 1}'
+        with pytest.raises(netstress_engine.NetStressError, match="future_field"):
+            netstress_engine.PacketEngine.from_config(scenario)
+        lenient = netstress_engine.PacketEngine.from_config(scenario, strict=False)
+        assert json.loads(lenient.to_config())["dry_run"] is True
+
+    def test_packet_engine_rejects_invalid_config(self):
+        """Test that invalid settings fail at construction with the field named"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        cases = [
+            (dict(threads=0), "threads must be at least 1"),
+            (dict(packet_size=0), "packet_size must be at least 1 byte"),
+            (dict(packet_size=65508), "exceeds the 65507-byte UDP maximum"),
+            (dict(port=0), "port must be between 1 and 65535"),
+        ]
+        for overrides, message in cases:
+            kwargs = dict(target="127.0.0.1", port=9000, dry_run=True, **overrides)
+            with pytest.raises(netstress_engine.NetStressError, match=message):
+                netstress_engine.PacketEngine(**kwargs)
+
+    def test_checksum_helpers(self):
+        """Test the checksum and fill helpers against a known IPv4 header"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        def reference_checksum(data):
+            if len(data) % 2:
+                data += b"\x00"
+            total = sum(int.from_bytes(data[i:i + 2], "big") for i in range(0, len(data), 2))
+            while total >> 16:
+                total = (total & 0xFFFF) + (total >> 16)
+            return ~total & 0xFFFF
+
+        # IPv4 header with its checksum field zeroed; the correct value is 0xB861
+        header = bytes.fromhex("450000730000400040110000c0a80001c0a800c7")
+        assert netstress_engine.checksum(header) == 0xB861
+        assert netstress_engine.checksum(header) == reference_checksum(header)
+        filled = header[:10] + (0xB861).to_bytes(2, "big") + header[12:]
+        assert netstress_engine.checksum(filled) == 0
+        assert netstress_engine.checksum(b"") == 0xFFFF
+
+        # Split sums agree with checksumming the concatenation
+        pseudo = header[12:20] + bytes([0, 17, 0, 9])
+        segment = b"\x30\x39\x00\x35\x00\x09\x00\x00x"
+        expected = reference_checksum(pseudo + segment)
+        assert netstress_engine.ip_checksum_with_pseudo(pseudo, segment) == expected
+        assert netstress_engine.ip_checksum_with_pseudo(pseudo, b"") == reference_checksum(pseudo)
+        with pytest.raises(RuntimeError, match="even number of bytes"):
+            netstress_engine.ip_checksum_with_pseudo(b"odd", segment)
+
+        assert netstress_engine.fill_payload(4, 0x41) == b"AAAA"
+        assert netstress_engine.fill_payload(0, 0x41) == b""
+        large = netstress_engine.fill_payload(1 << 20, 0xFF)
+        assert len(large) == 1 << 20 and set(large) == {0xFF}
+        assert netstress_engine.checksum(large) == reference_checksum(large)
+
+    def test_rate_limiters(self):
+        """Test the token bucket and sliding window limiters from Python"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        bucket = netstress_engine.PyTokenBucket(100, 10)
+        assert all(bucket.try_acquire() for _ in range(10))
+        assert not bucket.try_acquire()
+        assert bucket.available() == 0
+        assert bucket.acquire() > 0
+        assert bucket.current_rate() == 100
+        with pytest.raises(RuntimeError, match="bucket of 10"):
+            bucket.acquire(11)
+        bucket.set_rate(0)
+        assert bucket.acquire(1000) == 0
+
+        # Counts past u64::MAX / 1000 milli-tokens saturate instead of wrapping
+        huge = 2**64 - 1
+        bucket = netstress_engine.PyTokenBucket(1, huge)
+        assert bucket.try_acquire(huge)
+        assert not bucket.try_acquire(1000)
+        fast = netstress_engine.PyTokenBucket(huge, 1)
+        assert fast.acquire() < 1
+        assert fast.current_rate() == huge
+
+        window = netstress_engine.PySlidingWindowLimiter(100, window_ms=100)
+        assert all(window.try_acquire() for _ in range(10))
+        assert not window.try_acquire()
+        assert window.available() == 0
+        assert window.acquire() > 0
+        assert window.current_rate() > 0
+        with pytest.raises(RuntimeError, match="no events"):
+            netstress_engine.PySlidingWindowLimiter(1, window_ms=100)
+
+    def test_packet_engine_calibrate(self):
+        """Test that calibration ramps to the rate limit on a target that never errors"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 1, 256, dry_run=True)
+        with pytest.raises(netstress_engine.NetStressError, match="ceiling"):
+            engine.calibrate(step_secs=0.05)
+
+        engine.set_rate(2000)
+        assert engine.calibrate(step_secs=0.05, step=500) == 2000
+        assert not engine.is_running()
+
+    def test_packet_engine_final_report(self):
+        """Test the JSON report before and after the engine stops"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        engine = netstress_engine.PacketEngine("127.0.0.1", 9, 2, 256, dry_run=True)
+        engine.set_rate(2000)
+        engine.start()
+        time.sleep(0.2)
+        assert json.loads(engine.final_report())["complete"] is False
+        engine.stop()
+
+        report = json.loads(engine.final_report())
+        assert report["complete"] is True
+        assert report["backend"] == "dry_run"
+        assert report["packets_sent"] > 0
+        assert len(report["threads"]) == 2
+        for key in ("config", "bytes_sent", "errors", "average_pps", "peak_pps",
+                    "started_at", "ended_at"):
+            assert key in report
+
+    def test_packet_engine_response_stats(self):
+        """Test that replies from a local UDP echo responder are counted"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        responder = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        responder.bind(("127.0.0.1", 0))
+        responder.settimeout(0.5)
+        port = responder.getsockname()[1]
+
+        def echo():
+            try:
+                while True:
+                    data, addr = responder.recvfrom(512)
+                    responder.sendto(data[:12], addr)
+            except OSError:
+                pass
+
+        import threading
+        threading.Thread(target=echo, daemon=True).start()
+
+        assert netstress_engine.PacketEngine("127.0.0.1", port, 1, 64).response_stats() is None
+        engine = netstress_engine.PacketEngine(
+            "127.0.0.1", port, 1, 64, collect_responses=True, response_timeout_secs=0.2
+        )
+        engine.set_rate(500)
+        engine.start()
+        time.sleep(0.3)
+        engine.stop()
+        responder.close()
+
+        stats = engine.response_stats()
+        assert stats["responses"] > 0
+        assert stats["p50_latency_secs"] < 0.1
+
+    def test_packet_engine_max_cpu_percent(self):
+        """Test the CPU governor option is validated and starts unthrottled"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        with pytest.raises(netstress_engine.NetStressError, match="max_cpu_percent"):
+            netstress_engine.PacketEngine("127.0.0.1", 9, 1, 64, max_cpu_percent=150.0)
+
+        engine = netstress_engine.PacketEngine(
+            "127.0.0.1", 9, 1, 64, dry_run=True, max_cpu_percent=100.0
+        )
+        engine.start()
+        assert engine.governed_rate() is None
+        engine.stop()
+
+    def test_build_payload_function(self):
+        """Test application-layer payloads for every protocol"""
+        if not RUST_ENGINE_AVAILABLE:
+            pytest.skip("Rust engine not available")
+
+        http = netstress_engine.build_payload("http", {"host": "example.com", "path": "/a"})
+        assert http.startswith(b"GET /a HTTP/1.1\r\nHost: example.com\r\n")
+
+        post = netstress_engine.build_payload("http_post", {"host": "example.com", "body": b"x=1"})
+        assert post.startswith(b"POST / HTTP/1.1\r\n")
+        assert post.endswith(b"\r\n\r\nx=1")
+
+        h2 = netstress_engine.build_payload("http2", {"authority": "example.com"})
+        assert h2.startswith(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+
+        tls = netstress_engine.build_payload("tls", {"server_name": "example.com"})
+        assert tls[0] == 0x16  # TLS handshake record
+        assert b"example.com" in tls
+
+        dns = netstress_engine.build_payload("dns", {"domain": "example.com"})
+        assert dns[12:25] == b"\x07example\x03com\x00"
+
+        # Full packets carry the same payload over the protocol's transport
+        packet = netstress_engine.build_packet(
+            src_ip="10.0.0.1",
+            dst_ip="10.0.0.2",
+            src_port=5353,
+            dst_port=53,
+            protocol="dns",
+            params={"domain": "example.com"},
+        )
+        assert packet[9] == 17  # UDP
+        assert packet[28 + 12:28 + 25] == b"\x07example\x03com\x00"
+
+        with pytest.raises(RuntimeError, match="requires 'host'"):
+            netstress_engine.build_payload("http", {})
+        with pytest.raises(RuntimeError, match="does not take 'hots'"):
+            netstress_engine.build_payload("http", {"host": "a", "hots": "b"})
+        with pytest.raises(RuntimeError, match="Unknown protocol"):
+            netstress_engine.build_payload("gopher", {})
+
     def test_memory_management(self):
         """Test memory management in bindings"""
         if not RUST_ENGINE_AVAILABLE:
@@ -377,7 +841,7 @@ class TestRustPythonBindings:
             
             # Get stats to exercise the binding
             stats = engine.get_stats()
-            assert isinstance(stats, dict)
+            assert isinstance(stats, netstress_engine.StatsSnapshot)
             
             # Delete explicitly
             del engine